@@ -0,0 +1,41 @@
+use std::process::Command;
+use vergen_gitcl::{Emitter, Gitcl};
+
+#[path = "src/keycode_gen.rs"]
+mod keycode_gen;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Git commit hash as VERGEN_GIT_SHA (left unset when not building from a git checkout)
+    let git = Gitcl::builder().sha(true).build();
+    Emitter::default().add_instructions(&git)?.emit()?;
+
+    // Toolchain version as RUSTC_VERSION
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=RUSTC_VERSION={}", rustc_version);
+
+    // Fallback keycode table from a QMK checkout; left empty without one
+    println!("cargo:rerun-if-env-changed=QMK_FIRMWARE");
+    let mut entries = Vec::new();
+    if let Ok(qmk) = std::env::var("QMK_FIRMWARE") {
+        let header = std::path::Path::new(&qmk).join("quantum/keycode.h");
+        println!("cargo:rerun-if-changed={}", header.display());
+        match std::fs::read_to_string(&header) {
+            Ok(source) => entries = keycode_gen::parse_keycode_header(&source),
+            Err(e) => println!(
+                "cargo:warning=QMK_FIRMWARE is set but {} could not be read: {}",
+                header.display(),
+                e
+            ),
+        }
+    }
+    let out = std::path::Path::new(&std::env::var("OUT_DIR")?).join("generated_keycodes.rs");
+    std::fs::write(out, keycode_gen::render_keycode_table(&entries))?;
+    Ok(())
+}
@@ -0,0 +1,56 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use qmk_viewer::keymap_c::parse_keymap_c;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+    let source = source.to_string();
+
+    // Run the parser on a worker thread so a pathological input shows up as a timeout
+    // instead of hanging the fuzzer
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(parse_keymap_c(&source));
+    });
+    let result = match rx.recv_timeout(TIMEOUT) {
+        Ok(result) => result,
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            panic!("parse_keymap_c took longer than {:?}", TIMEOUT)
+        }
+        // The worker panicked: let libfuzzer report it
+        Err(mpsc::RecvTimeoutError::Disconnected) => panic!("parse_keymap_c panicked"),
+    };
+
+    if let Ok(cfg) = result {
+        // Header-only sources (keymap.h) legitimately yield layer names without layers
+        if cfg.layers.is_empty() {
+            assert!(
+                cfg.layer_names.is_some(),
+                "Ok with neither layers nor layer names"
+            );
+            return;
+        }
+        // Layers may differ in length (a short or empty LAYOUT() is kept as written), but
+        // each one has a name and every key is a non-empty, trimmed token
+        assert_eq!(
+            cfg.layer_names.as_ref().map(Vec::len),
+            Some(cfg.layers.len()),
+            "layer names don't match the layers"
+        );
+        for token in cfg.layers.iter().flatten() {
+            assert!(
+                !token.is_empty() && token.trim() == token,
+                "untrimmed or empty token {:?}",
+                token
+            );
+        }
+    }
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use qmk_viewer::keycodes::translate_token;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(token) = std::str::from_utf8(data) {
+        let _ = translate_token(token);
+    }
+});
@@ -1,18 +1,208 @@
 use crate::keyboard::KeyboardLayout;
+use crate::keycodes::{translate_token_checked, CustomKeycodeTable, ViaKeycodeTable};
+use crate::keymap_c::EncoderAction;
 use anyhow::Context;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeSet;
 
-#[derive(Debug, Deserialize)]
+/// Keymap formats recognised by [`detect_format_and_parse`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeymapFormat {
+    /// QMK configurator JSON, i.e. a serialized [`KeymapConfig`]
+    QmkJson,
+    /// Layout saved from VIA
+    Via,
+    /// `keymap.c` source
+    KeymapC,
+}
+
+impl KeymapFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::QmkJson => "QMK JSON",
+            Self::Via => "VIA keymap",
+            Self::KeymapC => "keymap.c",
+        }
+    }
+}
+
+/// Layout file saved from VIA: keycodes per layer, as names or (older exports) numbers
+#[derive(Deserialize)]
+struct ViaKeymap {
+    name: Option<String>,
+    layers: Vec<Vec<serde_json::Value>>,
+}
+
+/// Parse a layout saved from VIA
+pub fn parse_via_json(text: &str) -> anyhow::Result<KeymapConfig> {
+    let via: ViaKeymap = serde_json::from_str(text).context("failed to parse VIA JSON")?;
+    if via.layers.is_empty() {
+        anyhow::bail!("VIA JSON has no layers");
+    }
+    let layers = via
+        .layers
+        .into_iter()
+        .map(|layer| {
+            layer
+                .into_iter()
+                .map(|key| match key {
+                    serde_json::Value::String(s) => s,
+                    serde_json::Value::Number(n) => match n.as_u64() {
+                        Some(code) => format!("0x{:04X}", code),
+                        None => n.to_string(),
+                    },
+                    other => other.to_string(),
+                })
+                .collect()
+        })
+        .collect();
+    Ok(KeymapConfig {
+        keyboard: via.name.unwrap_or_else(|| "via".to_string()),
+        keymap: "via".to_string(),
+        layers,
+        layout: None,
+        layer_names: None,
+        encoder_map: Vec::new(),
+        custom_keycodes: CustomKeycodeTable::default(),
+    })
+}
+
+/// Parse keymap text of unknown format, trying QMK JSON, then VIA JSON, then `keymap.c`
+///
+/// When nothing matches, the error is the one from the QMK JSON attempt.
+pub fn detect_format_and_parse(text: &str) -> anyhow::Result<(KeymapFormat, KeymapConfig)> {
+    let first_error = match serde_json::from_str::<KeymapConfig>(text) {
+        Ok(cfg) => return Ok((KeymapFormat::QmkJson, cfg)),
+        Err(e) => anyhow::Error::new(e).context("failed to parse QMK JSON"),
+    };
+    if let Ok(cfg) = parse_via_json(text) {
+        return Ok((KeymapFormat::Via, cfg));
+    }
+    match crate::keymap_c::parse_keymap_c(text) {
+        Ok(cfg) if !cfg.layers.is_empty() => Ok((KeymapFormat::KeymapC, cfg)),
+        _ => Err(first_error),
+    }
+}
+
+/// Parse keymap text of unknown format, see [`detect_format_and_parse`]
+pub fn detect_and_parse(text: &str) -> anyhow::Result<KeymapConfig> {
+    detect_format_and_parse(text).map(|(_, cfg)| cfg)
+}
+
+/// What `qmk_viewer --validate` reports about a keymap
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationSummary {
+    pub keyboard: String,
+    pub layer_count: usize,
+    pub keys_per_layer: Vec<usize>,
+    /// See [`KeymapConfig::unknown_tokens`]
+    pub unknown_tokens: Vec<String>,
+}
+
+/// Add `tok` to `unknown` unless a keycode table or `custom` labels it; of a function-like
+/// token such as `LT(1, KC_SPC)` only the keycode arguments are checked, layer and
+/// modifier arguments are not keycodes
+fn collect_unknown_tokens(tok: &str, custom: &CustomKeycodeTable, unknown: &mut BTreeSet<String>) {
+    if let Some((_, args)) = tok.strip_suffix(')').and_then(|t| t.split_once('(')) {
+        for arg in crate::keymap_c::split_items(args) {
+            if arg.starts_with("KC_") || arg.contains('(') {
+                collect_unknown_tokens(&arg, custom, unknown);
+            }
+        }
+        return;
+    }
+    if custom.translate(tok).is_none() && !translate_token_checked(tok).is_known() {
+        unknown.insert(tok.to_string());
+    }
+}
+
+/// Keycode in a JSON keymap: a name, or its 16-bit value in some QMK Configurator exports
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonKeycode {
+    Name(String),
+    Code(u16),
+}
+
+/// Layers with numeric keycodes replaced by their QMK names
+fn deserialize_layers<'de, D: Deserializer<'de>>(de: D) -> Result<Vec<Vec<String>>, D::Error> {
+    let layers = Vec::<Vec<JsonKeycode>>::deserialize(de)?;
+    Ok(layers
+        .into_iter()
+        .map(|layer| {
+            layer
+                .into_iter()
+                .map(|key| match key {
+                    JsonKeycode::Name(name) => name,
+                    JsonKeycode::Code(code) => ViaKeycodeTable::token(code),
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// QMK JSON keymap, as written by `qmk c2json` and exported by QMK Configurator
+///
+/// `keyboard` is QMK's keyboard path (e.g. `planck/rev6`). Export metadata such as
+/// `version`, `notes`, `documentation` and `author` is ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeymapConfig {
     pub keyboard: String,
+    #[serde(default)]
     pub keymap: String,
-    pub layers: Vec<Vec<String>>, // each layer contains keycodes for the keyboard
+    /// Keycodes of each layer in `LAYOUT` order
+    #[serde(deserialize_with = "deserialize_layers")]
+    pub layers: Vec<Vec<String>>,
+    #[serde(default)]
     pub layout: Option<String>,
+    #[serde(default)]
     pub layer_names: Option<Vec<String>>, // optional human-friendly names
+    /// Rotary encoder bindings per layer, empty for keyboards without an encoder map
+    #[serde(default)]
+    pub encoder_map: Vec<EncoderAction>,
+    /// Labels for the keymap's own keycodes; also read from a `custom_keycodes.json` file
+    /// next to the keymap
+    #[serde(default, skip_serializing_if = "CustomKeycodeTable::is_empty")]
+    pub custom_keycodes: CustomKeycodeTable,
 }
 
+/// Sidecar file with custom keycode labels, looked up next to a loaded keymap
+pub const CUSTOM_KEYCODES_FILE: &str = "custom_keycodes.json";
+
 impl KeymapConfig {
     pub fn load_from_path(path: &str) -> anyhow::Result<Self> {
+        let mut cfg = Self::load_keymap_file(path)?;
+        let path = std::path::Path::new(path);
+        let dir = if path.is_dir() {
+            Some(path)
+        } else {
+            path.parent()
+        };
+        if let Some(dir) = dir {
+            cfg.merge_custom_keycodes_file(&dir.join(CUSTOM_KEYCODES_FILE));
+        }
+        Ok(cfg)
+    }
+
+    /// Add the labels of a custom keycodes file; labels from the keymap itself win
+    pub fn merge_custom_keycodes_file(&mut self, file: &std::path::Path) {
+        let Ok(data) = std::fs::read_to_string(file) else {
+            return;
+        };
+        match serde_json::from_str::<CustomKeycodeTable>(&data) {
+            Ok(table) => {
+                for (token, label) in table.0 {
+                    self.custom_keycodes.0.entry(token).or_insert(label);
+                }
+            }
+            Err(e) => eprintln!("⚠️ Ignoring {}: {}", file.display(), e),
+        }
+    }
+
+    fn load_keymap_file(path: &str) -> anyhow::Result<Self> {
+        if std::path::Path::new(path).is_dir() {
+            return Self::load_from_dir(path);
+        }
         let data = std::fs::read_to_string(path)
             .with_context(|| format!("failed to read config file: {}", path))?;
         if path.ends_with(".json") {
@@ -21,14 +211,108 @@ impl KeymapConfig {
             return Ok(cfg);
         }
         if path.ends_with(".c") {
+            // Pick up matrix defines and aliases from a config.h next to keymap.c
+            let config_h = std::path::Path::new(path).with_file_name("config.h");
+            if let Ok(config_source) = std::fs::read_to_string(&config_h) {
+                return crate::keymap_c::parse_keymap_c_multi([
+                    (config_h.to_string_lossy().to_string(), config_source),
+                    (path.to_string(), data),
+                ])
+                .with_context(|| format!("failed to parse keymap.c: {}", path));
+            }
             return crate::keymap_c::parse_keymap_c(&data)
                 .with_context(|| format!("failed to parse keymap.c: {}", path));
         }
-        anyhow::bail!("unsupported config format (expected .json or .c): {}", path)
+        if path.ends_with(".h") {
+            // Prefer the sibling keymap.c and use the header for layer names and aliases
+            let sibling = std::path::Path::new(path).with_extension("c");
+            if let Ok(c_source) = std::fs::read_to_string(&sibling) {
+                let mut cfg = crate::keymap_c::parse_keymap_c(&c_source)
+                    .with_context(|| format!("failed to parse keymap.c: {}", sibling.display()))?;
+                crate::keymap_c::merge_header(&mut cfg, &data);
+                return Ok(cfg);
+            }
+            return crate::keymap_c::parse_keymap_c(&data)
+                .with_context(|| format!("failed to parse keymap.h: {}", path));
+        }
+        anyhow::bail!(
+            "unsupported config format (expected .json, .c or .h): {}",
+            path
+        )
+    }
+
+    /// Parse keymap text whose format is given by the extension of `file_name`
+    ///
+    /// Used for sources without sibling files (e.g. downloads), so `.c` and `.h` files are
+    /// parsed on their own.
+    pub fn parse_source(file_name: &str, data: &str) -> anyhow::Result<Self> {
+        if file_name.ends_with(".json") {
+            return serde_json::from_str(data)
+                .with_context(|| format!("failed to parse JSON: {}", file_name));
+        }
+        if file_name.ends_with(".c") || file_name.ends_with(".h") {
+            return crate::keymap_c::parse_keymap_c(data)
+                .with_context(|| format!("failed to parse keymap: {}", file_name));
+        }
+        anyhow::bail!(
+            "unsupported config format (expected .json, .c or .h): {}",
+            file_name
+        )
+    }
+
+    /// Load a QMK keymap folder (`keymap.c` plus optional `config.h`, `keymap.h` and `rules.mk`)
+    pub fn load_from_dir(dir: &str) -> anyhow::Result<Self> {
+        let dir_path = std::path::Path::new(dir);
+        let mut files = Vec::new();
+        for name in ["config.h", "keymap.h", "keymap.c", "rules.mk"] {
+            let file = dir_path.join(name);
+            if let Ok(content) = std::fs::read_to_string(&file) {
+                files.push((file.to_string_lossy().to_string(), content));
+            }
+        }
+        if !files.iter().any(|(name, _)| name.ends_with("keymap.c")) {
+            anyhow::bail!("no keymap.c found in folder: {}", dir);
+        }
+        crate::keymap_c::parse_keymap_c_multi(files)
+            .with_context(|| format!("failed to parse keymap folder: {}", dir))
+    }
+
+    /// Replace the keycodes of `layer_idx` and leave every other layer as it is
+    ///
+    /// Missing layers up to `layer_idx` are added empty (and named "Layer N" when the keymap
+    /// has layer names).
+    pub fn merge_layer(&mut self, layer_idx: usize, new_keys: Vec<String>) {
+        if layer_idx >= self.layers.len() {
+            self.layers.resize(layer_idx + 1, Vec::new());
+        }
+        self.layers[layer_idx] = new_keys;
+        if let Some(names) = &mut self.layer_names {
+            while names.len() < self.layers.len() {
+                names.push(format!("Layer {}", names.len()));
+            }
+        }
     }
 
     /// Convert this keymap configuration to a generic keyboard layout
     pub fn to_keyboard_layout(&self) -> KeyboardLayout {
         KeyboardLayout::from_layout_data(self.layers.clone(), self.layer_names.clone())
     }
+
+    /// Keycodes that would be shown as their raw token, sorted and without duplicates
+    pub fn unknown_tokens(&self) -> Vec<String> {
+        let mut unknown = BTreeSet::new();
+        for tok in self.layers.iter().flatten() {
+            collect_unknown_tokens(tok.trim(), &self.custom_keycodes, &mut unknown);
+        }
+        unknown.into_iter().collect()
+    }
+
+    pub fn validation_summary(&self) -> ValidationSummary {
+        ValidationSummary {
+            keyboard: self.keyboard.clone(),
+            layer_count: self.layers.len(),
+            keys_per_layer: self.layers.iter().map(Vec::len).collect(),
+            unknown_tokens: self.unknown_tokens(),
+        }
+    }
 }
@@ -1,22 +1,184 @@
+use crate::hid::{MockConfig, RawHidFormat};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+/// Version written to new config files; older files are migrated on load
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Keymap saved under a name with [`save_profile`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeymapProfile {
+    pub name: String,
+    /// Stored copy of the keymap in the profiles dir
+    pub path: String,
+    /// Seconds since the Unix epoch when the profile was last saved or loaded
+    pub last_used: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AppConfig {
+    /// Format version of the stored file (0 for files written before versioning)
+    #[serde(default)]
+    pub config_version: u32,
     pub last_keymap_path: Option<String>,
+    /// File the saved keymap copy was made from, shown as the source after a restart
+    #[serde(default)]
+    pub last_keymap_source_path: Option<String>,
+    /// Render keys at their physical positions when the layout provides them
+    #[serde(default)]
+    pub show_physical_layout: bool,
+    /// Mark the two halves of a split keyboard in the physical layout
+    #[serde(default)]
+    pub show_split: bool,
+    /// Label transparent keys with the dimmed key they inherit from a lower layer
+    #[serde(default)]
+    pub show_effective_keys: bool,
+    /// Layers drawn as overlays on top of the active layer, by index
+    #[serde(default)]
+    pub pinned_layers: Vec<u8>,
+    /// Name of the color theme, `None` for the built-in default
+    #[serde(default)]
+    pub theme: Option<String>,
+    /// Recently loaded keymap paths, newest first
+    #[serde(default)]
+    pub recent_files: VecDeque<String>,
+    /// OS keyboard layout used to label letter keys (see `KeyboardLocale::name`)
+    #[serde(default)]
+    pub keyboard_locale: Option<String>,
+    /// Merge a reloaded single-layer keymap into the shown keymap instead of replacing it
+    #[serde(default)]
+    pub merge_mode: bool,
+    /// Simulated key timing used when running without a keyboard, `None` for the default
+    #[serde(default)]
+    pub mock_config: Option<MockConfig>,
+    /// QMK `info.json` of the connected keyboard, needed to read its keymap over VIA
+    #[serde(default)]
+    pub via_info_json: Option<String>,
+    /// Packet layout of the raw HID firmware
+    #[serde(default)]
+    pub rawhid_format: RawHidFormat,
+    /// Named keymaps, see [`save_profile`]
+    #[serde(default)]
+    pub profiles: Vec<KeymapProfile>,
+    /// Key geometry of the keyboard view, set from the settings panel
+    #[serde(default = "default_key_size_px")]
+    pub key_size_px: f32,
+    #[serde(default = "default_key_spacing_px")]
+    pub key_spacing_px: f32,
+    #[serde(default = "default_border_radius_px")]
+    pub border_radius_px: f32,
+    /// Key label size relative to the body text
+    #[serde(default = "default_label_scale")]
+    pub label_scale: f32,
+    /// Scale of the keyboard view, from the zoom slider in the top bar
+    #[serde(default = "default_zoom")]
+    pub zoom: f32,
+}
+
+fn default_key_size_px() -> f32 {
+    56.0
+}
+
+fn default_key_spacing_px() -> f32 {
+    8.0
+}
+
+fn default_border_radius_px() -> f32 {
+    6.0
+}
+
+fn default_label_scale() -> f32 {
+    1.15
+}
+
+fn default_zoom() -> f32 {
+    1.0
 }
 
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            config_version: CONFIG_VERSION,
+            last_keymap_path: None,
+            last_keymap_source_path: None,
+            show_physical_layout: false,
+            show_split: false,
+            show_effective_keys: false,
+            pinned_layers: Vec::new(),
+            theme: None,
+            recent_files: VecDeque::new(),
+            keyboard_locale: None,
+            merge_mode: false,
+            mock_config: None,
+            via_info_json: None,
+            rawhid_format: RawHidFormat::default(),
+            profiles: Vec::new(),
+            key_size_px: default_key_size_px(),
+            key_spacing_px: default_key_spacing_px(),
+            border_radius_px: default_border_radius_px(),
+            label_scale: default_label_scale(),
+            zoom: default_zoom(),
+        }
+    }
+}
+
+/// Version 0 files predate `config_version`, `theme` and `recent_files`
+fn migrate_v0_to_v1(mut v: Value) -> Value {
+    if let Some(obj) = v.as_object_mut() {
+        obj.entry("last_keymap_path").or_insert(Value::Null);
+        obj.entry("show_physical_layout")
+            .or_insert(Value::Bool(false));
+        obj.entry("theme").or_insert(Value::Null);
+        obj.entry("recent_files")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        obj.insert("config_version".to_string(), Value::from(1));
+    }
+    v
+}
+
+/// Bring a stored config up to [`CONFIG_VERSION`]; files from newer versions are left as
+/// they are and unknown fields are ignored when deserializing
+fn migrate_config(mut v: Value) -> Value {
+    let version = v.get("config_version").and_then(Value::as_u64).unwrap_or(0);
+    if version < 1 {
+        v = migrate_v0_to_v1(v);
+    }
+    v
+}
+
+/// `$<var>/qmk_viewer` when the XDG variable is set to an absolute path, otherwise the
+/// platform default from `dirs`; the directory is created if it doesn't exist
+fn app_dir(var: &str, platform_default: Option<PathBuf>) -> Result<PathBuf> {
+    let base = std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or(platform_default)
+        .ok_or_else(|| anyhow::anyhow!("Could not find {} directory", var))?;
+    let dir = base.join("qmk_viewer");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Settings (`config.json`): `$XDG_CONFIG_HOME/qmk_viewer`, `~/.config/qmk_viewer` on Linux
 pub fn get_config_dir() -> Result<PathBuf> {
-    let config_dir = dirs::config_dir()
-        .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?
-        .join("qmk_viewer");
+    app_dir("XDG_CONFIG_HOME", dirs::config_dir())
+}
 
-    // Create the directory if it doesn't exist
-    fs::create_dir_all(&config_dir)?;
+/// Larger persistent data such as saved keymaps: `$XDG_DATA_HOME/qmk_viewer`,
+/// `~/.local/share/qmk_viewer` on Linux
+pub fn get_data_dir() -> Result<PathBuf> {
+    app_dir("XDG_DATA_HOME", dirs::data_dir())
+}
 
-    Ok(config_dir)
+/// Disposable files such as parse caches: `$XDG_CACHE_HOME/qmk_viewer`,
+/// `~/.cache/qmk_viewer` on Linux
+pub fn get_cache_dir() -> Result<PathBuf> {
+    app_dir("XDG_CACHE_HOME", dirs::cache_dir())
 }
 
 pub fn get_config_path() -> Result<PathBuf> {
@@ -28,7 +190,8 @@ pub fn load_app_config() -> Result<AppConfig> {
 
     if config_path.exists() {
         let content = fs::read_to_string(&config_path)?;
-        let config: AppConfig = serde_json::from_str(&content)?;
+        let value: Value = serde_json::from_str(&content)?;
+        let config: AppConfig = serde_json::from_value(migrate_config(value))?;
         Ok(config)
     } else {
         Ok(AppConfig::default())
@@ -42,58 +205,333 @@ pub fn save_app_config(config: &AppConfig) -> Result<()> {
     Ok(())
 }
 
+/// Names of the saved keymap copy, in order of preference
+const SAVED_KEYMAP_FILES: [&str; 3] = ["last_keymap.json", "last_keymap.c", "last_keymap.h"];
+
+/// Saved keymap copy found by [`get_saved_keymap_path`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedKeymapInfo {
+    pub cached_path: String,
+    /// File the copy was made from, `None` for copies saved by older versions
+    pub source_path: Option<String>,
+}
+
+/// Copy `source_path` to the data dir and remember it as the copy's source
 pub fn save_keymap_file(source_path: &str) -> Result<String> {
-    let config_dir = get_config_dir()?;
+    let data_dir = get_data_dir()?;
     let content = fs::read_to_string(source_path)?;
 
     // Determine file extension
-    let source_path = Path::new(source_path);
-    let extension = source_path
+    let source = Path::new(source_path);
+    let extension = source
         .extension()
         .and_then(|ext| ext.to_str())
         .unwrap_or("json");
 
     let saved_filename = format!("last_keymap.{}", extension);
-    let saved_path = config_dir.join(&saved_filename);
+    let saved_path = data_dir.join(&saved_filename);
 
     // Write the content to the saved file
     fs::write(&saved_path, content)?;
+    set_saved_keymap_source(Some(source_path))?;
 
     // Return the path as string
     Ok(saved_path.to_string_lossy().to_string())
 }
 
-pub fn clear_saved_keymap() -> Result<()> {
-    let config_dir = get_config_dir()?;
-
-    // Remove any saved keymap files
-    let keymap_files = ["last_keymap.json", "last_keymap.c", "last_keymap.h"];
-    for filename in &keymap_files {
-        let path = config_dir.join(filename);
+/// Save an edited keymap as the saved keymap copy, replacing any saved .c/.h copy so the
+/// edit is what gets restored on the next start
+///
+/// `source_path` is remembered as the copy's source like in [`save_keymap_file`]; `None`
+/// forgets the previous one, which no longer describes the copy.
+pub fn save_keymap_json(json: &str, source_path: Option<&str>) -> Result<String> {
+    let data_dir = get_data_dir()?;
+    for filename in &SAVED_KEYMAP_FILES[1..] {
+        let path = data_dir.join(filename);
         if path.exists() {
             fs::remove_file(&path)?;
         }
     }
+    let saved_path = data_dir.join(SAVED_KEYMAP_FILES[0]);
+    fs::write(&saved_path, json)?;
+    set_saved_keymap_source(source_path)?;
+    Ok(saved_path.to_string_lossy().to_string())
+}
+
+/// Store the source of the saved keymap copy as an absolute path, so it still names the
+/// same file when the app is started from another directory; sources that are not files
+/// (URLs) are kept as they are
+fn set_saved_keymap_source(source_path: Option<&str>) -> Result<()> {
+    let source = source_path.map(|path| {
+        fs::canonicalize(path)
+            .map_or_else(|_| path.to_string(), |p| p.to_string_lossy().to_string())
+    });
+    let mut config = load_app_config()?;
+    config.last_keymap_source_path = source;
+    save_app_config(&config)
+}
+
+pub fn clear_saved_keymap() -> Result<()> {
+    // Remove any saved keymap files, including copies left in the config dir by older versions
+    for dir in [get_data_dir()?, get_config_dir()?] {
+        for filename in &SAVED_KEYMAP_FILES {
+            let path = dir.join(filename);
+            if path.exists() {
+                fs::remove_file(&path)?;
+            }
+        }
+    }
 
     // Clear the config
     let mut config = load_app_config()?;
     config.last_keymap_path = None;
+    config.last_keymap_source_path = None;
     save_app_config(&config)?;
 
     Ok(())
 }
 
-pub fn get_saved_keymap_path() -> Result<Option<String>> {
-    let config_dir = get_config_dir()?;
-
-    // Check for saved keymap files in order of preference
-    let keymap_files = ["last_keymap.json", "last_keymap.c", "last_keymap.h"];
-    for filename in &keymap_files {
-        let path = config_dir.join(filename);
-        if path.exists() {
-            return Ok(Some(path.to_string_lossy().to_string()));
+pub fn get_saved_keymap_path() -> Result<Option<SavedKeymapInfo>> {
+    // Check for saved keymap files in order of preference; older versions kept them in the
+    // config dir
+    for dir in [get_data_dir()?, get_config_dir()?] {
+        for filename in &SAVED_KEYMAP_FILES {
+            let path = dir.join(filename);
+            if path.exists() {
+                return Ok(Some(SavedKeymapInfo {
+                    cached_path: path.to_string_lossy().to_string(),
+                    source_path: load_app_config()?.last_keymap_source_path,
+                }));
+            }
         }
     }
 
     Ok(None)
 }
+
+/// Name of the profile backed by the saved keymap copy ([`save_keymap_file`])
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Profile copies: `{config_dir}/profiles/{name}.{ext}`
+fn get_profiles_dir() -> Result<PathBuf> {
+    let dir = get_config_dir()?.join("profiles");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Profile names become file names, so only letters, digits, spaces, `-` and `_` are allowed
+fn validate_profile_name(name: &str) -> Result<&str> {
+    let name = name.trim();
+    if name.is_empty() {
+        anyhow::bail!("Profile name is empty");
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+    {
+        anyhow::bail!(
+            "Invalid profile name {:?}: use letters, digits, spaces, '-' and '_'",
+            name
+        );
+    }
+    Ok(name)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Copy `source_path` into the profiles dir under `name`, replacing a profile of the same
+/// name; saving as [`DEFAULT_PROFILE`] is the same as [`save_keymap_file`]
+pub fn save_profile(name: &str, source_path: &str) -> Result<String> {
+    let name = validate_profile_name(name)?;
+    if name == DEFAULT_PROFILE {
+        return save_keymap_file(source_path);
+    }
+    let content = fs::read_to_string(source_path)?;
+    let extension = Path::new(source_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("json");
+    let saved_path = get_profiles_dir()?.join(format!("{}.{}", name, extension));
+
+    let mut config = load_app_config()?;
+    // A copy with another extension would be left behind when a .c profile is saved as .json
+    if let Some(old) = config.profiles.iter().find(|p| p.name == name) {
+        if Path::new(&old.path) != saved_path {
+            let _ = fs::remove_file(&old.path);
+        }
+    }
+    fs::write(&saved_path, content)?;
+
+    let saved_path = saved_path.to_string_lossy().to_string();
+    config.profiles.retain(|p| p.name != name);
+    config.profiles.push(KeymapProfile {
+        name: name.to_string(),
+        path: saved_path.clone(),
+        last_used: unix_now(),
+    });
+    save_app_config(&config)?;
+    Ok(saved_path)
+}
+
+/// Saved profiles, most recently used first; the saved keymap copy is listed as
+/// [`DEFAULT_PROFILE`] when there is one
+pub fn list_profiles() -> Result<Vec<KeymapProfile>> {
+    let mut profiles = load_app_config()?.profiles;
+    profiles.sort_by_key(|p| std::cmp::Reverse(p.last_used));
+    if let Some(saved) = get_saved_keymap_path()? {
+        let last_used = fs::metadata(&saved.cached_path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs());
+        profiles.insert(
+            0,
+            KeymapProfile {
+                name: DEFAULT_PROFILE.to_string(),
+                path: saved.cached_path,
+                last_used,
+            },
+        );
+    }
+    Ok(profiles)
+}
+
+/// Path of the stored copy of profile `name`, marking the profile as used
+pub fn load_profile(name: &str) -> Result<String> {
+    if name == DEFAULT_PROFILE {
+        return get_saved_keymap_path()?
+            .map(|saved| saved.cached_path)
+            .ok_or_else(|| anyhow::anyhow!("No keymap is saved"));
+    }
+    let mut config = load_app_config()?;
+    let profile = config
+        .profiles
+        .iter_mut()
+        .find(|p| p.name == name)
+        .ok_or_else(|| anyhow::anyhow!("No profile named {:?}", name))?;
+    if !Path::new(&profile.path).exists() {
+        anyhow::bail!("Profile {:?} is missing its file {}", name, profile.path);
+    }
+    profile.last_used = unix_now();
+    let path = profile.path.clone();
+    save_app_config(&config)?;
+    Ok(path)
+}
+
+/// Remove profile `name` and its stored copy; deleting [`DEFAULT_PROFILE`] clears the
+/// saved keymap
+pub fn delete_profile(name: &str) -> Result<()> {
+    if name == DEFAULT_PROFILE {
+        return clear_saved_keymap();
+    }
+    let mut config = load_app_config()?;
+    let idx = config
+        .profiles
+        .iter()
+        .position(|p| p.name == name)
+        .ok_or_else(|| anyhow::anyhow!("No profile named {:?}", name))?;
+    let profile = config.profiles.remove(idx);
+    if Path::new(&profile.path).exists() {
+        fs::remove_file(&profile.path)?;
+    }
+    save_app_config(&config)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_show_physical_layout_round_trip() {
+        let config = AppConfig {
+            last_keymap_path: Some("/tmp/keymap.c".to_string()),
+            show_physical_layout: true,
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        let back: AppConfig = serde_json::from_str(&json).unwrap();
+        assert!(back.show_physical_layout);
+        assert_eq!(back.last_keymap_path.as_deref(), Some("/tmp/keymap.c"));
+
+        // Configs written before the field existed default to the grid view
+        let old: AppConfig = serde_json::from_str(r#"{"last_keymap_path":null}"#).unwrap();
+        assert!(!old.show_physical_layout);
+    }
+
+    #[test]
+    fn test_pinned_layers_round_trip() {
+        let config = AppConfig {
+            pinned_layers: vec![2, 0],
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&config).unwrap();
+        let back: AppConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.pinned_layers, vec![2, 0]);
+
+        let old: AppConfig = serde_json::from_str(r#"{"last_keymap_path":null}"#).unwrap();
+        assert!(old.pinned_layers.is_empty());
+    }
+
+    #[test]
+    fn test_key_geometry_round_trip() {
+        let config = AppConfig {
+            key_size_px: 40.0,
+            label_scale: 1.5,
+            ..Default::default()
+        };
+        let back: AppConfig =
+            serde_json::from_str(&serde_json::to_string(&config).unwrap()).unwrap();
+        assert_eq!(back.key_size_px, 40.0);
+        assert_eq!(back.label_scale, 1.5);
+
+        // Older configs get the default geometry
+        let old: AppConfig = serde_json::from_str(r#"{"last_keymap_path":null}"#).unwrap();
+        assert_eq!(old.key_size_px, 56.0);
+        assert_eq!(old.key_spacing_px, 8.0);
+        assert_eq!(old.border_radius_px, 6.0);
+        assert_eq!(old.zoom, 1.0);
+    }
+
+    #[test]
+    fn test_validate_profile_name() {
+        assert_eq!(
+            validate_profile_name(" Work laptop ").unwrap(),
+            "Work laptop"
+        );
+        assert_eq!(validate_profile_name("gaming_v2-b").unwrap(), "gaming_v2-b");
+        for bad in ["", "   ", "../config", "a/b", "a\\b", "x.json"] {
+            assert!(validate_profile_name(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_migrate_v0_adds_new_fields() {
+        let v0 = serde_json::json!({ "last_keymap_path": "/tmp/keymap.c" });
+        let migrated = migrate_config(v0);
+        assert_eq!(migrated["theme"], Value::Null);
+        assert_eq!(migrated["config_version"], CONFIG_VERSION);
+
+        let config: AppConfig = serde_json::from_value(migrated).unwrap();
+        assert!(config.recent_files.is_empty());
+        assert_eq!(config.last_keymap_path.as_deref(), Some("/tmp/keymap.c"));
+        assert_eq!(config.config_version, CONFIG_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_keeps_current_config() {
+        let config = AppConfig {
+            theme: Some("Nord".to_string()),
+            recent_files: VecDeque::from(["/tmp/a.json".to_string()]),
+            ..Default::default()
+        };
+        let value = serde_json::to_value(&config).unwrap();
+        let back: AppConfig = serde_json::from_value(migrate_config(value)).unwrap();
+        assert_eq!(back.theme.as_deref(), Some("Nord"));
+        assert_eq!(back.recent_files, ["/tmp/a.json"]);
+    }
+}
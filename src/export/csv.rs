@@ -0,0 +1,102 @@
+//! CSV export of every key of every layer, one record per (layer, key)
+
+use super::layer_name;
+use crate::keyboard::KeyboardState;
+use crate::keycodes::keycode_category;
+
+const HEADER: [&str; 9] = [
+    "layer_index",
+    "layer_name",
+    "row",
+    "col",
+    "key_index",
+    "raw_token",
+    "translated_label",
+    "is_transparent",
+    "key_type",
+];
+
+/// Render the keymap as RFC 4180 CSV with a header row
+pub fn export_csv(state: &KeyboardState) -> String {
+    let mut csv = String::new();
+    push_record(&mut csv, &HEADER.map(String::from));
+    let (rows, cols) = (state.keyboard.rows, state.keyboard.cols);
+    for layer in 0..state.keyboard.raw_legends.len() {
+        let name = layer_name(state, layer);
+        for row in 0..rows {
+            for col in 0..cols {
+                let raw = state.raw_legend_at(layer, row, col).unwrap_or_default();
+                push_record(
+                    &mut csv,
+                    &[
+                        layer.to_string(),
+                        name.clone(),
+                        row.to_string(),
+                        col.to_string(),
+                        (row * cols + col).to_string(),
+                        raw.to_string(),
+                        state
+                            .legend_at(layer, row, col)
+                            .unwrap_or_default()
+                            .to_string(),
+                        state.is_transparent_key(layer, row, col).to_string(),
+                        keycode_category(raw).name().to_string(),
+                    ],
+                );
+            }
+        }
+    }
+    csv
+}
+
+fn push_record(csv: &mut String, fields: &[String]) {
+    let fields: Vec<String> = fields.iter().map(|f| escape_field(f)).collect();
+    csv.push_str(&fields.join(","));
+    csv.push_str("\r\n");
+}
+
+/// Quote a field when it contains a separator, quote or line break
+fn escape_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::KeyboardLayout;
+
+    #[test]
+    fn test_export_csv_one_record_per_key() {
+        let layers = vec![
+            vec!["KC_A".to_string(); 12],
+            vec!["MT(MOD_LSFT, KC_B)".to_string(); 12],
+            vec!["KC_TRNS".to_string(); 12],
+        ];
+        let mut layout = KeyboardLayout::from_layout_data(layers, None);
+        layout.layer_names[1] = "Lower, \"sym\"".to_string();
+        let key_count = layout.rows * layout.cols;
+        let state = KeyboardState::new(layout);
+
+        let output = export_csv(&state);
+        let mut reader = ::csv::Reader::from_reader(output.as_bytes());
+        assert_eq!(reader.headers().unwrap().len(), HEADER.len());
+        let records: Vec<::csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 3 * key_count);
+        for record in &records {
+            assert_eq!(record.len(), HEADER.len());
+            if &record[7] == "false" {
+                assert!(!record[5].is_empty());
+            }
+        }
+        // Fields with commas and quotes survive the round trip
+        assert_eq!(&records[key_count][1], "Lower, \"sym\"");
+        assert_eq!(&records[key_count][5], "MT(MOD_LSFT, KC_B)");
+        assert_eq!(&records[key_count][8], "mod_tap");
+        assert_eq!(&records[0][8], "letter");
+        assert_eq!(&records[2 * key_count][7], "true");
+    }
+}
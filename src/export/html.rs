@@ -0,0 +1,170 @@
+//! Self-contained HTML export of every layer of a keymap
+
+use super::svg::render_layer_svg;
+use super::{escape_xml, layer_name, Theme};
+use crate::keyboard::KeyboardState;
+
+/// Render the full keymap as a single HTML page with inline CSS, one `<svg>` per layer
+/// and a small script to switch between layers.
+pub fn export_html(state: &KeyboardState, theme: &Theme) -> String {
+    let layer_count = state.keyboard.legends.len();
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>QMK Keyboard Viewer - Keymap</title>\n<style>\n");
+    html.push_str(&format!(
+        "body {{ background: {}; color: {}; font-family: sans-serif; margin: 24px; }}\n",
+        theme.background, theme.sub_text
+    ));
+    html.push_str(&format!(
+        ".tabs button {{ background: {}; color: {}; border: 1px solid {}; border-radius: 6px; padding: 6px 12px; margin-right: 6px; cursor: pointer; }}\n",
+        theme.key, theme.text, theme.sub_text
+    ));
+    html.push_str(&format!(
+        ".tabs button.active {{ border-color: {}; }}\n",
+        theme.layer_switch
+    ));
+    html.push_str(".layer { display: none; margin-top: 16px; }\n");
+    html.push_str(".layer.active { display: block; }\n");
+    html.push_str("</style>\n</head>\n<body>\n<div class=\"tabs\">\n");
+
+    for layer in 0..layer_count {
+        html.push_str(&format!(
+            "<button data-layer=\"{}\" onclick=\"showLayer({})\"{}>{}</button>\n",
+            layer,
+            layer,
+            if layer == 0 { " class=\"active\"" } else { "" },
+            escape_xml(&layer_name(state, layer))
+        ));
+    }
+    html.push_str("</div>\n");
+
+    for layer in 0..layer_count {
+        html.push_str(&format!(
+            "<div class=\"layer{}\" id=\"layer-{}\">\n",
+            if layer == 0 { " active" } else { "" },
+            layer
+        ));
+        html.push_str(&render_layer_svg(state, layer, theme));
+        html.push_str("</div>\n");
+    }
+
+    html.push_str(
+        "<script>\n\
+function showLayer(n) {\n\
+  document.querySelectorAll('.layer').forEach(function (el) {\n\
+    el.classList.toggle('active', el.id === 'layer-' + n);\n\
+  });\n\
+  document.querySelectorAll('.tabs button').forEach(function (el) {\n\
+    el.classList.toggle('active', el.dataset.layer === String(n));\n\
+  });\n\
+}\n\
+</script>\n</body>\n</html>\n",
+    );
+    html
+}
+
+/// Render a one-page report: keyboard name and source, one `<section>` per layer and a
+/// legend table explaining the key border colors
+pub fn export_report(
+    state: &KeyboardState,
+    theme: &Theme,
+    keyboard_name: &str,
+    source: Option<&str>,
+) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!(
+        "<title>{} - QMK Keyboard Viewer Report</title>\n<style>\n",
+        escape_xml(keyboard_name)
+    ));
+    html.push_str(&format!(
+        "body {{ background: {}; color: {}; font-family: sans-serif; margin: 24px; }}\n",
+        theme.background, theme.sub_text
+    ));
+    html.push_str(&format!(
+        "h1, h2 {{ color: {}; }}\nsection {{ margin-bottom: 32px; }}\n",
+        theme.text
+    ));
+    html.push_str(&format!(
+        "table {{ border-collapse: collapse; }}\ntd, th {{ border: 1px solid {}; padding: 4px 10px; text-align: left; }}\n",
+        theme.key
+    ));
+    html.push_str(".swatch { display: inline-block; width: 14px; height: 14px; border: 2px solid; border-radius: 3px; }\n");
+    html.push_str("</style>\n</head>\n<body>\n");
+
+    html.push_str(&format!("<h1>{}</h1>\n", escape_xml(keyboard_name)));
+    if let Some(source) = source {
+        html.push_str(&format!(
+            "<p>Source: <code>{}</code></p>\n",
+            escape_xml(source)
+        ));
+    }
+
+    for layer in 0..state.keyboard.legends.len() {
+        html.push_str(&format!(
+            "<section id=\"layer-{}\">\n<h2>Layer {}: {}</h2>\n",
+            layer,
+            layer,
+            escape_xml(&layer_name(state, layer))
+        ));
+        html.push_str(&render_layer_svg(state, layer, theme));
+        html.push_str("</section>\n");
+    }
+
+    html.push_str("<h2>Legend</h2>\n<table>\n<tr><th>Border</th><th>Key type</th></tr>\n");
+    for (color, title) in [
+        (&theme.mod_tap, "MT(mod, key) - mod-tap"),
+        (&theme.layer_tap, "LT(layer, key) - layer-tap"),
+        (&theme.layer_switch, "OSL ★ / TO / DF(layer) - layer switch"),
+        (&theme.sub_text, "Other function keys"),
+    ] {
+        html.push_str(&format!(
+            "<tr><td><span class=\"swatch\" style=\"border-color: {}\"></span></td><td>{}</td></tr>\n",
+            color,
+            escape_xml(title)
+        ));
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::KeyboardLayout;
+
+    #[test]
+    fn test_export_html_one_svg_per_layer() {
+        let layers = vec![
+            vec!["KC_A".to_string(); 12],
+            vec!["MT(MOD_LSFT, KC_B)".to_string(); 12],
+            vec!["KC_TRNS".to_string(); 12],
+        ];
+        let mut layout = KeyboardLayout::from_layout_data(layers, None);
+        let total_keys = layout.rows * layout.cols;
+        layout.layer_names[1] = "Lower <1>".to_string();
+        let state = KeyboardState::new(layout);
+
+        let html = export_html(&state, &Theme::default());
+        let svg_re = regex::Regex::new(r"<svg\b").unwrap();
+        let rect_re = regex::Regex::new(r"<rect\b").unwrap();
+        assert_eq!(svg_re.find_iter(&html).count(), 3);
+        assert_eq!(rect_re.find_iter(&html).count(), 3 * total_keys);
+        assert!(html.contains("Lower &lt;1&gt;"));
+        assert!(html.contains("function showLayer"));
+        assert!(html.contains(&Theme::default().mod_tap));
+    }
+
+    #[test]
+    fn test_export_report_one_section_per_layer() {
+        let layers = vec![vec!["KC_A".to_string(); 12], vec!["KC_1".to_string(); 12]];
+        let state = KeyboardState::new(KeyboardLayout::from_layout_data(layers, None));
+
+        let html = export_report(&state, &Theme::default(), "thooams", Some("keymap.c"));
+        let section_re = regex::Regex::new(r"<section\b").unwrap();
+        assert_eq!(section_re.find_iter(&html).count(), 2);
+        assert!(html.contains("<title>thooams - "));
+        assert!(html.contains("Layer 1: Layer 1"));
+        assert!(html.contains("keymap.c"));
+    }
+}
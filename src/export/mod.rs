@@ -0,0 +1,67 @@
+//! Export of keyboard layouts to shareable file formats
+
+pub mod csv;
+pub mod html;
+pub mod png;
+pub mod svg;
+
+use crate::keyboard::KeyboardState;
+
+/// Color theme used by exporters, as CSS hex colors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub background: String,
+    pub key: String,
+    pub text: String,
+    pub sub_text: String,
+    pub mod_tap: String,
+    pub layer_tap: String,
+    pub layer_switch: String,
+}
+
+impl Theme {
+    /// Catppuccin Mocha, matching the viewer palette
+    pub fn catppuccin_mocha() -> Self {
+        Self {
+            background: "#1e1e2e".to_string(),
+            key: "#313141".to_string(),
+            text: "#ffffff".to_string(),
+            sub_text: "#c6d0f5".to_string(),
+            mod_tap: "#fab387".to_string(),
+            layer_tap: "#89b4fa".to_string(),
+            layer_switch: "#f9e2af".to_string(),
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::catppuccin_mocha()
+    }
+}
+
+/// Name of `layer`, or "Layer N" when the layout has no name for it
+pub(crate) fn layer_name(state: &KeyboardState, layer: usize) -> String {
+    state
+        .keyboard
+        .layer_names
+        .get(layer)
+        .cloned()
+        .unwrap_or_else(|| format!("Layer {}", layer))
+}
+
+/// Escape text for inclusion in HTML/SVG markup
+pub(crate) fn escape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            other => out.push(other),
+        }
+    }
+    out
+}
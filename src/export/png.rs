@@ -0,0 +1,330 @@
+//! PNG rendering of a single keymap layer
+//!
+//! The layer is painted with egui in an off-screen context, tessellated, and the resulting
+//! meshes are rasterized in software so no window or GPU is needed.
+
+use super::svg::{
+    key_border, key_boxes, legend_entries, KEY_GAP, LEGEND_ENTRY_WIDTH, LEGEND_HEIGHT,
+};
+use super::Theme;
+use crate::keyboard::KeyboardState;
+use anyhow::Context as _;
+use egui::epaint::{ImageData, ImageDelta, Mesh, Primitive};
+use egui::{Align2, Color32, FontId, Pos2, Rect, Stroke, TextureId, Vec2};
+use image::codecs::png::PngEncoder;
+use image::ImageEncoder;
+use std::collections::HashMap;
+
+/// Render `layer` at `zoom` (1.0 = one key is 56 px) and encode it as PNG bytes,
+/// with a legend of the key border colors below the keys when `legend` is set
+pub fn export_png(
+    state: &KeyboardState,
+    layer: usize,
+    zoom: f32,
+    legend: bool,
+) -> anyhow::Result<Vec<u8>> {
+    anyhow::ensure!(
+        zoom.is_finite() && zoom > 0.0,
+        "invalid zoom factor {}",
+        zoom
+    );
+    let theme = Theme::default();
+    let (boxes, width, keys_height) = key_boxes(state);
+    let height = if legend {
+        keys_height + LEGEND_HEIGHT
+    } else {
+        keys_height
+    };
+    let screen = Rect::from_min_size(Pos2::ZERO, Vec2::new(width, height));
+
+    let ctx = egui::Context::default();
+    let mut input = egui::RawInput {
+        screen_rect: Some(screen),
+        ..Default::default()
+    };
+    input
+        .viewports
+        .entry(egui::ViewportId::ROOT)
+        .or_default()
+        .native_pixels_per_point = Some(zoom);
+
+    let output = ctx.run(input, |ctx| {
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        painter.rect_filled(screen, 0.0, hex_color(&theme.background));
+        for key in &boxes {
+            let (r, c) = (key.row, key.col);
+            let rect = Rect::from_min_size(Pos2::new(key.x, key.y), Vec2::new(key.w, key.h));
+            let border = key_border(state, layer, r, c, &theme).map(hex_color);
+            let fill = if state.is_transparent_key(layer, r, c) {
+                Color32::TRANSPARENT
+            } else {
+                hex_color(&theme.key)
+            };
+            let stroke = border.map_or(Stroke::NONE, |color| Stroke::new(1.2, color));
+            painter.rect(rect, 6.0, fill, stroke);
+
+            let (main, sub) = state.display_parts(layer, r, c);
+            let center = rect.center();
+            if !main.is_empty() {
+                painter.text(
+                    center,
+                    Align2::CENTER_CENTER,
+                    main,
+                    FontId::proportional(16.0),
+                    hex_color(&theme.text),
+                );
+            }
+            if !sub.is_empty() {
+                painter.text(
+                    center + Vec2::new(0.0, 16.0),
+                    Align2::CENTER_CENTER,
+                    sub,
+                    FontId::proportional(11.0),
+                    border.unwrap_or_else(|| hex_color(&theme.sub_text)),
+                );
+            }
+        }
+        if legend {
+            for (i, (color, label)) in legend_entries(&theme).iter().enumerate() {
+                let x = KEY_GAP + i as f32 * LEGEND_ENTRY_WIDTH;
+                let swatch =
+                    Rect::from_min_size(Pos2::new(x, keys_height + 4.0), Vec2::splat(16.0));
+                painter.rect_stroke(swatch, 4.0, Stroke::new(2.0, hex_color(color)));
+                painter.text(
+                    Pos2::new(x + 24.0, swatch.center().y),
+                    Align2::LEFT_CENTER,
+                    *label,
+                    FontId::proportional(12.0),
+                    hex_color(&theme.sub_text),
+                );
+            }
+        }
+    });
+
+    let pixels_per_point = output.pixels_per_point;
+    let mut canvas = Canvas::new(
+        (width * pixels_per_point).ceil() as usize,
+        (height * pixels_per_point).ceil() as usize,
+    );
+    for (id, delta) in &output.textures_delta.set {
+        canvas.set_texture(*id, delta);
+    }
+    for clipped in ctx.tessellate(output.shapes, pixels_per_point) {
+        if let Primitive::Mesh(mesh) = &clipped.primitive {
+            canvas.draw_mesh(mesh, clipped.clip_rect, pixels_per_point);
+        }
+    }
+    canvas.encode()
+}
+
+/// Parse a `#rrggbb` theme color, falling back to magenta so typos stand out
+fn hex_color(hex: &str) -> Color32 {
+    let digits = hex.trim_start_matches('#');
+    let channel = |i: usize| {
+        digits
+            .get(i..i + 2)
+            .and_then(|s| u8::from_str_radix(s, 16).ok())
+    };
+    match (digits.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Color32::from_rgb(r, g, b),
+        _ => Color32::from_rgb(255, 0, 255),
+    }
+}
+
+/// An egui texture uploaded to the software rasterizer
+struct Texture {
+    size: [usize; 2],
+    pixels: Vec<Color32>,
+}
+
+impl Texture {
+    /// Nearest texel at normalized `uv`; glyphs are rasterized at the output scale so
+    /// filtering isn't needed
+    fn sample(&self, uv: Pos2) -> Color32 {
+        let [w, h] = self.size;
+        if w == 0 || h == 0 {
+            return Color32::WHITE;
+        }
+        let x = ((uv.x * w as f32) as usize).min(w - 1);
+        let y = ((uv.y * h as f32) as usize).min(h - 1);
+        self.pixels[y * w + x]
+    }
+}
+
+/// Premultiplied RGBA target that egui meshes are blended onto
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 4]>,
+    textures: HashMap<TextureId, Texture>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![[0.0; 4]; width * height],
+            textures: HashMap::new(),
+        }
+    }
+
+    /// Apply a texture upload, either a whole image or a patch at `delta.pos`
+    fn set_texture(&mut self, id: TextureId, delta: &ImageDelta) {
+        let (size, pixels): ([usize; 2], Vec<Color32>) = match &delta.image {
+            ImageData::Color(image) => (image.size, image.pixels.clone()),
+            ImageData::Font(font) => (font.size, font.srgba_pixels(None).collect()),
+        };
+        match (delta.pos, self.textures.get_mut(&id)) {
+            (Some([x0, y0]), Some(texture)) => {
+                let [tw, th] = texture.size;
+                for y in 0..size[1].min(th.saturating_sub(y0)) {
+                    for x in 0..size[0].min(tw.saturating_sub(x0)) {
+                        texture.pixels[(y0 + y) * tw + x0 + x] = pixels[y * size[0] + x];
+                    }
+                }
+            }
+            _ => {
+                self.textures.insert(id, Texture { size, pixels });
+            }
+        }
+    }
+
+    fn draw_mesh(&mut self, mesh: &Mesh, clip_rect: Rect, pixels_per_point: f32) {
+        let clip = Rect::from_min_max(
+            (clip_rect.min.to_vec2() * pixels_per_point).to_pos2(),
+            (clip_rect.max.to_vec2() * pixels_per_point).to_pos2(),
+        );
+        let texture = self.textures.remove(&mesh.texture_id);
+        for tri in mesh.indices.chunks_exact(3) {
+            let v = [
+                &mesh.vertices[tri[0] as usize],
+                &mesh.vertices[tri[1] as usize],
+                &mesh.vertices[tri[2] as usize],
+            ];
+            let p = v.map(|v| (v.pos.to_vec2() * pixels_per_point).to_pos2());
+            let colors = v.map(|v| v.color.to_array());
+            let area = edge(p[0], p[1], p[2]);
+            if area.abs() < f32::EPSILON {
+                continue;
+            }
+            // Bounding box of the triangle, limited to the clip rect and the canvas
+            let bounds = Rect::from_points(&p).intersect(clip);
+            let x0 = bounds.min.x.floor().max(0.0) as usize;
+            let y0 = bounds.min.y.floor().max(0.0) as usize;
+            let x1 = (bounds.max.x.ceil().max(0.0) as usize).min(self.width);
+            let y1 = (bounds.max.y.ceil().max(0.0) as usize).min(self.height);
+
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let pt = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                    let w0 = edge(p[1], p[2], pt) / area;
+                    let w1 = edge(p[2], p[0], pt) / area;
+                    let w2 = 1.0 - w0 - w1;
+                    if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                        continue;
+                    }
+                    let uv = Pos2::new(
+                        w0 * v[0].uv.x + w1 * v[1].uv.x + w2 * v[2].uv.x,
+                        w0 * v[0].uv.y + w1 * v[1].uv.y + w2 * v[2].uv.y,
+                    );
+                    let texel = texture
+                        .as_ref()
+                        .map_or(Color32::WHITE, |t| t.sample(uv))
+                        .to_array();
+                    let mut src = [0.0; 4];
+                    for (i, channel) in src.iter_mut().enumerate() {
+                        let vertex = w0 * colors[0][i] as f32
+                            + w1 * colors[1][i] as f32
+                            + w2 * colors[2][i] as f32;
+                        *channel = vertex / 255.0 * texel[i] as f32 / 255.0;
+                    }
+                    let keep = 1.0 - src[3];
+                    let dst = &mut self.pixels[y * self.width + x];
+                    for (d, s) in dst.iter_mut().zip(src) {
+                        *d = s + *d * keep;
+                    }
+                }
+            }
+        }
+        if let Some(texture) = texture {
+            self.textures.insert(mesh.texture_id, texture);
+        }
+    }
+
+    fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        let mut rgba = Vec::with_capacity(self.pixels.len() * 4);
+        for [r, g, b, a] in &self.pixels {
+            let unmultiply = if *a > 0.0 { 1.0 / a } else { 0.0 };
+            for channel in [r * unmultiply, g * unmultiply, b * unmultiply, *a] {
+                rgba.push((channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+            }
+        }
+        let mut bytes = Vec::new();
+        PngEncoder::new(&mut bytes)
+            .write_image(
+                &rgba,
+                self.width as u32,
+                self.height as u32,
+                image::ColorType::Rgba8,
+            )
+            .context("failed to encode PNG")?;
+        Ok(bytes)
+    }
+}
+
+/// Twice the signed area of triangle `a`, `b`, `c`
+fn edge(a: Pos2, b: Pos2, c: Pos2) -> f32 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::KeyboardLayout;
+
+    fn two_key_state() -> KeyboardState {
+        let mut layout = KeyboardLayout::new(1, 2, vec!["Base".to_string()]);
+        layout.raw_legends[0] = vec!["KC_A".to_string(), "LT(1, KC_SPC)".to_string()];
+        KeyboardState::new(layout)
+    }
+
+    #[test]
+    fn test_png_size_follows_zoom_and_legend() {
+        let state = two_key_state();
+        let (_, width, height) = key_boxes(&state);
+
+        let png = image::load_from_memory(&export_png(&state, 0, 1.0, false).unwrap()).unwrap();
+        assert_eq!(png.width(), width.ceil() as u32);
+        assert_eq!(png.height(), height.ceil() as u32);
+
+        let png = image::load_from_memory(&export_png(&state, 0, 2.0, true).unwrap()).unwrap();
+        assert_eq!(png.width(), (width * 2.0).ceil() as u32);
+        assert_eq!(png.height(), ((height + LEGEND_HEIGHT) * 2.0).ceil() as u32);
+    }
+
+    #[test]
+    fn test_png_paints_background_and_keys() {
+        let state = two_key_state();
+        let (boxes, _, _) = key_boxes(&state);
+        let png = image::load_from_memory(&export_png(&state, 0, 1.0, false).unwrap())
+            .unwrap()
+            .to_rgba8();
+        let theme = Theme::default();
+
+        // Gap left of the first key, away from the anti-aliased edges of the image
+        let gap = png.get_pixel(2, 2).0;
+        assert_eq!(gap, hex_color(&theme.background).to_array());
+        // Top middle of the first key: inside the fill, clear of the border and the label
+        let key = &boxes[0];
+        let inside = png
+            .get_pixel((key.x + key.w / 2.0) as u32, (key.y + 4.0) as u32)
+            .0;
+        assert_eq!(inside, hex_color(&theme.key).to_array());
+    }
+
+    #[test]
+    fn test_export_png_rejects_bad_zoom() {
+        assert!(export_png(&two_key_state(), 0, 0.0, false).is_err());
+    }
+}
@@ -0,0 +1,237 @@
+//! SVG rendering of a single keymap layer
+
+use super::{escape_xml, Theme};
+use crate::keyboard::{KeyPosition, KeyboardState};
+use anyhow::Context;
+
+const KEY_SIZE: f32 = 56.0;
+pub(super) const KEY_GAP: f32 = 8.0;
+/// Height of the legend row below the keys
+pub(super) const LEGEND_HEIGHT: f32 = 32.0;
+/// Horizontal space of one legend entry
+pub(super) const LEGEND_ENTRY_WIDTH: f32 = 180.0;
+
+/// Where one key is drawn, in pixels
+pub(super) struct KeyBox {
+    pub row: usize,
+    pub col: usize,
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+/// Key boxes laid out like the viewer: at the physical positions when the layout has them,
+/// otherwise on the grid with the split gap, plus the size of the whole board
+pub(super) fn key_boxes(state: &KeyboardState) -> (Vec<KeyBox>, f32, f32) {
+    let rows = state.keyboard.rows;
+    let cols = state.keyboard.cols;
+    let unit = KEY_SIZE + KEY_GAP;
+    if let Some(positions) = &state.keyboard.physical_positions {
+        let boxes: Vec<KeyBox> = positions
+            .iter()
+            .map(KeyPosition::placed)
+            .enumerate()
+            .take(rows * cols)
+            .map(|(idx, p)| KeyBox {
+                row: idx / cols,
+                col: idx % cols,
+                x: KEY_GAP + p.x * unit,
+                y: KEY_GAP + p.y * unit,
+                w: p.w * unit - KEY_GAP,
+                h: p.h * unit - KEY_GAP,
+            })
+            .collect();
+        let width = boxes.iter().map(|b| b.x + b.w).fold(0.0, f32::max) + KEY_GAP;
+        let height = boxes.iter().map(|b| b.y + b.h).fold(0.0, f32::max) + KEY_GAP;
+        return (boxes, width, height);
+    }
+
+    let gap_px = state
+        .keyboard
+        .split
+        .filter(|s| s.split_col > 0 && s.split_col < cols)
+        .map_or(0.0, |s| s.gap_px);
+    let boxes = (0..rows * cols)
+        .filter_map(|idx| {
+            let (row, col) = (idx / cols, idx % cols);
+            let (grid_col, right_half) = state.keyboard.grid_column(row, col)?;
+            let gap = if right_half { gap_px } else { 0.0 };
+            Some(KeyBox {
+                row,
+                col,
+                x: KEY_GAP + grid_col as f32 * unit + gap,
+                y: KEY_GAP + row as f32 * unit,
+                w: KEY_SIZE,
+                h: KEY_SIZE,
+            })
+        })
+        .collect();
+    let width = cols as f32 * unit + KEY_GAP + gap_px;
+    let height = rows as f32 * unit + KEY_GAP;
+    (boxes, width, height)
+}
+
+/// Border color of a key by its kind, `None` for plain keys
+pub(super) fn key_border<'a>(
+    state: &KeyboardState,
+    layer: usize,
+    row: usize,
+    col: usize,
+    theme: &'a Theme,
+) -> Option<&'a str> {
+    if state.is_mt_key(layer, row, col) {
+        Some(&theme.mod_tap)
+    } else if state.is_lt_key(layer, row, col) {
+        Some(&theme.layer_tap)
+    } else if state.is_osl_key(layer, row, col)
+        || state.is_to_key(layer, row, col)
+        || state.is_df_key(layer, row, col)
+    {
+        Some(&theme.layer_switch)
+    } else if state.is_function_key(layer, row, col) {
+        Some(&theme.sub_text)
+    } else {
+        None
+    }
+}
+
+/// Border color and label of each legend entry, matching the viewer's legend
+pub(super) fn legend_entries(theme: &Theme) -> [(&str, &'static str); 3] {
+    [
+        (&theme.mod_tap, "MT(mod, key)"),
+        (&theme.layer_tap, "LT(layer, key)"),
+        (&theme.layer_switch, "OSL / TO / DF(layer)"),
+    ]
+}
+
+/// Render one layer as an inline `<svg>` element with one `<rect>` per key
+pub fn render_layer_svg(state: &KeyboardState, layer: usize, theme: &Theme) -> String {
+    render_svg(state, layer, theme, false)
+}
+
+/// Write `layer` to `path` as a standalone SVG file with a legend of the key border colors
+pub fn export_svg(state: &KeyboardState, layer: usize, path: &str) -> anyhow::Result<()> {
+    let mut svg = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    svg.push_str(&render_svg(state, layer, &Theme::default(), true));
+    std::fs::write(path, svg).with_context(|| format!("failed to write {}", path))
+}
+
+fn render_svg(state: &KeyboardState, layer: usize, theme: &Theme, legend: bool) -> String {
+    let (boxes, width, keys_height) = key_boxes(state);
+    let height = if legend {
+        keys_height + LEGEND_HEIGHT
+    } else {
+        keys_height
+    };
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+        width, height, width, height
+    );
+    for key in boxes {
+        let (r, c) = (key.row, key.col);
+        let is_trns = state.is_transparent_key(layer, r, c);
+        let border = key_border(state, layer, r, c, theme).unwrap_or("none");
+        let fill = if is_trns { "none" } else { theme.key.as_str() };
+        svg.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"6\" fill=\"{}\" stroke=\"{}\" stroke-width=\"1.2\"/>\n",
+            key.x, key.y, key.w, key.h, fill, border
+        ));
+
+        let (main, sub) = state.display_parts(layer, r, c);
+        let cx = key.x + key.w / 2.0;
+        let cy = key.y + key.h / 2.0;
+        if !main.is_empty() {
+            svg.push_str(&format!(
+                "<text x=\"{}\" y=\"{}\" fill=\"{}\" font-size=\"16\" text-anchor=\"middle\" dominant-baseline=\"middle\">{}</text>\n",
+                cx,
+                cy,
+                theme.text,
+                escape_xml(&main)
+            ));
+        }
+        if !sub.is_empty() {
+            let color = if border == "none" {
+                theme.sub_text.as_str()
+            } else {
+                border
+            };
+            svg.push_str(&format!(
+                "<text x=\"{}\" y=\"{}\" fill=\"{}\" font-size=\"11\" text-anchor=\"middle\">{}</text>\n",
+                cx,
+                cy + 20.0,
+                color,
+                escape_xml(&sub)
+            ));
+        }
+    }
+    if legend {
+        svg.push_str(&legend_svg(theme, keys_height));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Legend row at `y`: a border swatch per key kind
+fn legend_svg(theme: &Theme, y: f32) -> String {
+    let mut svg = String::from("<g class=\"legend\">\n");
+    for (i, (color, label)) in legend_entries(theme).iter().enumerate() {
+        let x = KEY_GAP + i as f32 * LEGEND_ENTRY_WIDTH;
+        svg.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"16\" height=\"16\" rx=\"4\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>\n",
+            x,
+            y + 4.0,
+            color
+        ));
+        svg.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" fill=\"{}\" font-size=\"12\">{}</text>\n",
+            x + 24.0,
+            y + 16.0,
+            theme.sub_text,
+            escape_xml(label)
+        ));
+    }
+    svg.push_str("</g>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboard::KeyboardLayout;
+
+    #[test]
+    fn test_svg_uses_physical_positions() {
+        let mut layout = KeyboardLayout::new(1, 2, vec!["Base".to_string()]);
+        layout.raw_legends[0] = vec!["KC_A".to_string(), "KC_SPC".to_string()];
+        layout.physical_positions = Some(vec![
+            KeyPosition::new(0.0, 0.0, 1.0, 1.0),
+            KeyPosition::new(1.0, 0.0, 2.0, 1.0),
+        ]);
+        let state = KeyboardState::new(layout);
+        let svg = render_layer_svg(&state, 0, &Theme::default());
+        // Three key units wide, the spacebar twice as wide as a 1U key minus the gap
+        let unit = KEY_SIZE + KEY_GAP;
+        assert!(svg.contains(&format!("width=\"{}\"", 3.0 * unit + KEY_GAP)));
+        assert!(svg.contains(&format!("width=\"{}\"", 2.0 * unit - KEY_GAP)));
+        assert!(!svg.contains("class=\"legend\""));
+    }
+
+    #[test]
+    fn test_export_svg_writes_utf8_with_legend() {
+        let mut layout = KeyboardLayout::new(1, 2, vec!["Base".to_string()]);
+        layout.raw_legends[0] = vec!["KC_LEFT".to_string(), "LT(1, KC_SPC)".to_string()];
+        let state = KeyboardState::new(layout);
+        let path = std::env::temp_dir().join(format!("qmk_viewer_svg_{}.svg", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        export_svg(&state, 0, &path).unwrap();
+        let svg = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(svg.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+        assert!(svg.contains("class=\"legend\""));
+        assert!(svg.contains(&state.display_parts(0, 0, 0).0));
+        assert!(svg.contains(&Theme::default().layer_tap));
+    }
+}
@@ -1,17 +1,149 @@
+use crate::config::KeymapConfig;
+use crate::keycodes::ViaKeycodeTable;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::{BitAnd, BitOr, Not};
+use std::sync::mpsc::Sender;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Pressed state of up to [`PressedBits::CAPACITY`] keys, one bit per key index
+///
+/// Serialized as a single integer, so snapshots of boards with at most 64 keys look the
+/// same as when this was a `u64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub struct PressedBits(pub [u64; 2]);
+
+impl PressedBits {
+    pub const CAPACITY: usize = 128;
+
+    /// Whether key `idx` is pressed; indices past the capacity never are
+    pub fn is_set(&self, idx: usize) -> bool {
+        idx < Self::CAPACITY && (self.0[idx / 64] >> (idx % 64)) & 1 == 1
+    }
+
+    /// Mark key `idx` as pressed; indices past the capacity are ignored
+    pub fn set(&mut self, idx: usize) {
+        if idx < Self::CAPACITY {
+            self.0[idx / 64] |= 1u64 << (idx % 64);
+        }
+    }
+
+    /// Mark key `idx` as released
+    pub fn clear(&mut self, idx: usize) {
+        if idx < Self::CAPACITY {
+            self.0[idx / 64] &= !(1u64 << (idx % 64));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == [0; 2]
+    }
+
+    pub fn count_ones(&self) -> u32 {
+        self.0.iter().map(|word| word.count_ones()).sum()
+    }
+
+    /// Indices of the pressed keys, in ascending order, found by scanning for set bits
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().flat_map(|(word_idx, &word)| {
+            let mut rest = word;
+            std::iter::from_fn(move || {
+                (rest != 0).then(|| {
+                    let bit = rest.trailing_zeros() as usize;
+                    rest &= rest - 1;
+                    word_idx * 64 + bit
+                })
+            })
+        })
+    }
+
+    pub fn to_u128(self) -> u128 {
+        u128::from(self.0[0]) | u128::from(self.0[1]) << 64
+    }
+}
+
+impl From<u64> for PressedBits {
+    fn from(bits: u64) -> Self {
+        Self([bits, 0])
+    }
+}
+
+impl From<u128> for PressedBits {
+    fn from(bits: u128) -> Self {
+        Self([bits as u64, (bits >> 64) as u64])
+    }
+}
+
+impl FromIterator<usize> for PressedBits {
+    fn from_iter<I: IntoIterator<Item = usize>>(keys: I) -> Self {
+        let mut bits = Self::default();
+        for idx in keys {
+            bits.set(idx);
+        }
+        bits
+    }
+}
+
+/// Compare with a bit mask of the first 64 keys
+impl PartialEq<u64> for PressedBits {
+    fn eq(&self, other: &u64) -> bool {
+        *self == Self::from(*other)
+    }
+}
+
+impl BitAnd for PressedBits {
+    type Output = Self;
+    fn bitand(self, rhs: Self) -> Self {
+        Self([self.0[0] & rhs.0[0], self.0[1] & rhs.0[1]])
+    }
+}
+
+impl BitOr for PressedBits {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Self([self.0[0] | rhs.0[0], self.0[1] | rhs.0[1]])
+    }
+}
+
+impl Not for PressedBits {
+    type Output = Self;
+    fn not(self) -> Self {
+        Self([!self.0[0], !self.0[1]])
+    }
+}
+
+impl fmt::UpperHex for PressedBits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::UpperHex::fmt(&self.to_u128(), f)
+    }
+}
+
+impl Serialize for PressedBits {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            [low, 0] => serializer.serialize_u64(low),
+            _ => serializer.serialize_u128(self.to_u128()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PressedBits {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u128::deserialize(deserializer).map(Self::from)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Report {
     pub epoch_ms: u128,
     pub active_layer: u8,
-    pub pressed_bits: u64, // lower 48 bits used for 4x12
+    pub pressed_bits: PressedBits,
 }
 
 impl Report {
     pub const PLANCK_NUM_KEYS: usize = 48;
 
-    pub fn now(active_layer: u8, pressed_bits: u64) -> Self {
+    pub fn now(active_layer: u8, pressed_bits: impl Into<PressedBits>) -> Self {
         let epoch_ms = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map(|d| d.as_millis())
@@ -19,34 +151,312 @@ impl Report {
         Self {
             epoch_ms,
             active_layer,
-            pressed_bits,
+            pressed_bits: pressed_bits.into(),
         }
     }
+
+    /// Report with the given keys pressed and a zero timestamp, so tests can build
+    /// `pressed_bits` from key indices (indices past [`PressedBits::CAPACITY`] are ignored)
+    pub fn new_for_test(active_layer: u8, keys: &[usize]) -> Self {
+        Self {
+            epoch_ms: 0,
+            active_layer,
+            pressed_bits: keys.iter().copied().collect(),
+        }
+    }
+
+    /// What changed between `prev` and this report
+    pub fn diff(&self, prev: &Report) -> ReportDiff {
+        ReportDiff {
+            layer_changed: self.active_layer != prev.active_layer,
+            newly_pressed: self.pressed_bits & !prev.pressed_bits,
+            newly_released: !self.pressed_bits & prev.pressed_bits,
+        }
+    }
+}
+
+/// Change between two consecutive reports, one bit per key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReportDiff {
+    pub layer_changed: bool,
+    pub newly_pressed: PressedBits,
+    pub newly_released: PressedBits,
+}
+
+impl ReportDiff {
+    pub fn is_empty(&self) -> bool {
+        !self.layer_changed && self.newly_pressed.is_empty() && self.newly_released.is_empty()
+    }
+}
+
+/// State of the link to the keyboard, reported by the reader thread
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    Disconnected,
+    Scanning,
+    Connecting {
+        device_name: String,
+    },
+    Connected {
+        device_name: String,
+        vid: u16,
+        pid: u16,
+    },
+    Error(String),
+}
+
+/// Sends [`ConnectionStatus`] changes, skipping repeats of the last status
+#[derive(Debug, Default)]
+pub struct StatusReporter {
+    tx: Option<Sender<ConnectionStatus>>,
+    last: Option<ConnectionStatus>,
+}
+
+impl StatusReporter {
+    pub fn new(tx: Sender<ConnectionStatus>) -> Self {
+        Self {
+            tx: Some(tx),
+            last: None,
+        }
+    }
+
+    pub fn report(&mut self, status: ConnectionStatus) {
+        if self.last.as_ref() == Some(&status) {
+            return;
+        }
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(status.clone());
+        }
+        self.last = Some(status);
+    }
 }
 
 pub trait HidSource {
     fn poll(&mut self) -> Option<Report>;
 }
 
-pub fn parse_rawhid_packet(bytes: &[u8]) -> Option<Report> {
-    // Simple protocol: [layer: u8][pressed_bits: u64 little-endian] -> we only use 6 LSB bytes
-    if bytes.len() < 7 {
-        return None;
+/// Why a raw HID packet could not be turned into a [`Report`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    #[error("packet too short: got {got} bytes, need {need}")]
+    TooShort { got: usize, need: usize },
+    #[error("packet checksum does not match its payload")]
+    InvalidChecksum,
+}
+
+/// Layout of the packets sent by the raw HID firmware, set with `rawhid_format` in
+/// `config.json`
+///
+/// QMK pads every raw HID report to 32 bytes, so neither the width of the pressed field nor
+/// the presence of a checksum can be told from the packet length: both are set here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RawHidFormat {
+    /// 16 bytes of pressed state instead of 6, see [`parse_rawhid_packet_v2`]
+    #[serde(default)]
+    pub wide: bool,
+    /// The payload is followed by a checksum byte (XOR of the whole payload)
+    #[serde(default)]
+    pub checksum: bool,
+}
+
+/// Split `bytes` into `[layer: u8][pressed bits, little-endian]` of `payload_len` bytes and,
+/// when `checksum` is set, check the byte that follows the payload
+fn split_packet(
+    bytes: &[u8],
+    payload_len: usize,
+    checksum: bool,
+) -> Result<(u8, &[u8]), ParseError> {
+    let need = payload_len + usize::from(checksum);
+    if bytes.len() < need {
+        return Err(ParseError::TooShort {
+            got: bytes.len(),
+            need,
+        });
+    }
+    if checksum {
+        let xor = bytes[..payload_len].iter().fold(0u8, |acc, b| acc ^ b);
+        if xor != bytes[payload_len] {
+            return Err(ParseError::InvalidChecksum);
+        }
+    }
+    Ok((bytes[0], &bytes[1..payload_len]))
+}
+
+/// Parse a raw HID packet: `[layer: u8][pressed: 6 bytes little-endian][checksum: u8]`, the
+/// checksum only when `format` asks for it; bytes past the packet (report padding) are ignored
+///
+/// With [`RawHidFormat::wide`] the packet is read with [`parse_rawhid_packet_v2`], so boards
+/// with more than 48 keys can send up to 16 bytes of pressed state.
+pub fn parse_rawhid_packet(bytes: &[u8], format: &RawHidFormat) -> Result<Report, ParseError> {
+    if format.wide {
+        return parse_rawhid_packet_v2(bytes, format);
     }
-    let active_layer = bytes[0];
+    let (active_layer, pressed) = split_packet(bytes, 7, format.checksum)?;
     let mut buf = [0u8; 8];
-    buf[..6].copy_from_slice(&bytes[1..7]);
+    buf[..6].copy_from_slice(pressed);
     let pressed_bits = u64::from_le_bytes(buf);
-    Some(Report::now(active_layer, pressed_bits))
+    Ok(Report::now(active_layer, pressed_bits))
+}
+
+/// Layer byte plus 16 bytes of pressed state
+const WIDE_PACKET_LEN: usize = 17;
+
+/// Parse the wide protocol: `[layer: u8][pressed: 16 bytes little-endian][checksum: u8]`, the
+/// checksum only when `format` asks for it
+///
+/// The pressed field is 128 bits wide for boards with more than 48 keys.
+pub fn parse_rawhid_packet_v2(bytes: &[u8], format: &RawHidFormat) -> Result<Report, ParseError> {
+    let (active_layer, pressed) = split_packet(bytes, WIDE_PACKET_LEN, format.checksum)?;
+    let mut buf = [0u8; 16];
+    buf.copy_from_slice(pressed);
+    Ok(Report::now(active_layer, u128::from_le_bytes(buf)))
+}
+
+/// Line format printed by the firmware on the QMK console
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConsoleFormat {
+    /// Try JSON first, then the prefix format
+    #[default]
+    Auto,
+    /// `L:<layer> B:<hex bits>`
+    Prefix,
+    /// `{"layer":2,"bits":"0x0000001234"}`
+    Json,
+}
+
+/// Parse one console line; lines in neither format (debug prints, garbage) give `None`
+pub fn parse_console_line(line: &str, format: &ConsoleFormat) -> Option<Report> {
+    match format {
+        ConsoleFormat::Auto => parse_console_json(line).or_else(|| parse_console_prefix(line)),
+        ConsoleFormat::Prefix => parse_console_prefix(line),
+        ConsoleFormat::Json => parse_console_json(line),
+    }
+}
+
+fn parse_console_prefix(line: &str) -> Option<Report> {
+    let mut layer: Option<u8> = None;
+    let mut bits: Option<u128> = None;
+    for part in line.split_whitespace() {
+        if let Some(val) = part.strip_prefix("L:") {
+            layer = val.parse::<u8>().ok();
+        } else if let Some(val) = part.strip_prefix("B:") {
+            bits = u128::from_str_radix(val, 16).ok();
+        }
+    }
+    Some(Report::now(layer?, bits?))
+}
+
+/// `layer` may be a number or a numeric string; `bits` a number, a decimal string or a
+/// `0x` hex string
+fn parse_console_json(line: &str) -> Option<Report> {
+    let value: serde_json::Value = serde_json::from_str(line.trim()).ok()?;
+    let number = |field: &serde_json::Value| match field {
+        serde_json::Value::Number(n) => n.as_u64().map(u128::from),
+        serde_json::Value::String(s) => {
+            let s = s.trim();
+            match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+                Some(hex) => u128::from_str_radix(hex, 16).ok(),
+                None => s.parse().ok(),
+            }
+        }
+        _ => None,
+    };
+    let layer = u8::try_from(number(value.get("layer")?)?).ok()?;
+    let bits = number(value.get("bits")?)?;
+    Some(Report::now(layer, bits))
+}
+
+/// Scripted behaviors for [`MockHidSource`], useful for deterministic UI testing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockScenario {
+    /// A single pressed key moving across the board while layers cycle
+    MovingKey,
+    /// Layers cycle with no key pressed
+    LayerCycle,
+    /// Press `key_idx` at t=0, hold it for `hold_ms`, release, then repeat
+    MtHoldRelease { key_idx: usize, hold_ms: u64 },
+    /// All keys pressed for one cycle, then none for the next
+    AllKeysPressed,
+    /// Never produces a report
+    Idle,
+}
+
+/// Timing and shape of the simulated key presses of [`MockHidSource`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MockConfig {
+    /// Time before the moving key advances to the next key
+    pub key_cycle_ms: u64,
+    /// Time spent on each layer before switching to the next one
+    pub layer_cycle_ms: u64,
+    /// Layers cycled through, inclusive
+    pub min_layer: u8,
+    pub max_layer: u8,
+    /// Number of adjacent keys pressed together by the moving key (e.g. to test chords)
+    pub simultaneous_keys: usize,
+}
+
+impl Default for MockConfig {
+    /// One key moving every poll, four layers switched about every second
+    fn default() -> Self {
+        Self {
+            key_cycle_ms: MockHidSource::POLL_INTERVAL_MS,
+            layer_cycle_ms: MockHidSource::CYCLE_POLLS * MockHidSource::POLL_INTERVAL_MS,
+            min_layer: 0,
+            max_layer: 3,
+            simultaneous_keys: 1,
+        }
+    }
 }
 
 pub struct MockHidSource {
     counter: u64,
+    scenario: MockScenario,
+    config: MockConfig,
 }
 
 impl MockHidSource {
+    /// Simulated time between two polls, matching the reader thread sleep
+    pub const POLL_INTERVAL_MS: u64 = 8;
+    /// Number of polls per cycle (~1s)
+    const CYCLE_POLLS: u64 = 120;
+    /// Release time between two presses in [`MockScenario::MtHoldRelease`]
+    const RELEASE_MS: u64 = 200;
+
     pub fn new() -> Self {
-        Self { counter: 0 }
+        Self::new_with_scenario(MockScenario::MovingKey)
+    }
+
+    pub fn new_with_scenario(scenario: MockScenario) -> Self {
+        Self {
+            counter: 0,
+            scenario,
+            config: MockConfig::default(),
+        }
+    }
+
+    /// Moving-key source with custom timing and layer range
+    pub fn new_with_config(config: MockConfig) -> Self {
+        Self {
+            config,
+            ..Self::new()
+        }
+    }
+
+    /// Simulated milliseconds elapsed since the first poll
+    fn elapsed_ms(&self) -> u64 {
+        self.counter.saturating_sub(1) * Self::POLL_INTERVAL_MS
+    }
+
+    /// Number of layer switches so far
+    fn layer_cycle(&self) -> u64 {
+        self.counter * Self::POLL_INTERVAL_MS / self.config.layer_cycle_ms.max(1)
+    }
+
+    /// Layer shown after `cycle` switches, within the configured range
+    fn cycled_layer(&self, cycle: u64) -> u8 {
+        let min = self.config.min_layer.min(self.config.max_layer);
+        let span = (self.config.max_layer - min) as u64 + 1;
+        min + (cycle % span) as u8
     }
 }
 
@@ -59,11 +469,205 @@ impl Default for MockHidSource {
 impl HidSource for MockHidSource {
     fn poll(&mut self) -> Option<Report> {
         self.counter = self.counter.wrapping_add(1);
-        let layer = ((self.counter / 120) % 4) as u8; // cycle layers every ~1s
-        let idx = (self.counter % Report::PLANCK_NUM_KEYS as u64) as usize;
-        let mut bits = 0u64;
-        bits |= 1u64 << idx; // single moving key
-        Some(Report::now(layer, bits))
+        let cycle = self.layer_cycle();
+        let layer = self.cycled_layer(cycle);
+        match self.scenario {
+            MockScenario::MovingKey => {
+                let num_keys = Report::PLANCK_NUM_KEYS as u64;
+                let step = self.counter * Self::POLL_INTERVAL_MS / self.config.key_cycle_ms.max(1);
+                let first = step % num_keys;
+                // Adjacent keys after the moving one, wrapping around the board
+                let bits = (0..self.config.simultaneous_keys.min(Report::PLANCK_NUM_KEYS) as u64)
+                    .fold(0u64, |bits, offset| {
+                        bits | 1u64 << ((first + offset) % num_keys)
+                    });
+                Some(Report::now(layer, bits))
+            }
+            MockScenario::LayerCycle => Some(Report::now(layer, PressedBits::default())),
+            MockScenario::MtHoldRelease { key_idx, hold_ms } => {
+                let t = self.elapsed_ms() % (hold_ms + Self::RELEASE_MS);
+                let held = (t < hold_ms).then_some(key_idx);
+                Some(Report::now(0, held.into_iter().collect::<PressedBits>()))
+            }
+            MockScenario::AllKeysPressed => {
+                let all = (1u64 << Report::PLANCK_NUM_KEYS) - 1;
+                let bits = if cycle.is_multiple_of(2) { all } else { 0 };
+                Some(Report::now(0, bits))
+            }
+            MockScenario::Idle => None,
+        }
+    }
+}
+
+/// Usage page and usage of the raw HID interface, shared by VIA and the viewer's reports
+pub const VIA_USAGE_PAGE: u16 = 0xFF60;
+pub const VIA_USAGE: u16 = 0x61;
+/// Size of every VIA request and response
+pub const VIA_PACKET_LEN: usize = 32;
+/// Packets read while waiting for a response; reports sent by the firmware are skipped
+const VIA_MAX_SKIPPED: usize = 16;
+/// First VIA protocol version using the keycode ranges of QMK 0.19+
+pub const VIA_MIN_PROTOCOL: u16 = 12;
+
+/// VIA command ids, the first byte of a request and of its response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ViaCommand {
+    GetProtocolVersion = 0x01,
+    DynamicKeymapGetKeycode = 0x04,
+    DynamicKeymapGetLayerCount = 0x11,
+}
+
+impl ViaCommand {
+    /// Response id of a command the firmware does not handle
+    pub const UNHANDLED: u8 = 0xFF;
+
+    /// Request packet: the command id followed by `args`
+    pub fn request(self, args: &[u8]) -> [u8; VIA_PACKET_LEN] {
+        let mut packet = [0u8; VIA_PACKET_LEN];
+        packet[0] = self as u8;
+        packet[1..=args.len()].copy_from_slice(args);
+        packet
+    }
+}
+
+/// Why a VIA exchange failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum ViaError {
+    #[error("keyboard does not handle VIA command {0:?}")]
+    Unhandled(ViaCommand),
+    #[error("no response to VIA command {0:?}")]
+    NoResponse(ViaCommand),
+    #[error("VIA protocol version {0} predates the keycodes of QMK 0.19, version 12 is needed")]
+    UnsupportedProtocol(u16),
+}
+
+/// Packet link to a keyboard speaking the VIA protocol
+pub trait ViaTransport {
+    fn send(&mut self, packet: &[u8; VIA_PACKET_LEN]) -> anyhow::Result<()>;
+    /// Next packet from the keyboard, `None` when nothing arrived in time
+    fn receive(&mut self) -> anyhow::Result<Option<[u8; VIA_PACKET_LEN]>>;
+}
+
+/// Send `command` and wait for the response that echoes it
+pub fn via_exchange(
+    link: &mut impl ViaTransport,
+    command: ViaCommand,
+    args: &[u8],
+) -> anyhow::Result<[u8; VIA_PACKET_LEN]> {
+    link.send(&command.request(args))?;
+    for _ in 0..VIA_MAX_SKIPPED {
+        let Some(response) = link.receive()? else {
+            break;
+        };
+        match response[0] {
+            id if id == command as u8 => return Ok(response),
+            ViaCommand::UNHANDLED => return Err(ViaError::Unhandled(command).into()),
+            _ => {}
+        }
+    }
+    Err(ViaError::NoResponse(command).into())
+}
+
+/// Read every layer of the dynamic keymap
+///
+/// `matrix` lists the switch matrix `(row, col)` of each key in `LAYOUT` order (see
+/// [`crate::keyboard::KeyboardLayout::info_json_matrix`]), so the layers come back in the
+/// same order as a keymap file.
+///
+/// Keycodes are read with [`ViaKeycodeTable`], so keyboards older than VIA protocol
+/// [`VIA_MIN_PROTOCOL`] are refused rather than shown with the wrong keys.
+pub fn download_via_keymap(
+    link: &mut impl ViaTransport,
+    matrix: &[(u8, u8)],
+) -> anyhow::Result<KeymapConfig> {
+    let response = via_exchange(link, ViaCommand::GetProtocolVersion, &[])?;
+    let version = u16::from_be_bytes([response[1], response[2]]);
+    if version < VIA_MIN_PROTOCOL {
+        return Err(ViaError::UnsupportedProtocol(version).into());
+    }
+    let layer_count = via_exchange(link, ViaCommand::DynamicKeymapGetLayerCount, &[])?[1];
+    let mut layers = Vec::with_capacity(layer_count as usize);
+    for layer in 0..layer_count {
+        let keys = matrix
+            .iter()
+            .map(|&(row, col)| {
+                let response = via_exchange(
+                    link,
+                    ViaCommand::DynamicKeymapGetKeycode,
+                    &[layer, row, col],
+                )?;
+                Ok(ViaKeycodeTable::token(u16::from_be_bytes([
+                    response[4],
+                    response[5],
+                ])))
+            })
+            .collect::<anyhow::Result<Vec<String>>>()?;
+        layers.push(keys);
+    }
+    Ok(KeymapConfig {
+        keyboard: "via".to_string(),
+        keymap: "via".to_string(),
+        layers,
+        layout: None,
+        layer_names: None,
+        encoder_map: Vec::new(),
+        custom_keycodes: Default::default(),
+    })
+}
+
+/// VIA link over the raw HID interface of a keyboard
+#[cfg(feature = "via")]
+pub struct ViaHidSource {
+    device: hidapi::HidDevice,
+    matrix: Vec<(u8, u8)>,
+}
+
+#[cfg(feature = "via")]
+impl ViaHidSource {
+    /// How long to wait for each response
+    const TIMEOUT_MS: i32 = 100;
+
+    pub fn new(device: hidapi::HidDevice, matrix: Vec<(u8, u8)>) -> Self {
+        Self { device, matrix }
+    }
+
+    /// Open the first device exposing the VIA raw HID interface
+    pub fn open(api: &hidapi::HidApi, matrix: Vec<(u8, u8)>) -> anyhow::Result<Self> {
+        let info = api
+            .device_list()
+            .find(|dev| dev.usage_page() == VIA_USAGE_PAGE && dev.usage() == VIA_USAGE)
+            .ok_or_else(|| anyhow::anyhow!("no VIA raw HID device found"))?;
+        Ok(Self::new(info.open_device(api)?, matrix))
+    }
+
+    /// Read the keymap stored in the keyboard's EEPROM
+    pub fn download_keymap(&mut self) -> anyhow::Result<KeymapConfig> {
+        let matrix = std::mem::take(&mut self.matrix);
+        let result = download_via_keymap(self, &matrix);
+        self.matrix = matrix;
+        result
+    }
+
+    pub fn into_device(self) -> hidapi::HidDevice {
+        self.device
+    }
+}
+
+#[cfg(feature = "via")]
+impl ViaTransport for ViaHidSource {
+    fn send(&mut self, packet: &[u8; VIA_PACKET_LEN]) -> anyhow::Result<()> {
+        // hidapi expects the report id first; raw HID has none
+        let mut buf = [0u8; VIA_PACKET_LEN + 1];
+        buf[1..].copy_from_slice(packet);
+        self.device.write(&buf)?;
+        Ok(())
+    }
+
+    fn receive(&mut self) -> anyhow::Result<Option<[u8; VIA_PACKET_LEN]>> {
+        let mut buf = [0u8; VIA_PACKET_LEN];
+        let n = self.device.read_timeout(&mut buf, Self::TIMEOUT_MS)?;
+        Ok((n > 0).then_some(buf))
     }
 }
 
@@ -72,13 +676,67 @@ pub struct RawHidSource {
     ctx: hidapi::HidApi,
     // We lazily open device by vendor/product or usage page; for now keep optional handle
     device: Option<hidapi::HidDevice>,
+    status: StatusReporter,
+    format: RawHidFormat,
+    /// Where to send the keymap read over VIA after connecting
+    #[cfg(feature = "via")]
+    keymap_tx: Option<Sender<KeymapConfig>>,
+    /// Matrix positions read over VIA, in `LAYOUT` order
+    #[cfg(feature = "via")]
+    via_matrix: Vec<(u8, u8)>,
 }
 
 #[cfg(feature = "rawhid")]
 impl RawHidSource {
     pub fn new() -> Self {
         let ctx = hidapi::HidApi::new().expect("hidapi init");
-        Self { ctx, device: None }
+        Self {
+            ctx,
+            device: None,
+            status: StatusReporter::default(),
+            format: RawHidFormat::default(),
+            #[cfg(feature = "via")]
+            keymap_tx: None,
+            #[cfg(feature = "via")]
+            via_matrix: Vec::new(),
+        }
+    }
+
+    /// Report connection changes on `tx`
+    pub fn with_status_sender(mut self, tx: Sender<ConnectionStatus>) -> Self {
+        self.status = StatusReporter::new(tx);
+        self
+    }
+
+    /// Packet layout the firmware sends
+    pub fn with_format(mut self, format: RawHidFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Read the keymap over VIA whenever a keyboard connects and send it on `tx`
+    #[cfg(feature = "via")]
+    pub fn with_via_download(mut self, matrix: Vec<(u8, u8)>, tx: Sender<KeymapConfig>) -> Self {
+        self.keymap_tx = Some(tx);
+        self.via_matrix = matrix;
+        self
+    }
+
+    /// Send the keymap of a newly opened device when VIA download is enabled
+    #[cfg(feature = "via")]
+    fn download_via_keymap(&self, device: hidapi::HidDevice) -> hidapi::HidDevice {
+        let Some(tx) = &self.keymap_tx else {
+            return device;
+        };
+        let mut via = ViaHidSource::new(device, self.via_matrix.clone());
+        match via.download_keymap() {
+            Ok(config) => {
+                println!("✅ Downloaded {} layers over VIA", config.layers.len());
+                let _ = tx.send(config);
+            }
+            Err(e) => eprintln!("⚠️ Failed to download the keymap over VIA: {}", e),
+        }
+        via.into_device()
     }
 }
 
@@ -96,6 +754,7 @@ impl RawHidSource {
             return;
         }
         eprintln!("Scanning HID devices...");
+        self.status.report(ConnectionStatus::Scanning);
         for dev in self.ctx.device_list() {
             let product = dev.product_string().unwrap_or_default();
             let vendor = dev.vendor_id();
@@ -112,15 +771,26 @@ impl RawHidSource {
             let is_qmk_rawhid = usage_page == 0xFF60 && usage == 0x61;
             if is_qmk_rawhid {
                 eprintln!("Trying to open Planck Raw HID device...");
+                self.status.report(ConnectionStatus::Connecting {
+                    device_name: product.to_string(),
+                });
                 match dev.open_device(&self.ctx) {
                     Ok(d) => {
                         eprintln!("Successfully opened Planck device (VID={:04X} PID={:04X} usage_page=0x{:04X} usage=0x{:04X})",
                                   vendor, product_id, usage_page, usage);
+                        #[cfg(feature = "via")]
+                        let d = self.download_via_keymap(d);
                         self.device = Some(d);
+                        self.status.report(ConnectionStatus::Connected {
+                            device_name: product.to_string(),
+                            vid: vendor,
+                            pid: product_id,
+                        });
                         return;
                     }
                     Err(e) => {
                         eprintln!("Failed to open Planck device: {:?}", e);
+                        self.status.report(ConnectionStatus::Error(e.to_string()));
                     }
                 }
             }
@@ -138,12 +808,21 @@ impl HidSource for RawHidSource {
         match dev.read_timeout(&mut buf, 1) {
             Ok(n) if n > 0 => {
                 eprintln!("Received {} bytes: {:02X?}", n, &buf[..n]);
-                parse_rawhid_packet(&buf[..n])
+                match parse_rawhid_packet(&buf[..n], &self.format) {
+                    Ok(report) => Some(report),
+                    Err(e) => {
+                        log::warn!("Dropping raw HID packet: {}", e);
+                        None
+                    }
+                }
             }
             Ok(0) => None,
             Ok(_) => None, // Handle any other Ok values
             Err(e) => {
                 eprintln!("HID read error: {:?}", e);
+                // Drop the handle so the next poll scans for the device again
+                self.device = None;
+                self.status.report(ConnectionStatus::Disconnected);
                 None
             }
         }
@@ -156,6 +835,8 @@ pub struct QmkConsoleSource {
     buf: String,
     last_try: std::time::Instant,
     override_port: Option<String>,
+    status: StatusReporter,
+    format: ConsoleFormat,
 }
 
 #[cfg(feature = "qmk_console")]
@@ -169,9 +850,36 @@ impl QmkConsoleSource {
             buf: String::new(),
             last_try: std::time::Instant::now(),
             override_port: port,
+            status: StatusReporter::default(),
+            format: ConsoleFormat::default(),
         }
     }
 
+    /// Only accept lines in `format` instead of detecting it per line
+    pub fn with_format(mut self, format: ConsoleFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Report connection changes on `tx`
+    pub fn with_status_sender(mut self, tx: Sender<ConnectionStatus>) -> Self {
+        self.status = StatusReporter::new(tx);
+        self
+    }
+
+    fn report_port(&mut self, name: &str) {
+        let status = if self.port.is_some() {
+            ConnectionStatus::Connected {
+                device_name: name.to_string(),
+                vid: 0,
+                pid: 0,
+            }
+        } else {
+            ConnectionStatus::Scanning
+        };
+        self.status.report(status);
+    }
+
     fn open_port_name(&self, name: &str) -> Option<Box<dyn serialport::SerialPort>> {
         serialport::new(name, 115_200)
             .timeout(std::time::Duration::from_millis(1))
@@ -189,14 +897,17 @@ impl QmkConsoleSource {
         self.last_try = std::time::Instant::now();
         if let Some(name) = self.override_port.clone() {
             self.port = self.open_port_name(&name);
+            self.report_port(&name);
             return;
         }
+        self.status.report(ConnectionStatus::Scanning);
         if let Ok(ports) = serialport::available_ports() {
             for p in ports {
                 let name = p.port_name.to_lowercase();
                 if name.contains("usbmodem") || name.contains("usbserial") {
                     if let Some(port) = self.open_port_name(&p.port_name) {
                         self.port = Some(port);
+                        self.report_port(&p.port_name);
                         break;
                     }
                 }
@@ -230,17 +941,7 @@ impl HidSource for QmkConsoleSource {
         if let Some(line) = self.try_read_line() {
             // Log raw line for debugging
             eprintln!("console: {}", line);
-            let mut layer: Option<u8> = None;
-            let mut bits: Option<u64> = None;
-            for part in line.split_whitespace() {
-                if let Some(val) = part.strip_prefix("L:") {
-                    layer = val.parse::<u8>().ok();
-                } else if let Some(val) = part.strip_prefix("B:") {
-                    bits = u64::from_str_radix(val, 16).ok();
-                }
-            }
-            if let (Some(l), Some(b)) = (layer, bits) {
-                let rep = Report::now(l, b);
+            if let Some(rep) = parse_console_line(&line, &self.format) {
                 eprintln!(
                     "parsed: layer={} bits=0x{:012X}",
                     rep.active_layer, rep.pressed_bits
@@ -0,0 +1,148 @@
+//! Local IPC so external scripts can drive the viewer (e.g. switch layers on app focus)
+//!
+//! Commands are newline-separated JSON objects such as `{"cmd":"set_layer","layer":"NAV"}`,
+//! read from a Unix socket at [`SOCKET_PATH`] or a named pipe at [`PIPE_NAME`] on Windows.
+
+use serde::Deserialize;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::mpsc::Sender;
+
+#[cfg(unix)]
+pub const SOCKET_PATH: &str = "/tmp/qmk_viewer.sock";
+#[cfg(windows)]
+pub const PIPE_NAME: &str = r"\\.\pipe\qmk_viewer";
+
+/// A command sent by an external script
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum IpcCommand {
+    /// Show the layer with this name (case-insensitive)
+    SetLayer { layer: String },
+}
+
+/// Parse one line of the IPC protocol
+pub fn parse_command(line: &str) -> anyhow::Result<IpcCommand> {
+    Ok(serde_json::from_str(line.trim())?)
+}
+
+/// Forward every valid command read from `stream` until it is closed
+fn forward_commands<R: Read>(stream: R, tx: &Sender<IpcCommand>) {
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_command(&line) {
+            Ok(cmd) => {
+                if tx.send(cmd).is_err() {
+                    return;
+                }
+            }
+            Err(e) => eprintln!("⚠️ Ignoring IPC command {:?}: {}", line, e),
+        }
+    }
+}
+
+/// Listen for commands on [`SOCKET_PATH`] in a background thread
+#[cfg(unix)]
+pub fn spawn_listener(tx: Sender<IpcCommand>) -> anyhow::Result<()> {
+    use std::os::unix::net::UnixListener;
+
+    // A socket left behind by a previous run would make bind fail
+    let _ = std::fs::remove_file(SOCKET_PATH);
+    let listener = UnixListener::bind(SOCKET_PATH)?;
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => forward_commands(stream, &tx),
+                Err(e) => eprintln!("⚠️ IPC connection failed: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Listen for commands on [`PIPE_NAME`] in a background thread
+#[cfg(windows)]
+pub fn spawn_listener(tx: Sender<IpcCommand>) -> anyhow::Result<()> {
+    use std::os::windows::io::FromRawHandle;
+    use windows_sys::Win32::Foundation::{
+        GetLastError, ERROR_PIPE_CONNECTED, INVALID_HANDLE_VALUE,
+    };
+    use windows_sys::Win32::Storage::FileSystem::PIPE_ACCESS_INBOUND;
+    use windows_sys::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_TYPE_BYTE,
+        PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    };
+
+    let name: Vec<u16> = PIPE_NAME.encode_utf16().chain(Some(0)).collect();
+    let create_pipe = move || unsafe {
+        CreateNamedPipeW(
+            name.as_ptr(),
+            PIPE_ACCESS_INBOUND,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT,
+            PIPE_UNLIMITED_INSTANCES,
+            0,
+            4096,
+            0,
+            std::ptr::null(),
+        )
+    };
+
+    // Create the first instance up front so a bad pipe name is reported to the caller
+    let mut handle = create_pipe();
+    if handle == INVALID_HANDLE_VALUE {
+        anyhow::bail!("failed to create named pipe {}", PIPE_NAME);
+    }
+    std::thread::spawn(move || loop {
+        let connected = unsafe {
+            ConnectNamedPipe(handle, std::ptr::null_mut()) != 0
+                || GetLastError() == ERROR_PIPE_CONNECTED
+        };
+        // The file owns the handle and closes this pipe instance when dropped
+        let pipe = unsafe { std::fs::File::from_raw_handle(handle as _) };
+        if connected {
+            forward_commands(pipe, &tx);
+        } else {
+            drop(pipe);
+        }
+        handle = create_pipe();
+        if handle == INVALID_HANDLE_VALUE {
+            eprintln!("❌ Failed to recreate named pipe {}", PIPE_NAME);
+            return;
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_parse_set_layer() {
+        assert_eq!(
+            parse_command(r#"{"cmd":"set_layer","layer":"NAV"}"#).unwrap(),
+            IpcCommand::SetLayer {
+                layer: "NAV".to_string()
+            }
+        );
+        assert!(parse_command(r#"{"cmd":"reboot"}"#).is_err());
+        assert!(parse_command("not json").is_err());
+    }
+
+    #[test]
+    fn test_forward_commands_skips_invalid_lines() {
+        let (tx, rx) = mpsc::channel();
+        let input = "{\"cmd\":\"set_layer\",\"layer\":\"NAV\"}\n\ngarbage\n{\"cmd\":\"set_layer\",\"layer\":\"Base\"}\n";
+        forward_commands(input.as_bytes(), &tx);
+        let layers: Vec<_> = rx
+            .try_iter()
+            .map(|IpcCommand::SetLayer { layer }| layer)
+            .collect();
+        assert_eq!(layers, vec!["NAV", "Base"]);
+    }
+}
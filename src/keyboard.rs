@@ -1,13 +1,181 @@
-use crate::keycodes::{layer_display_name, mod_to_glyph, translate_token};
+use crate::hid::{PressedBits, Report};
+use crate::keycodes::{
+    layer_display_name, mod_to_glyph, sanitize_glyphs, translate_algr_combo, translate_token,
+    translate_token_locale, CustomKeycodeTable, KeyboardLocale,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Physical placement of one key, in key units (1.0 = one standard 1U key)
+///
+/// The key is turned clockwise by `rotation_angle` degrees around `(rotation_x, rotation_y)`,
+/// like the `r`, `rx` and `ry` fields of a QMK `info.json` layout.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct KeyPosition {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+    #[serde(default)]
+    pub rotation_angle: f32,
+    #[serde(default)]
+    pub rotation_x: f32,
+    #[serde(default)]
+    pub rotation_y: f32,
+}
+
+impl KeyPosition {
+    pub const fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self {
+            x,
+            y,
+            w,
+            h,
+            rotation_angle: 0.0,
+            rotation_x: 0.0,
+            rotation_y: 0.0,
+        }
+    }
+
+    /// Unrotated box the key is drawn in: same size, centred where the rotation moves the
+    /// key's centre to
+    pub fn placed(&self) -> Self {
+        if self.rotation_angle == 0.0 {
+            return *self;
+        }
+        let (sin, cos) = self.rotation_angle.to_radians().sin_cos();
+        let dx = self.x + self.w / 2.0 - self.rotation_x;
+        let dy = self.y + self.h / 2.0 - self.rotation_y;
+        let cx = self.rotation_x + dx * cos - dy * sin;
+        let cy = self.rotation_y + dx * sin + dy * cos;
+        Self::new(cx - self.w / 2.0, cy - self.h / 2.0, self.w, self.h)
+    }
+}
+
+/// One key of an `info.json` layout
+#[derive(Deserialize)]
+struct InfoJsonKey {
+    /// `[row, col]` of the key in the switch matrix
+    #[serde(default)]
+    matrix: Option<[u8; 2]>,
+    x: f32,
+    y: f32,
+    #[serde(default = "one_unit")]
+    w: f32,
+    #[serde(default = "one_unit")]
+    h: f32,
+    #[serde(default)]
+    r: f32,
+    #[serde(default)]
+    rx: f32,
+    #[serde(default)]
+    ry: f32,
+}
+
+fn one_unit() -> f32 {
+    1.0
+}
+
+/// Keys of `layout_name` (the first layout by name when `None`) in an `info.json`, with the
+/// name of the layout they were read from
+fn info_json_keys(
+    json_str: &str,
+    layout_name: Option<&str>,
+) -> anyhow::Result<(String, Vec<InfoJsonKey>)> {
+    let info: serde_json::Value =
+        serde_json::from_str(json_str).map_err(|e| anyhow::anyhow!("invalid info.json: {}", e))?;
+    let layouts = info
+        .get("layouts")
+        .and_then(|layouts| layouts.as_object())
+        .ok_or_else(|| anyhow::anyhow!("no layouts in info.json"))?;
+    let found = match layout_name {
+        Some(name) => layouts.get_key_value(name),
+        None => layouts.iter().next(),
+    };
+    let (name, layout) = found
+        .ok_or_else(|| anyhow::anyhow!("no layout {} in info.json", layout_name.unwrap_or("")))?;
+    let keys = layout
+        .get("layout")
+        .ok_or_else(|| anyhow::anyhow!("no layout {} in info.json", name))?;
+    let keys = serde_json::from_value(keys.clone())
+        .map_err(|e| anyhow::anyhow!("invalid key in layout {}: {}", name, e))?;
+    Ok((name.clone(), keys))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct KeyboardLayout {
     pub rows: usize,
     pub cols: usize,
     pub layer_names: Vec<String>,
     pub legends: Vec<Vec<String>>, // [layer][index] normalized label text
     pub raw_legends: Vec<Vec<String>>, // [layer][index] original tokens
+    /// Optional physical key placement, indexed like the legends (`row * cols + col`)
+    #[serde(default)]
+    pub physical_positions: Option<Vec<KeyPosition>>,
+    /// Gap between the halves of a split keyboard in the grid view, `None` for one-piece boards
+    #[serde(default)]
+    pub split: Option<SplitConfig>,
+}
+
+/// Where the grid view separates the two halves of a split keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SplitConfig {
+    /// Number of left-hand columns; the gap is inserted after them
+    pub split_col: usize,
+    pub gap_px: f32,
+    /// Keys of a last row that doesn't fill the grid (the thumb keys of a Corne), drawn
+    /// centered on the gap, `None` when the last row is a full row
+    #[serde(default)]
+    pub thumb_keys: Option<usize>,
+}
+
+impl SplitConfig {
+    /// Default for [`SplitConfig::gap_px`]
+    pub const DEFAULT_GAP_PX: f32 = 40.0;
+
+    /// Halves of `cols / 2` columns each, with the default gap
+    pub fn at_midpoint(cols: usize) -> Self {
+        Self {
+            split_col: cols / 2,
+            gap_px: Self::DEFAULT_GAP_PX,
+            thumb_keys: None,
+        }
+    }
+}
+
+/// Inconsistency between the layers of a [`KeyboardLayout`], see
+/// [`KeyboardLayout::validate_consistency`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationWarning {
+    /// The layer does not have `rows * cols` keys
+    LayerLengthMismatch {
+        layer: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// The key has a label or a token but not both: the layer's two lists were padded
+    /// to different lengths
+    PaddedKey { layer: usize, index: usize },
+}
+
+impl std::fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LayerLengthMismatch {
+                layer,
+                expected,
+                found,
+            } => write!(
+                f,
+                "layer {} has {} keys, expected {}",
+                layer, found, expected
+            ),
+            Self::PaddedKey { layer, index } => {
+                write!(f, "key {} on layer {} is only partly defined", index, layer)
+            }
+        }
+    }
 }
 
 impl KeyboardLayout {
@@ -21,92 +189,601 @@ impl KeyboardLayout {
             layer_names,
             legends: vec![vec![String::new(); total_keys]; layer_count],
             raw_legends: vec![vec![String::new(); total_keys]; layer_count],
+            physical_positions: None,
+            split: None,
         }
     }
 
+    /// Create a `rows x cols` layout with `layer_count` empty layers ("Layer 0", "Layer 1", ...)
+    /// and physical positions on a uniform 1U grid, so the physical renderer draws exactly
+    /// what the grid renderer does
+    pub fn with_uniform_grid(rows: usize, cols: usize, layer_count: usize) -> Self {
+        let layer_names = (0..layer_count.max(1))
+            .map(|i| format!("Layer {}", i))
+            .collect();
+        let mut layout = Self::new(rows, cols, layer_names);
+        layout.physical_positions = Some(
+            (0..rows * cols)
+                .map(|i| KeyPosition::new((i % cols) as f32, (i / cols) as f32, 1.0, 1.0))
+                .collect(),
+        );
+        layout
+    }
+
     /// Auto-detect dimensions from layout data and create keyboard layout
     pub fn from_layout_data(layers: Vec<Vec<String>>, layer_names: Option<Vec<String>>) -> Self {
-        let layer_count = layers.len().max(1);
-        let default_layer_names = (0..layer_count).map(|i| format!("Layer {}", i)).collect();
-        let layer_names = layer_names.unwrap_or(default_layer_names);
-
         // Calculate dimensions by finding the maximum number of keys in any layer
         let max_keys = layers.iter().map(|layer| layer.len()).max().unwrap_or(0);
 
         // Try to determine rows/cols from common keyboard layouts
         let (rows, cols) = Self::estimate_dimensions(max_keys);
+        Self::from_layout_data_with_dims(layers, layer_names, rows, cols)
+    }
 
-        let total_keys = rows * cols;
-
-        // Process layers to normalize keycodes and pad to total_keys
-        let processed_layers: Vec<Vec<String>> = layers
+    /// Build a layout with the key positions of `layout_name` from a QMK `info.json`
+    ///
+    /// `layers` hold the keycodes in `LAYOUT` order, which is also the order of the
+    /// positions in the file. The grid is estimated as in [`KeyboardLayout::from_layout_data`]
+    /// and is all that is left when the layout lists no keys.
+    pub fn from_info_json(
+        json_str: &str,
+        layout_name: &str,
+        layers: Vec<Vec<String>>,
+    ) -> anyhow::Result<Self> {
+        let (_, keys) = info_json_keys(json_str, Some(layout_name))?;
+        if let Some((layer, found)) = layers
             .iter()
-            .map(|layer| {
-                let mut processed = layer.iter().map(|s| translate_token(s)).collect::<Vec<_>>();
-                // Pad with empty strings if needed
-                while processed.len() < total_keys {
-                    processed.push(String::new());
-                }
-                processed
+            .map(Vec::len)
+            .enumerate()
+            .find(|&(_, len)| !keys.is_empty() && len != keys.len())
+        {
+            anyhow::bail!(
+                "layer {} has {} keys, layout {} has {}",
+                layer,
+                found,
+                layout_name,
+                keys.len()
+            );
+        }
+
+        let mut layout = Self::from_layout_data(layers, None);
+        if !keys.is_empty() {
+            layout.physical_positions = Some(
+                keys.iter()
+                    .map(|k| KeyPosition {
+                        rotation_angle: k.r,
+                        rotation_x: k.rx,
+                        rotation_y: k.ry,
+                        ..KeyPosition::new(k.x, k.y, k.w, k.h)
+                    })
+                    .collect(),
+            );
+        }
+        Ok(layout)
+    }
+
+    /// Switch matrix `(row, col)` of every key of an `info.json` layout, in `LAYOUT` order
+    ///
+    /// Without `layout_name` the first layout by name is used.
+    pub fn info_json_matrix(
+        json_str: &str,
+        layout_name: Option<&str>,
+    ) -> anyhow::Result<Vec<(u8, u8)>> {
+        let (name, keys) = info_json_keys(json_str, layout_name)?;
+        keys.iter()
+            .enumerate()
+            .map(|(idx, key)| {
+                let [row, col] = key.matrix.ok_or_else(|| {
+                    anyhow::anyhow!("key {} of layout {} has no matrix position", idx, name)
+                })?;
+                Ok((row, col))
             })
-            .collect();
+            .collect()
+    }
 
-        // Create raw legends (original tokens) with padding
-        let raw_layers: Vec<Vec<String>> = layers
-            .iter()
-            .map(|layer| {
-                let mut raw = layer.clone();
-                while raw.len() < total_keys {
-                    raw.push("_______".to_string());
+    /// Names of the layouts of a QMK `info.json`
+    pub fn info_json_layout_names(json_str: &str) -> anyhow::Result<Vec<String>> {
+        let info: serde_json::Value = serde_json::from_str(json_str)
+            .map_err(|e| anyhow::anyhow!("invalid info.json: {}", e))?;
+        let layouts = info
+            .get("layouts")
+            .and_then(|layouts| layouts.as_object())
+            .ok_or_else(|| anyhow::anyhow!("no layouts in info.json"))?;
+        Ok(layouts.keys().cloned().collect())
+    }
+
+    /// Build a one-layer layout with empty legends from the key shapes of a Keyboard Layout
+    /// Editor (keyboard-layout-editor.com) JSON file
+    ///
+    /// Each row is an array of legend strings, one per key, and objects changing the
+    /// properties of the keys that follow: `x`/`y` offsets, `w`/`h` sizes (reset after each
+    /// key), and the `r` rotation about `rx`/`ry`, which also restart the position. Decals
+    /// (`d`) are not keys. Colors and secondary shapes are not kept, a layout has no place
+    /// for them. An object in front of the first row holds keyboard metadata.
+    pub fn from_kle_json(kle_json: &str) -> anyhow::Result<Self> {
+        use serde_json::Value;
+
+        let value: Value = serde_json::from_str(kle_json)?;
+        let rows = value
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("KLE layout must be a JSON array of rows"))?;
+        let mut positions = Vec::new();
+        let (mut r, mut rx, mut ry) = (0.0, 0.0, 0.0);
+        let mut y = 0.0;
+        for (row_idx, row) in rows.iter().enumerate() {
+            let items = match row {
+                Value::Array(items) => items,
+                Value::Object(_) if row_idx == 0 => continue,
+                _ => anyhow::bail!("row {} of the KLE layout is not an array", row_idx),
+            };
+            let mut x = rx;
+            let (mut w, mut h, mut decal) = (1.0, 1.0, false);
+            for item in items {
+                match item {
+                    Value::Object(props) => {
+                        let num = |key: &str| props.get(key).and_then(Value::as_f64);
+                        if let Some(v) = num("r") {
+                            r = v as f32;
+                        }
+                        if num("rx").is_some() || num("ry").is_some() {
+                            rx = num("rx").map_or(rx, |v| v as f32);
+                            ry = num("ry").map_or(ry, |v| v as f32);
+                            x = rx;
+                            y = ry;
+                        }
+                        x += num("x").unwrap_or(0.0) as f32;
+                        y += num("y").unwrap_or(0.0) as f32;
+                        w = num("w").map_or(w, |v| v as f32);
+                        h = num("h").map_or(h, |v| v as f32);
+                        decal = props.get("d").and_then(Value::as_bool).unwrap_or(decal);
+                    }
+                    Value::String(_) => {
+                        if !decal {
+                            positions.push(KeyPosition {
+                                rotation_angle: r,
+                                rotation_x: rx,
+                                rotation_y: ry,
+                                ..KeyPosition::new(x, y, w, h)
+                            });
+                        }
+                        x += w;
+                        (w, h, decal) = (1.0, 1.0, false);
+                    }
+                    _ => anyhow::bail!("unexpected {} in row {} of the KLE layout", item, row_idx),
                 }
-                raw
-            })
-            .collect();
+            }
+            y += 1.0;
+        }
+        if positions.is_empty() {
+            anyhow::bail!("KLE layout has no keys");
+        }
 
-        Self {
+        let mut layout = Self::from_layout_data(vec![vec![String::new(); positions.len()]], None);
+        layout.physical_positions = Some(positions);
+        Ok(layout)
+    }
+
+    /// Lay out keys in `LAYOUT` order on a `rows` x `cols` grid, adding rows if the keys
+    /// don't fit
+    pub fn from_layout_data_with_dims(
+        layers: Vec<Vec<String>>,
+        layer_names: Option<Vec<String>>,
+        rows: usize,
+        cols: usize,
+    ) -> Self {
+        let layer_count = layers.len().max(1);
+        let default_layer_names = (0..layer_count).map(|i| format!("Layer {}", i)).collect();
+        let layer_names = layer_names.unwrap_or(default_layer_names);
+        let max_keys = layers.iter().map(|layer| layer.len()).max().unwrap_or(0);
+
+        let total_keys = rows * cols;
+
+        // Normalized labels next to the original tokens, padded to total_keys
+        let legends = layers
+            .iter()
+            .map(|layer| layer.iter().map(|s| translate_token(s)).collect())
+            .collect();
+        let mut layout = Self {
             rows,
             cols,
             layer_names,
-            legends: processed_layers,
-            raw_legends: raw_layers,
+            legends,
+            raw_legends: layers,
+            physical_positions: None,
+            split: None,
+        };
+        for layer in 0..layout.raw_legends.len() {
+            layout.pad_layer(layer, total_keys);
+        }
+
+        // A known keyboard close to the key count may be a little too small; add rows rather
+        // than hiding the extra keys
+        if max_keys > total_keys && cols > 0 {
+            layout.resize(max_keys.div_ceil(cols), cols);
+        }
+
+        // Known split boards show their halves apart
+        let split_board = crate::keyboards::registry::detect_by_key_count(max_keys)
+            .is_some_and(|known| known.split && known.cols == cols);
+        if split_board {
+            let last_row_keys = max_keys.saturating_sub((layout.rows - 1) * cols);
+            layout.split = Some(SplitConfig {
+                thumb_keys: (last_row_keys < cols).then_some(last_row_keys),
+                ..SplitConfig::at_midpoint(cols)
+            });
+        }
+        layout
+    }
+
+    /// Column the grid view draws the key at `row`, `col` in, and whether it is right of
+    /// the split gap; `None` for the empty cells after the thumb keys of a split board
+    pub fn grid_column(&self, row: usize, col: usize) -> Option<(usize, bool)> {
+        let Some(split) = self
+            .split
+            .filter(|s| s.split_col > 0 && s.split_col < self.cols)
+        else {
+            return Some((col, false));
+        };
+        match split.thumb_keys {
+            Some(keys) if row + 1 == self.rows => {
+                // Half of the thumb keys on each side of the gap
+                let left = keys / 2;
+                let offset = split.split_col.saturating_sub(left);
+                (col < keys).then_some((offset + col, col >= left))
+            }
+            _ => Some((col, col >= split.split_col)),
+        }
+    }
+
+    /// Pad `layer` with empty labels (and `_______` tokens) up to `target_len` keys
+    pub fn pad_layer(&mut self, layer: usize, target_len: usize) {
+        if let Some(legends) = self.legends.get_mut(layer) {
+            if legends.len() < target_len {
+                legends.resize(target_len, String::new());
+            }
+        }
+        if let Some(raw) = self.raw_legends.get_mut(layer) {
+            if raw.len() < target_len {
+                raw.resize(target_len, "_______".to_string());
+            }
+        }
+    }
+
+    /// Append an empty layer named `name`
+    pub fn add_layer(&mut self, name: String) {
+        let layer = self.raw_legends.len();
+        // Keep names aligned with the key data before naming the new layer
+        while self.layer_names.len() < layer {
+            self.layer_names
+                .push(format!("Layer {}", self.layer_names.len()));
+        }
+        self.layer_names.insert(layer, name);
+        self.legends.push(Vec::new());
+        self.raw_legends.push(Vec::new());
+        self.pad_layer(layer, self.rows * self.cols);
+    }
+
+    /// Copy every key of layer `from` into layer `to`, adding empty layers up to `to` if needed
+    ///
+    /// The name of `to` is kept; it has no effect when `from` does not exist.
+    pub fn clone_layer(&mut self, from: usize, to: usize) {
+        if from >= self.raw_legends.len() {
+            return;
+        }
+        while self.raw_legends.len() <= to {
+            let name = format!("Layer {}", self.raw_legends.len());
+            self.add_layer(name);
+        }
+        self.legends[to] = self.legends[from].clone();
+        self.raw_legends[to] = self.raw_legends[from].clone();
+    }
+
+    /// Exchange the keys and names of layers `a` and `b`
+    pub fn swap_layers(&mut self, a: usize, b: usize) -> anyhow::Result<()> {
+        let count = self.raw_legends.len();
+        if a >= count || b >= count {
+            anyhow::bail!("cannot swap layers {} and {}: only {} layers", a, b, count);
+        }
+        self.legends.swap(a, b);
+        self.raw_legends.swap(a, b);
+        if a.max(b) < self.layer_names.len() {
+            self.layer_names.swap(a, b);
+        }
+        Ok(())
+    }
+
+    /// Remove layer `index` and its name; the last remaining layer can't be removed
+    pub fn remove_layer(&mut self, index: usize) -> anyhow::Result<()> {
+        let count = self.raw_legends.len();
+        if index >= count {
+            anyhow::bail!("no layer {}: only {} layers", index, count);
+        }
+        if count == 1 {
+            anyhow::bail!("cannot remove the last layer");
+        }
+        self.legends.remove(index);
+        self.raw_legends.remove(index);
+        if index < self.layer_names.len() {
+            self.layer_names.remove(index);
+        }
+        Ok(())
+    }
+
+    /// Change the matrix to `new_rows x new_cols`
+    ///
+    /// Keys keep their `(row, col)`: cells inside the new bounds are preserved, new cells are
+    /// empty and cells outside are dropped. Keys stored past the end of the old grid count as
+    /// further rows, so growing the row count makes them visible.
+    pub fn resize(&mut self, new_rows: usize, new_cols: usize) {
+        let old_cols = self.cols;
+        let remap = |cells: &Vec<String>, empty: &str| -> Vec<String> {
+            (0..new_rows * new_cols)
+                .map(|idx| {
+                    let (row, col) = (idx / new_cols, idx % new_cols);
+                    if col < old_cols {
+                        cells.get(row * old_cols + col).cloned()
+                    } else {
+                        None
+                    }
+                    .unwrap_or_else(|| empty.to_string())
+                })
+                .collect()
+        };
+        self.legends = self.legends.iter().map(|l| remap(l, "")).collect();
+        self.raw_legends = self
+            .raw_legends
+            .iter()
+            .map(|l| remap(l, "_______"))
+            .collect();
+
+        // Positions are indexed like the legends, so they only survive a row change
+        if new_cols == old_cols {
+            if let Some(positions) = &mut self.physical_positions {
+                positions.truncate(new_rows * new_cols);
+            }
+        } else {
+            self.physical_positions = None;
+            self.split = None;
+        }
+        // The thumb keys no longer sit on the last row
+        if new_rows != self.rows {
+            if let Some(split) = &mut self.split {
+                split.thumb_keys = None;
+            }
+        }
+        self.rows = new_rows;
+        self.cols = new_cols;
+    }
+
+    /// Check that every layer has `rows * cols` keys, each with both a label and a token
+    pub fn validate_consistency(&self) -> Vec<ValidationWarning> {
+        let expected = self.rows * self.cols;
+        let layer_count = self.legends.len().max(self.raw_legends.len());
+        let mut warnings = Vec::new();
+        for layer in 0..layer_count {
+            let legends = self.legends.get(layer).map_or(0, Vec::len);
+            let raw = self.raw_legends.get(layer).map_or(0, Vec::len);
+            let found = legends.max(raw);
+            if found != expected {
+                warnings.push(ValidationWarning::LayerLengthMismatch {
+                    layer,
+                    expected,
+                    found,
+                });
+            }
+            warnings.extend(
+                (legends.min(raw)..found)
+                    .map(|index| ValidationWarning::PaddedKey { layer, index }),
+            );
+        }
+        warnings
+    }
+
+    /// List the `(row, col)` positions left transparent (`KC_TRNS`, `_______`, `KC_NO`) on a layer
+    pub fn unused_keys_in_layer(&self, layer: usize) -> Vec<(usize, usize)> {
+        let Some(raw) = self.raw_legends.get(layer) else {
+            return Vec::new();
+        };
+        raw.iter()
+            .enumerate()
+            .take(self.rows * self.cols)
+            .filter(|(_, tok)| matches!(tok.trim(), "KC_TRNS" | "_______" | "KC_NO"))
+            .map(|(idx, _)| (idx / self.cols, idx % self.cols))
+            .collect()
+    }
+
+    /// Hit test a point against the physical layout, `unit` being the size of 1U in pixels
+    ///
+    /// Returns the `(row, col)` of the first key whose bounding rect contains `(x, y)`,
+    /// or `None` when no physical positions are loaded or nothing is under the point.
+    pub fn key_at_position(&self, x: f32, y: f32, unit: f32) -> Option<(usize, usize)> {
+        if self.cols == 0 {
+            return None;
         }
+        let positions = self.physical_positions.as_ref()?;
+        positions
+            .iter()
+            .take(self.rows * self.cols)
+            .map(KeyPosition::placed)
+            .position(|pos| {
+                x >= pos.x * unit
+                    && x < (pos.x + pos.w) * unit
+                    && y >= pos.y * unit
+                    && y < (pos.y + pos.h) * unit
+            })
+            .map(|idx| (idx / self.cols, idx % self.cols))
     }
 
     /// Estimate keyboard dimensions based on total key count
+    ///
+    /// A known keyboard detected from the count wins (see
+    /// [`detect_by_key_count`](crate::keyboards::registry::detect_by_key_count)); otherwise
+    /// every factorization `rows * cols` of `n` (or `n + 1`, for the Planck MIT 2U spacebar)
+    /// with `rows <= cols` is scored by how close `cols / rows` is to 3:1, which is both the
+    /// common ergo ratio and the 12:4 ortho grid. `n - 1` is never used since it could not hold every key.
     fn estimate_dimensions(total_keys: usize) -> (usize, usize) {
-        match total_keys {
-            0..=20 => (3, 7),     // Small keyboards
-            21..=40 => (4, 10),   // 40% keyboards
-            41..=50 => (4, 12),   // Planck-like
-            51..=60 => (5, 12),   // 60% keyboards
-            61..=70 => (5, 14),   // 65% keyboards
-            71..=80 => (6, 14),   // 75% keyboards
-            81..=90 => (6, 15),   // 80% keyboards
-            91..=100 => (6, 17),  // 90% keyboards
-            101..=110 => (6, 18), // 100% keyboards
-            _ => {
-                // For very large keyboards, try to make a reasonable grid
-                let cols = ((total_keys as f64).sqrt().ceil() as usize).max(10);
-                let rows = total_keys.div_ceil(cols);
-                (rows, cols)
+        // Ideal columns per row (3:1 ergo, 12:4 ortho)
+        const IDEAL_RATIO: f32 = 3.0;
+        // Slight preference for grids without an empty cell
+        const PADDING_PENALTY: f32 = 0.1;
+
+        if total_keys == 0 {
+            // Nothing to lay out; keep a small placeholder grid
+            return (3, 7);
+        }
+        if let Some(known) = crate::keyboards::registry::detect_by_key_count(total_keys) {
+            return (known.rows, known.cols);
+        }
+
+        let mut best: Option<((usize, usize), f32)> = None;
+        for (n, penalty) in [(total_keys, 0.0), (total_keys + 1, PADDING_PENALTY)] {
+            for rows in (1..=n).take_while(|r| r * r <= n) {
+                if !n.is_multiple_of(rows) {
+                    continue;
+                }
+                let cols = n / rows;
+                let score = (cols as f32 / rows as f32 - IDEAL_RATIO).abs() + penalty;
+                if best.is_none_or(|(_, best_score)| score < best_score) {
+                    best = Some(((rows, cols), score));
+                }
             }
         }
+        best.map(|(dims, _)| dims).unwrap_or((1, total_keys))
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct KeyboardState {
     pub keyboard: KeyboardLayout,
     pub active_layer: u8,
-    pub pressed_bits: u64,
+    pub pressed_bits: PressedBits,
+    /// Keyboard locale (e.g. "de", "fr_FR") used to resolve AltGr combos
+    pub locale: Option<String>,
+    /// OS layout used to label letter keys; `None` shows the US labels
+    pub keyboard_locale: Option<KeyboardLocale>,
+    /// Glyphs the UI fonts can render; `None` means no probe has run yet
+    pub supported_glyphs: Option<HashSet<char>>,
+    /// Layers activated on top of the active one (e.g. an armed one-shot layer), oldest first
+    pub layer_stack: Vec<u8>,
+    /// When each currently pressed key went down, by key index
+    pub pressed_started: HashMap<usize, Instant>,
+    /// Number of presses per key index over all layers, for the heatmap
+    pub press_counts: Vec<u64>,
+    /// Number of presses per key index made while each layer was active, by layer
+    pub layer_press_counts: Vec<Vec<u64>>,
+    /// Hold time after which a mod-tap key is shown as its modifier
+    pub mt_hold_threshold: Duration,
+    /// Labels for the keymap's own keycodes, used before the built-in translation
+    pub custom_table: CustomKeycodeTable,
+}
+
+/// JSON snapshot of a keyboard state, used for UI regression tests
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    rows: usize,
+    cols: usize,
+    layer_names: &'a [String],
+    raw_legends: &'a [Vec<String>],
+    legends: &'a [Vec<String>],
+    active_layer: u8,
+    pressed_bits: PressedBits,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    physical_positions: Option<&'a [KeyPosition]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    split: Option<SplitConfig>,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    rows: usize,
+    cols: usize,
+    layer_names: Vec<String>,
+    raw_legends: Vec<Vec<String>>,
+    legends: Vec<Vec<String>>,
+    active_layer: u8,
+    pressed_bits: PressedBits,
+    #[serde(default)]
+    physical_positions: Option<Vec<KeyPosition>>,
+    #[serde(default)]
+    split: Option<SplitConfig>,
+}
+
+impl Serialize for KeyboardState {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SnapshotRef {
+            rows: self.keyboard.rows,
+            cols: self.keyboard.cols,
+            layer_names: &self.keyboard.layer_names,
+            raw_legends: &self.keyboard.raw_legends,
+            legends: &self.keyboard.legends,
+            active_layer: self.active_layer,
+            pressed_bits: self.pressed_bits,
+            physical_positions: self.keyboard.physical_positions.as_deref(),
+            split: self.keyboard.split,
+        }
+        .serialize(serializer)
+    }
 }
 
 impl KeyboardState {
+    /// Default for [`KeyboardState::mt_hold_threshold`]
+    pub const DEFAULT_MT_HOLD: Duration = Duration::from_millis(500);
+
+    /// Serialize the layout, active layer and pressed keys as pretty-printed JSON
+    pub fn to_snapshot_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("keyboard state serializes to JSON")
+    }
+
+    /// Rebuild a state from [`KeyboardState::to_snapshot_json`] output
+    pub fn from_snapshot_json(s: &str) -> anyhow::Result<Self> {
+        let snapshot: Snapshot = serde_json::from_str(s)?;
+        let mut state = Self::new(KeyboardLayout {
+            rows: snapshot.rows,
+            cols: snapshot.cols,
+            layer_names: snapshot.layer_names,
+            legends: snapshot.legends,
+            raw_legends: snapshot.raw_legends,
+            physical_positions: snapshot.physical_positions,
+            split: snapshot.split,
+        });
+        state.active_layer = snapshot.active_layer;
+        state.pressed_bits = snapshot.pressed_bits;
+        Ok(state)
+    }
+
     pub fn new(keyboard: KeyboardLayout) -> Self {
         Self {
             keyboard,
             active_layer: 0,
-            pressed_bits: 0,
+            pressed_bits: PressedBits::default(),
+            locale: None,
+            keyboard_locale: None,
+            supported_glyphs: None,
+            layer_stack: Vec::new(),
+            pressed_started: HashMap::new(),
+            press_counts: Vec::new(),
+            layer_press_counts: Vec::new(),
+            mt_hold_threshold: Self::DEFAULT_MT_HOLD,
+            custom_table: CustomKeycodeTable::default(),
+        }
+    }
+
+    pub fn set_locale(&mut self, locale: Option<String>) {
+        self.locale = locale;
+    }
+
+    pub fn set_keyboard_locale(&mut self, locale: Option<KeyboardLocale>) {
+        self.keyboard_locale = locale;
+    }
+
+    /// Label for a keycode, taking custom keycodes and the keyboard locale into account
+    fn translate(&self, tok: &str) -> String {
+        if let Some(label) = self.custom_table.translate(tok) {
+            return label.to_string();
+        }
+        match self.keyboard_locale {
+            Some(locale) => translate_token_locale(tok, locale),
+            None => translate_token(tok),
         }
     }
 
@@ -114,10 +791,126 @@ impl KeyboardState {
         self.active_layer = layer;
     }
 
-    pub fn set_pressed_bits(&mut self, bits: u64) {
+    /// Show `bits` as the pressed keys, recording when newly pressed keys went down and
+    /// counting them as presses on the active layer
+    pub fn set_pressed_bits(&mut self, bits: impl Into<PressedBits>) {
+        let bits = bits.into();
+        let now = Instant::now();
+        self.pressed_started.retain(|&idx, _| bits.is_set(idx));
+        for idx in bits.iter() {
+            self.pressed_started.entry(idx).or_insert(now);
+        }
+        let layer = self.active_layer as usize;
+        if self.layer_press_counts.len() <= layer {
+            self.layer_press_counts.resize_with(layer + 1, Vec::new);
+        }
+        for idx in (bits & !self.pressed_bits).iter() {
+            for counts in [&mut self.press_counts, &mut self.layer_press_counts[layer]] {
+                if counts.len() <= idx {
+                    counts.resize(idx + 1, 0);
+                }
+                counts[idx] += 1;
+            }
+        }
         self.pressed_bits = bits;
     }
 
+    /// Presses of key `key_index`, on `layer` only or on every layer when `None`
+    pub fn press_count(&self, key_index: usize, layer: Option<usize>) -> u64 {
+        self.counts_for(layer).get(key_index).copied().unwrap_or(0)
+    }
+
+    /// Highest [`KeyboardState::press_count`] of any key
+    pub fn max_press_count(&self, layer: Option<usize>) -> u64 {
+        self.counts_for(layer).iter().copied().max().unwrap_or(0)
+    }
+
+    fn counts_for(&self, layer: Option<usize>) -> &[u64] {
+        match layer {
+            Some(layer) => self
+                .layer_press_counts
+                .get(layer)
+                .map_or(&[], Vec::as_slice),
+            None => &self.press_counts,
+        }
+    }
+
+    /// Forget all press counts
+    pub fn reset_heatmap(&mut self) {
+        self.press_counts.clear();
+        self.layer_press_counts.clear();
+    }
+
+    /// How long key `key_index` has been held, or `None` if it is not pressed
+    pub fn press_duration(&self, key_index: usize) -> Option<Duration> {
+        self.press_duration_at(key_index, Instant::now())
+    }
+
+    fn press_duration_at(&self, key_index: usize, now: Instant) -> Option<Duration> {
+        if !self.pressed_bits.is_set(key_index) {
+            return None;
+        }
+        self.pressed_started
+            .get(&key_index)
+            .map(|t0| now.saturating_duration_since(*t0))
+    }
+
+    /// Show the layer and pressed keys of `rep`
+    pub fn apply_report(&mut self, rep: &Report) {
+        self.set_layer(rep.active_layer);
+        self.set_pressed_bits(rep.pressed_bits);
+    }
+
+    /// Replay `(key_index, is_press)` events without waiting: event `i` happens
+    /// `delays_ms[i]` after the previous one, the first one counting from `reference_time`
+    ///
+    /// Updates `pressed_bits` and `pressed_started` as live reports would, so hold-dependent
+    /// rendering can be tested deterministically (pass a `reference_time` in the past).
+    pub fn simulate_press_sequence(
+        &mut self,
+        sequence: &[(usize, bool)],
+        delays_ms: &[u64],
+        reference_time: Instant,
+    ) {
+        let mut time = reference_time;
+        for (i, &(idx, is_press)) in sequence.iter().enumerate() {
+            time += Duration::from_millis(delays_ms.get(i).copied().unwrap_or(0));
+            if idx >= PressedBits::CAPACITY {
+                continue;
+            }
+            if is_press {
+                self.pressed_started.entry(idx).or_insert(time);
+                self.pressed_bits.set(idx);
+            } else {
+                self.pressed_started.remove(&idx);
+                self.pressed_bits.clear(idx);
+            }
+        }
+    }
+
+    /// Whether a pressed mod-tap key has been held long enough to act as its modifier
+    pub fn is_mt_held(&self, layer: usize, row: usize, col: usize, now: Instant) -> bool {
+        self.is_pressed(row, col)
+            && self.is_mt_key(layer, row, col)
+            && self
+                .index_for(row, col)
+                .and_then(|idx| self.press_duration_at(idx, now))
+                .is_some_and(|held| held >= self.mt_hold_threshold)
+    }
+
+    /// Whether every key of a chord (e.g. a combo's keys, as key indexes) is pressed
+    pub fn key_chord_active(&self, indices: &[usize]) -> bool {
+        !indices.is_empty() && self.key_chord_partial(indices) == indices.len()
+    }
+
+    /// How many keys of a chord are currently pressed
+    pub fn key_chord_partial(&self, indices: &[usize]) -> usize {
+        indices
+            .iter()
+            .filter(|&&i| self.pressed_bits.is_set(i))
+            .count()
+    }
+
     pub fn index_for(&self, row: usize, col: usize) -> Option<usize> {
         if row < self.keyboard.rows && col < self.keyboard.cols {
             Some(row * self.keyboard.cols + col)
@@ -127,10 +920,24 @@ impl KeyboardState {
     }
 
     pub fn is_pressed(&self, row: usize, col: usize) -> bool {
-        match self.index_for(row, col) {
-            Some(i) if i < 64 => ((self.pressed_bits >> i) & 1) == 1,
-            _ => false,
-        }
+        self.index_for(row, col)
+            .is_some_and(|i| self.pressed_bits.is_set(i))
+    }
+
+    /// Flat indices of the pressed keys that exist on the board, in ascending order; one
+    /// step per pressed key rather than a scan of the whole matrix
+    pub fn iter_pressed_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        let key_count = self.keyboard.rows * self.keyboard.cols;
+        self.pressed_bits
+            .iter()
+            .take_while(move |&idx| idx < key_count)
+    }
+
+    /// `(row, col)` of each pressed key, in the order of [`Self::iter_pressed_indices`]
+    pub fn iter_pressed_keys(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let cols = self.keyboard.cols;
+        self.iter_pressed_indices()
+            .map(move |idx| (idx / cols, idx % cols))
     }
 
     pub fn legend_at(&self, layer: usize, row: usize, col: usize) -> Option<&str> {
@@ -151,6 +958,62 @@ impl KeyboardState {
             .map(|s| s.as_str())
     }
 
+    /// Replace the raw token of a key and refresh its label; returns the previous token
+    pub fn set_raw_token(
+        &mut self,
+        layer: usize,
+        row: usize,
+        col: usize,
+        token: &str,
+    ) -> Option<String> {
+        let idx = self.index_for(row, col)?;
+        let raw = self.keyboard.raw_legends.get_mut(layer)?.get_mut(idx)?;
+        let previous = std::mem::replace(raw, token.to_string());
+        if let Some(legend) = self
+            .keyboard
+            .legends
+            .get_mut(layer)
+            .and_then(|l| l.get_mut(idx))
+        {
+            *legend = translate_token(token);
+        }
+        Some(previous)
+    }
+
+    /// Keys of `layer` whose raw token or displayed labels contain `query` (case-insensitive)
+    pub fn find_key_by_label(&self, query: &str, layer: usize) -> Vec<(usize, usize)> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let (rows, cols) = (self.keyboard.rows, self.keyboard.cols);
+        (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| (row, col)))
+            .filter(|&(row, col)| {
+                let (main, sub) = self.display_parts(layer, row, col);
+                [
+                    self.raw_legend_at(layer, row, col).unwrap_or_default(),
+                    self.legend_at(layer, row, col).unwrap_or_default(),
+                    &main,
+                    &sub,
+                ]
+                .iter()
+                .any(|text| text.to_lowercase().contains(&query))
+            })
+            .collect()
+    }
+
+    /// [`KeyboardState::find_key_by_label`] over every layer, as `(layer, row, col)`
+    pub fn find_key_by_label_all_layers(&self, query: &str) -> Vec<(usize, usize, usize)> {
+        (0..self.keyboard.raw_legends.len())
+            .flat_map(|layer| {
+                self.find_key_by_label(query, layer)
+                    .into_iter()
+                    .map(move |(row, col)| (layer, row, col))
+            })
+            .collect()
+    }
+
     pub fn is_transparent_key(&self, layer: usize, row: usize, col: usize) -> bool {
         match self.raw_legend_at(layer, row, col) {
             Some(r) => {
@@ -205,30 +1068,183 @@ impl KeyboardState {
         matches!(self.raw_legend_at(layer, row, col), Some(r) if r.trim_start().starts_with("OSL("))
     }
 
-    pub fn is_shift_pressed(&self) -> bool {
-        // Check if any shift key is pressed by looking for MOD_LSFT, MOD_RSFT, KC_LSFT, KC_RSFT, etc.
-        for row in 0..self.keyboard.rows {
-            for col in 0..self.keyboard.cols {
-                if self.is_pressed(row, col) {
-                    if let Some(raw) = self.raw_legend_at(self.active_layer as usize, row, col) {
-                        let s = raw.trim();
-                        if s.contains("MOD_LSFT")
-                            || s.contains("MOD_RSFT")
-                            || s.contains("KC_LSFT")
-                            || s.contains("KC_RSFT")
-                            || s.contains("LSFT")
-                            || s.contains("RSFT")
-                        {
-                            return true;
-                        }
-                    }
-                }
+    pub fn is_to_key(&self, layer: usize, row: usize, col: usize) -> bool {
+        matches!(self.raw_legend_at(layer, row, col), Some(r) if r.trim_start().starts_with("TO("))
+    }
+
+    pub fn is_df_key(&self, layer: usize, row: usize, col: usize) -> bool {
+        matches!(self.raw_legend_at(layer, row, col), Some(r) if r.trim_start().starts_with("DF("))
+    }
+
+    /// Human-friendly name for a layer token as used in MO/LT/TO/DF
+    ///
+    /// Numeric indices look up the loaded layer names, identifiers are matched
+    /// case-insensitively against them, and anything else falls back to
+    /// [`layer_display_name`].
+    pub fn resolve_layer_name(&self, token: &str) -> String {
+        let t = token.trim();
+        if let Ok(n) = t.parse::<usize>() {
+            return self
+                .keyboard
+                .layer_names
+                .get(n)
+                .cloned()
+                .unwrap_or_else(|| t.to_string());
+        }
+        let ident = t.trim_start_matches('_');
+        match self
+            .keyboard
+            .layer_names
+            .iter()
+            .find(|name| name.eq_ignore_ascii_case(t) || name.eq_ignore_ascii_case(ident))
+        {
+            Some(name) => layer_display_name(name),
+            None => layer_display_name(ident),
+        }
+    }
+
+    /// Index of the layer a layer-key argument refers to (number or layer name)
+    fn resolve_layer_index(&self, token: &str) -> Option<usize> {
+        let t = token.trim();
+        if let Ok(n) = t.parse::<usize>() {
+            return Some(n);
+        }
+        let ident = t.trim_start_matches('_');
+        self.keyboard
+            .layer_names
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(t) || name.eq_ignore_ascii_case(ident))
+    }
+
+    /// Whether the one-shot layer `layer` is armed (on the layer stack)
+    pub fn is_osl_active(&self, layer: u8) -> bool {
+        self.layer_stack.contains(&layer)
+    }
+
+    /// Layers a key falls through, as `(layer, raw token)` pairs
+    ///
+    /// Starts at the top of `layer_stack` (or `active_layer` when the stack is empty), walks
+    /// down to layer 0 and stops at the first key that is not transparent. Transparent keys
+    /// are included so the whole inheritance path can be shown.
+    pub fn transparent_chain(&self, row: usize, col: usize) -> Vec<(usize, String)> {
+        let mut layers: Vec<usize> = self.layer_stack.iter().rev().map(|&l| l as usize).collect();
+        for layer in (0..=self.active_layer as usize).rev() {
+            if !layers.contains(&layer) {
+                layers.push(layer);
+            }
+        }
+        let mut chain = Vec::new();
+        for layer in layers {
+            let Some(raw) = self.raw_legend_at(layer, row, col) else {
+                continue;
+            };
+            let raw = raw.trim();
+            chain.push((layer, raw.to_string()));
+            if !matches!(raw, "KC_TRNS" | "KC_TRANSPARENT" | "_______") {
+                break;
             }
         }
-        false
+        chain
+    }
+
+    /// Raw keycode a key on the active layer actually sends
+    ///
+    /// Walks down from `active_layer` to layer 0, skipping `KC_TRNS`, `_______`, `KC_NO` and empty slots.
+    /// Every lower layer is treated as active; `layer_state` is not modelled yet.
+    pub fn effective_keycode_at(&self, row: usize, col: usize) -> Option<&str> {
+        let layer = self.effective_layer_from(self.active_layer as usize, row, col)?;
+        self.raw_legend_at(layer, row, col).map(str::trim)
+    }
+
+    /// First layer at or below `layer` whose key is not transparent, see
+    /// [`KeyboardState::effective_keycode_at`]
+    pub fn effective_layer_from(&self, layer: usize, row: usize, col: usize) -> Option<usize> {
+        (0..=layer).rev().find(|&l| {
+            self.raw_legend_at(l, row, col).is_some_and(|raw| {
+                !matches!(
+                    raw.trim(),
+                    "" | "KC_TRNS" | "KC_TRANSPARENT" | "_______" | "KC_NO"
+                )
+            })
+        })
+    }
+
+    /// True when a pressed key on the active layer carries any of `modifier_tokens`
+    /// (plain keycodes, MOD_* masks and MT / *_T wrapped modifiers all contain the token)
+    fn is_modifier_pressed(&self, modifier_tokens: &[&str]) -> bool {
+        let layer = self.active_layer as usize;
+        self.iter_pressed_keys().any(|(row, col)| {
+            self.raw_legend_at(layer, row, col).is_some_and(|raw| {
+                let s = raw.trim();
+                modifier_tokens.iter().any(|tok| s.contains(tok))
+            })
+        })
+    }
+
+    pub fn is_shift_pressed(&self) -> bool {
+        self.is_modifier_pressed(&["MOD_LSFT", "MOD_RSFT", "KC_LSFT", "KC_RSFT", "LSFT", "RSFT"])
+    }
+
+    pub fn is_ctrl_pressed(&self) -> bool {
+        self.is_modifier_pressed(&[
+            "MOD_LCTL", "MOD_RCTL", "KC_LCTL", "KC_RCTL", "KC_LCTRL", "KC_RCTRL", "LCTL", "RCTL",
+        ])
+    }
+
+    pub fn is_alt_pressed(&self) -> bool {
+        self.is_modifier_pressed(&[
+            "MOD_LALT", "MOD_RALT", "KC_LALT", "KC_RALT", "KC_LOPT", "KC_ROPT", "LALT", "RALT",
+        ])
+    }
+
+    pub fn is_gui_pressed(&self) -> bool {
+        self.is_modifier_pressed(&[
+            "MOD_LGUI", "MOD_RGUI", "KC_LGUI", "KC_RGUI", "KC_LCMD", "KC_RCMD", "KC_LWIN",
+            "KC_RWIN", "LGUI", "RGUI", "LCMD", "RCMD",
+        ])
+    }
+
+    /// Apply held modifiers to a single-letter label: Shift uppercases it and
+    /// Ctrl / Alt prefix the chord (e.g. "Ctrl+C")
+    fn apply_held_modifiers(&self, main: String) -> String {
+        if main.len() != 1 || !main.chars().next().unwrap().is_ascii_lowercase() {
+            return main;
+        }
+        let ctrl = self.is_ctrl_pressed();
+        let alt = self.is_alt_pressed();
+        if !ctrl && !alt {
+            return if self.is_shift_pressed() {
+                main.to_uppercase()
+            } else {
+                main
+            };
+        }
+        let mut label = String::new();
+        if ctrl {
+            label.push_str("Ctrl+");
+        }
+        if alt {
+            label.push_str("Alt+");
+        }
+        if self.is_shift_pressed() {
+            label.push_str("Shift+");
+        }
+        label.push_str(&main.to_uppercase());
+        label
     }
 
     pub fn display_parts(&self, layer: usize, row: usize, col: usize) -> (String, String) {
+        let (main, sub) = self.display_parts_unsanitized(layer, row, col);
+        match &self.supported_glyphs {
+            Some(supported) => (
+                sanitize_glyphs(&main, supported),
+                sanitize_glyphs(&sub, supported),
+            ),
+            None => (main, sub),
+        }
+    }
+
+    fn display_parts_unsanitized(&self, layer: usize, row: usize, col: usize) -> (String, String) {
         let Some(raw) = self.raw_legend_at(layer, row, col) else {
             return (String::new(), String::new());
         };
@@ -238,20 +1254,11 @@ impl KeyboardState {
             return (String::new(), String::new());
         }
 
-        let shift_pressed = self.is_shift_pressed();
-
         // MT(mod, key) => main=key, sub=mod glyph
         if let Some(inner) = s.strip_prefix("MT(").and_then(|t| t.strip_suffix(')')) {
             let parts: Vec<&str> = inner.split(',').map(|p| p.trim()).collect();
             if parts.len() >= 2 {
-                let mut main = translate_token(parts[1]);
-                // Apply shift transformation to letters
-                if shift_pressed
-                    && main.len() == 1
-                    && main.chars().next().unwrap().is_ascii_lowercase()
-                {
-                    main = main.to_uppercase();
-                }
+                let main = self.apply_held_modifiers(self.translate(parts[1]));
                 let sub = mod_to_glyph(parts[0]);
                 return (main, sub);
             }
@@ -261,14 +1268,7 @@ impl KeyboardState {
         if let Some(pos) = s.find("_T(") {
             let (mod_tok, rest) = s.split_at(pos);
             if let Some(inner) = rest.strip_prefix("_T(").and_then(|t| t.strip_suffix(')')) {
-                let mut main = translate_token(inner.trim());
-                // Apply shift transformation to letters
-                if shift_pressed
-                    && main.len() == 1
-                    && main.chars().next().unwrap().is_ascii_lowercase()
-                {
-                    main = main.to_uppercase();
-                }
+                let main = self.apply_held_modifiers(self.translate(inner.trim()));
                 let sub = mod_to_glyph(mod_tok.trim());
                 return (main, sub);
             }
@@ -277,34 +1277,46 @@ impl KeyboardState {
         if let Some(inner) = s.strip_prefix("LT(").and_then(|t| t.strip_suffix(')')) {
             let parts: Vec<&str> = inner.split(',').map(|p| p.trim()).collect();
             if parts.len() >= 2 {
-                let mut main = translate_token(parts[1]);
-                // Apply shift transformation to letters
-                if shift_pressed
-                    && main.len() == 1
-                    && main.chars().next().unwrap().is_ascii_lowercase()
-                {
-                    main = main.to_uppercase();
-                }
-                let layer_tok = parts[0];
-                let sub = layer_display_name(layer_tok);
+                let main = self.apply_held_modifiers(self.translate(parts[1]));
+                let sub = self.resolve_layer_name(parts[0]);
                 return (main, sub);
             }
         }
-        // MO(layer) / OSL(layer) => main=layer, sub=MO/OSL
+        // MO(layer) => main=layer, sub=MO
         if let Some(inner) = s.strip_prefix("MO(").and_then(|t| t.strip_suffix(')')) {
-            let main = layer_display_name(inner);
+            let main = self.resolve_layer_name(inner);
             return (main, "MO".to_string());
         }
-        if let Some(_inner) = s.strip_prefix("OSL(").and_then(|t| t.strip_suffix(')')) {
-            // OSL: show only a star, single line (colored in UI)
-            return ("★".to_string(), String::new());
+        if let Some(inner) = s.strip_prefix("OSL(").and_then(|t| t.strip_suffix(')')) {
+            // OSL(layer) => main=star (doubled while armed), sub=layer name (colored in UI)
+            let armed = self
+                .resolve_layer_index(inner)
+                .and_then(|idx| u8::try_from(idx).ok())
+                .is_some_and(|idx| self.is_osl_active(idx));
+            let main = if armed { "★★" } else { "★" };
+            return (main.to_string(), self.resolve_layer_name(inner));
         }
-        // Default: single label
-        let mut main = translate_token(s);
-        // Apply shift transformation to letters
-        if shift_pressed && main.len() == 1 && main.chars().next().unwrap().is_ascii_lowercase() {
-            main = main.to_uppercase();
+        // ALGR(key) => main=key (locale-aware AltGr symbol), sub=AltGr
+        if let Some(inner) = s.strip_prefix("ALGR(").and_then(|t| t.strip_suffix(')')) {
+            let main = match self.locale.as_deref() {
+                Some(locale) => translate_algr_combo(inner, locale),
+                None => self.translate(inner),
+            };
+            return (main, "AltGr".to_string());
+        }
+        // On European layouts the right Alt key acts as AltGr
+        if (s == "KC_RALT" || s == "KC_ALGR") && self.locale.is_some() {
+            return ("AltGr".to_string(), String::new());
         }
+        // TO(layer) / DF(layer) => main=layer name, sub=TO/DF
+        if let Some(inner) = s.strip_prefix("TO(").and_then(|t| t.strip_suffix(')')) {
+            return (self.resolve_layer_name(inner), "TO".to_string());
+        }
+        if let Some(inner) = s.strip_prefix("DF(").and_then(|t| t.strip_suffix(')')) {
+            return (self.resolve_layer_name(inner), "DF".to_string());
+        }
+        // Default: single label
+        let main = self.apply_held_modifiers(self.translate(s));
         (main, String::new())
     }
 }
@@ -335,11 +1347,171 @@ mod tests {
         assert_eq!(layout.legends[1][0], "1");
     }
 
+    #[test]
+    fn test_unused_keys_in_layer() {
+        let names = ["Base", "Lower", "Raise"].map(String::from).to_vec();
+        let mut layout = KeyboardLayout::new(3, 4, names);
+        layout.raw_legends[0] = vec!["KC_A".to_string(); 12];
+        layout.raw_legends[1] = vec!["KC_1".to_string(); 12];
+        layout.raw_legends[2] = vec!["KC_F1".to_string(); 12];
+        for (idx, tok) in [
+            (0, "_______"),
+            (3, "_______"),
+            (4, "KC_TRNS"),
+            (7, "KC_NO"),
+            (11, "_______"),
+        ] {
+            layout.raw_legends[1][idx] = tok.to_string();
+        }
+
+        let unused = layout.unused_keys_in_layer(1);
+        assert_eq!(unused.len(), 5);
+        assert_eq!(unused, vec![(0, 0), (0, 3), (1, 0), (1, 3), (2, 3)]);
+        assert!(layout.unused_keys_in_layer(0).is_empty());
+        assert!(layout.unused_keys_in_layer(5).is_empty());
+    }
+
+    #[test]
+    fn test_with_uniform_grid() {
+        let layout = KeyboardLayout::with_uniform_grid(4, 12, 3);
+        assert_eq!(layout.layer_names, vec!["Layer 0", "Layer 1", "Layer 2"]);
+        assert_eq!(layout.legends.len(), 3);
+        assert_eq!(layout.raw_legends[2].len(), 48);
+
+        let positions = layout.physical_positions.as_ref().unwrap();
+        assert_eq!(positions.len(), 48);
+        crate::keyboards::assert_no_overlap(positions);
+        // Every key is found at its own grid cell
+        assert_eq!(layout.key_at_position(5.5, 2.5, 1.0), Some((2, 5)));
+    }
+
+    #[test]
+    fn test_from_info_json() {
+        let json = r#"{
+            "keyboard_name": "tiny",
+            "layouts": {
+                "LAYOUT": {
+                    "layout": [
+                        {"matrix": [0, 0], "x": 0, "y": 0, "w": 1.5},
+                        {"matrix": [0, 1], "x": 1.5, "y": 0},
+                        {"matrix": [1, 0], "x": 0.25, "y": 1, "w": 2.25},
+                        {"matrix": [1, 1], "x": 3, "y": 0.5, "r": 90, "rx": 3, "ry": 0.5}
+                    ]
+                }
+            }
+        }"#;
+        let layers = vec![["KC_TAB", "KC_Q", "KC_SPC", "KC_ENT"]
+            .map(String::from)
+            .to_vec()];
+        let layout = KeyboardLayout::from_info_json(json, "LAYOUT", layers.clone()).unwrap();
+        let positions = layout.physical_positions.as_ref().unwrap();
+        assert_eq!(positions.len(), 4);
+        assert_eq!(positions[0], KeyPosition::new(0.0, 0.0, 1.5, 1.0));
+        assert_eq!(positions[2].w, 2.25);
+        assert_eq!(positions[3].rotation_angle, 90.0);
+        // Turned a quarter clockwise around its top-left corner: now left of that corner
+        let placed = positions[3].placed();
+        assert!((placed.x - 2.0).abs() < 1e-5 && (placed.y - 0.5).abs() < 1e-5);
+        assert_eq!(layout.raw_legends[0][3], "KC_ENT");
+
+        assert!(KeyboardLayout::from_info_json(json, "LAYOUT_ortho", layers).is_err());
+        assert_eq!(
+            KeyboardLayout::info_json_matrix(json, None).unwrap(),
+            vec![(0, 0), (0, 1), (1, 0), (1, 1)]
+        );
+        assert_eq!(
+            KeyboardLayout::info_json_layout_names(json).unwrap(),
+            vec!["LAYOUT".to_string()]
+        );
+        let short = vec![vec!["KC_A".to_string()]];
+        assert!(KeyboardLayout::from_info_json(json, "LAYOUT", short).is_err());
+        let empty = r#"{"layouts": {"LAYOUT": {"layout": []}}}"#;
+        let grid = KeyboardLayout::from_info_json(empty, "LAYOUT", vec![vec![]]).unwrap();
+        assert!(grid.physical_positions.is_none());
+    }
+
+    #[test]
+    fn test_from_kle_json() {
+        let kle = r#"[
+            {"name": "test"},
+            ["Esc", {"x": 0.5}, "F1"],
+            [{"w": 1.5}, "Tab", "Q"],
+            [{"r": 15, "rx": 3, "ry": 2}, "A", {"d": true}, "decal", {"y": 0.5}, "B"],
+            ["C"]
+        ]"#;
+        let layout = KeyboardLayout::from_kle_json(kle).unwrap();
+        let positions = layout.physical_positions.as_ref().unwrap();
+        let boxes: Vec<_> = positions.iter().map(|p| (p.x, p.y, p.w)).collect();
+        assert_eq!(
+            boxes,
+            [
+                (0.0, 0.0, 1.0),
+                (1.5, 0.0, 1.0),
+                (0.0, 1.0, 1.5),
+                (1.5, 1.0, 1.0),
+                (3.0, 2.0, 1.0),
+                (5.0, 2.5, 1.0),
+                // Rows of a rotated cluster start at its rx
+                (3.0, 3.5, 1.0),
+            ]
+        );
+        assert_eq!(positions[3].rotation_angle, 0.0);
+        assert!(positions[4..]
+            .iter()
+            .all(|p| (p.rotation_angle, p.rotation_x, p.rotation_y) == (15.0, 3.0, 2.0)));
+        // Only the key shapes are imported
+        assert_eq!(layout.raw_legends.len(), 1);
+        assert!(layout.raw_legends[0][..7].iter().all(String::is_empty));
+
+        assert!(KeyboardLayout::from_kle_json(r#"{"layers": []}"#).is_err());
+        assert!(KeyboardLayout::from_kle_json("[]").is_err());
+    }
+
+    #[test]
+    fn test_key_at_position() {
+        let mut layout = KeyboardLayout::new(2, 3, vec!["Base".to_string()]);
+        assert_eq!(layout.key_at_position(10.0, 10.0, 64.0), None);
+
+        let key = |x: f32, y: f32, w: f32| KeyPosition::new(x, y, w, 1.0);
+        // Top row of three 1U keys, bottom row with a 2U spacebar in the middle
+        layout.physical_positions = Some(vec![
+            key(0.0, 0.0, 1.0),
+            key(1.0, 0.0, 1.0),
+            key(2.0, 0.0, 1.0),
+            key(0.0, 1.0, 0.5),
+            key(0.5, 1.0, 2.0),
+            key(2.5, 1.0, 0.5),
+        ]);
+
+        let unit = 64.0;
+        // Center of the 2U spacebar: x = (0.5 + 1.0) * unit, y = 1.5 * unit
+        assert_eq!(
+            layout.key_at_position(1.5 * unit, 1.5 * unit, unit),
+            Some((1, 1))
+        );
+        // Right half of the spacebar, under what would be column 2 on a uniform grid
+        assert_eq!(
+            layout.key_at_position(2.2 * unit, 1.5 * unit, unit),
+            Some((1, 1))
+        );
+        assert_eq!(
+            layout.key_at_position(2.7 * unit, 1.5 * unit, unit),
+            Some((1, 2))
+        );
+        assert_eq!(
+            layout.key_at_position(0.5 * unit, 0.5 * unit, unit),
+            Some((0, 0))
+        );
+        assert_eq!(layout.key_at_position(3.5 * unit, 0.5 * unit, unit), None);
+    }
+
     #[test]
     fn test_estimate_dimensions() {
         assert_eq!(KeyboardLayout::estimate_dimensions(48), (4, 12)); // Planck-like
         assert_eq!(KeyboardLayout::estimate_dimensions(60), (5, 12)); // 60% keyboard
-        assert_eq!(KeyboardLayout::estimate_dimensions(100), (6, 17)); // 100% keyboard
+        assert_eq!(KeyboardLayout::estimate_dimensions(108), (6, 18)); // 100% keyboard
+        assert_eq!(KeyboardLayout::estimate_dimensions(100), (5, 20));
+        assert_eq!(KeyboardLayout::estimate_dimensions(47), (4, 12)); // Planck MIT
     }
 
     #[test]
@@ -366,11 +1538,128 @@ mod tests {
     fn test_is_pressed() {
         let layout = KeyboardLayout::new(4, 12, vec!["Base".to_string()]);
         let mut state = KeyboardState::new(layout);
-        state.set_pressed_bits(1); // First key pressed
+        state.apply_report(&Report::new_for_test(0, &[0])); // First key pressed
         assert!(state.is_pressed(0, 0));
         assert!(!state.is_pressed(0, 1));
     }
 
+    #[test]
+    fn test_iter_pressed_keys_matches_is_pressed() {
+        let layout = KeyboardLayout::new(3, 50, vec!["Base".to_string()]);
+        let mut state = KeyboardState::new(layout);
+        // Pressed keys in both 64-bit words of the bitmask
+        state.apply_report(&Report::new_for_test(0, &[0, 49, 63, 64, 101, 127]));
+
+        let expected: Vec<(usize, usize)> = (0..3)
+            .flat_map(|row| (0..50).map(move |col| (row, col)))
+            .filter(|&(row, col)| state.is_pressed(row, col))
+            .collect();
+        assert_eq!(state.iter_pressed_keys().collect::<Vec<_>>(), expected);
+        assert_eq!(
+            state.iter_pressed_indices().collect::<Vec<_>>(),
+            expected
+                .iter()
+                .filter_map(|&(row, col)| state.index_for(row, col))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            state.iter_pressed_indices().collect::<Vec<_>>(),
+            vec![0, 49, 63, 64, 101, 127]
+        );
+
+        let mut small = KeyboardState::new(KeyboardLayout::new(2, 2, vec!["Base".to_string()]));
+        small.apply_report(&Report::new_for_test(0, &[1, 3, 4, 70]));
+        assert_eq!(
+            small.iter_pressed_keys().collect::<Vec<_>>(),
+            vec![(0, 1), (1, 1)]
+        );
+        small.apply_report(&Report::new_for_test(0, &[]));
+        assert_eq!(small.iter_pressed_indices().count(), 0);
+    }
+
+    #[test]
+    fn test_resolve_layer_name() {
+        let names = ["Base", "Lower", "Nav Cluster"].map(String::from).to_vec();
+        let mut state = KeyboardState::new(KeyboardLayout::new(4, 12, names));
+        assert_eq!(state.resolve_layer_name("2"), "Nav Cluster");
+        assert_eq!(state.resolve_layer_name(" 1 "), "Lower");
+        // Out of range indices stay numeric
+        assert_eq!(state.resolve_layer_name("7"), "7");
+        // Identifiers match loaded names case-insensitively, ignoring a leading underscore
+        assert_eq!(state.resolve_layer_name("LOWER"), "Lower");
+        assert_eq!(state.resolve_layer_name("_lower"), "Lower");
+        // Unknown names fall back to the friendly alias table
+        assert_eq!(state.resolve_layer_name("NAV"), "Nav");
+        assert_eq!(state.resolve_layer_name("_ADJUST"), "ADJUST");
+
+        state.keyboard.raw_legends[0][0] = "MO(2)".to_string();
+        state.keyboard.raw_legends[0][1] = "LT(LOWER, KC_SPC)".to_string();
+        state.keyboard.raw_legends[0][2] = "TO(_LOWER)".to_string();
+        state.keyboard.raw_legends[0][3] = "DF(0)".to_string();
+        assert_eq!(state.display_parts(0, 0, 0).0, "Nav Cluster");
+        assert_eq!(state.display_parts(0, 0, 1).1, "Lower");
+        assert_eq!(state.display_parts(0, 0, 2).0, "Lower");
+        assert_eq!(state.display_parts(0, 0, 3).0, "Base");
+    }
+
+    #[test]
+    fn test_modifier_predicates() {
+        let layout = KeyboardLayout::new(4, 12, vec!["Base".to_string()]);
+        let mut state = KeyboardState::new(layout);
+        state.keyboard.raw_legends[0][0] = "KC_A".to_string();
+        state.keyboard.raw_legends[0][1] = "KC_LCTL".to_string();
+        state.keyboard.raw_legends[0][2] = "MT(MOD_RCTL, KC_S)".to_string();
+        state.keyboard.raw_legends[0][3] = "KC_RALT".to_string();
+        state.keyboard.raw_legends[0][4] = "LALT_T(KC_D)".to_string();
+        state.keyboard.raw_legends[0][5] = "KC_LGUI".to_string();
+        state.keyboard.raw_legends[0][6] = "MT(MOD_LGUI, KC_F)".to_string();
+        state.keyboard.raw_legends[0][7] = "KC_LSFT".to_string();
+
+        let only = |state: &KeyboardState| {
+            (
+                state.is_ctrl_pressed(),
+                state.is_alt_pressed(),
+                state.is_gui_pressed(),
+                state.is_shift_pressed(),
+            )
+        };
+        assert_eq!(only(&state), (false, false, false, false));
+        for (bit, expected) in [
+            (1, (true, false, false, false)),
+            (2, (true, false, false, false)),
+            (3, (false, true, false, false)),
+            (4, (false, true, false, false)),
+            (5, (false, false, true, false)),
+            (6, (false, false, true, false)),
+            (7, (false, false, false, true)),
+        ] {
+            state.apply_report(&Report::new_for_test(0, &[bit]));
+            assert_eq!(only(&state), expected, "key {}", bit);
+        }
+    }
+
+    #[test]
+    fn test_display_parts_with_held_modifiers() {
+        let layout = KeyboardLayout::new(4, 12, vec!["Base".to_string()]);
+        let mut state = KeyboardState::new(layout);
+        state.keyboard.raw_legends[0][0] = "KC_C".to_string();
+        state.keyboard.raw_legends[0][1] = "KC_LCTL".to_string();
+        state.keyboard.raw_legends[0][2] = "LALT_T(KC_D)".to_string();
+        state.keyboard.raw_legends[0][3] = "KC_LSFT".to_string();
+
+        state.apply_report(&Report::new_for_test(0, &[1]));
+        assert_eq!(state.display_parts(0, 0, 0).0, "Ctrl+C");
+        state.apply_report(&Report::new_for_test(0, &[2]));
+        assert_eq!(state.display_parts(0, 0, 0).0, "Alt+C");
+        state.apply_report(&Report::new_for_test(0, &[1, 2]));
+        assert_eq!(state.display_parts(0, 0, 0).0, "Ctrl+Alt+C");
+        state.apply_report(&Report::new_for_test(0, &[3]));
+        assert_eq!(state.display_parts(0, 0, 0).0, "C");
+        // Non-letter labels are left alone
+        state.apply_report(&Report::new_for_test(0, &[1]));
+        assert_eq!(state.display_parts(0, 0, 1).0, "Ctrl");
+    }
+
     #[test]
     fn test_display_parts() {
         let layout = KeyboardLayout::new(4, 12, vec!["Base".to_string()]);
@@ -380,4 +1669,374 @@ mod tests {
         assert_eq!(main, "a");
         assert_eq!(sub, "");
     }
+
+    #[test]
+    fn test_display_parts_to_df() {
+        let names = ["Base", "Lower", "Raise"].map(String::from).to_vec();
+        let layout = KeyboardLayout::new(4, 12, names);
+        let mut state = KeyboardState::new(layout);
+        state.keyboard.raw_legends[0][0] = "TO(2)".to_string();
+        state.keyboard.raw_legends[0][1] = "DF(1)".to_string();
+        state.keyboard.raw_legends[0][2] = "TO(NAV)".to_string();
+        assert_eq!(
+            state.display_parts(0, 0, 0),
+            ("Raise".to_string(), "TO".to_string())
+        );
+        assert_eq!(
+            state.display_parts(0, 0, 1),
+            ("Lower".to_string(), "DF".to_string())
+        );
+        assert_eq!(
+            state.display_parts(0, 0, 2),
+            ("Nav".to_string(), "TO".to_string())
+        );
+        assert!(state.is_function_key(0, 0, 0));
+        assert!(state.is_to_key(0, 0, 0));
+        assert!(state.is_df_key(0, 0, 1));
+    }
+
+    #[test]
+    fn test_display_parts_algr() {
+        let layout = KeyboardLayout::new(4, 12, vec!["Base".to_string()]);
+        let mut state = KeyboardState::new(layout);
+        state.keyboard.raw_legends[0][0] = "ALGR(KC_E)".to_string();
+        state.keyboard.raw_legends[0][1] = "KC_RALT".to_string();
+        assert_eq!(
+            state.display_parts(0, 0, 0),
+            ("e".to_string(), "AltGr".to_string())
+        );
+        assert_eq!(state.display_parts(0, 0, 1).0, "Alt");
+
+        state.set_locale(Some("de".to_string()));
+        assert_eq!(
+            state.display_parts(0, 0, 0),
+            ("€".to_string(), "AltGr".to_string())
+        );
+        assert_eq!(state.display_parts(0, 0, 1).0, "AltGr");
+    }
+
+    #[test]
+    fn test_display_parts_keyboard_locale() {
+        let mut state = KeyboardState::new(KeyboardLayout::new(4, 12, vec!["Base".to_string()]));
+        state.keyboard.raw_legends[0][0] = "KC_A".to_string();
+        state.keyboard.raw_legends[0][1] = "LSFT_T(KC_Z)".to_string();
+        assert_eq!(state.display_parts(0, 0, 0).0, "a");
+
+        state.set_keyboard_locale(Some(KeyboardLocale::AZERTY));
+        assert_eq!(state.display_parts(0, 0, 0).0, "q");
+        assert_eq!(state.display_parts(0, 0, 1).0, "w");
+    }
+
+    #[test]
+    fn test_resize_adds_row_and_keeps_keys() {
+        let mut layout = KeyboardLayout::new(4, 12, vec!["Base".to_string(), "Lower".to_string()]);
+        layout.raw_legends[0][0] = "KC_Q".to_string();
+        layout.legends[0][0] = "q".to_string();
+        layout.raw_legends[1][47] = "KC_RGHT".to_string();
+
+        layout.resize(5, 12);
+        assert_eq!((layout.rows, layout.cols), (5, 12));
+        assert!(layout.raw_legends.iter().all(|l| l.len() == 60));
+        assert!(layout.legends.iter().all(|l| l.len() == 60));
+        assert_eq!(layout.raw_legends[0][0], "KC_Q");
+        assert_eq!(layout.legends[0][0], "q");
+        assert_eq!(layout.raw_legends[1][47], "KC_RGHT");
+        assert!(layout.raw_legends[0][48..].iter().all(|t| t == "_______"));
+        assert!(layout.legends[0][48..].iter().all(String::is_empty));
+
+        // Dropping the last column drops the keys in it and keeps the others by (row, col)
+        layout.resize(5, 11);
+        assert_eq!(layout.raw_legends[0][0], "KC_Q");
+        assert!(!layout.raw_legends[1].contains(&"KC_RGHT".to_string()));
+        assert_eq!(layout.raw_legends[0].len(), 55);
+    }
+
+    #[test]
+    fn test_from_layout_data_keeps_extra_keys() {
+        // 49 keys are close enough to the Planck to get its 4x12 grid, plus a row for the last key
+        let mut layer = vec!["KC_A".to_string(); 48];
+        layer.push("KC_B".to_string());
+        let layout = KeyboardLayout::from_layout_data(vec![layer], None);
+        assert!(layout.rows * layout.cols >= 49);
+        assert!(layout.raw_legends[0].contains(&"KC_B".to_string()));
+        assert_eq!(layout.raw_legends[0].len(), layout.rows * layout.cols);
+    }
+
+    #[test]
+    fn test_transparent_chain() {
+        let names = ["Base", "Lower", "Raise", "Adjust"]
+            .map(String::from)
+            .to_vec();
+        let mut state = KeyboardState::new(KeyboardLayout::new(4, 12, names));
+        state.keyboard.raw_legends[0][0] = "KC_A".to_string();
+        for layer in 1..4 {
+            state.keyboard.raw_legends[layer][0] = "KC_TRNS".to_string();
+        }
+        state.keyboard.raw_legends[1][1] = "KC_B".to_string();
+        state.keyboard.raw_legends[2][1] = "KC_TRNS".to_string();
+        state.keyboard.raw_legends[3][1] = "_______".to_string();
+        state.set_layer(3);
+
+        let chain = state.transparent_chain(0, 0);
+        assert_eq!(chain.len(), 4);
+        assert_eq!(chain.first(), Some(&(3, "KC_TRNS".to_string())));
+        assert_eq!(chain.last(), Some(&(0, "KC_A".to_string())));
+        // Falling through stops at the first layer with a real keycode
+        assert_eq!(state.transparent_chain(0, 1).len(), 3);
+
+        // An armed layer on the stack is looked at first
+        state.set_layer(0);
+        state.layer_stack.push(2);
+        let layers: Vec<usize> = state
+            .transparent_chain(0, 0)
+            .iter()
+            .map(|(l, _)| *l)
+            .collect();
+        assert_eq!(layers, vec![2, 0]);
+    }
+
+    #[test]
+    fn test_effective_keycode_at() {
+        let names = ["Base", "Lower", "Raise"].map(String::from).to_vec();
+        let mut state = KeyboardState::new(KeyboardLayout::new(2, 2, names));
+        state.keyboard.raw_legends[0][0] = "KC_A".to_string();
+        state.keyboard.raw_legends[1][0] = "KC_NO".to_string();
+        state.keyboard.raw_legends[2][0] = "KC_TRNS".to_string();
+        state.keyboard.raw_legends[1][1] = "KC_B".to_string();
+        state.keyboard.raw_legends[2][1] = "_______".to_string();
+        state.set_layer(2);
+
+        assert_eq!(state.effective_keycode_at(0, 0), Some("KC_A"));
+        assert_eq!(state.effective_keycode_at(0, 1), Some("KC_B"));
+        // Nothing to fall through to
+        assert_eq!(state.effective_keycode_at(1, 0), None);
+        state.set_layer(0);
+        assert_eq!(state.effective_keycode_at(0, 1), None);
+        assert_eq!(state.effective_layer_from(2, 0, 1), Some(1));
+    }
+
+    #[test]
+    fn test_display_parts_osl_layer_name() {
+        let names = ["Base", "Nav", "Symbols"].map(String::from).to_vec();
+        let mut state = KeyboardState::new(KeyboardLayout::new(4, 12, names));
+        state.keyboard.raw_legends[0][0] = "OSL(2)".to_string();
+        state.keyboard.raw_legends[0][1] = "OSL(_NAV)".to_string();
+
+        assert_eq!(
+            state.display_parts(0, 0, 0),
+            ("★".to_string(), "Symbols".to_string())
+        );
+        assert!(!state.display_parts(0, 0, 1).1.is_empty());
+
+        state.layer_stack.push(2);
+        assert!(state.is_osl_active(2));
+        assert_eq!(state.display_parts(0, 0, 0).0, "★★");
+        assert_eq!(state.display_parts(0, 0, 1).0, "★");
+    }
+
+    #[test]
+    fn test_display_parts_glyph_fallback() {
+        let layout = KeyboardLayout::new(4, 12, vec!["Base".to_string()]);
+        let mut state = KeyboardState::new(layout);
+        state.keyboard.raw_legends[0][0] = "OSL(1)".to_string();
+        assert_eq!(state.display_parts(0, 0, 0).0, "★");
+        state.supported_glyphs = Some(HashSet::new());
+        assert_eq!(state.display_parts(0, 0, 0).0, "OSL");
+        state.supported_glyphs = Some(['★'].into_iter().collect());
+        assert_eq!(state.display_parts(0, 0, 0).0, "★");
+    }
+
+    #[test]
+    fn test_validate_consistency() {
+        let layers = vec![vec!["KC_A".to_string(); 12], vec!["KC_1".to_string(); 12]];
+        let mut layout = KeyboardLayout::from_layout_data(layers, None);
+        assert!(layout.validate_consistency().is_empty());
+
+        let expected = layout.rows * layout.cols;
+        layout.raw_legends[1].truncate(expected - 2);
+        layout.legends[1].truncate(expected - 2);
+        layout.raw_legends[0].push("KC_B".to_string());
+        let warnings = layout.validate_consistency();
+        assert_eq!(warnings.len(), 3, "{:?}", warnings);
+        assert_eq!(
+            warnings[0],
+            ValidationWarning::LayerLengthMismatch {
+                layer: 0,
+                expected,
+                found: expected + 1
+            }
+        );
+        assert_eq!(
+            warnings[1],
+            ValidationWarning::PaddedKey {
+                layer: 0,
+                index: expected
+            }
+        );
+        assert_eq!(
+            warnings[2],
+            ValidationWarning::LayerLengthMismatch {
+                layer: 1,
+                expected,
+                found: expected - 2
+            }
+        );
+    }
+
+    #[test]
+    fn test_layer_management() {
+        let names = ["Base", "Lower"].map(String::from).to_vec();
+        let mut layout = KeyboardLayout::new(4, 12, names);
+        layout.raw_legends[0] = (0..48).map(|i| format!("KC_{}", i)).collect();
+        layout.legends[0] = (0..48).map(|i| i.to_string()).collect();
+
+        // Cloning past the end adds the missing layers
+        layout.clone_layer(0, 3);
+        assert_eq!(layout.raw_legends.len(), 4);
+        assert_eq!(layout.raw_legends[3], layout.raw_legends[0]);
+        assert_eq!(layout.legends[3], layout.legends[0]);
+        assert_eq!(layout.layer_names, ["Base", "Lower", "Layer 2", "Layer 3"]);
+        assert_eq!(layout.raw_legends[2].len(), 48);
+
+        layout.swap_layers(0, 1).unwrap();
+        assert_eq!(layout.layer_names[..2], ["Lower", "Base"]);
+        assert_eq!(layout.raw_legends[1][5], "KC_5");
+        assert!(layout.raw_legends[0].iter().all(String::is_empty));
+        assert!(layout.swap_layers(0, 4).is_err());
+
+        layout.add_layer("Adjust".to_string());
+        assert_eq!(
+            layout.layer_names.last().map(String::as_str),
+            Some("Adjust")
+        );
+        assert!(layout.raw_legends[4].iter().all(|t| t == "_______"));
+
+        layout.remove_layer(0).unwrap();
+        assert_eq!(layout.layer_names[0], "Base");
+        assert_eq!(layout.raw_legends.len(), 4);
+        for _ in 0..3 {
+            layout.remove_layer(0).unwrap();
+        }
+        assert!(layout.remove_layer(0).is_err());
+        assert!(layout.remove_layer(7).is_err());
+    }
+
+    #[test]
+    fn test_custom_keycodes_in_display_parts() {
+        let mut layout = KeyboardLayout::new(1, 4, vec!["Base".to_string()]);
+        layout.raw_legends[0] = ["U_LAYER_1", "LT(1, SNIP)", "MY_CUSTOM_KEY", "KC_A"]
+            .map(String::from)
+            .to_vec();
+        let mut state = KeyboardState::new(layout);
+        state.custom_table = CustomKeycodeTable(HashMap::from([
+            ("U_LAYER_1".to_string(), "L1".to_string()),
+            ("SNIP".to_string(), "Snip".to_string()),
+        ]));
+        assert_eq!(state.display_parts(0, 0, 0).0, "L1");
+        assert_eq!(state.display_parts(0, 0, 1).0, "Snip");
+        // Unknown tokens still show the raw token
+        assert_eq!(state.display_parts(0, 0, 2).0, "MY_CUSTOM_KEY");
+        assert_eq!(state.display_parts(0, 0, 3).0, "a");
+    }
+
+    #[test]
+    fn test_key_chord_active() {
+        let mut state = KeyboardState::new(KeyboardLayout::new(4, 12, vec!["Base".to_string()]));
+        let chord = [3, 15, 40];
+        assert!(!state.key_chord_active(&chord));
+        assert_eq!(state.key_chord_partial(&chord), 0);
+
+        state.apply_report(&Report::new_for_test(0, &[3, 40, 7]));
+        assert!(!state.key_chord_active(&chord));
+        assert_eq!(state.key_chord_partial(&chord), 2);
+
+        state.apply_report(&Report::new_for_test(0, &[3, 15, 40]));
+        assert!(state.key_chord_active(&chord));
+        assert_eq!(state.key_chord_partial(&chord), 3);
+        assert!(!state.key_chord_active(&[]));
+        assert!(!state.key_chord_active(&[3, 64]));
+    }
+
+    #[test]
+    fn test_simulate_press_sequence_mt_hold() {
+        let layout = KeyboardLayout::new(4, 12, vec!["Base".to_string()]);
+        let mut state = KeyboardState::new(layout);
+        state.keyboard.raw_legends[0][0] = "MT(MOD_LSFT, KC_A)".to_string();
+        let now = Instant::now();
+        let start = now - Duration::from_millis(600);
+
+        state.simulate_press_sequence(&[(0, true)], &[0], start);
+        assert_eq!(state.pressed_bits, 1);
+        assert_eq!(state.pressed_started[&0], start);
+        assert_eq!(
+            now.duration_since(state.pressed_started[&0]).as_millis(),
+            600
+        );
+        assert!(state.is_mt_held(0, 0, 0, now));
+        assert!(!state.is_mt_held(0, 0, 0, start + Duration::from_millis(100)));
+
+        state.simulate_press_sequence(&[(1, true), (0, false)], &[0, 600], start);
+        assert_eq!(state.pressed_bits, 0b10);
+        assert!(!state.pressed_started.contains_key(&0));
+        assert!(!state.is_mt_held(0, 0, 0, now));
+
+        // The threshold is configurable per state
+        state.mt_hold_threshold = Duration::from_millis(50);
+        state.simulate_press_sequence(&[(0, true)], &[0], now - Duration::from_millis(100));
+        assert!(state.is_mt_held(0, 0, 0, now));
+    }
+
+    #[test]
+    fn test_press_counts() {
+        let mut state = KeyboardState::new(KeyboardLayout::new(2, 2, vec!["Base".to_string()]));
+        state.apply_report(&Report::new_for_test(0, &[0]));
+        state.apply_report(&Report::new_for_test(0, &[0, 1]));
+        state.apply_report(&Report::new_for_test(0, &[]));
+        // Pressed again after switching layers
+        state.apply_report(&Report::new_for_test(1, &[0]));
+        assert_eq!(state.press_count(0, None), 2);
+        assert_eq!(state.press_count(1, None), 1);
+        assert_eq!(state.press_count(0, Some(1)), 1);
+        assert_eq!(state.press_count(1, Some(1)), 0);
+        assert_eq!(state.max_press_count(None), 2);
+        assert_eq!(state.max_press_count(Some(3)), 0);
+
+        state.reset_heatmap();
+        assert_eq!(state.max_press_count(None), 0);
+    }
+
+    #[test]
+    fn test_press_duration() {
+        let mut state = KeyboardState::new(KeyboardLayout::new(4, 12, vec!["Base".to_string()]));
+        assert_eq!(state.press_duration(0), None);
+
+        state.apply_report(&Report::new_for_test(0, &[0, 5]));
+        assert!(state.press_duration(0).is_some_and(|d| d >= Duration::ZERO));
+        assert!(state.press_duration(5).is_some());
+        assert_eq!(state.press_duration(1), None);
+        assert_eq!(state.press_duration(64), None);
+
+        // Keys that stay down keep their start time; released keys are forgotten
+        let started = state.pressed_started[&0];
+        state.apply_report(&Report::new_for_test(0, &[0]));
+        assert_eq!(state.pressed_started[&0], started);
+        assert_eq!(state.press_duration(5), None);
+        assert!(!state.pressed_started.contains_key(&5));
+    }
+
+    #[test]
+    fn test_set_raw_token_updates_display_parts() {
+        let layout = KeyboardLayout::new(4, 12, vec!["Base".to_string()]);
+        let mut state = KeyboardState::new(layout);
+        state.keyboard.raw_legends[0][1] = "KC_A".to_string();
+        assert_eq!(
+            state.set_raw_token(0, 0, 1, "LT(1, KC_SPC)"),
+            Some("KC_A".to_string())
+        );
+        assert_eq!(state.raw_legend_at(0, 0, 1), Some("LT(1, KC_SPC)"));
+        let (main, sub) = state.display_parts(0, 0, 1);
+        assert_eq!(main, "Space");
+        assert!(!sub.is_empty());
+        assert_eq!(state.set_raw_token(0, 9, 0, "KC_B"), None);
+    }
 }
@@ -0,0 +1,77 @@
+//! foostan Corne (crkbd): 42-key columnar-stagger split (3x6 + 3 thumb keys per half)
+
+use crate::keyboard::{KeyPosition, KeyboardLayout};
+
+/// Corne keyboard definition
+///
+/// The 42 keys of `LAYOUT_split_3x6_3` are shown on a 4x12 grid: three rows of 6 keys per
+/// half, then the thumb keys. Positions are listed in QMK `LAYOUT` order.
+pub struct CorneLayout;
+
+impl CorneLayout {
+    pub const ROWS: usize = 4;
+    pub const COLS: usize = 12;
+
+    pub const DEFAULT_LAYER_NAMES: &'static [&'static str] = &["Base", "Lower", "Raise", "Adjust"];
+
+    /// Stagger of the left-hand columns (outer pinky to inner index), in key units
+    const COLUMN_OFFSETS: [f32; 6] = [0.375, 0.375, 0.125, 0.0, 0.125, 0.25];
+    /// Horizontal mirror axis between the two halves
+    const MIRROR_AXIS: f32 = 7.5;
+
+    /// 42-key Corne with the default layer names
+    pub fn corne_default() -> KeyboardLayout {
+        let names = Self::DEFAULT_LAYER_NAMES
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mut layout = KeyboardLayout::new(Self::ROWS, Self::COLS, names);
+        layout.physical_positions = Some(Self::physical_positions());
+        layout
+    }
+
+    /// Same key on the right half
+    fn mirror(pos: KeyPosition) -> KeyPosition {
+        KeyPosition {
+            x: 2.0 * Self::MIRROR_AXIS - pos.x - pos.w,
+            ..pos
+        }
+    }
+
+    /// Key placement in `LAYOUT` order, the thumb keys stepping down towards the center
+    pub fn physical_positions() -> Vec<KeyPosition> {
+        let finger = |row: usize, col: usize| {
+            KeyPosition::new(col as f32, row as f32 + Self::COLUMN_OFFSETS[col], 1.0, 1.0)
+        };
+        let thumbs: Vec<KeyPosition> = (0..3)
+            .map(|i| KeyPosition::new(3.5 + i as f32, 3.5 + 0.125 * i as f32, 1.0, 1.0))
+            .collect();
+
+        let mut positions = Vec::new();
+        for row in 0..3 {
+            positions.extend((0..6).map(|col| finger(row, col)));
+            positions.extend((0..6).rev().map(|col| Self::mirror(finger(row, col))));
+        }
+        positions.extend(thumbs.iter().copied());
+        positions.extend(thumbs.iter().rev().map(|&p| Self::mirror(p)));
+        positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corne_layout() {
+        let layout = CorneLayout::corne_default();
+        assert_eq!((layout.rows, layout.cols), (4, 12));
+        let positions = layout.physical_positions.unwrap();
+        assert_eq!(positions.len(), 42);
+        // Right half keys are listed left to right
+        assert!(positions[6].x < positions[11].x);
+        assert_eq!(positions[6].x, 9.0);
+        let max_finger_y = positions[..36].iter().map(|p| p.y).fold(f32::MIN, f32::max);
+        assert!(positions[36..].iter().all(|p| p.y > max_finger_y));
+    }
+}
@@ -0,0 +1,139 @@
+//! Ferris / Sweep: minimalist 34-key split (3x5 + 2 thumb keys per half)
+
+use crate::keyboard::{KeyPosition, KeyboardLayout};
+
+/// Ferris keyboard definition
+///
+/// The 34 keys are shown on a 4x10 grid: three rows of 5 keys per half, then the thumb keys.
+/// The common 36-key variant adds an outer thumb key to each half.
+pub struct FerrisLayout;
+
+impl FerrisLayout {
+    pub const ROWS: usize = 4;
+    pub const COLS: usize = 10;
+
+    pub const DEFAULT_LAYER_NAMES: &'static [&'static str] = &["Base", "Nav", "Num", "Sym", "Fun"];
+
+    /// Key placement in `LAYOUT` order: strong pinky stagger, middle finger highest, and
+    /// the thumb keys stepping down towards the center
+    pub const DEFAULT_PHYSICAL_POSITIONS: &'static [KeyPosition] = &[
+        // Top row
+        KeyPosition::new(0.0, 0.5, 1.0, 1.0),
+        KeyPosition::new(1.0, 0.125, 1.0, 1.0),
+        KeyPosition::new(2.0, 0.0, 1.0, 1.0),
+        KeyPosition::new(3.0, 0.125, 1.0, 1.0),
+        KeyPosition::new(4.0, 0.25, 1.0, 1.0),
+        KeyPosition::new(8.0, 0.25, 1.0, 1.0),
+        KeyPosition::new(9.0, 0.125, 1.0, 1.0),
+        KeyPosition::new(10.0, 0.0, 1.0, 1.0),
+        KeyPosition::new(11.0, 0.125, 1.0, 1.0),
+        KeyPosition::new(12.0, 0.5, 1.0, 1.0),
+        // Home row
+        KeyPosition::new(0.0, 1.5, 1.0, 1.0),
+        KeyPosition::new(1.0, 1.125, 1.0, 1.0),
+        KeyPosition::new(2.0, 1.0, 1.0, 1.0),
+        KeyPosition::new(3.0, 1.125, 1.0, 1.0),
+        KeyPosition::new(4.0, 1.25, 1.0, 1.0),
+        KeyPosition::new(8.0, 1.25, 1.0, 1.0),
+        KeyPosition::new(9.0, 1.125, 1.0, 1.0),
+        KeyPosition::new(10.0, 1.0, 1.0, 1.0),
+        KeyPosition::new(11.0, 1.125, 1.0, 1.0),
+        KeyPosition::new(12.0, 1.5, 1.0, 1.0),
+        // Bottom row
+        KeyPosition::new(0.0, 2.5, 1.0, 1.0),
+        KeyPosition::new(1.0, 2.125, 1.0, 1.0),
+        KeyPosition::new(2.0, 2.0, 1.0, 1.0),
+        KeyPosition::new(3.0, 2.125, 1.0, 1.0),
+        KeyPosition::new(4.0, 2.25, 1.0, 1.0),
+        KeyPosition::new(8.0, 2.25, 1.0, 1.0),
+        KeyPosition::new(9.0, 2.125, 1.0, 1.0),
+        KeyPosition::new(10.0, 2.0, 1.0, 1.0),
+        KeyPosition::new(11.0, 2.125, 1.0, 1.0),
+        KeyPosition::new(12.0, 2.5, 1.0, 1.0),
+        // Thumb keys
+        KeyPosition::new(3.5, 3.375, 1.0, 1.0),
+        KeyPosition::new(4.5, 3.625, 1.0, 1.0),
+        KeyPosition::new(7.5, 3.625, 1.0, 1.0),
+        KeyPosition::new(8.5, 3.375, 1.0, 1.0),
+    ];
+
+    /// Placement for [`FerrisLayout::ferris_36`]
+    pub const PHYSICAL_POSITIONS_36: &'static [KeyPosition] = &[
+        // Top row
+        KeyPosition::new(0.0, 0.5, 1.0, 1.0),
+        KeyPosition::new(1.0, 0.125, 1.0, 1.0),
+        KeyPosition::new(2.0, 0.0, 1.0, 1.0),
+        KeyPosition::new(3.0, 0.125, 1.0, 1.0),
+        KeyPosition::new(4.0, 0.25, 1.0, 1.0),
+        KeyPosition::new(8.0, 0.25, 1.0, 1.0),
+        KeyPosition::new(9.0, 0.125, 1.0, 1.0),
+        KeyPosition::new(10.0, 0.0, 1.0, 1.0),
+        KeyPosition::new(11.0, 0.125, 1.0, 1.0),
+        KeyPosition::new(12.0, 0.5, 1.0, 1.0),
+        // Home row
+        KeyPosition::new(0.0, 1.5, 1.0, 1.0),
+        KeyPosition::new(1.0, 1.125, 1.0, 1.0),
+        KeyPosition::new(2.0, 1.0, 1.0, 1.0),
+        KeyPosition::new(3.0, 1.125, 1.0, 1.0),
+        KeyPosition::new(4.0, 1.25, 1.0, 1.0),
+        KeyPosition::new(8.0, 1.25, 1.0, 1.0),
+        KeyPosition::new(9.0, 1.125, 1.0, 1.0),
+        KeyPosition::new(10.0, 1.0, 1.0, 1.0),
+        KeyPosition::new(11.0, 1.125, 1.0, 1.0),
+        KeyPosition::new(12.0, 1.5, 1.0, 1.0),
+        // Bottom row
+        KeyPosition::new(0.0, 2.5, 1.0, 1.0),
+        KeyPosition::new(1.0, 2.125, 1.0, 1.0),
+        KeyPosition::new(2.0, 2.0, 1.0, 1.0),
+        KeyPosition::new(3.0, 2.125, 1.0, 1.0),
+        KeyPosition::new(4.0, 2.25, 1.0, 1.0),
+        KeyPosition::new(8.0, 2.25, 1.0, 1.0),
+        KeyPosition::new(9.0, 2.125, 1.0, 1.0),
+        KeyPosition::new(10.0, 2.0, 1.0, 1.0),
+        KeyPosition::new(11.0, 2.125, 1.0, 1.0),
+        KeyPosition::new(12.0, 2.5, 1.0, 1.0),
+        // Thumb keys
+        KeyPosition::new(2.5, 3.25, 1.0, 1.0),
+        KeyPosition::new(3.5, 3.375, 1.0, 1.0),
+        KeyPosition::new(4.5, 3.625, 1.0, 1.0),
+        KeyPosition::new(7.5, 3.625, 1.0, 1.0),
+        KeyPosition::new(8.5, 3.375, 1.0, 1.0),
+        KeyPosition::new(9.5, 3.25, 1.0, 1.0),
+    ];
+
+    /// 34-key Ferris with the default layer names
+    pub fn ferris_default() -> KeyboardLayout {
+        Self::build(Self::DEFAULT_PHYSICAL_POSITIONS)
+    }
+
+    /// 36-key Ferris with three thumb keys per half
+    pub fn ferris_36() -> KeyboardLayout {
+        Self::build(Self::PHYSICAL_POSITIONS_36)
+    }
+
+    fn build(positions: &[KeyPosition]) -> KeyboardLayout {
+        let names = Self::DEFAULT_LAYER_NAMES
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mut layout = KeyboardLayout::new(Self::ROWS, Self::COLS, names);
+        layout.physical_positions = Some(positions.to_vec());
+        layout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ferris_variants() {
+        let small = FerrisLayout::ferris_default();
+        let full = FerrisLayout::ferris_36();
+        assert_eq!((small.rows, small.cols), (4, 10));
+        assert_eq!((full.rows, full.cols), (4, 10));
+        assert_eq!(small.physical_positions.map(|p| p.len()), Some(34));
+        assert_eq!(full.physical_positions.map(|p| p.len()), Some(36));
+        assert_eq!(small.layer_names.len(), 5);
+    }
+}
@@ -0,0 +1,143 @@
+//! splitkb.com Kyria: columnar-stagger split with an angled thumb fan
+
+use crate::keyboard::{KeyPosition, KeyboardLayout};
+
+/// Kyria keyboard definition
+///
+/// The default 50-key build has, per half, three rows of 6 keys, 2 inner keys next to the
+/// bottom row and 5 thumb keys on an arc. The 64-key build adds a number row and a sixth
+/// thumb key on each half. Positions are listed in QMK `LAYOUT` order.
+pub struct KyriaLayout;
+
+impl KyriaLayout {
+    /// Matrix used to display the 50-key `LAYOUT`
+    pub const ROWS_50: usize = 5;
+    pub const COLS_50: usize = 10;
+    /// Matrix used to display the 64-key `LAYOUT`
+    pub const ROWS_64: usize = 4;
+    pub const COLS_64: usize = 16;
+
+    pub const DEFAULT_LAYER_NAMES: &'static [&'static str] =
+        &["Qwerty", "Lower", "Raise", "Adjust"];
+
+    /// Base stagger of the left-hand columns (outer pinky to inner index), in key units
+    const COLUMN_OFFSETS: [f32; 6] = [0.75, 0.75, 0.25, 0.0, 0.25, 0.375];
+    /// Tilt of each half: every column drops by tan(3.5°) relative to its outer neighbour
+    const COLUMN_STAGGER_DEG: f32 = 3.5;
+    /// Angle between two neighbouring thumb keys on the arc
+    const THUMB_ARC_STEP_DEG: f32 = 15.0;
+    const THUMB_ARC_RADIUS: f32 = 5.0;
+    /// Horizontal mirror axis between the two halves
+    const MIRROR_AXIS: f32 = 8.5;
+
+    /// 50-key Kyria with the default layer names
+    pub fn kyria_50() -> KeyboardLayout {
+        Self::build(Self::ROWS_50, Self::COLS_50, false)
+    }
+
+    /// 64-key Kyria (number row and an extra thumb key per half)
+    pub fn kyria_64() -> KeyboardLayout {
+        Self::build(Self::ROWS_64, Self::COLS_64, true)
+    }
+
+    fn build(rows: usize, cols: usize, full: bool) -> KeyboardLayout {
+        let names = Self::DEFAULT_LAYER_NAMES
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mut layout = KeyboardLayout::new(rows, cols, names);
+        layout.physical_positions = Some(Self::physical_positions(full));
+        layout
+    }
+
+    /// Vertical offset of left-hand column `col`
+    fn column_offset(col: usize) -> f32 {
+        Self::COLUMN_OFFSETS[col] + col as f32 * Self::COLUMN_STAGGER_DEG.to_radians().tan()
+    }
+
+    /// Same key on the right half
+    fn mirror(pos: KeyPosition) -> KeyPosition {
+        KeyPosition {
+            x: 2.0 * Self::MIRROR_AXIS - pos.x - pos.w,
+            ..pos
+        }
+    }
+
+    /// Key placement in `LAYOUT` order for the 50-key (`full = false`) or 64-key build
+    pub fn physical_positions(full: bool) -> Vec<KeyPosition> {
+        let finger_rows = if full { 4 } else { 3 };
+        let finger = |row: usize, col: usize| {
+            KeyPosition::new(col as f32, row as f32 + Self::column_offset(col), 1.0, 1.0)
+        };
+
+        // Inner keys next to the bottom row, each a little lower than the previous one
+        let bottom = finger_rows - 1;
+        let inner_y = bottom as f32 + Self::column_offset(5) + 0.5;
+        let inner = [
+            KeyPosition::new(6.0, inner_y, 1.0, 1.0),
+            KeyPosition::new(7.0, inner_y + 0.25, 1.0, 1.0),
+        ];
+
+        // Thumb keys on an arc below the inner keys; the middle key of the 50-key fan is at
+        // angle 0 and keys further in turn (and drop) by THUMB_ARC_STEP_DEG each
+        let thumb_top = inner[1].y + inner[1].h;
+        let first_step: i32 = if full { -2 } else { -1 };
+        let thumbs: Vec<KeyPosition> = (first_step..=3)
+            .map(|step| {
+                let angle = (step as f32 * Self::THUMB_ARC_STEP_DEG).to_radians();
+                KeyPosition::new(
+                    3.5 + Self::THUMB_ARC_RADIUS * angle.sin(),
+                    thumb_top + Self::THUMB_ARC_RADIUS * (1.0 - angle.cos()),
+                    1.0,
+                    1.0,
+                )
+            })
+            .collect();
+
+        let mut positions = Vec::new();
+        for row in 0..finger_rows {
+            positions.extend((0..6).map(|col| finger(row, col)));
+            if row == bottom {
+                positions.extend(inner);
+                positions.extend(inner.iter().rev().map(|&p| Self::mirror(p)));
+            }
+            positions.extend((0..6).rev().map(|col| Self::mirror(finger(row, col))));
+        }
+        positions.extend(thumbs.iter().copied());
+        positions.extend(thumbs.iter().rev().map(|&p| Self::mirror(p)));
+        positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keyboards::assert_no_overlap;
+
+    #[test]
+    fn test_kyria_variants() {
+        let small = KyriaLayout::kyria_50();
+        let full = KyriaLayout::kyria_64();
+        let small_positions = small.physical_positions.unwrap();
+        let full_positions = full.physical_positions.unwrap();
+        assert_eq!(small_positions.len(), 50);
+        assert_eq!(full_positions.len(), 64);
+        assert!(small_positions.len() <= small.rows * small.cols);
+        assert!(full_positions.len() <= full.rows * full.cols);
+        assert_no_overlap(&small_positions);
+        assert_no_overlap(&full_positions);
+    }
+
+    #[test]
+    fn test_kyria_thumbs_below_fingers() {
+        for (positions, thumb_count) in [
+            (KyriaLayout::physical_positions(false), 10),
+            (KyriaLayout::physical_positions(true), 12),
+        ] {
+            let (fingers, thumbs) = positions.split_at(positions.len() - thumb_count);
+            let max_finger_y = fingers.iter().map(|p| p.y).fold(f32::MIN, f32::max);
+            assert!(thumbs.iter().all(|p| p.y > max_finger_y));
+            assert!(thumbs.iter().any(|p| p.y.fract() != 0.0));
+        }
+    }
+}
@@ -1 +1,19 @@
+pub mod corne;
+pub mod ferris;
+pub mod kyria;
+pub mod moonlander;
 pub mod planck;
+pub mod registry;
+pub mod sofle;
+pub mod split;
+
+/// Panics if two of `positions` overlap
+#[cfg(test)]
+pub(crate) fn assert_no_overlap(positions: &[crate::keyboard::KeyPosition]) {
+    for (i, a) in positions.iter().enumerate() {
+        for b in &positions[i + 1..] {
+            let overlap = a.x < b.x + b.w && b.x < a.x + a.w && a.y < b.y + b.h && b.y < a.y + a.h;
+            assert!(!overlap, "{:?} overlaps {:?}", a, b);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! ZSA Moonlander MkI: 72-key split with a detachable thumb module
+
+use crate::keyboard::{KeyPosition, KeyboardLayout};
+
+/// Moonlander keyboard definition
+///
+/// The QMK `LAYOUT` has 72 entries: three rows of 7 keys per half, a row of 6, a row of 5
+/// followed by the two red thumb keys, and the 3 thumb keys of each half.
+pub struct MoonlanderLayout;
+
+impl MoonlanderLayout {
+    pub const ROWS: usize = 6;
+    pub const COLS: usize = 12;
+
+    /// `LAYOUT` indices of the thumb modules (red keys, then the 3 thumb keys of each half)
+    pub const THUMB_INDICES: [usize; 8] = [59, 60, 66, 67, 68, 69, 70, 71];
+
+    pub const DEFAULT_LAYER_NAMES: &'static [&'static str] =
+        &["Base", "Symbols", "Media", "Adjust"];
+
+    /// Key placement in `LAYOUT` order: columnar stagger on the main block and the thumb
+    /// modules set apart below the inner columns
+    pub const DEFAULT_PHYSICAL_POSITIONS: &'static [KeyPosition] = &[
+        // Number row
+        KeyPosition::new(0.0, 0.5, 1.0, 1.0),
+        KeyPosition::new(1.0, 0.5, 1.0, 1.0),
+        KeyPosition::new(2.0, 0.25, 1.0, 1.0),
+        KeyPosition::new(3.0, 0.0, 1.0, 1.0),
+        KeyPosition::new(4.0, 0.25, 1.0, 1.0),
+        KeyPosition::new(5.0, 0.375, 1.0, 1.0),
+        KeyPosition::new(6.0, 0.375, 1.0, 1.0),
+        KeyPosition::new(11.0, 0.375, 1.0, 1.0),
+        KeyPosition::new(12.0, 0.375, 1.0, 1.0),
+        KeyPosition::new(13.0, 0.25, 1.0, 1.0),
+        KeyPosition::new(14.0, 0.0, 1.0, 1.0),
+        KeyPosition::new(15.0, 0.25, 1.0, 1.0),
+        KeyPosition::new(16.0, 0.5, 1.0, 1.0),
+        KeyPosition::new(17.0, 0.5, 1.0, 1.0),
+        // Top row
+        KeyPosition::new(0.0, 1.5, 1.0, 1.0),
+        KeyPosition::new(1.0, 1.5, 1.0, 1.0),
+        KeyPosition::new(2.0, 1.25, 1.0, 1.0),
+        KeyPosition::new(3.0, 1.0, 1.0, 1.0),
+        KeyPosition::new(4.0, 1.25, 1.0, 1.0),
+        KeyPosition::new(5.0, 1.375, 1.0, 1.0),
+        KeyPosition::new(6.0, 1.375, 1.0, 1.0),
+        KeyPosition::new(11.0, 1.375, 1.0, 1.0),
+        KeyPosition::new(12.0, 1.375, 1.0, 1.0),
+        KeyPosition::new(13.0, 1.25, 1.0, 1.0),
+        KeyPosition::new(14.0, 1.0, 1.0, 1.0),
+        KeyPosition::new(15.0, 1.25, 1.0, 1.0),
+        KeyPosition::new(16.0, 1.5, 1.0, 1.0),
+        KeyPosition::new(17.0, 1.5, 1.0, 1.0),
+        // Home row
+        KeyPosition::new(0.0, 2.5, 1.0, 1.0),
+        KeyPosition::new(1.0, 2.5, 1.0, 1.0),
+        KeyPosition::new(2.0, 2.25, 1.0, 1.0),
+        KeyPosition::new(3.0, 2.0, 1.0, 1.0),
+        KeyPosition::new(4.0, 2.25, 1.0, 1.0),
+        KeyPosition::new(5.0, 2.375, 1.0, 1.0),
+        KeyPosition::new(6.0, 2.375, 1.0, 1.0),
+        KeyPosition::new(11.0, 2.375, 1.0, 1.0),
+        KeyPosition::new(12.0, 2.375, 1.0, 1.0),
+        KeyPosition::new(13.0, 2.25, 1.0, 1.0),
+        KeyPosition::new(14.0, 2.0, 1.0, 1.0),
+        KeyPosition::new(15.0, 2.25, 1.0, 1.0),
+        KeyPosition::new(16.0, 2.5, 1.0, 1.0),
+        KeyPosition::new(17.0, 2.5, 1.0, 1.0),
+        // Bottom row
+        KeyPosition::new(0.0, 3.5, 1.0, 1.0),
+        KeyPosition::new(1.0, 3.5, 1.0, 1.0),
+        KeyPosition::new(2.0, 3.25, 1.0, 1.0),
+        KeyPosition::new(3.0, 3.0, 1.0, 1.0),
+        KeyPosition::new(4.0, 3.25, 1.0, 1.0),
+        KeyPosition::new(5.0, 3.375, 1.0, 1.0),
+        KeyPosition::new(12.0, 3.375, 1.0, 1.0),
+        KeyPosition::new(13.0, 3.25, 1.0, 1.0),
+        KeyPosition::new(14.0, 3.0, 1.0, 1.0),
+        KeyPosition::new(15.0, 3.25, 1.0, 1.0),
+        KeyPosition::new(16.0, 3.5, 1.0, 1.0),
+        KeyPosition::new(17.0, 3.5, 1.0, 1.0),
+        // Modifier row, then the red thumb keys
+        KeyPosition::new(0.0, 4.5, 1.0, 1.0),
+        KeyPosition::new(1.0, 4.5, 1.0, 1.0),
+        KeyPosition::new(2.0, 4.25, 1.0, 1.0),
+        KeyPosition::new(3.0, 4.0, 1.0, 1.0),
+        KeyPosition::new(4.0, 4.25, 1.0, 1.0),
+        KeyPosition::new(5.5, 5.75, 2.0, 1.0),
+        KeyPosition::new(10.5, 5.75, 2.0, 1.0),
+        KeyPosition::new(13.0, 4.25, 1.0, 1.0),
+        KeyPosition::new(14.0, 4.0, 1.0, 1.0),
+        KeyPosition::new(15.0, 4.25, 1.0, 1.0),
+        KeyPosition::new(16.0, 4.5, 1.0, 1.0),
+        KeyPosition::new(17.0, 4.5, 1.0, 1.0),
+        // Thumb keys
+        KeyPosition::new(5.0, 6.75, 1.0, 1.0),
+        KeyPosition::new(6.0, 6.875, 1.0, 1.0),
+        KeyPosition::new(7.0, 7.0, 1.0, 1.0),
+        KeyPosition::new(10.0, 7.0, 1.0, 1.0),
+        KeyPosition::new(11.0, 6.875, 1.0, 1.0),
+        KeyPosition::new(12.0, 6.75, 1.0, 1.0),
+    ];
+
+    /// Moonlander layout with the default layer names and physical positions
+    pub fn moonlander_default() -> KeyboardLayout {
+        let names = Self::DEFAULT_LAYER_NAMES
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mut layout = KeyboardLayout::new(Self::ROWS, Self::COLS, names);
+        layout.physical_positions = Some(Self::DEFAULT_PHYSICAL_POSITIONS.to_vec());
+        layout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moonlander_positions_do_not_overlap() {
+        let positions = MoonlanderLayout::DEFAULT_PHYSICAL_POSITIONS;
+        assert_eq!(
+            positions.len(),
+            MoonlanderLayout::ROWS * MoonlanderLayout::COLS
+        );
+        crate::keyboards::assert_no_overlap(positions);
+    }
+
+    #[test]
+    fn test_moonlander_thumbs_below_fingers() {
+        let positions = MoonlanderLayout::DEFAULT_PHYSICAL_POSITIONS;
+        let finger_bottom = positions
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !MoonlanderLayout::THUMB_INDICES.contains(i))
+            .map(|(_, p)| p.y + p.h)
+            .fold(f32::MIN, f32::max);
+        for idx in MoonlanderLayout::THUMB_INDICES {
+            assert!(
+                positions[idx].y >= finger_bottom,
+                "thumb key {} overlaps the fingers",
+                idx
+            );
+        }
+
+        let layout = MoonlanderLayout::moonlander_default();
+        assert_eq!((layout.rows, layout.cols), (6, 12));
+        assert_eq!(layout.layer_names.len(), 4);
+    }
+}
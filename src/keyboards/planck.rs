@@ -13,9 +13,7 @@ impl PlanckLayout {
 
     /// Create a default Planck keyboard layout
     pub fn planck_default() -> KeyboardLayout {
-        KeyboardLayout::new(
-            Self::ROWS,
-            Self::COLS,
+        Self::with_layer_names(
             Self::DEFAULT_LAYER_NAMES
                 .iter()
                 .map(|s| s.to_string())
@@ -25,7 +23,10 @@ impl PlanckLayout {
 
     /// Create a Planck layout with custom layer names
     pub fn with_layer_names(layer_names: Vec<String>) -> KeyboardLayout {
-        KeyboardLayout::new(Self::ROWS, Self::COLS, layer_names)
+        let mut layout =
+            KeyboardLayout::with_uniform_grid(Self::ROWS, Self::COLS, layer_names.len());
+        layout.layer_names = layer_names;
+        layout
     }
 }
 
@@ -0,0 +1,140 @@
+//! Known keyboards with their matrix dimensions and USB identifiers
+
+use super::{
+    corne::CorneLayout, ferris::FerrisLayout, kyria::KyriaLayout, moonlander::MoonlanderLayout,
+    planck::PlanckLayout, sofle::SofleLayout,
+};
+use crate::keyboard::KeyboardLayout;
+
+/// Key count difference still matched by [`detect_by_key_count`]
+pub const DETECT_TOLERANCE: usize = 1;
+
+/// A keyboard the viewer knows about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnownKeyboard {
+    pub name: &'static str,
+    /// Name shown to the user
+    pub display_name: &'static str,
+    pub rows: usize,
+    pub cols: usize,
+    /// Number of keys used by the default layout
+    pub key_count: usize,
+    /// Common `(vendor_id, product_id)` pairs
+    pub usb_ids: &'static [(u16, u16)],
+    /// Two halves, each with half of the grid columns
+    pub split: bool,
+}
+
+impl KnownKeyboard {
+    /// Empty layout of this keyboard with its default layer names and physical positions
+    pub fn layout(&self) -> KeyboardLayout {
+        match self.name {
+            "planck" => PlanckLayout::planck_default(),
+            "sofle" => SofleLayout::sofle_default(),
+            "kyria" => KyriaLayout::kyria_50(),
+            "kyria_64" => KyriaLayout::kyria_64(),
+            "corne" => CorneLayout::corne_default(),
+            "moonlander" => MoonlanderLayout::moonlander_default(),
+            "ferris" => FerrisLayout::ferris_default(),
+            "ferris_36" => FerrisLayout::ferris_36(),
+            _ => KeyboardLayout::with_uniform_grid(self.rows, self.cols, 1),
+        }
+    }
+}
+
+pub const KNOWN_KEYBOARDS: &[KnownKeyboard] = &[
+    KnownKeyboard {
+        name: "planck",
+        display_name: "Planck",
+        rows: 4,
+        cols: 12,
+        key_count: 48,
+        usb_ids: &[(0x03A8, 0xA4F9)],
+        split: false,
+    },
+    KnownKeyboard {
+        name: "sofle",
+        display_name: "Sofle",
+        rows: 5,
+        cols: 12,
+        key_count: 58,
+        usb_ids: &[(0xFC32, 0x0287)],
+        split: true,
+    },
+    KnownKeyboard {
+        name: "kyria",
+        display_name: "Kyria",
+        rows: 5,
+        cols: 10,
+        key_count: 50,
+        usb_ids: &[(0x8D1D, 0x9D9D)],
+        split: true,
+    },
+    KnownKeyboard {
+        name: "kyria_64",
+        display_name: "Kyria (64 keys)",
+        rows: 4,
+        cols: 16,
+        key_count: 64,
+        usb_ids: &[],
+        split: true,
+    },
+    KnownKeyboard {
+        name: "corne",
+        display_name: "Corne",
+        rows: 4,
+        cols: 12,
+        key_count: 42,
+        usb_ids: &[(0x4653, 0x0001)],
+        split: true,
+    },
+    KnownKeyboard {
+        name: "moonlander",
+        display_name: "Moonlander",
+        rows: 6,
+        cols: 12,
+        key_count: 72,
+        usb_ids: &[(0x3297, 0x1969)],
+        split: true,
+    },
+    KnownKeyboard {
+        name: "ferris",
+        display_name: "Ferris",
+        rows: 4,
+        cols: 10,
+        key_count: 34,
+        usb_ids: &[],
+        split: true,
+    },
+    KnownKeyboard {
+        name: "ferris_36",
+        display_name: "Ferris (36 keys)",
+        rows: 4,
+        cols: 10,
+        key_count: 36,
+        usb_ids: &[],
+        split: true,
+    },
+];
+
+/// Closest known keyboard whose key count is within `tolerance` of `key_count`
+/// and whose matrix can hold that many keys
+pub fn find_by_key_count(key_count: usize, tolerance: usize) -> Option<&'static KnownKeyboard> {
+    KNOWN_KEYBOARDS
+        .iter()
+        .filter(|kb| kb.key_count.abs_diff(key_count) <= tolerance)
+        .filter(|kb| kb.rows * kb.cols >= key_count)
+        .min_by_key(|kb| kb.key_count.abs_diff(key_count))
+}
+
+/// Known keyboard a keymap with `key_count` keys was most likely written for
+pub fn detect_by_key_count(key_count: usize) -> Option<&'static KnownKeyboard> {
+    find_by_key_count(key_count, DETECT_TOLERANCE)
+}
+
+/// Known keyboard matching a USB vendor/product id pair
+pub fn find_by_usb_id(vendor_id: u16, product_id: u16) -> Option<&'static KnownKeyboard> {
+    KNOWN_KEYBOARDS
+        .iter()
+        .find(|kb| kb.usb_ids.contains(&(vendor_id, product_id)))
+}
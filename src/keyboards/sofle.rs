@@ -0,0 +1,143 @@
+//! Sofle RGB: 58-key split with a rotary encoder on each half
+
+use crate::keyboard::{KeyPosition, KeyboardLayout};
+
+/// Sofle RGB keyboard definition
+///
+/// The QMK `LAYOUT` has 60 entries on a 5x12 grid: four rows of 6 keys per half, the two
+/// encoder clicks in the middle of the bottom row and 5 thumb keys per half.
+pub struct SofleLayout;
+
+impl SofleLayout {
+    pub const ROWS: usize = 5;
+    pub const COLS: usize = 12;
+
+    /// Physical keys, not counting the two encoders
+    pub const KEY_COUNT: usize = 58;
+
+    /// `LAYOUT` indices of the left and right encoder clicks
+    pub const ENCODER_INDICES: [usize; 2] = [42, 43];
+
+    pub const DEFAULT_LAYER_NAMES: &'static [&'static str] =
+        &["Qwerty", "Lower", "Raise", "Adjust"];
+
+    /// Key placement in `LAYOUT` order: columnar stagger (pinky columns lowest, middle
+    /// finger highest), thumb keys fanning down towards the center and the encoders drawn
+    /// 1.5U tall so they stand out from the keys
+    pub const DEFAULT_PHYSICAL_POSITIONS: &'static [KeyPosition] = &[
+        // Number row
+        KeyPosition::new(0.0, 0.5, 1.0, 1.0),
+        KeyPosition::new(1.0, 0.5, 1.0, 1.0),
+        KeyPosition::new(2.0, 0.25, 1.0, 1.0),
+        KeyPosition::new(3.0, 0.0, 1.0, 1.0),
+        KeyPosition::new(4.0, 0.25, 1.0, 1.0),
+        KeyPosition::new(5.0, 0.375, 1.0, 1.0),
+        KeyPosition::new(9.5, 0.375, 1.0, 1.0),
+        KeyPosition::new(10.5, 0.25, 1.0, 1.0),
+        KeyPosition::new(11.5, 0.0, 1.0, 1.0),
+        KeyPosition::new(12.5, 0.25, 1.0, 1.0),
+        KeyPosition::new(13.5, 0.5, 1.0, 1.0),
+        KeyPosition::new(14.5, 0.5, 1.0, 1.0),
+        // Top row
+        KeyPosition::new(0.0, 1.5, 1.0, 1.0),
+        KeyPosition::new(1.0, 1.5, 1.0, 1.0),
+        KeyPosition::new(2.0, 1.25, 1.0, 1.0),
+        KeyPosition::new(3.0, 1.0, 1.0, 1.0),
+        KeyPosition::new(4.0, 1.25, 1.0, 1.0),
+        KeyPosition::new(5.0, 1.375, 1.0, 1.0),
+        KeyPosition::new(9.5, 1.375, 1.0, 1.0),
+        KeyPosition::new(10.5, 1.25, 1.0, 1.0),
+        KeyPosition::new(11.5, 1.0, 1.0, 1.0),
+        KeyPosition::new(12.5, 1.25, 1.0, 1.0),
+        KeyPosition::new(13.5, 1.5, 1.0, 1.0),
+        KeyPosition::new(14.5, 1.5, 1.0, 1.0),
+        // Home row
+        KeyPosition::new(0.0, 2.5, 1.0, 1.0),
+        KeyPosition::new(1.0, 2.5, 1.0, 1.0),
+        KeyPosition::new(2.0, 2.25, 1.0, 1.0),
+        KeyPosition::new(3.0, 2.0, 1.0, 1.0),
+        KeyPosition::new(4.0, 2.25, 1.0, 1.0),
+        KeyPosition::new(5.0, 2.375, 1.0, 1.0),
+        KeyPosition::new(9.5, 2.375, 1.0, 1.0),
+        KeyPosition::new(10.5, 2.25, 1.0, 1.0),
+        KeyPosition::new(11.5, 2.0, 1.0, 1.0),
+        KeyPosition::new(12.5, 2.25, 1.0, 1.0),
+        KeyPosition::new(13.5, 2.5, 1.0, 1.0),
+        KeyPosition::new(14.5, 2.5, 1.0, 1.0),
+        // Bottom row, encoders in the middle
+        KeyPosition::new(0.0, 3.5, 1.0, 1.0),
+        KeyPosition::new(1.0, 3.5, 1.0, 1.0),
+        KeyPosition::new(2.0, 3.25, 1.0, 1.0),
+        KeyPosition::new(3.0, 3.0, 1.0, 1.0),
+        KeyPosition::new(4.0, 3.25, 1.0, 1.0),
+        KeyPosition::new(5.0, 3.375, 1.0, 1.0),
+        KeyPosition::new(6.25, 2.75, 1.0, 1.5),
+        KeyPosition::new(8.25, 2.75, 1.0, 1.5),
+        KeyPosition::new(9.5, 3.375, 1.0, 1.0),
+        KeyPosition::new(10.5, 3.25, 1.0, 1.0),
+        KeyPosition::new(11.5, 3.0, 1.0, 1.0),
+        KeyPosition::new(12.5, 3.25, 1.0, 1.0),
+        KeyPosition::new(13.5, 3.5, 1.0, 1.0),
+        KeyPosition::new(14.5, 3.5, 1.0, 1.0),
+        // Thumb cluster
+        KeyPosition::new(1.0, 4.5, 1.0, 1.0),
+        KeyPosition::new(2.0, 4.25, 1.0, 1.0),
+        KeyPosition::new(3.0, 4.0, 1.0, 1.0),
+        KeyPosition::new(4.0, 4.375, 1.0, 1.0),
+        KeyPosition::new(5.0, 4.75, 1.0, 1.0),
+        KeyPosition::new(9.5, 4.75, 1.0, 1.0),
+        KeyPosition::new(10.5, 4.375, 1.0, 1.0),
+        KeyPosition::new(11.5, 4.0, 1.0, 1.0),
+        KeyPosition::new(12.5, 4.25, 1.0, 1.0),
+        KeyPosition::new(13.5, 4.5, 1.0, 1.0),
+    ];
+
+    /// Sofle layout with the default layer names and physical positions
+    pub fn sofle_default() -> KeyboardLayout {
+        let names = Self::DEFAULT_LAYER_NAMES
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mut layout = KeyboardLayout::new(Self::ROWS, Self::COLS, names);
+        layout.physical_positions = Some(Self::DEFAULT_PHYSICAL_POSITIONS.to_vec());
+        layout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sofle_positions() {
+        let positions = SofleLayout::DEFAULT_PHYSICAL_POSITIONS;
+        assert_eq!(positions.len(), SofleLayout::ROWS * SofleLayout::COLS);
+
+        let encoders: Vec<_> = positions
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.h == 1.5)
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(encoders, SofleLayout::ENCODER_INDICES);
+        assert_eq!(positions.len() - encoders.len(), SofleLayout::KEY_COUNT);
+
+        let min_x = positions.iter().map(|p| p.x).fold(f32::MAX, f32::min);
+        let max_x = positions.iter().map(|p| p.x + p.w).fold(f32::MIN, f32::max);
+        let min_y = positions.iter().map(|p| p.y).fold(f32::MAX, f32::min);
+        let max_y = positions.iter().map(|p| p.y + p.h).fold(f32::MIN, f32::max);
+        assert_eq!((min_x, max_x), (0.0, 15.5));
+        assert_eq!((min_y, max_y), (0.0, 5.75));
+
+        crate::keyboards::assert_no_overlap(positions);
+    }
+
+    #[test]
+    fn test_sofle_default() {
+        let layout = SofleLayout::sofle_default();
+        assert_eq!((layout.rows, layout.cols), (5, 12));
+        assert_eq!(layout.layer_names.len(), 4);
+        assert_eq!(layout.legends[0].len(), 60);
+        assert_eq!(layout.physical_positions.as_ref().map(Vec::len), Some(60));
+    }
+}
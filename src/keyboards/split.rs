@@ -0,0 +1,33 @@
+//! Generic split keyboards: two halves with the same number of columns
+
+use crate::keyboard::{KeyboardLayout, SplitConfig};
+
+/// Split keyboard without a dedicated definition (Lily58, Let's Split, ...)
+pub struct SplitLayout;
+
+impl SplitLayout {
+    /// `rows` x `2 * cols_per_half` grid with the default gap after the left half
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(rows: usize, cols_per_half: usize, layer_names: Vec<String>) -> KeyboardLayout {
+        let mut layout = KeyboardLayout::new(rows, cols_per_half * 2, layer_names);
+        layout.split = Some(SplitConfig {
+            split_col: cols_per_half,
+            gap_px: SplitConfig::DEFAULT_GAP_PX,
+            thumb_keys: None,
+        });
+        layout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_layout() {
+        let layout = SplitLayout::new(3, 7, vec!["Base".to_string()]);
+        assert_eq!((layout.rows, layout.cols), (3, 14));
+        assert_eq!(layout.split.map(|s| s.split_col), Some(7));
+        assert_eq!(layout.raw_legends[0].len(), 42);
+    }
+}
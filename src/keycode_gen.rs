@@ -0,0 +1,95 @@
+//! Keycode table generation from QMK's `quantum/keycode.h`, shared by `build.rs` and tests
+
+/// Keycodes defined in a QMK header, as `(name, value)` sorted by name
+///
+/// Reads `#define KC_FOO 0xNNNN` lines and `KC_FOO = 0xNNNN,` enum entries; aliases of
+/// other names and anything not prefixed with `KC_` are skipped.
+pub fn parse_keycode_header(source: &str) -> Vec<(String, u16)> {
+    let mut entries: Vec<(String, u16)> = source
+        .lines()
+        .filter_map(|line| {
+            let line = line.split("//").next()?.trim();
+            let (name, value) = match line.strip_prefix("#define") {
+                Some(rest) => {
+                    let mut parts = rest.split_whitespace();
+                    (parts.next()?, parts.next()?)
+                }
+                None => {
+                    let (name, value) = line.split_once('=')?;
+                    (name.trim(), value.trim().trim_end_matches(','))
+                }
+            };
+            let value = value.trim().strip_prefix("0x")?;
+            if !name.starts_with("KC_")
+                || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            {
+                return None;
+            }
+            Some((name.to_string(), u16::from_str_radix(value, 16).ok()?))
+        })
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries.dedup_by(|a, b| a.0 == b.0);
+    entries
+}
+
+/// Rust source of `generated_keycodes.rs` for the given entries
+pub fn render_keycode_table(entries: &[(String, u16)]) -> String {
+    let mut out =
+        String::from("/// Keycodes generated from QMK's `quantum/keycode.h`, sorted by name\n");
+    out.push_str("pub(crate) const KEYCODE_TABLE: &[(&str, u16)] = &[\n");
+    for (name, value) in entries {
+        out.push_str(&format!("    ({:?}, 0x{:04X}),\n", name, value));
+    }
+    out.push_str("];\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keycodes::generated_label;
+
+    const HEADER: &str = "\
+enum qk_keycode_defines {
+    KC_NO = 0x0000,
+    KC_A = 0x0004,
+    KC_LANGUAGE_1 = 0x0090, // Hangul / English
+    KC_SYSTEM_REQUEST = 0x009A,
+};
+#define KC_EXECUTE 0x0074
+#define KC_ENT KC_ENTER
+#define QK_BOOT 0x7C00
+";
+
+    #[test]
+    fn test_parse_keycode_header() {
+        let entries = parse_keycode_header(HEADER);
+        let names: Vec<&str> = entries.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "KC_A",
+                "KC_EXECUTE",
+                "KC_LANGUAGE_1",
+                "KC_NO",
+                "KC_SYSTEM_REQUEST"
+            ]
+        );
+        assert_eq!(entries[2].1, 0x90);
+        let source = render_keycode_table(&entries);
+        assert!(source.contains("(\"KC_EXECUTE\", 0x0074),"), "{}", source);
+    }
+
+    #[test]
+    fn test_generated_label_falls_back_after_hand_written_map() {
+        let entries = parse_keycode_header(HEADER);
+        let table: Vec<(&str, u16)> = entries.iter().map(|(n, v)| (n.as_str(), *v)).collect();
+        // KC_LANGUAGE_1 is not in the hand-written map
+        assert_eq!(
+            generated_label(&table, "KC_LANGUAGE_1"),
+            Some("LANGUAGE_1".to_string())
+        );
+        assert_eq!(generated_label(&table, "KC_UNKNOWN"), None);
+    }
+}
@@ -3,8 +3,340 @@
 //! This module provides comprehensive mapping of QMK keycodes to human-readable
 //! labels and symbols, based on the official QMK documentation.
 
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Labels for a keymap's own keycodes (the ones defined from `SAFE_RANGE` on), by token
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CustomKeycodeTable(pub HashMap<String, String>);
+
+impl CustomKeycodeTable {
+    pub fn translate(&self, tok: &str) -> Option<&str> {
+        self.0.get(tok.trim()).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Unicode glyphs whose availability is probed at runtime, with their ASCII fallback
+pub const PROBED_GLYPHS: &[(char, &str)] = &[
+    ('⌘', "Cmd"),
+    ('⌥', "Opt"),
+    ('⌃', "Ctrl"),
+    ('⇧', "Shift"),
+    ('←', "Left"),
+    ('→', "Right"),
+    ('↑', "Up"),
+    ('↓', "Down"),
+    ('⌫', "Bksp"),
+    ('⌦', "Del"),
+    ('↩', "Enter"),
+    ('⎋', "Esc"),
+    ('⇥', "Tab"),
+    ('␣', "Space"),
+    ('★', "OSL"),
+    ('🖱', "M"),
+];
+
+/// Every keycode token with a dedicated translation (letters, numbers, specials, keypad,
+/// lighting, mouse and French KF_* keycodes); used by tests to cover the whole table
+pub const ALL_QMK_TOKENS: &[&str] = &[
+    "KC_A",
+    "KC_B",
+    "KC_C",
+    "KC_D",
+    "KC_E",
+    "KC_F",
+    "KC_G",
+    "KC_H",
+    "KC_I",
+    "KC_J",
+    "KC_K",
+    "KC_L",
+    "KC_M",
+    "KC_N",
+    "KC_O",
+    "KC_P",
+    "KC_Q",
+    "KC_R",
+    "KC_S",
+    "KC_T",
+    "KC_U",
+    "KC_V",
+    "KC_W",
+    "KC_X",
+    "KC_Y",
+    "KC_Z",
+    "KC_1",
+    "KC_2",
+    "KC_3",
+    "KC_4",
+    "KC_5",
+    "KC_6",
+    "KC_7",
+    "KC_8",
+    "KC_9",
+    "KC_0",
+    "KC_SPC",
+    "KC_SPACE",
+    "KC_ENT",
+    "KC_ENTER",
+    "KC_ESC",
+    "KC_TAB",
+    "KC_BSPC",
+    "KC_DEL",
+    "KC_LEFT",
+    "KC_RGHT",
+    "KC_RIGHT",
+    "KC_UP",
+    "KC_DOWN",
+    "KC_HOME",
+    "KC_END",
+    "KC_PGUP",
+    "KC_PG_U",
+    "KC_PGDN",
+    "KC_PG_D",
+    "KC_LSFT",
+    "KC_RSFT",
+    "KC_LCTL",
+    "KC_RCTL",
+    "KC_LALT",
+    "KC_RALT",
+    "KC_LGUI",
+    "KC_RGUI",
+    "KC_CAPS",
+    "KC_CAPSLOCK",
+    "KC_LPRN",
+    "KC_RPRN",
+    "KC_LBRC",
+    "KC_RBRC",
+    "KC_LCBR",
+    "KC_RCBR",
+    "KC_LABK",
+    "KC_RABK",
+    "KC_COMM",
+    "KC_DOT",
+    "KC_SLSH",
+    "KC_BSLS",
+    "KC_PIPE",
+    "KC_COLN",
+    "KC_SCLN",
+    "KC_QUOT",
+    "KC_DQUO",
+    "KC_GRV",
+    "KC_TILD",
+    "KC_AT",
+    "KC_HASH",
+    "KC_DLR",
+    "KC_PERC",
+    "KC_AMPR",
+    "KC_ASTR",
+    "KC_MINS",
+    "KC_UNDS",
+    "KC_EQL",
+    "KC_PLUS",
+    "KC_EXLM",
+    "KC_CIRC",
+    "KC_F1",
+    "KC_F2",
+    "KC_F3",
+    "KC_F4",
+    "KC_F5",
+    "KC_F6",
+    "KC_F7",
+    "KC_F8",
+    "KC_F9",
+    "KC_F10",
+    "KC_F11",
+    "KC_F12",
+    "KC_F13",
+    "KC_F14",
+    "KC_F15",
+    "KC_F16",
+    "KC_F17",
+    "KC_F18",
+    "KC_F19",
+    "KC_F20",
+    "KC_F21",
+    "KC_F22",
+    "KC_F23",
+    "KC_F24",
+    "KC_PSCR",
+    "KC_APP",
+    "KC_KP_0",
+    "KC_KP_1",
+    "KC_KP_2",
+    "KC_KP_3",
+    "KC_KP_4",
+    "KC_KP_5",
+    "KC_KP_6",
+    "KC_KP_7",
+    "KC_KP_8",
+    "KC_KP_9",
+    "KC_KP_DOT",
+    "KC_KP_POINT",
+    "KC_KP_PERIOD",
+    "KC_KP_COMMA",
+    "KC_KP_PLUS",
+    "KC_KP_MINUS",
+    "KC_KP_SUBTRACT",
+    "KC_KP_ASTERISK",
+    "KC_KP_MULTIPLY",
+    "KC_KP_SLASH",
+    "KC_KP_DIVIDE",
+    "KC_KP_ENTER",
+    "KC_KP_EQUAL",
+    "KC_KP_EQUAL_AS400",
+    "KC_P0",
+    "KC_P5",
+    "KC_PDOT",
+    "KC_PCMM",
+    "KC_PPLS",
+    "KC_PMNS",
+    "KC_PAST",
+    "KC_PSLS",
+    "KC_PENT",
+    "KC_PEQL",
+    "KC_NUMLOCK",
+    "KC_NUM",
+    "KC_NUM_LOCK",
+    "KC_LOCKING_CAPS",
+    "KC_LOCKING_NUM",
+    "KC_LOCKING_SCROLL",
+    "KC_SCROLL_LOCK",
+    "KC_PAUSE",
+    "KC_PRINT_SCREEN",
+    "RGB_TOG",
+    "RGB_MOD",
+    "RGB_RMOD",
+    "RGB_HUI",
+    "RGB_HUD",
+    "RGB_SAI",
+    "RGB_SAD",
+    "RGB_VAI",
+    "RGB_VAD",
+    "RGB_SPI",
+    "RGB_SPD",
+    "RGB_MODE_FORWARD",
+    "RGB_MODE_REVERSE",
+    "RGB_MODE_PLAIN",
+    "RGB_MODE_BREATHE",
+    "RGB_MODE_RAINBOW",
+    "RGB_MODE_SWIRL",
+    "RGB_MODE_SNAKE",
+    "RGB_MODE_KNIGHT",
+    "RGB_MODE_XMAS",
+    "RGB_MODE_GRADIENT",
+    "RGB_MODE_RGBTEST",
+    "RGB_MODE_TWINKLE",
+    "BL_TOGG",
+    "BL_UP",
+    "BL_DOWN",
+    "BL_ON",
+    "BL_OFF",
+    "BL_STEP",
+    "BL_BRTG",
+    "KC_MS_UP",
+    "KC_MS_DOWN",
+    "KC_MS_LEFT",
+    "KC_MS_RIGHT",
+    "KC_MS_BTN1",
+    "KC_MS_BTN2",
+    "KC_MS_BTN3",
+    "KC_MS_BTN4",
+    "KC_MS_BTN5",
+    "KC_MS_WH_UP",
+    "KC_MS_WH_DOWN",
+    "KC_MS_WH_LEFT",
+    "KC_MS_WH_RIGHT",
+    "KC_MS_ACCEL0",
+    "KC_MS_ACCEL1",
+    "KC_MS_ACCEL2",
+    "KF_EGRV",
+    "KF_EACU",
+    "KF_ECRC",
+    "KF_AGRV",
+    "KF_UGRV",
+    "KF_UCRC",
+    "KF_ICRC",
+    "KF_ACRC",
+    "KF_CCED",
+    "KF_DIAE",
+    "KF_AE",
+    "KF_OE",
+    "KF_OCRC",
+    "KF_LAQT",
+    "KF_RAQT",
+    "KF_LDQT",
+    "KF_RDQT",
+    "KF_MDOT",
+    "KF_BDOT",
+    "KF_DEG",
+    "KF_EURO",
+    "KF_UNDS",
+    "KF_SUP2",
+    "KF_IQES",
+    "KF_LARW",
+    "KF_RARW",
+    "KF_MICR",
+    "KF_PSMS",
+    "KF_CROS",
+    "KF_QUOT",
+    "KF_SLCT",
+    "KF_CUT",
+    "KF_COPY",
+    "KF_PSTE",
+    "KF_SAVE",
+    "KF_UNDO",
+    "KF_REDO",
+];
+
+/// Replace probed glyphs missing from `supported` by their ASCII fallback
+pub fn sanitize_glyphs(label: &str, supported: &HashSet<char>) -> String {
+    let mut out = String::with_capacity(label.len());
+    for ch in label.chars() {
+        match PROBED_GLYPHS.iter().find(|(g, _)| *g == ch) {
+            Some((_, fallback)) if !supported.contains(&ch) => out.push_str(fallback),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Outcome of [`translate_token_checked`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranslateResult {
+    /// Label from one of the keycode tables, empty for transparent keys
+    Known(String),
+    /// Token none of the tables know, labelled as it is
+    Unknown(String),
+}
+
+impl TranslateResult {
+    pub fn is_known(&self) -> bool {
+        matches!(self, Self::Known(_))
+    }
+
+    pub fn into_label(self) -> String {
+        match self {
+            Self::Known(label) | Self::Unknown(label) => label,
+        }
+    }
+}
+
 /// Translate a QMK keycode token to a human-readable label
 pub fn translate_token(tok: &str) -> String {
+    translate_token_checked(tok).into_label()
+}
+
+/// Like [`translate_token`], but tells whether the token was found in a keycode table
+///
+/// Function-like tokens such as `MO(1)` or `LT(1, KC_A)` are labelled elsewhere and come
+/// back [`TranslateResult::Unknown`].
+pub fn translate_token_checked(tok: &str) -> TranslateResult {
     let t = tok.trim();
 
     // Normalize some malformed keypad tokens that may contain spaces or missing 'K'
@@ -18,61 +350,137 @@ pub fn translate_token(tok: &str) -> String {
             canonical = format!("KC_KP_{}", rest);
         }
     } else if let Some(stripped) = upper.strip_prefix("KC_P") {
-        // Accept aliases like KC_P0, KC_P 1 → treat as KC_KP_<REST>
+        // Accept aliases like KC_P0, KC_P 1 → treat as KC_KP_<REST>; other KC_P* tokens
+        // (KC_PGUP, KC_PIPE, ...) are regular keycodes and must be left alone
         let rest = stripped.trim_start_matches('_');
-        if !rest.is_empty() {
+        let is_keypad_alias =
+            stripped.starts_with('_') || stripped.starts_with(|c: char| c.is_ascii_digit());
+        if !rest.is_empty() && is_keypad_alias {
             canonical = format!("KC_KP_{}", rest);
         }
     }
     let t = canonical.as_str();
-    if t == "TRNS" || t == "NO" || t == "_______" || t == "KC_TRNS" || t == "KC_NO" {
-        return String::new();
+    if t.is_empty() || t == "TRNS" || t == "NO" || t == "_______" || t == "KC_TRNS" || t == "KC_NO"
+    {
+        return TranslateResult::Known(String::new());
+    }
+
+    // Unicode input: UC(0x00E9), X(n) / XP(i, j) in UNICODEMAP mode
+    if let Some(result) = translate_uc(t) {
+        return TranslateResult::Known(result);
+    }
+    if let Some(result) = translate_unicode_map(t, &[]) {
+        return TranslateResult::Known(result);
     }
 
     // French accents and specials (KF_* keycodes from the keymap)
     if let Some(result) = translate_french_accents(t) {
-        return result;
+        return TranslateResult::Known(result);
     }
 
     // Brackets / punctuation tokens
     if let Some(result) = translate_punctuation(t) {
-        return result;
+        return TranslateResult::Known(result);
     }
 
     // Navigation / control
     if let Some(result) = translate_navigation(t) {
-        return result;
+        return TranslateResult::Known(result);
     }
 
     // Modifiers / locks
     if let Some(result) = translate_modifiers(t) {
-        return result;
+        return TranslateResult::Known(result);
     }
 
     // Basic letter keycodes (KC_A, KC_B, etc.)
     if t.starts_with("KC_") && t.len() == 4 {
         let letter = &t[3..4];
         if letter.chars().next().unwrap().is_ascii_alphabetic() {
-            return letter.to_lowercase();
+            return TranslateResult::Known(letter.to_lowercase());
         }
     }
 
     // Other KC_ keycodes
     if let Some(result) = translate_kc_keycodes(t) {
-        return result;
+        return TranslateResult::Known(result);
     }
 
     // Also handle single letter tokens (fallback)
     if t.len() == 1 && t.chars().next().unwrap().is_ascii_alphabetic() {
-        return t.to_lowercase();
+        return TranslateResult::Known(t.to_lowercase());
     }
 
     // Common icons
     if let Some(result) = translate_icons(t) {
-        return result;
+        return TranslateResult::Known(result);
+    }
+
+    // Keycodes from QMK's keycode.h that the maps above do not cover
+    if let Some(result) = translate_generated_token(t) {
+        return TranslateResult::Known(result);
     }
 
-    t.to_string()
+    TranslateResult::Unknown(t.to_string())
+}
+
+// KEYCODE_TABLE, generated by build.rs from $QMK_FIRMWARE/quantum/keycode.h (empty when
+// QMK_FIRMWARE is not set)
+include!(concat!(env!("OUT_DIR"), "/generated_keycodes.rs"));
+
+/// Label for a keycode from the generated QMK table: its name without the `KC_` prefix
+pub fn translate_generated_token(t: &str) -> Option<String> {
+    generated_label(KEYCODE_TABLE, t)
+}
+
+/// Look `t` up in a keycode table sorted by name, such as [`KEYCODE_TABLE`]
+pub(crate) fn generated_label(table: &[(&str, u16)], t: &str) -> Option<String> {
+    let idx = table.binary_search_by(|(name, _)| (*name).cmp(t)).ok()?;
+    let name = table[idx].0;
+    Some(name.strip_prefix("KC_").unwrap_or(name).to_string())
+}
+
+/// Translate a `UC(codepoint)` token (hex `0x..` or decimal) to its character
+///
+/// Non-printable or invalid codepoints are shown as `U+XXXX`.
+pub fn translate_uc(tok: &str) -> Option<String> {
+    let inner = tok.trim().strip_prefix("UC(")?.strip_suffix(')')?.trim();
+    let code = parse_codepoint(inner)?;
+    Some(match char::from_u32(code) {
+        Some(ch) if is_printable(ch) => ch.to_string(),
+        _ => format!("U+{:04X}", code),
+    })
+}
+
+/// Translate `X(n)` / `XP(i, j)` (UNICODEMAP mode) using a `(index, label)` table
+///
+/// Unknown indices are shown as `U:N`.
+pub fn translate_unicode_map(tok: &str, table: &[(u32, &str)]) -> Option<String> {
+    let t = tok.trim();
+    let inner = t
+        .strip_prefix("XP(")
+        .or_else(|| t.strip_prefix("X("))?
+        .strip_suffix(')')?;
+    // XP(lower, upper) shows the unshifted entry
+    let index = parse_codepoint(inner.split(',').next()?.trim())?;
+    Some(match table.iter().find(|(i, _)| *i == index) {
+        Some((_, label)) => label.to_string(),
+        None => format!("U:{}", index),
+    })
+}
+
+fn parse_codepoint(s: &str) -> Option<u32> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn is_printable(ch: char) -> bool {
+    let code = ch as u32;
+    // Noncharacters: U+FDD0..U+FDEF and the last two codepoints of every plane
+    let noncharacter = (0xFDD0..=0xFDEF).contains(&code) || (code & 0xFFFE) == 0xFFFE;
+    !ch.is_control() && !ch.is_whitespace() && !noncharacter
 }
 
 fn translate_french_accents(t: &str) -> Option<String> {
@@ -330,30 +738,164 @@ fn translate_kc_keycodes(t: &str) -> Option<String> {
         "KC_PSCR" => Some("PrtSc".to_string()),
         "KC_APP" => Some("Menu".to_string()),
 
-        // Keypad (numpad) keys
-        "KC_KP_0" => Some("0".to_string()),
-        "KC_KP_1" => Some("1".to_string()),
-        "KC_KP_2" => Some("2".to_string()),
-        "KC_KP_3" => Some("3".to_string()),
-        "KC_KP_4" => Some("4".to_string()),
-        "KC_KP_5" => Some("5".to_string()),
-        "KC_KP_6" => Some("6".to_string()),
-        "KC_KP_7" => Some("7".to_string()),
-        "KC_KP_8" => Some("8".to_string()),
-        "KC_KP_9" => Some("9".to_string()),
-        "KC_KP_DOT" | "KC_KP_POINT" | "KC_KP_PERIOD" => Some(".".to_string()),
-        "KC_KP_COMMA" => Some(",".to_string()),
-        "KC_KP_PLUS" => Some("+".to_string()),
-        "KC_KP_MINUS" | "KC_KP_SUBTRACT" => Some("-".to_string()),
-        "KC_KP_ASTERISK" | "KC_KP_MULTIPLY" => Some("*".to_string()),
-        "KC_KP_SLASH" | "KC_KP_DIVIDE" => Some("/".to_string()),
-        "KC_KP_ENTER" => Some("Enter".to_string()),
-        "KC_KP_EQUAL" | "KC_KP_EQUAL_AS400" => Some("=".to_string()),
-        "KC_NUMLOCK" | "KC_NUM" | "KC_LOCKING_NUM" => Some("Num".to_string()),
-        _ => None,
+        // Keypad (numpad) keys, prefixed so they are not mistaken for the number row. KC_P0
+        // style aliases are normalized to KC_KP_0 by translate_token; the short symbol
+        // aliases (KC_PSLS, ...) are listed here
+        "KC_KP_0" => Some("KP:0".to_string()),
+        "KC_KP_1" => Some("KP:1".to_string()),
+        "KC_KP_2" => Some("KP:2".to_string()),
+        "KC_KP_3" => Some("KP:3".to_string()),
+        "KC_KP_4" => Some("KP:4".to_string()),
+        "KC_KP_5" => Some("KP:5".to_string()),
+        "KC_KP_6" => Some("KP:6".to_string()),
+        "KC_KP_7" => Some("KP:7".to_string()),
+        "KC_KP_8" => Some("KP:8".to_string()),
+        "KC_KP_9" => Some("KP:9".to_string()),
+        "KC_KP_DOT" | "KC_KP_POINT" | "KC_KP_PERIOD" | "KC_PDOT" => Some("KP:.".to_string()),
+        "KC_KP_COMMA" | "KC_PCMM" => Some("KP:,".to_string()),
+        "KC_KP_PLUS" | "KC_PPLS" => Some("KP:+".to_string()),
+        "KC_KP_MINUS" | "KC_KP_SUBTRACT" | "KC_PMNS" => Some("KP:-".to_string()),
+        "KC_KP_ASTERISK" | "KC_KP_MULTIPLY" | "KC_PAST" => Some("KP:*".to_string()),
+        "KC_KP_SLASH" | "KC_KP_DIVIDE" | "KC_PSLS" => Some("KP:/".to_string()),
+        "KC_KP_ENTER" | "KC_PENT" => Some("KP:Enter".to_string()),
+        "KC_KP_EQUAL" | "KC_KP_EQUAL_AS400" | "KC_PEQL" => Some("KP:=".to_string()),
+        "KC_NUMLOCK" | "KC_NUM" | "KC_NUM_LOCK" => Some("Num".to_string()),
+        _ => translate_locking_keys(t)
+            .or_else(|| translate_extra_keycodes(t))
+            .or_else(|| translate_rgb_keycodes(t))
+            .or_else(|| translate_mouse_keycodes(t)),
     }
 }
 
+/// Locking modifiers (sent as a locking key rather than a toggle) and the lock/pause keys
+fn translate_locking_keys(t: &str) -> Option<String> {
+    let label = match t {
+        "KC_LOCKING_CAPS" => "⇪L",
+        "KC_LOCKING_NUM" => "NumL",
+        "KC_LOCKING_SCROLL" => "ScrL",
+        "KC_SCROLL_LOCK" => "ScrlLk",
+        "KC_PAUSE" => "Pause",
+        "KC_PRINT_SCREEN" => "PrtSc",
+        _ => return None,
+    };
+    Some(label.to_string())
+}
+
+/// Long-form media, application and system-control keycodes (`KC_MEDIA_*`, `KC_WWW_*`,
+/// `KC_SYSTEM_*`, ...)
+fn translate_extra_keycodes(t: &str) -> Option<String> {
+    let label = match t {
+        // Media
+        "KC_MEDIA_PLAY_PAUSE" => "Play",
+        "KC_MEDIA_STOP" => "Stop",
+        "KC_MEDIA_NEXT_TRACK" => "Next",
+        "KC_MEDIA_PREV_TRACK" => "Prev",
+        "KC_MEDIA_FAST_FORWARD" => "FFwd",
+        "KC_MEDIA_REWIND" => "Rewind",
+        "KC_MEDIA_SELECT" => "Media",
+        "KC_MEDIA_EJECT" => "Eject",
+        "KC_AUDIO_MUTE" => "Mute",
+        "KC_AUDIO_VOL_UP" => "Vol+",
+        "KC_AUDIO_VOL_DOWN" => "Vol-",
+
+        // Applications and browser (consumer page)
+        "KC_CALCULATOR" => "Calc",
+        "KC_MAIL" => "Mail",
+        "KC_MY_COMPUTER" => "MyPC",
+        "KC_WWW_SEARCH" => "Web:Srch",
+        "KC_WWW_HOME" => "Web:Home",
+        "KC_WWW_BACK" => "Web:Back",
+        "KC_WWW_FORWARD" => "Web:Fwd",
+        "KC_WWW_STOP" => "Web:Stop",
+        "KC_WWW_REFRESH" => "Web:Rfsh",
+        "KC_WWW_FAVORITES" => "Web:Fav",
+
+        // System control
+        "KC_SYSTEM_POWER" => "Power",
+        "KC_SYSTEM_SLEEP" => "Sleep",
+        "KC_SYSTEM_WAKE" => "Wake",
+        "KC_BRIGHTNESS_UP" => "Bri+",
+        "KC_BRIGHTNESS_DOWN" => "Bri-",
+        _ => return None,
+    };
+    Some(label.to_string())
+}
+
+/// Mouse keys under their current (`MS_*`, `QK_MOUSE_*`) and legacy (`KC_MS_*`, `KC_BTN*`,
+/// `KC_WH_*`, `KC_ACL*`) names; 🖱 falls back to "M" in [`sanitize_glyphs`]
+fn translate_mouse_keycodes(t: &str) -> Option<String> {
+    let label = match t {
+        // Cursor
+        "KC_MS_UP" | "KC_MS_U" | "MS_UP" | "QK_MOUSE_CURSOR_UP" => "🖱↑",
+        "KC_MS_DOWN" | "KC_MS_D" | "MS_DOWN" | "QK_MOUSE_CURSOR_DOWN" => "🖱↓",
+        "KC_MS_LEFT" | "KC_MS_L" | "MS_LEFT" | "QK_MOUSE_CURSOR_LEFT" => "🖱←",
+        "KC_MS_RIGHT" | "KC_MS_R" | "MS_RGHT" | "QK_MOUSE_CURSOR_RIGHT" => "🖱→",
+
+        // Buttons
+        "KC_MS_BTN1" | "KC_BTN1" | "MS_BTN1" | "QK_MOUSE_BUTTON_1" => "LMB",
+        "KC_MS_BTN2" | "KC_BTN2" | "MS_BTN2" | "QK_MOUSE_BUTTON_2" => "RMB",
+        "KC_MS_BTN3" | "KC_BTN3" | "MS_BTN3" | "QK_MOUSE_BUTTON_3" => "MMB",
+        "KC_MS_BTN4" | "KC_BTN4" | "MS_BTN4" | "QK_MOUSE_BUTTON_4" => "MB4",
+        "KC_MS_BTN5" | "KC_BTN5" | "MS_BTN5" | "QK_MOUSE_BUTTON_5" => "MB5",
+        "KC_MS_BTN6" | "KC_BTN6" | "MS_BTN6" | "QK_MOUSE_BUTTON_6" => "MB6",
+        "KC_MS_BTN7" | "KC_BTN7" | "MS_BTN7" | "QK_MOUSE_BUTTON_7" => "MB7",
+        "KC_MS_BTN8" | "KC_BTN8" | "MS_BTN8" | "QK_MOUSE_BUTTON_8" => "MB8",
+
+        // Wheel
+        "KC_MS_WH_UP" | "KC_WH_U" | "MS_WHLU" | "QK_MOUSE_WHEEL_UP" => "WH↑",
+        "KC_MS_WH_DOWN" | "KC_WH_D" | "MS_WHLD" | "QK_MOUSE_WHEEL_DOWN" => "WH↓",
+        "KC_MS_WH_LEFT" | "KC_WH_L" | "MS_WHLL" | "QK_MOUSE_WHEEL_LEFT" => "WH←",
+        "KC_MS_WH_RIGHT" | "KC_WH_R" | "MS_WHLR" | "QK_MOUSE_WHEEL_RIGHT" => "WH→",
+
+        // Acceleration
+        "KC_MS_ACCEL0" | "KC_ACL0" | "MS_ACL0" | "QK_MOUSE_ACCELERATION_0" => "Acc0",
+        "KC_MS_ACCEL1" | "KC_ACL1" | "MS_ACL1" | "QK_MOUSE_ACCELERATION_1" => "Acc1",
+        "KC_MS_ACCEL2" | "KC_ACL2" | "MS_ACL2" | "QK_MOUSE_ACCELERATION_2" => "Acc2",
+        _ => return None,
+    };
+    Some(label.to_string())
+}
+
+/// RGB lighting (`RGB_*`) and simple backlight (`BL_*`) controls
+fn translate_rgb_keycodes(t: &str) -> Option<String> {
+    let label = match t {
+        "RGB_TOG" => "RGB",
+        "RGB_MOD" | "RGB_MODE_FORWARD" => "RGB>",
+        "RGB_RMOD" | "RGB_MODE_REVERSE" => "RGB<",
+        "RGB_HUI" => "Hue+",
+        "RGB_HUD" => "Hue-",
+        "RGB_SAI" => "Sat+",
+        "RGB_SAD" => "Sat-",
+        "RGB_VAI" => "Val+",
+        "RGB_VAD" => "Val-",
+        "RGB_SPI" => "Spd+",
+        "RGB_SPD" => "Spd-",
+
+        // Effect modes, long and short names
+        "RGB_MODE_PLAIN" | "RGB_M_P" => "Plain",
+        "RGB_MODE_BREATHE" | "RGB_M_B" => "Breath",
+        "RGB_MODE_RAINBOW" | "RGB_M_R" => "Rainbw",
+        "RGB_MODE_SWIRL" | "RGB_M_SW" => "Swirl",
+        "RGB_MODE_SNAKE" | "RGB_M_SN" => "Snake",
+        "RGB_MODE_KNIGHT" | "RGB_M_K" => "Knight",
+        "RGB_MODE_XMAS" | "RGB_M_X" => "Xmas",
+        "RGB_MODE_GRADIENT" | "RGB_M_G" => "Grad",
+        "RGB_MODE_RGBTEST" | "RGB_M_T" => "Test",
+        "RGB_MODE_TWINKLE" | "RGB_M_TW" => "Twinkl",
+
+        // Backlight
+        "BL_TOGG" => "BL",
+        "BL_UP" | "BL_INC" => "BL+",
+        "BL_DOWN" | "BL_DEC" => "BL-",
+        "BL_ON" => "BLOn",
+        "BL_OFF" => "BLOff",
+        "BL_STEP" => "BL>",
+        "BL_BRTG" => "BLBrth",
+        _ => return None,
+    };
+    Some(label.to_string())
+}
+
 fn translate_icons(t: &str) -> Option<String> {
     match t {
         "UNDO" => Some("↺".to_string()),
@@ -373,6 +915,258 @@ fn translate_icons(t: &str) -> Option<String> {
     }
 }
 
+/// Broad kind of a keycode, used to group keys in exports
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCategory {
+    Transparent,
+    Letter,
+    Number,
+    Modifier,
+    ModTap,
+    LayerControl,
+    Function,
+    Navigation,
+    Media,
+    Mouse,
+    Symbol,
+    Other,
+}
+
+impl KeyCategory {
+    /// Lower-case name used in exports
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyCategory::Transparent => "transparent",
+            KeyCategory::Letter => "letter",
+            KeyCategory::Number => "number",
+            KeyCategory::Modifier => "modifier",
+            KeyCategory::ModTap => "mod_tap",
+            KeyCategory::LayerControl => "layer_control",
+            KeyCategory::Function => "function",
+            KeyCategory::Navigation => "navigation",
+            KeyCategory::Media => "media",
+            KeyCategory::Mouse => "mouse",
+            KeyCategory::Symbol => "symbol",
+            KeyCategory::Other => "other",
+        }
+    }
+}
+
+/// Classify a raw keycode token
+pub fn keycode_category(tok: &str) -> KeyCategory {
+    let t = tok.trim();
+    let label = translate_token(t);
+    if t.is_empty() || label.is_empty() {
+        return KeyCategory::Transparent;
+    }
+    const LAYER_FUNCTIONS: [&str; 8] = ["MO(", "LT(", "TG(", "TO(", "DF(", "OSL(", "TT(", "LM("];
+    if LAYER_FUNCTIONS.iter().any(|f| t.starts_with(f)) {
+        return KeyCategory::LayerControl;
+    }
+    if t.starts_with("MT(") || t.contains("_T(") {
+        return KeyCategory::ModTap;
+    }
+    let code = t.strip_prefix("KC_").unwrap_or(t);
+    if t.starts_with("OSM(") || t.starts_with("OS_") || translate_modifiers(code).is_some() {
+        return KeyCategory::Modifier;
+    }
+    if t.starts_with("KC_") && code.len() == 1 {
+        let ch = code.chars().next().unwrap_or_default();
+        if ch.is_ascii_alphabetic() {
+            return KeyCategory::Letter;
+        }
+        if ch.is_ascii_digit() {
+            return KeyCategory::Number;
+        }
+    }
+    if code.starts_with('F') && code.len() > 1 && code[1..].chars().all(|c| c.is_ascii_digit()) {
+        return KeyCategory::Function;
+    }
+    if code.starts_with("MEDIA_")
+        || code.starts_with("AUDIO_")
+        || code.starts_with("SYSTEM_")
+        || matches!(
+            code,
+            "MPLY" | "MSTP" | "MNXT" | "MPRV" | "MFFD" | "MRWD" | "VOLU" | "VOLD" | "MUTE"
+        )
+    {
+        return KeyCategory::Media;
+    }
+    if code.starts_with("MS_")
+        || code.starts_with("BTN")
+        || code.starts_with("WH_")
+        || code.starts_with("ACL")
+        || code.starts_with("QK_MOUSE_")
+    {
+        return KeyCategory::Mouse;
+    }
+    if matches!(
+        code,
+        "LEFT" | "RGHT" | "RIGHT" | "UP" | "DOWN" | "HOME" | "END" | "PGUP" | "PGDN"
+    ) {
+        return KeyCategory::Navigation;
+    }
+    let mut chars = label.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        if ch.is_ascii_digit() {
+            return KeyCategory::Number;
+        }
+        if !ch.is_alphanumeric() {
+            return KeyCategory::Symbol;
+        }
+    }
+    KeyCategory::Other
+}
+
+/// OS keyboard layout the firmware's keycodes are typed into
+///
+/// QMK keycodes name US key positions, so on other layouts the same `KC_*` produces a
+/// different character (e.g. `KC_A` types `q` on AZERTY).
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyboardLocale {
+    US,
+    AZERTY,
+    QWERTZ,
+    Dvorak,
+}
+
+impl KeyboardLocale {
+    pub const ALL: [KeyboardLocale; 4] = [
+        KeyboardLocale::US,
+        KeyboardLocale::AZERTY,
+        KeyboardLocale::QWERTZ,
+        KeyboardLocale::Dvorak,
+    ];
+
+    /// Name used in the UI and stored in the app config
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyboardLocale::US => "US",
+            KeyboardLocale::AZERTY => "AZERTY",
+            KeyboardLocale::QWERTZ => "QWERTZ",
+            KeyboardLocale::Dvorak => "Dvorak",
+        }
+    }
+
+    /// Parse a stored name (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|locale| locale.name().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// Character typed by a letter-position keycode on `locale`, `None` when it matches US
+fn locale_letter(kc: &str, locale: KeyboardLocale) -> Option<&'static str> {
+    match locale {
+        KeyboardLocale::US => None,
+        KeyboardLocale::AZERTY => match kc {
+            "KC_A" => Some("q"),
+            "KC_Q" => Some("a"),
+            "KC_W" => Some("z"),
+            "KC_Z" => Some("w"),
+            "KC_SCLN" => Some("m"),
+            "KC_M" => Some(","),
+            _ => None,
+        },
+        KeyboardLocale::QWERTZ => match kc {
+            "KC_Y" => Some("z"),
+            "KC_Z" => Some("y"),
+            "KC_SCLN" => Some("ö"),
+            "KC_QUOT" => Some("ä"),
+            "KC_LBRC" => Some("ü"),
+            _ => None,
+        },
+        KeyboardLocale::Dvorak => match kc {
+            "KC_Q" => Some("'"),
+            "KC_W" => Some(","),
+            "KC_E" => Some("."),
+            "KC_R" => Some("p"),
+            "KC_T" => Some("y"),
+            "KC_Y" => Some("f"),
+            "KC_U" => Some("g"),
+            "KC_I" => Some("c"),
+            "KC_O" => Some("r"),
+            "KC_P" => Some("l"),
+            "KC_S" => Some("o"),
+            "KC_D" => Some("e"),
+            "KC_F" => Some("u"),
+            "KC_G" => Some("i"),
+            "KC_H" => Some("d"),
+            "KC_J" => Some("h"),
+            "KC_K" => Some("t"),
+            "KC_L" => Some("n"),
+            "KC_SCLN" => Some("s"),
+            "KC_Z" => Some(";"),
+            "KC_X" => Some("q"),
+            "KC_C" => Some("j"),
+            "KC_V" => Some("k"),
+            "KC_B" => Some("x"),
+            "KC_N" => Some("b"),
+            "KC_COMM" => Some("w"),
+            "KC_DOT" => Some("v"),
+            "KC_SLSH" => Some("z"),
+            _ => None,
+        },
+    }
+}
+
+/// Like [`translate_token`], but letter keys show what they type on `locale`
+pub fn translate_token_locale(tok: &str, locale: KeyboardLocale) -> String {
+    let t = tok.trim();
+    match locale_letter(&t.to_uppercase(), locale) {
+        Some(letter) => letter.to_string(),
+        None => translate_token(t),
+    }
+}
+
+/// Translate the inner keycode of an `ALGR(kc)` combo for the given keyboard locale
+///
+/// Falls back to the plain keycode label when the combo is unknown for the locale.
+pub fn translate_algr_combo(kc: &str, locale: &str) -> String {
+    let k = kc.trim();
+    let lang = locale.trim().to_lowercase();
+    let combo = if lang.starts_with("de") {
+        match k {
+            "KC_E" => Some("€"),
+            "KC_Q" => Some("@"),
+            "KC_M" => Some("µ"),
+            "KC_2" => Some("²"),
+            "KC_3" => Some("³"),
+            "KC_7" => Some("{"),
+            "KC_8" => Some("["),
+            "KC_9" => Some("]"),
+            "KC_0" => Some("}"),
+            "KC_MINS" => Some("\\"),
+            "KC_RBRC" => Some("~"),
+            "KC_NUBS" => Some("|"),
+            _ => None,
+        }
+    } else if lang.starts_with("fr") {
+        match k {
+            "KC_E" => Some("€"),
+            "KC_2" => Some("~"),
+            "KC_3" => Some("#"),
+            "KC_4" => Some("{"),
+            "KC_5" => Some("["),
+            "KC_6" => Some("|"),
+            "KC_7" => Some("`"),
+            "KC_8" => Some("\\"),
+            "KC_9" => Some("^"),
+            "KC_0" => Some("@"),
+            "KC_MINS" => Some("]"),
+            "KC_EQL" => Some("}"),
+            _ => None,
+        }
+    } else {
+        None
+    };
+    combo
+        .map(|c| c.to_string())
+        .unwrap_or_else(|| translate_token(k))
+}
+
 /// Convert modifier token to glyph representation
 pub fn mod_to_glyph(m: &str) -> String {
     let mm = m.trim();
@@ -391,6 +1185,174 @@ pub fn mod_to_glyph(m: &str) -> String {
     }
 }
 
+/// QMK tokens for the 16-bit keycodes read over VIA, using the keycode ranges of QMK 0.19+
+///
+/// These ranges came with VIA protocol 12; keyboards speaking an older protocol number
+/// their keycodes differently and are not supported.
+pub struct ViaKeycodeTable;
+
+impl ViaKeycodeTable {
+    /// Basic keycodes outside the numbered runs (letters, digits, F-keys, keypad digits)
+    const BASIC: &'static [(u16, &'static str)] = &[
+        (0x00, "KC_NO"),
+        (0x01, "KC_TRNS"),
+        (0x28, "KC_ENT"),
+        (0x29, "KC_ESC"),
+        (0x2A, "KC_BSPC"),
+        (0x2B, "KC_TAB"),
+        (0x2C, "KC_SPC"),
+        (0x2D, "KC_MINS"),
+        (0x2E, "KC_EQL"),
+        (0x2F, "KC_LBRC"),
+        (0x30, "KC_RBRC"),
+        (0x31, "KC_BSLS"),
+        (0x32, "KC_NUHS"),
+        (0x33, "KC_SCLN"),
+        (0x34, "KC_QUOT"),
+        (0x35, "KC_GRV"),
+        (0x36, "KC_COMM"),
+        (0x37, "KC_DOT"),
+        (0x38, "KC_SLSH"),
+        (0x39, "KC_CAPS"),
+        (0x46, "KC_PSCR"),
+        (0x47, "KC_SCRL"),
+        (0x48, "KC_PAUS"),
+        (0x49, "KC_INS"),
+        (0x4A, "KC_HOME"),
+        (0x4B, "KC_PGUP"),
+        (0x4C, "KC_DEL"),
+        (0x4D, "KC_END"),
+        (0x4E, "KC_PGDN"),
+        (0x4F, "KC_RGHT"),
+        (0x50, "KC_LEFT"),
+        (0x51, "KC_DOWN"),
+        (0x52, "KC_UP"),
+        (0x53, "KC_NUM"),
+        (0x54, "KC_PSLS"),
+        (0x55, "KC_PAST"),
+        (0x56, "KC_PMNS"),
+        (0x57, "KC_PPLS"),
+        (0x58, "KC_PENT"),
+        (0x63, "KC_PDOT"),
+        (0x64, "KC_NUBS"),
+        (0x65, "KC_APP"),
+        (0x67, "KC_PEQL"),
+        (0xA5, "KC_PWR"),
+        (0xA6, "KC_SLEP"),
+        (0xA7, "KC_WAKE"),
+        (0xA8, "KC_MUTE"),
+        (0xA9, "KC_VOLU"),
+        (0xAA, "KC_VOLD"),
+        (0xAB, "KC_MNXT"),
+        (0xAC, "KC_MPRV"),
+        (0xAD, "KC_MSTP"),
+        (0xAE, "KC_MPLY"),
+        (0xBD, "KC_BRIU"),
+        (0xBE, "KC_BRID"),
+        (0xE0, "KC_LCTL"),
+        (0xE1, "KC_LSFT"),
+        (0xE2, "KC_LALT"),
+        (0xE3, "KC_LGUI"),
+        (0xE4, "KC_RCTL"),
+        (0xE5, "KC_RSFT"),
+        (0xE6, "KC_RALT"),
+        (0xE7, "KC_RGUI"),
+    ];
+
+    /// Token for `code`, or `0xNNNN` when the keycode is not known
+    pub fn token(code: u16) -> String {
+        Self::decode(code).unwrap_or_else(|| format!("0x{:04X}", code))
+    }
+
+    fn decode(code: u16) -> Option<String> {
+        let low = code & 0xFF;
+        let mods = (code >> 8) as u8 & 0x1F;
+        // LM and OSM keep their mods in the low bits, TO/MO/... their layer
+        let low_mods = code as u8 & 0x1F;
+        let layer = code & 0x1F;
+        let token = match code {
+            0x0000..=0x00FF => return Self::basic(code),
+            // LCTL(kc), LCTL(LSFT(kc)), ...
+            0x0100..=0x1FFF => Self::mod_names(mods)
+                .iter()
+                .rev()
+                .fold(Self::basic(low)?, |acc, m| format!("{}({})", m, acc)),
+            0x2000..=0x3FFF => match Self::mod_names(mods).as_slice() {
+                [single] => format!("{}_T({})", single, Self::basic(low)?),
+                _ => format!("MT({}, {})", Self::mod_mask(mods), Self::basic(low)?),
+            },
+            0x4000..=0x4FFF => format!("LT({}, {})", (code >> 8) & 0x0F, Self::basic(low)?),
+            0x5000..=0x51FF => format!("LM({}, {})", (code >> 5) & 0x0F, Self::mod_mask(low_mods)),
+            0x5200..=0x521F => format!("TO({})", layer),
+            0x5220..=0x523F => format!("MO({})", layer),
+            0x5240..=0x525F => format!("DF({})", layer),
+            0x5260..=0x527F => format!("TG({})", layer),
+            0x5280..=0x529F => format!("OSL({})", layer),
+            0x52A0..=0x52BF => format!("OSM({})", Self::mod_mask(low_mods)),
+            0x52C0..=0x52DF => format!("TT({})", layer),
+            0x5700..=0x57FF => format!("TD({})", low),
+            0x7700..=0x777F => format!("QK_MACRO_{}", code & 0x7F),
+            0x7C00 => "QK_BOOT".to_string(),
+            0x7C01 => "QK_REBOOT".to_string(),
+            0x7C02 => "DB_TOGG".to_string(),
+            0x7C03 => "EE_CLR".to_string(),
+            0x7E00..=0x7E3F => format!("QK_KB_{}", code & 0x3F),
+            0x7E40..=0x7FFF => format!("QK_USER_{}", code - 0x7E40),
+            _ => return Self::generated(code),
+        };
+        Some(token)
+    }
+
+    fn basic(code: u16) -> Option<String> {
+        let offset = |start: u16| (code - start) as u8;
+        let token = match code {
+            0x04..=0x1D => format!("KC_{}", (b'A' + offset(0x04)) as char),
+            0x1E..=0x26 => format!("KC_{}", offset(0x1E) + 1),
+            0x27 => "KC_0".to_string(),
+            0x3A..=0x45 => format!("KC_F{}", offset(0x3A) + 1),
+            0x59..=0x61 => format!("KC_P{}", offset(0x59) + 1),
+            0x62 => "KC_P0".to_string(),
+            0x68..=0x73 => format!("KC_F{}", offset(0x68) + 13),
+            _ => match Self::BASIC.binary_search_by_key(&code, |&(c, _)| c) {
+                Ok(idx) => Self::BASIC[idx].1.to_string(),
+                Err(_) => return Self::generated(code),
+            },
+        };
+        Some(token)
+    }
+
+    /// Fall back to the keycode table generated from a QMK checkout
+    fn generated(code: u16) -> Option<String> {
+        KEYCODE_TABLE
+            .iter()
+            .find(|&&(_, value)| value == code)
+            .map(|(name, _)| name.to_string())
+    }
+
+    /// Modifier names of a 5-bit mod field; bit 4 selects the right-hand modifiers
+    fn mod_names(mods: u8) -> Vec<String> {
+        let side = if mods & 0x10 != 0 { 'R' } else { 'L' };
+        [(0x01, "CTL"), (0x02, "SFT"), (0x04, "ALT"), (0x08, "GUI")]
+            .iter()
+            .filter(|&&(bit, _)| mods & bit != 0)
+            .map(|(_, name)| format!("{}{}", side, name))
+            .collect()
+    }
+
+    /// `MOD_LCTL | MOD_LSFT` style mask of a 5-bit mod field
+    fn mod_mask(mods: u8) -> String {
+        let names: Vec<String> = Self::mod_names(mods)
+            .iter()
+            .map(|m| format!("MOD_{}", m))
+            .collect();
+        if names.is_empty() {
+            "0".to_string()
+        } else {
+            names.join(" | ")
+        }
+    }
+}
+
 /// Get display name for layer token
 pub fn layer_display_name(token: &str) -> String {
     let t = token.trim();
@@ -415,6 +1377,56 @@ pub fn layer_display_name(token: &str) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_via_keycode_table() {
+        for (code, token) in [
+            (0x0000, "KC_NO"),
+            (0x0001, "KC_TRNS"),
+            (0x0004, "KC_A"),
+            (0x001D, "KC_Z"),
+            (0x001E, "KC_1"),
+            (0x0027, "KC_0"),
+            (0x0045, "KC_F12"),
+            (0x0073, "KC_F24"),
+            (0x00E5, "KC_RSFT"),
+            (0x0204, "LSFT(KC_A)"),
+            (0x0304, "LCTL(LSFT(KC_A))"),
+            (0x1404, "RALT(KC_A)"),
+            (0x2129, "LCTL_T(KC_ESC)"),
+            (0x2B2C, "MT(MOD_LCTL | MOD_LSFT | MOD_LGUI, KC_SPC)"),
+            (0x422C, "LT(2, KC_SPC)"),
+            (0x5022, "LM(1, MOD_LSFT)"),
+            (0x5203, "TO(3)"),
+            (0x5221, "MO(1)"),
+            (0x5262, "TG(2)"),
+            (0x5281, "OSL(1)"),
+            (0x52A2, "OSM(MOD_LSFT)"),
+            (0x5705, "TD(5)"),
+            (0x7C00, "QK_BOOT"),
+            (0x7E41, "QK_USER_1"),
+        ] {
+            assert_eq!(ViaKeycodeTable::token(code), token, "0x{:04X}", code);
+        }
+        assert_eq!(ViaKeycodeTable::token(0x7A00), "0x7A00");
+    }
+
+    #[test]
+    fn test_translate_token_checked() {
+        assert_eq!(
+            translate_token_checked("KC_A"),
+            TranslateResult::Known("a".to_string())
+        );
+        assert_eq!(
+            translate_token_checked("_______"),
+            TranslateResult::Known(String::new())
+        );
+        assert_eq!(
+            translate_token_checked("MY_MACRO"),
+            TranslateResult::Unknown("MY_MACRO".to_string())
+        );
+        assert_eq!(translate_token("MY_MACRO"), "MY_MACRO");
+    }
+
     #[test]
     fn test_basic_letter_keycodes() {
         assert_eq!(translate_token("KC_A"), "a");
@@ -452,6 +1464,127 @@ mod tests {
         assert_eq!(translate_token("KC_DEL"), "Del");
     }
 
+    #[test]
+    fn test_numpad_keycodes() {
+        let tokens = [
+            "KC_KP_0",
+            "KC_KP_1",
+            "KC_KP_2",
+            "KC_KP_3",
+            "KC_KP_4",
+            "KC_KP_5",
+            "KC_KP_6",
+            "KC_KP_7",
+            "KC_KP_8",
+            "KC_KP_9",
+            "KC_KP_DOT",
+            "KC_KP_COMMA",
+            "KC_KP_PLUS",
+            "KC_KP_MINUS",
+            "KC_KP_ASTERISK",
+            "KC_KP_SLASH",
+            "KC_KP_ENTER",
+            "KC_KP_EQUAL",
+            "KC_PSLS",
+            "KC_PENT",
+        ];
+        for token in tokens {
+            let label = translate_token(token);
+            assert!(label.contains("KP"), "{} -> {:?}", token, label);
+        }
+        assert_eq!(translate_token("KC_P0"), "KP:0");
+        assert_eq!(translate_token("KC_P 1"), "KP:1");
+        assert_eq!(translate_token("KC_KP_ 2"), "KP:2");
+        assert_ne!(translate_token("KC_KP_1"), translate_token("KC_1"));
+        // Other KC_P* keycodes are not keypad aliases
+        assert_eq!(translate_token("KC_PGUP"), "PgUp");
+        assert_eq!(translate_token("KC_NUM_LOCK"), "Num");
+    }
+
+    #[test]
+    fn test_custom_keycode_table() {
+        let table = CustomKeycodeTable(HashMap::from([
+            ("U_LAYER_1".to_string(), "L1".to_string()),
+            ("SNIP".to_string(), "✂".to_string()),
+        ]));
+        assert_eq!(table.translate("U_LAYER_1"), Some("L1"));
+        assert_eq!(table.translate(" SNIP "), Some("✂"));
+        assert_eq!(table.translate("MY_CUSTOM_KEY"), None);
+    }
+
+    #[test]
+    fn test_locking_keys() {
+        let tokens = [
+            "KC_LOCKING_CAPS",
+            "KC_LOCKING_NUM",
+            "KC_LOCKING_SCROLL",
+            "KC_SCROLL_LOCK",
+            "KC_PAUSE",
+            "KC_PRINT_SCREEN",
+        ];
+        let labels: HashSet<String> = tokens.iter().map(|t| translate_token(t)).collect();
+        assert_eq!(labels.len(), tokens.len(), "{:?}", labels);
+        assert!(labels.iter().all(|l| !l.is_empty()));
+        assert_eq!(
+            translate_token("KC_PRINT_SCREEN"),
+            translate_token("KC_PSCR")
+        );
+        assert_eq!(translate_token("KC_NUM_LOCK"), "Num");
+    }
+
+    #[test]
+    fn test_extra_keycodes() {
+        let expected = [
+            ("KC_MEDIA_PLAY_PAUSE", "Play"),
+            ("KC_MEDIA_STOP", "Stop"),
+            ("KC_MEDIA_NEXT_TRACK", "Next"),
+            ("KC_MEDIA_PREV_TRACK", "Prev"),
+            ("KC_MEDIA_FAST_FORWARD", "FFwd"),
+            ("KC_MEDIA_REWIND", "Rewind"),
+            ("KC_MEDIA_SELECT", "Media"),
+            ("KC_MEDIA_EJECT", "Eject"),
+            ("KC_AUDIO_MUTE", "Mute"),
+            ("KC_AUDIO_VOL_UP", "Vol+"),
+            ("KC_AUDIO_VOL_DOWN", "Vol-"),
+            ("KC_CALCULATOR", "Calc"),
+            ("KC_MAIL", "Mail"),
+            ("KC_MY_COMPUTER", "MyPC"),
+            ("KC_WWW_SEARCH", "Web:Srch"),
+            ("KC_WWW_HOME", "Web:Home"),
+            ("KC_WWW_BACK", "Web:Back"),
+            ("KC_WWW_FORWARD", "Web:Fwd"),
+            ("KC_WWW_STOP", "Web:Stop"),
+            ("KC_WWW_REFRESH", "Web:Rfsh"),
+            ("KC_WWW_FAVORITES", "Web:Fav"),
+            ("KC_SYSTEM_POWER", "Power"),
+            ("KC_SYSTEM_SLEEP", "Sleep"),
+            ("KC_SYSTEM_WAKE", "Wake"),
+            ("KC_BRIGHTNESS_UP", "Bri+"),
+            ("KC_BRIGHTNESS_DOWN", "Bri-"),
+        ];
+        for (token, label) in expected {
+            assert_eq!(translate_token(token), label, "{}", token);
+        }
+    }
+
+    #[test]
+    fn test_keycode_category() {
+        assert_eq!(keycode_category("KC_A"), KeyCategory::Letter);
+        assert_eq!(keycode_category("KC_7"), KeyCategory::Number);
+        assert_eq!(keycode_category("KC_LSFT"), KeyCategory::Modifier);
+        assert_eq!(keycode_category("OSM(MOD_LSFT)"), KeyCategory::Modifier);
+        assert_eq!(keycode_category("LSFT_T(KC_A)"), KeyCategory::ModTap);
+        assert_eq!(keycode_category("LT(1, KC_SPC)"), KeyCategory::LayerControl);
+        assert_eq!(keycode_category("KC_F11"), KeyCategory::Function);
+        assert_eq!(keycode_category("KC_PGDN"), KeyCategory::Navigation);
+        assert_eq!(keycode_category("KC_MEDIA_STOP"), KeyCategory::Media);
+        assert_eq!(keycode_category("KC_MS_BTN1"), KeyCategory::Mouse);
+        assert_eq!(keycode_category("QK_MOUSE_WHEEL_UP"), KeyCategory::Mouse);
+        assert_eq!(keycode_category("KC_COMM"), KeyCategory::Symbol);
+        assert_eq!(keycode_category("_______"), KeyCategory::Transparent);
+        assert_eq!(keycode_category("QK_BOOT"), KeyCategory::Other);
+    }
+
     #[test]
     fn test_french_accents() {
         assert_eq!(translate_token("KF_EGRV"), "è");
@@ -475,6 +1608,69 @@ mod tests {
         assert_eq!(mod_to_glyph("KC_LALT"), "Alt");
     }
 
+    #[test]
+    fn test_translate_uc() {
+        assert_eq!(translate_uc("UC(0x00E9)").as_deref(), Some("é"));
+        assert_eq!(translate_uc("UC(8364)").as_deref(), Some("€"));
+        assert_eq!(translate_uc("UC(0xFFFF)").as_deref(), Some("U+FFFF"));
+        assert_eq!(translate_uc("UC(0x110000)").as_deref(), Some("U+110000"));
+        assert_eq!(translate_uc("UC(0x0007)").as_deref(), Some("U+0007"));
+        assert_eq!(translate_uc("KC_A"), None);
+        assert_eq!(translate_token("UC(0x00E9)"), "é");
+
+        let table = [(0, "λ"), (1, "Λ")];
+        assert_eq!(translate_unicode_map("X(0)", &table).as_deref(), Some("λ"));
+        assert_eq!(
+            translate_unicode_map("XP(0, 1)", &table).as_deref(),
+            Some("λ")
+        );
+        assert_eq!(
+            translate_unicode_map("X(7)", &table).as_deref(),
+            Some("U:7")
+        );
+        assert_eq!(translate_token("X(3)"), "U:3");
+    }
+
+    #[test]
+    fn test_translate_algr_combo() {
+        assert_eq!(translate_algr_combo("KC_E", "de_DE"), "€");
+        assert_eq!(translate_algr_combo("KC_E", "fr"), "€");
+        assert_eq!(translate_algr_combo("KC_Q", "de"), "@");
+        assert_eq!(translate_algr_combo("KC_0", "fr_FR"), "@");
+        assert_eq!(translate_algr_combo("KC_E", "en_US"), "e");
+    }
+
+    #[test]
+    fn test_translate_token_locale() {
+        assert_eq!(translate_token_locale("KC_A", KeyboardLocale::AZERTY), "q");
+        assert_eq!(translate_token_locale("KC_Q", KeyboardLocale::QWERTZ), "q");
+        assert_eq!(translate_token_locale("KC_Z", KeyboardLocale::QWERTZ), "y");
+        assert_eq!(translate_token_locale("KC_S", KeyboardLocale::Dvorak), "o");
+        assert_eq!(translate_token_locale("KC_A", KeyboardLocale::US), "a");
+        // Non-letter keys keep their usual label
+        assert_eq!(
+            translate_token_locale("KC_ENT", KeyboardLocale::AZERTY),
+            translate_token("KC_ENT")
+        );
+        assert_eq!(
+            KeyboardLocale::from_name("azerty"),
+            Some(KeyboardLocale::AZERTY)
+        );
+        assert_eq!(KeyboardLocale::from_name("colemak"), None);
+    }
+
+    #[test]
+    fn test_sanitize_glyphs() {
+        let supported: HashSet<char> = ['⇧'].into_iter().collect();
+        assert_eq!(sanitize_glyphs("⇧", &supported), "⇧");
+        assert_eq!(sanitize_glyphs("★", &supported), "OSL");
+        assert_eq!(sanitize_glyphs("⌘+a", &HashSet::new()), "Cmd+a");
+        assert_eq!(sanitize_glyphs("é", &HashSet::new()), "é");
+        let arrows: HashSet<char> = ['↑'].into_iter().collect();
+        assert_eq!(sanitize_glyphs("🖱↑", &arrows), "M↑");
+        assert_eq!(sanitize_glyphs("🖱↑", &HashSet::new()), "MUp");
+    }
+
     #[test]
     fn test_layer_display_name() {
         assert_eq!(layer_display_name("DEF"), "Base");
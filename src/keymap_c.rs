@@ -1,22 +1,69 @@
 use crate::config::KeymapConfig;
+use crate::keycodes::CustomKeycodeTable;
+use serde::{Deserialize, Serialize};
+
+/// Keycodes bound to one rotary encoder on one layer (`ENCODER_MAP_ENABLE`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncoderAction {
+    pub layer: usize,
+    pub encoder: usize,
+    pub clockwise: String,
+    pub counter_clockwise: String,
+}
 
 pub fn parse_keymap_c(source: &str) -> anyhow::Result<KeymapConfig> {
+    // Names from `[0] /* QWERTY */` annotations, read before the comments are stripped
+    let comment_names: Vec<(String, String)> = source
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let index = line
+                .strip_prefix('[')?
+                .split(']')
+                .next()?
+                .trim()
+                .to_string();
+            Some((index, extract_layer_name_from_comment(line)?))
+        })
+        .collect();
     let source = strip_c_comments(source);
 
+    // Header files (keymap.h) have no keymaps array; they usually only carry the layer
+    // enum and #define aliases, so return the layer names with no layers
+    if !source.contains("keymaps") {
+        let names = extract_layer_enum(&source);
+        if !names.is_empty() {
+            return Ok(KeymapConfig {
+                keyboard: "planck".to_string(),
+                keymap: "keymap.h".to_string(),
+                layers: Vec::new(),
+                layout: None,
+                layer_names: Some(names),
+                encoder_map: Vec::new(),
+                custom_keycodes: CustomKeycodeTable::default(),
+            });
+        }
+    }
+
+    // Expand aliases such as `#define LOWER MO(_LOWER)` in the keys; layer names are still
+    // read from the unexpanded source so `[_LOWER]` keeps its name
+    let defines = extract_defines(&unconditional_lines(&source));
+    let expanded = resolve_defines(&source, &defines);
+
     // Try multiple parsing strategies for better compatibility
     let mut layers: Vec<Vec<String>> = Vec::new();
 
     // Strategy 1: Look for LAYOUT... ( ... ) blocks
-    layers.extend(extract_layout_blocks(&source));
+    layers.extend(extract_layout_blocks(&expanded));
 
     // Strategy 2: If no layouts found, look for keymap arrays
     if layers.is_empty() {
-        layers.extend(extract_keymap_arrays(&source));
+        layers.extend(extract_keymap_arrays(&expanded));
     }
 
     // Strategy 3: Look for const uint16_t PROGMEM keymaps[][]
     if layers.is_empty() {
-        layers.extend(extract_progmem_keymaps(&source));
+        layers.extend(extract_progmem_keymaps(&expanded));
     }
 
     if layers.is_empty() {
@@ -30,7 +77,16 @@ pub fn parse_keymap_c(source: &str) -> anyhow::Result<KeymapConfig> {
         let line = line.trim();
         if line.starts_with('[') {
             if let Some(end) = line.find(']') {
-                let name = line[1..end].to_string();
+                let mut name = line[1..end].to_string();
+                // A bare index is only a placeholder; prefer a name given in a comment
+                if name.trim().chars().all(|c| c.is_ascii_digit()) {
+                    if let Some((_, comment_name)) = comment_names
+                        .iter()
+                        .find(|(index, _)| *index == name.trim())
+                    {
+                        name = comment_name.clone();
+                    }
+                }
                 names.push(name);
                 idx += 1;
                 if idx >= layers.len() {
@@ -51,9 +107,302 @@ pub fn parse_keymap_c(source: &str) -> anyhow::Result<KeymapConfig> {
         layers,
         layout: Some("LAYOUT_ortho_4x12".to_string()),
         layer_names,
+        encoder_map: extract_encoder_map(&expanded),
+        custom_keycodes: CustomKeycodeTable::default(),
     })
 }
 
+/// Lines outside `#if` / `#ifdef` / `#ifndef` blocks, so defines that only apply to some
+/// builds are left out
+///
+/// An include guard (`#ifndef X_H` followed by `#define X_H`) applies to every build, so the
+/// header inside it is kept like one using `#pragma once`.
+fn unconditional_lines(source: &str) -> String {
+    // One entry per open block: whether it hides its lines
+    let mut open: Vec<bool> = Vec::new();
+    let mut out = String::new();
+    let mut lines = source.lines().peekable();
+    while let Some(line) = lines.next() {
+        let directive = line.trim_start().strip_prefix('#').map(str::trim_start);
+        match directive {
+            Some(d) if d.starts_with("if") => {
+                let guard = lines.peek().is_some_and(|next| is_include_guard(d, next));
+                open.push(!guard);
+            }
+            Some(d) if d.starts_with("endif") => {
+                open.pop();
+            }
+            _ if !open.contains(&true) => {
+                out.push_str(line);
+                out.push('\n');
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Whether the directive `d` (after its `#`) and the line after it form an include guard
+fn is_include_guard(d: &str, next: &str) -> bool {
+    let Some(name) = d.strip_prefix("ifndef").map(str::trim) else {
+        return false;
+    };
+    let defined = next
+        .trim()
+        .strip_prefix('#')
+        .and_then(|n| n.trim_start().strip_prefix("define"))
+        .map(str::trim);
+    !name.is_empty() && defined == Some(name)
+}
+
+/// First word of a `/* … */` or `// …` comment right after a line's leading `[n]` bracket
+fn extract_layer_name_from_comment(line: &str) -> Option<String> {
+    let line = line.trim_start();
+    let rest = line.strip_prefix('[')?;
+    let rest = rest[rest.find(']')? + 1..].trim_start();
+    let comment = rest
+        .strip_prefix("/*")
+        .or_else(|| rest.strip_prefix("//"))?
+        .trim_start_matches(['*', '/', ' ', '\t']);
+    let word: String = comment
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect();
+    (!word.is_empty()).then_some(word)
+}
+
+/// Parse a keymap split across several files (`config.h`, `keymap.h`, `keymap.c`, `rules.mk`)
+///
+/// Files are concatenated in dependency order (config.h, then other headers, then keymap.c),
+/// `#define` aliases are resolved across all of them and the combined source goes through
+/// [`parse_keymap_c`]. When `MATRIX_ROWS` and `MATRIX_COLS` are defined, each layer is padded
+/// with `KC_NO` up to the full matrix size.
+pub fn parse_keymap_c_multi<I>(files: I) -> anyhow::Result<KeymapConfig>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let mut files: Vec<(String, String)> = files
+        .into_iter()
+        // rules.mk only carries make feature flags, nothing the C parser understands
+        .filter(|(name, _)| name.ends_with(".c") || name.ends_with(".h"))
+        .collect();
+    if files.is_empty() {
+        anyhow::bail!("no keymap.c or header files given");
+    }
+    files.sort_by_key(|(name, _)| file_order(name));
+
+    let mut combined = String::new();
+    for (_, content) in &files {
+        combined.push_str(&strip_c_comments(content));
+        combined.push('\n');
+    }
+    let defines = extract_defines(&unconditional_lines(&combined));
+    let resolved = resolve_defines(&combined, &defines);
+    let mut config = parse_keymap_c(&resolved)?;
+
+    let matrix_dim = |name: &str| {
+        defines
+            .iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, v)| v.parse::<usize>().ok())
+    };
+    if let (Some(rows), Some(cols)) = (matrix_dim("MATRIX_ROWS"), matrix_dim("MATRIX_COLS")) {
+        let total = rows * cols;
+        for layer in config.layers.iter_mut() {
+            while layer.len() < total {
+                layer.push("KC_NO".to_string());
+            }
+        }
+    }
+    Ok(config)
+}
+
+/// Parse `encoder_map[][NUM_ENCODERS][NUM_DIRECTIONS] = { [layer] = { ENCODER_CCW_CW(ccw, cw), ... }, ... }`
+///
+/// Layers are numbered in the order their entries appear, like the keymap layers, and
+/// encoders in the order of their `ENCODER_CCW_CW` entries within a layer.
+pub fn extract_encoder_map(source: &str) -> Vec<EncoderAction> {
+    let source = strip_c_comments(source);
+    let Some(decl) = source.find("encoder_map[") else {
+        return Vec::new();
+    };
+    let after_decl = &source[decl..];
+    let Some(open) = after_decl
+        .find('=')
+        .and_then(|eq| after_decl[eq..].find('{').map(|o| eq + o))
+    else {
+        return Vec::new();
+    };
+    let body = &after_decl[open + 1..];
+    let body = &body[..closing_index(body, '{', '}').unwrap_or(body.len())];
+
+    let mut actions = Vec::new();
+    let mut rest = body;
+    let mut layer = 0usize;
+    while let Some(group_start) = rest.find('{') {
+        let group = &rest[group_start + 1..];
+        let group_end = closing_index(group, '{', '}').unwrap_or(group.len());
+        let mut entries = &group[..group_end];
+        let mut encoder = 0usize;
+        while let Some(pos) = entries.find("ENCODER_CCW_CW(") {
+            let args = &entries[pos + "ENCODER_CCW_CW(".len()..];
+            let args_end = closing_index(args, '(', ')').unwrap_or(args.len());
+            if let [ccw, cw] = split_items(&args[..args_end]).as_slice() {
+                actions.push(EncoderAction {
+                    layer,
+                    encoder,
+                    clockwise: cw.clone(),
+                    counter_clockwise: ccw.clone(),
+                });
+            }
+            encoder += 1;
+            entries = &args[args_end..];
+        }
+        layer += 1;
+        rest = &group[(group_end + 1).min(group.len())..];
+    }
+    actions
+}
+
+/// Index of the `close` that balances an `open` just before the start of `s`
+fn closing_index(s: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 0usize;
+    for (idx, ch) in s.char_indices() {
+        if ch == open {
+            depth += 1;
+        } else if ch == close {
+            if depth == 0 {
+                return Some(idx);
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+fn file_order(name: &str) -> u8 {
+    let file_name = std::path::Path::new(name)
+        .file_name()
+        .and_then(|f| f.to_str())
+        .unwrap_or(name);
+    match file_name {
+        "config.h" => 0,
+        _ if file_name.ends_with(".h") => 1,
+        _ => 2,
+    }
+}
+
+/// Expand object-like `#define` aliases and drop the preprocessor lines themselves
+fn resolve_defines(source: &str, defines: &[(String, String)]) -> String {
+    let ident = regex::Regex::new(r"\b[A-Za-z_]\w*\b").expect("valid regex");
+    let mut out = String::with_capacity(source.len());
+    let mut in_directive = false;
+    for line in source.lines() {
+        if in_directive || line.trim_start().starts_with('#') {
+            // Multi-line macros continue while lines end with a backslash
+            in_directive = line.trim_end().ends_with('\\');
+            continue;
+        } else {
+            let mut line = line.to_string();
+            // Follow alias chains a few levels deep (e.g. LOWER -> MO(_LOWER))
+            for _ in 0..4 {
+                let expanded = ident.replace_all(&line, |caps: &regex::Captures| {
+                    let name = &caps[0];
+                    defines
+                        .iter()
+                        .find(|(n, _)| n == name)
+                        .map(|(_, v)| v.clone())
+                        .unwrap_or_else(|| name.to_string())
+                });
+                if expanded == line {
+                    break;
+                }
+                line = expanded.into_owned();
+            }
+            out.push_str(&line);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Merge a companion header (keymap.h) into a config parsed from keymap.c
+///
+/// Object-like `#define` aliases from the header are expanded in the layer tokens, and
+/// placeholder layer names ("Layer N" or bare indices) are replaced by the header's layer enum.
+pub fn merge_header(config: &mut KeymapConfig, header: &str) {
+    let header = strip_c_comments(header);
+    let defines = extract_defines(&header);
+    for layer in config.layers.iter_mut() {
+        for tok in layer.iter_mut() {
+            // Follow alias chains a few levels deep (e.g. LOWER -> MO(_LOWER))
+            for _ in 0..4 {
+                match defines.iter().find(|(name, _)| name == tok) {
+                    Some((_, value)) => *tok = value.clone(),
+                    None => break,
+                }
+            }
+        }
+    }
+
+    let enum_names = extract_layer_enum(&header);
+    if enum_names.is_empty() {
+        return;
+    }
+    let names = config.layer_names.get_or_insert_with(Vec::new);
+    for (i, enum_name) in enum_names.iter().enumerate() {
+        match names.get_mut(i) {
+            Some(name) if *name == format!("Layer {}", i) || name.parse::<usize>().is_ok() => {
+                *name = enum_name.clone();
+            }
+            Some(_) => {}
+            None if config.layers.is_empty() || i < config.layers.len() => {
+                names.push(enum_name.clone())
+            }
+            None => break,
+        }
+    }
+}
+
+/// Extract layer names from an `enum layers { ... }` style declaration
+fn extract_layer_enum(source: &str) -> Vec<String> {
+    let re = regex::Regex::new(r"enum\s+(\w*)\s*\{([^}]*)\}").expect("valid regex");
+    for cap in re.captures_iter(source) {
+        if !cap[1].to_lowercase().contains("layer") {
+            continue;
+        }
+        return cap[2]
+            .split(',')
+            .map(|item| item.split('=').next().unwrap_or("").trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect();
+    }
+    Vec::new()
+}
+
+/// Extract object-like `#define NAME VALUE` aliases (function-like macros are skipped)
+fn extract_defines(source: &str) -> Vec<(String, String)> {
+    let mut defines = Vec::new();
+    for line in source.lines() {
+        let Some(rest) = line.trim().strip_prefix("#define") else {
+            continue;
+        };
+        let rest = rest.trim_start();
+        let name_len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let (name, value) = rest.split_at(name_len);
+        if name.is_empty() || value.starts_with('(') {
+            continue;
+        }
+        let value = value.trim();
+        if !value.is_empty() {
+            defines.push((name.to_string(), value.to_string()));
+        }
+    }
+    defines
+}
+
 fn strip_c_comments(s: &str) -> String {
     let mut out = String::with_capacity(s.len());
     let bytes = s.as_bytes();
@@ -72,31 +421,23 @@ fn strip_c_comments(s: &str) -> String {
                 i += 1;
             }
             i += 2.min(bytes.len().saturating_sub(i));
-        } else if bytes[i] == b'"' {
-            // String literal - preserve content and handle escaped quotes
-            out.push(bytes[i] as char);
+        } else if bytes[i] == b'"' || bytes[i] == b'\'' {
+            // String or character literal - preserve content, comment markers included
+            let quote = bytes[i];
+            out.push(quote as char);
             i += 1;
             while i < bytes.len() {
-                if bytes[i] == b'"' && (i == 0 || bytes[i - 1] != b'\\') {
-                    out.push(bytes[i] as char);
-                    i += 1;
-                    break;
-                }
-                out.push(bytes[i] as char);
+                let b = bytes[i];
+                out.push(b as char);
                 i += 1;
-            }
-        } else if bytes[i] == b'\'' {
-            // Character literal - preserve content
-            out.push(bytes[i] as char);
-            i += 1;
-            while i < bytes.len() {
-                if bytes[i] == b'\'' && (i == 0 || bytes[i - 1] != b'\\') {
+                if b == b'\\' && i < bytes.len() {
+                    // Escape sequence: the next byte never ends the literal, even if it is
+                    // a quote or the backslash of `\\`
                     out.push(bytes[i] as char);
                     i += 1;
+                } else if b == quote {
                     break;
                 }
-                out.push(bytes[i] as char);
-                i += 1;
             }
         } else {
             out.push(bytes[i] as char);
@@ -107,8 +448,9 @@ fn strip_c_comments(s: &str) -> String {
     out
 }
 
-fn split_items(inner: &str) -> Vec<String> {
-    // Split by commas not inside parentheses (handles MT(...), MO(...), LT(...))
+pub(crate) fn split_items(inner: &str) -> Vec<String> {
+    // Split by commas not inside parentheses, braces or brackets (handles MT(...), LT(...)
+    // and aggregate initializers such as {1, 2})
     let mut items = Vec::new();
     let mut depth = 0i32;
     let mut start = 0usize;
@@ -132,10 +474,10 @@ fn split_items(inner: &str) -> Vec<String> {
             '\'' if !in_string => {
                 in_char = !in_char;
             }
-            '(' if !in_string && !in_char => {
+            '(' | '{' | '[' if !in_string && !in_char => {
                 depth += 1;
             }
-            ')' if !in_string && !in_char => {
+            ')' | '}' | ']' if !in_string && !in_char => {
                 depth -= 1;
             }
             ',' if depth == 0 && !in_string && !in_char => {
@@ -203,9 +545,8 @@ fn extract_layout_blocks(source: &str) -> Vec<Vec<String>> {
                 .filter(|s| !s.is_empty())
                 .collect::<Vec<_>>();
 
-            if !items.is_empty() {
-                layers.push(items);
-            }
+            // An empty LAYOUT() is still a layer, so the layers after it keep their index
+            layers.push(items);
             i = end + 1;
             continue;
         }
@@ -264,67 +605,41 @@ fn extract_keymap_arrays(source: &str) -> Vec<Vec<String>> {
 }
 
 fn extract_progmem_keymaps(source: &str) -> Vec<Vec<String>> {
-    let mut layers: Vec<Vec<String>> = Vec::new();
-
-    // Look for const uint16_t PROGMEM keymaps[][] patterns
-    let lines: Vec<&str> = source.lines().collect();
-    let mut i = 0;
-
-    while i < lines.len() {
-        let line = lines[i].trim();
-        if line.contains("PROGMEM") && line.contains("keymaps") {
-            // Found keymaps declaration, look for the array content
-            i += 1;
-            while i < lines.len() {
-                let line = lines[i].trim();
-                if line.starts_with('{') || line.contains("LAYOUT") {
-                    // Extract layout from this line
-                    if let Some(layout_start) = line.find("LAYOUT") {
-                        let layout_part = &line[layout_start..];
-                        if let Some(paren_start) = layout_part.find('(') {
-                            let mut depth = 0;
-                            let mut end = paren_start;
-                            let chars: Vec<char> = layout_part.chars().collect();
-
-                            for (j, &ch) in chars.iter().enumerate().skip(paren_start) {
-                                match ch {
-                                    '(' => depth += 1,
-                                    ')' => {
-                                        if depth == 0 {
-                                            end = j;
-                                            break;
-                                        }
-                                        depth -= 1;
-                                    }
-                                    _ => {}
-                                }
-                            }
-
-                            if end > paren_start {
-                                let inner = &layout_part[paren_start + 1..end];
-                                let items = split_items(inner)
-                                    .into_iter()
-                                    .map(|s| s.trim().to_string())
-                                    .filter(|s| !s.is_empty())
-                                    .collect::<Vec<_>>();
-
-                                if !items.is_empty() {
-                                    layers.push(items);
-                                }
-                            }
-                        }
-                    }
-                } else if line.contains('}') && !line.contains("LAYOUT") {
-                    // End of keymaps array
+    // Look for const uint16_t PROGMEM keymaps[][...] = { ... } on the whole source, so
+    // LAYOUT calls spanning many lines are captured with balanced parentheses
+    let Some(decl) = source.find("keymaps") else {
+        return Vec::new();
+    };
+    let after_decl = &source[decl..];
+    let Some(eq) = after_decl.find('=') else {
+        return Vec::new();
+    };
+    let Some(open) = after_decl[eq..].find('{').map(|o| eq + o) else {
+        return Vec::new();
+    };
+
+    // Balanced brace capture of the array body
+    let body_start = open + 1;
+    let mut depth = 0usize;
+    let mut body_end = None;
+    for (idx, ch) in after_decl[body_start..].char_indices() {
+        match ch {
+            '{' => depth += 1,
+            '}' => {
+                if depth == 0 {
+                    body_end = Some(body_start + idx);
                     break;
                 }
-                i += 1;
+                depth -= 1;
             }
+            _ => {}
         }
-        i += 1;
     }
+    let body = &after_decl[body_start..body_end.unwrap_or(after_decl.len())];
 
-    layers
+    // Entries look like `[0] = LAYOUT(...)` or `[_BASE] = LAYOUT_ortho_4x12(...)`; the
+    // designators sit outside the parentheses, so only the LAYOUT blocks are extracted
+    extract_layout_blocks(body)
 }
 
 fn _normalize_token(tok: &str) -> String {
@@ -361,3 +676,83 @@ fn strip_func(s: &str, name: &str) -> Option<String> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_progmem_keymaps_multiline() {
+        let source = "const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {\n\
+            [0] = LAYOUT(\n    KC_A,\n    MT(MOD_LSFT, KC_B),\n    KC_C,\n    KC_D,\n    KC_E\n),\n\
+            [1] = LAYOUT(\n    KC_1,\n    KC_2,\n    KC_3,\n    KC_4,\n    KC_5\n)\n\
+            };\nvoid other(void) { LAYOUT(KC_X); }\n";
+        let layers = extract_progmem_keymaps(source);
+        assert_eq!(layers.len(), 2);
+        assert_eq!(
+            layers[0],
+            ["KC_A", "MT(MOD_LSFT, KC_B)", "KC_C", "KC_D", "KC_E"]
+        );
+        assert_eq!(layers[1].len(), 5);
+    }
+
+    #[test]
+    fn test_extract_layer_name_from_comment() {
+        assert_eq!(
+            extract_layer_name_from_comment("[0] /* QWERTY */ = LAYOUT("),
+            Some("QWERTY".to_string())
+        );
+        assert_eq!(
+            extract_layer_name_from_comment("  [1] // Lower layer"),
+            Some("Lower".to_string())
+        );
+        assert_eq!(
+            extract_layer_name_from_comment("[2] = LAYOUT( /* RAISE */"),
+            None
+        );
+        assert_eq!(extract_layer_name_from_comment("[3] /* */ = LAYOUT("), None);
+        assert_eq!(extract_layer_name_from_comment("KC_A, /* QWERTY */"), None);
+    }
+
+    #[test]
+    fn test_strip_c_comments_string_literals() {
+        // `*/` inside a string after a block comment is part of the string
+        assert_eq!(
+            strip_c_comments(r#"/* c */ s = "abc*/def";"#),
+            r#" s = "abc*/def";"#
+        );
+        // An escaped backslash ends the string, so the comment after it is still removed
+        assert_eq!(
+            strip_c_comments(r#"x = "dir\\"; /* note */ y = "abc*/def";"#),
+            r#"x = "dir\\";  y = "abc*/def";"#
+        );
+        assert_eq!(
+            strip_c_comments(r#"c = '\\'; /* a */ s = "*/";"#),
+            r#"c = '\\';  s = "*/";"#
+        );
+        assert_eq!(
+            strip_c_comments(r#"s = "quote \" /* kept */"; // gone"#),
+            r#"s = "quote \" /* kept */"; "#
+        );
+    }
+
+    #[test]
+    fn test_strip_c_comments_nested_markers() {
+        // `//` inside a block comment does not hide the end of the comment
+        assert_eq!(strip_c_comments("/* a // b */ KC_A"), " KC_A");
+        // `/*` inside a line comment does not open a block comment
+        assert_eq!(
+            strip_c_comments("// a /* b\nKC_A, */ KC_B"),
+            "\nKC_A, */ KC_B"
+        );
+    }
+
+    #[test]
+    fn test_split_items_nested_braces() {
+        assert_eq!(split_items("foo({1, 2}, bar)"), ["foo({1, 2}, bar)"]);
+        assert_eq!(
+            split_items("KC_A, {1, {2, 3}}, arr[1, 2], MT(MOD_LSFT, KC_B)"),
+            ["KC_A", "{1, {2, 3}}", "arr[1, 2]", "MT(MOD_LSFT, KC_B)"]
+        );
+    }
+}
@@ -1,8 +1,14 @@
 pub mod config;
 pub mod config_persistence;
+pub mod export;
 pub mod hid;
+#[cfg(feature = "ipc")]
+pub mod ipc;
 pub mod keyboard;
 pub mod keyboards;
+#[cfg(test)]
+mod keycode_gen;
 pub mod keycodes;
 pub mod keymap_c;
 pub mod ui;
+pub mod watcher;
@@ -4,7 +4,7 @@ use qmk_viewer::config_persistence::get_saved_keymap_path;
 use qmk_viewer::hid::QmkConsoleSource;
 #[cfg(feature = "rawhid")]
 use qmk_viewer::hid::RawHidSource;
-use qmk_viewer::hid::{HidSource, Report};
+use qmk_viewer::hid::{ConnectionStatus, HidSource, Report};
 use qmk_viewer::keyboard::KeyboardState;
 use qmk_viewer::keyboards::planck::PlanckLayout;
 use qmk_viewer::ui::KeyboardViewerApp;
@@ -15,28 +15,232 @@ use std::sync::mpsc;
 use std::thread;
 use std::time::Duration;
 
+const USAGE: &str = "\
+Usage: qmk_viewer [OPTIONS] [KEYMAP] [PORT]
+
+Arguments:
+  [KEYMAP]  Keymap file to load (.json, keymap.c or keymap.h)
+  [PORT]    Serial port of the QMK console (qmk_console builds only)
+
+Options:
+      --validate <FILE>  Parse a keymap, print a summary and exit (1 if it doesn't parse)
+      --json             Print the --validate summary as JSON
+      --list-devices     Print the connected HID devices and serial ports and exit
+  -h, --help             Print this help and exit
+  -V, --version          Print version and build information and exit";
+
+fn print_version() {
+    println!("qmk_viewer {}", env!("CARGO_PKG_VERSION"));
+    println!(
+        "commit: {}",
+        option_env!("VERGEN_GIT_SHA").unwrap_or("unknown")
+    );
+    println!("rustc: {}", env!("RUSTC_VERSION"));
+}
+
+/// `--validate`: parse `path` without starting the UI; returns the exit code
+fn validate_keymap(path: &str, json: bool) -> i32 {
+    let summary = match KeymapConfig::load_from_path(path) {
+        Ok(cfg) => cfg.validation_summary(),
+        Err(e) => {
+            eprintln!("❌ Failed to parse {}: {:#}", path, e);
+            if json {
+                let report = serde_json::json!({
+                    "path": path,
+                    "ok": false,
+                    "error": format!("{:#}", e),
+                });
+                println!("{}", report);
+            }
+            return 1;
+        }
+    };
+    if json {
+        let report = serde_json::json!({ "path": path, "ok": true, "summary": summary });
+        println!("{}", report);
+        return 0;
+    }
+    println!(
+        "✅ {}: keyboard {}, {} layers",
+        path, summary.keyboard, summary.layer_count
+    );
+    for (layer, keys) in summary.keys_per_layer.iter().enumerate() {
+        println!("  Layer {}: {} keys", layer, keys);
+    }
+    if summary.unknown_tokens.is_empty() {
+        println!("  No unknown tokens");
+    } else {
+        println!(
+            "⚠️ {} unknown tokens: {}",
+            summary.unknown_tokens.len(),
+            summary.unknown_tokens.join(", ")
+        );
+    }
+    0
+}
+
+/// HID devices as listed by hidapi, with the usage page that tells raw HID interfaces apart
+#[cfg(feature = "rawhid")]
+fn print_hid_devices() {
+    println!("HID devices:");
+    let api = match hidapi::HidApi::new() {
+        Ok(api) => api,
+        Err(e) => {
+            eprintln!("❌ Failed to list HID devices: {}", e);
+            return;
+        }
+    };
+    println!(
+        "  {:<6} {:<6} {:<10} {:<6} Product",
+        "VID", "PID", "UsagePage", "Usage"
+    );
+    for device in api.device_list() {
+        println!(
+            "  {:04x}   {:04x}   0x{:04x}     0x{:04x} {}",
+            device.vendor_id(),
+            device.product_id(),
+            device.usage_page(),
+            device.usage(),
+            device.product_string().unwrap_or("")
+        );
+    }
+}
+
+/// Serial ports, with the USB IDs of USB ports such as the QMK console
+#[cfg(feature = "qmk_console")]
+fn print_serial_ports() {
+    use serialport::SerialPortType;
+
+    println!("Serial ports:");
+    let ports = match serialport::available_ports() {
+        Ok(ports) => ports,
+        Err(e) => {
+            eprintln!("❌ Failed to list serial ports: {}", e);
+            return;
+        }
+    };
+    println!("  {:<24} {:<10} VID:PID", "Name", "Type");
+    for port in ports {
+        let (kind, ids) = match &port.port_type {
+            SerialPortType::UsbPort(usb) => ("USB", format!("{:04x}:{:04x}", usb.vid, usb.pid)),
+            SerialPortType::PciPort => ("PCI", String::new()),
+            SerialPortType::BluetoothPort => ("Bluetooth", String::new()),
+            SerialPortType::Unknown => ("Unknown", String::new()),
+        };
+        println!("  {:<24} {:<10} {}", port.port_name, kind, ids);
+    }
+}
+
+/// `--list-devices`: one section per compiled-in backend, a failing backend doesn't hide
+/// the other one
+fn list_devices() {
+    #[cfg(not(any(feature = "rawhid", feature = "qmk_console")))]
+    println!("No HID/serial features compiled in; rebuild with --features rawhid");
+
+    #[cfg(feature = "rawhid")]
+    print_hid_devices();
+    #[cfg(all(feature = "rawhid", not(feature = "qmk_console")))]
+    println!("\nSerial ports: not compiled in; rebuild with --features qmk_console");
+
+    #[cfg(all(feature = "qmk_console", not(feature = "rawhid")))]
+    println!("HID devices: not compiled in; rebuild with --features rawhid\n");
+    #[cfg(all(feature = "qmk_console", feature = "rawhid"))]
+    println!();
+    #[cfg(feature = "qmk_console")]
+    print_serial_ports();
+}
+
+/// Matrix positions from the `via_info_json` setting, `None` when it is unset or unreadable
+#[cfg(all(feature = "via", not(feature = "qmk_console")))]
+fn via_matrix() -> Option<Vec<(u8, u8)>> {
+    let path = qmk_viewer::config_persistence::load_app_config()
+        .ok()?
+        .via_info_json?;
+    let matrix = std::fs::read_to_string(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| qmk_viewer::keyboard::KeyboardLayout::info_json_matrix(&json, None));
+    match matrix {
+        Ok(matrix) => Some(matrix),
+        Err(e) => {
+            eprintln!(
+                "⚠️ VIA keymap download disabled, cannot read {}: {}",
+                path, e
+            );
+            None
+        }
+    }
+}
+
 fn main() {
     env_logger::init();
 
     let args: Vec<String> = std::env::args().collect();
+    if args.iter().skip(1).any(|a| a == "--version" || a == "-V") {
+        print_version();
+        return;
+    }
+    if args.iter().skip(1).any(|a| a == "--help" || a == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+    if let Some(idx) = args.iter().position(|a| a == "--validate") {
+        let json = args.iter().any(|a| a == "--json");
+        let Some(path) = args.get(idx + 1).filter(|a| !a.starts_with("--")) else {
+            eprintln!("❌ --validate needs a keymap file\n\n{}", USAGE);
+            std::process::exit(1);
+        };
+        std::process::exit(validate_keymap(path, json));
+    }
+    if args.iter().skip(1).any(|a| a == "--list-devices") {
+        list_devices();
+        return;
+    }
     let maybe_json = args.get(1).cloned();
-    let _maybe_port = args.get(2).cloned(); // optional: explicit serial port
+    #[cfg(feature = "qmk_console")]
+    let maybe_port = args.get(2).cloned(); // optional: explicit serial port
+    #[cfg(all(feature = "via", not(feature = "qmk_console")))]
+    let (keymap_tx, keymap_rx) = mpsc::channel::<KeymapConfig>();
 
     let (tx, rx) = mpsc::channel::<Report>();
+    let (status_tx, status_rx) = mpsc::channel::<ConnectionStatus>();
 
     // Spawn reader thread (mock by default; real when feature enabled)
     thread::spawn(move || {
         #[cfg(feature = "qmk_console")]
         let mut source: Box<dyn HidSource + Send> = {
-            let src = QmkConsoleSource::new_with_port(maybe_port);
+            let src = QmkConsoleSource::new_with_port(maybe_port).with_status_sender(status_tx);
             Box::new(src)
         };
 
         #[cfg(all(not(feature = "qmk_console"), feature = "rawhid"))]
-        let mut source: Box<dyn HidSource + Send> = Box::new(RawHidSource::new());
+        let mut source: Box<dyn HidSource + Send> = {
+            let format = qmk_viewer::config_persistence::load_app_config()
+                .map(|c| c.rawhid_format)
+                .unwrap_or_default();
+            let src = RawHidSource::new()
+                .with_status_sender(status_tx)
+                .with_format(format);
+            #[cfg(feature = "via")]
+            let src = match via_matrix() {
+                Some(matrix) => src.with_via_download(matrix, keymap_tx),
+                None => src,
+            };
+            Box::new(src)
+        };
 
         #[cfg(all(not(feature = "qmk_console"), not(feature = "rawhid")))]
-        let mut source: Box<dyn HidSource + Send> = Box::new(qmk_viewer::hid::MockHidSource::new());
+        let mut source: Box<dyn HidSource + Send> = {
+            let _ = status_tx.send(ConnectionStatus::Connected {
+                device_name: "Mock keyboard".to_string(),
+                vid: 0,
+                pid: 0,
+            });
+            let mock_config = qmk_viewer::config_persistence::load_app_config()
+                .ok()
+                .and_then(|c| c.mock_config)
+                .unwrap_or_default();
+            Box::new(qmk_viewer::hid::MockHidSource::new_with_config(mock_config))
+        };
 
         loop {
             if let Some(report) = source.poll() {
@@ -48,19 +252,27 @@ fn main() {
 
     let mut keyboard = PlanckLayout::planck_default();
     let mut keyboard_loaded = false;
+    let mut loaded_source: Option<(String, String)> = None;
+    let mut loaded_config = None;
 
     // Try to load from command line argument first
     if let Some(path) = maybe_json {
         if let Ok(cfg) = KeymapConfig::load_from_path(&path) {
             keyboard = cfg.to_keyboard_layout();
             keyboard_loaded = true;
+            loaded_source = Some((cfg.keyboard.clone(), path));
+            loaded_config = Some(cfg);
         }
     } else {
         // Try to load from saved keymap
-        if let Ok(Some(saved_path)) = get_saved_keymap_path() {
-            if let Ok(cfg) = KeymapConfig::load_from_path(&saved_path) {
+        if let Ok(Some(saved)) = get_saved_keymap_path() {
+            if let Ok(cfg) = KeymapConfig::load_from_path(&saved.cached_path) {
                 keyboard = cfg.to_keyboard_layout();
                 keyboard_loaded = true;
+                // Show where the keymap came from rather than the cached copy
+                let source = saved.source_path.unwrap_or(saved.cached_path);
+                loaded_source = Some((cfg.keyboard.clone(), source));
+                loaded_config = Some(cfg);
             }
         }
     }
@@ -90,6 +302,24 @@ fn main() {
         Box::new(move |cc| {
             let mut app = KeyboardViewerApp::new(cc, layout_state.clone(), rx);
             app.set_keyboard_loaded(keyboard_loaded);
+            if let Some(cfg) = loaded_config.clone() {
+                app.set_keymap_config(cfg);
+            }
+            app.set_connection_receiver(status_rx);
+            if let Some((keyboard_name, path)) = loaded_source.clone() {
+                app.watch_keymap_file(&path);
+                app.set_source(keyboard_name, path);
+            }
+            #[cfg(all(feature = "via", not(feature = "qmk_console")))]
+            app.set_via_keymap_receiver(keymap_rx);
+            #[cfg(feature = "ipc")]
+            {
+                let (ipc_tx, ipc_rx) = mpsc::channel();
+                match qmk_viewer::ipc::spawn_listener(ipc_tx) {
+                    Ok(()) => app.set_ipc_receiver(ipc_rx),
+                    Err(e) => eprintln!("⚠️ IPC disabled: {}", e),
+                }
+            }
             Ok(Box::new(app))
         }),
     );
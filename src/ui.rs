@@ -1,7 +1,19 @@
-use crate::config::KeymapConfig;
-use crate::config_persistence::{clear_saved_keymap, save_keymap_file};
-use crate::hid::Report;
-use crate::keyboard::KeyboardState;
+use crate::config::{detect_format_and_parse, KeymapConfig, CUSTOM_KEYCODES_FILE};
+use crate::config_persistence::{
+    clear_saved_keymap, delete_profile, list_profiles, load_app_config, load_profile,
+    save_app_config, save_keymap_file, save_keymap_json, save_profile, AppConfig, KeymapProfile,
+};
+use crate::export::csv::export_csv;
+use crate::export::html::{export_html, export_report};
+use crate::export::png::export_png;
+use crate::export::svg::export_svg;
+use crate::export::Theme;
+use crate::hid::{ConnectionStatus, Report};
+use crate::keyboard::{KeyPosition, KeyboardLayout, KeyboardState};
+use crate::keyboards::registry::{detect_by_key_count, KnownKeyboard, KNOWN_KEYBOARDS};
+use crate::keycodes::{translate_token, CustomKeycodeTable, KeyboardLocale, PROBED_GLYPHS};
+use crate::keymap_c::EncoderAction;
+use crate::watcher::FileWatcher;
 use eframe::egui::{self, Color32, Context, RichText, Sense, Vec2};
 
 // Catppuccin Mocha palette (subset)
@@ -11,25 +23,795 @@ impl Palette {
     const PEACH: Color32 = Color32::from_rgb(0xfa, 0xb3, 0x87);
     const YELLOW: Color32 = Color32::from_rgb(0xf9, 0xe2, 0xaf);
     const GREEN: Color32 = Color32::from_rgb(0xa6, 0xe3, 0xa1);
+    const RED: Color32 = Color32::from_rgb(0xf3, 0x8b, 0xa8);
     const _SURFACE: Color32 = Color32::from_rgb(0x1e, 0x1e, 0x2e); // base
     const OVERLAY: Color32 = Color32::from_rgb(0x31, 0x31, 0x41); // overlay0
+    const MANTLE: Color32 = Color32::from_rgb(0x28, 0x28, 0x38); // right half of a split board
     const TEXT: Color32 = Color32::from_rgb(0xc6, 0xd0, 0xf5);
+    const HEAT: Color32 = Color32::from_rgb(0xd2, 0x0f, 0x39); // hot end of the heatmap
 }
-use std::collections::HashMap;
-use std::sync::mpsc::Receiver;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::mpsc::{self, Receiver};
 use std::time::{Duration, Instant};
 
+/// Actions bound to keyboard shortcuts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shortcut {
+    OpenFile,
+    Unload,
+    ExportSvg,
+    NextLayer,
+    PreviousLayer,
+    ToggleDebug,
+    ToggleLegend,
+    ToggleTextarea,
+    ToggleSearch,
+    ToggleFullscreen,
+    Undo,
+    Redo,
+    JumpToLayer(u8),
+}
+
+/// Bindings listed in the legend panel
+const SHORTCUT_HELP: &[(&str, &str)] = &[
+    ("Ctrl+O", "Open keymap file"),
+    ("Ctrl+W", "Unload keyboard"),
+    ("Ctrl+S", "Export current layer as SVG"),
+    ("Tab / Shift+Tab", "Next / previous layer (mock mode)"),
+    ("Ctrl+1 … Ctrl+9", "Jump to layer 0 … 8"),
+    ("Ctrl+D", "Toggle debug panel"),
+    ("Ctrl+L", "Toggle legend"),
+    ("Ctrl+T", "Toggle text area"),
+    ("Ctrl+F", "Search keys (Enter: next match, Esc: clear)"),
+    ("F11", "Toggle fullscreen"),
+    ("Ctrl+Z / Ctrl+Shift+Z", "Undo / redo key edits"),
+    ("Ctrl+V", "Paste a keymap (before one is loaded)"),
+];
+
+impl Shortcut {
+    /// Consume the shortcuts pressed this frame; nothing fires while a text field has focus
+    /// so typing (including Tab) in the text area is left alone
+    fn from_input(input: &mut egui::InputState, text_focused: bool) -> Vec<Self> {
+        use egui::{Key, Modifiers};
+
+        if text_focused {
+            return Vec::new();
+        }
+        let mut shortcuts = Vec::new();
+        for (key, shortcut) in [
+            (Key::O, Shortcut::OpenFile),
+            (Key::W, Shortcut::Unload),
+            (Key::S, Shortcut::ExportSvg),
+            (Key::D, Shortcut::ToggleDebug),
+            (Key::L, Shortcut::ToggleLegend),
+            (Key::T, Shortcut::ToggleTextarea),
+            (Key::F, Shortcut::ToggleSearch),
+        ] {
+            if input.consume_key(Modifiers::COMMAND, key) {
+                shortcuts.push(shortcut);
+            }
+        }
+        let digits = [
+            Key::Num1,
+            Key::Num2,
+            Key::Num3,
+            Key::Num4,
+            Key::Num5,
+            Key::Num6,
+            Key::Num7,
+            Key::Num8,
+            Key::Num9,
+        ];
+        for (layer, key) in digits.into_iter().enumerate() {
+            if input.consume_key(Modifiers::COMMAND, key) {
+                shortcuts.push(Shortcut::JumpToLayer(layer as u8));
+            }
+        }
+        // Ctrl+Shift+Z first: the Ctrl+Z pattern would also match it
+        if input.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z) {
+            shortcuts.push(Shortcut::Redo);
+        }
+        if input.consume_key(Modifiers::COMMAND, Key::Z) {
+            shortcuts.push(Shortcut::Undo);
+        }
+        if input.consume_key(Modifiers::NONE, Key::F11) {
+            shortcuts.push(Shortcut::ToggleFullscreen);
+        }
+        // Shift+Tab first: a plain Tab pattern would also match it
+        if input.consume_key(Modifiers::SHIFT, Key::Tab) {
+            shortcuts.push(Shortcut::PreviousLayer);
+        }
+        if input.consume_key(Modifiers::NONE, Key::Tab) {
+            shortcuts.push(Shortcut::NextLayer);
+        }
+        shortcuts
+    }
+}
+
+/// Space around the keyboard that is not available to keys in fullscreen
+const FULLSCREEN_MARGIN: f32 = 60.0;
+/// Range and step of the zoom slider
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 2.0;
+const ZOOM_STEP: f32 = 0.25;
+/// Smallest key size used when fitting the keyboard to the window
+const MIN_FIT_KEY_SIZE: f32 = 16.0;
+
+/// Key size that makes a board of `board_units` (in key units) fill `available` while
+/// keeping its aspect ratio; one key unit is the key size plus `spacing`
+fn fit_key_size(available: Vec2, board_units: Vec2, spacing: f32) -> f32 {
+    let unit = (available.x / board_units.x.max(1.0)).min(available.y / board_units.y.max(1.0));
+    (unit - spacing).max(MIN_FIT_KEY_SIZE)
+}
+
+/// Key background for a key pressed `count` times when the busiest key was pressed `max` times
+fn heatmap_color(count: u64, max: u64) -> Color32 {
+    if count == 0 || max == 0 {
+        return Palette::OVERLAY;
+    }
+    Palette::OVERLAY.lerp_to_gamma(Palette::HEAT, count as f32 / max as f32)
+}
+
+/// Number of key edits that can be undone
+const UNDO_LIMIT: usize = 50;
+
+/// Number of key presses and releases kept in the key log
+const KEY_LOG_LIMIT: usize = 200;
+
+/// Age of a key log entry, e.g. "120ms ago", "4.5s ago" or "3m ago"
+fn format_ago(age: Duration) -> String {
+    if age < Duration::from_secs(1) {
+        format!("{}ms ago", age.as_millis())
+    } else if age < Duration::from_secs(60) {
+        format!("{:.1}s ago", age.as_secs_f32())
+    } else {
+        format!("{}m ago", age.as_secs() / 60)
+    }
+}
+
+/// Smallest horizontal gap between the two halves of a split keyboard, in key units
+const SPLIT_MIN_GAP: f32 = 2.0;
+
+/// X position (in key units) of the line between the halves of a split keyboard
+///
+/// Keys are assigned to a half by which side of the layout's x midpoint their centre is on;
+/// the board counts as split when the halves are at least [`SPLIT_MIN_GAP`] apart, and the
+/// line is drawn in the middle of that gap.
+fn split_line_x(positions: &[KeyPosition]) -> Option<f32> {
+    let min_x = positions.iter().map(|p| p.x).fold(f32::MAX, f32::min);
+    let max_x = positions.iter().map(|p| p.x + p.w).fold(f32::MIN, f32::max);
+    let mid = (min_x + max_x) / 2.0;
+    let (left, right): (Vec<&KeyPosition>, Vec<&KeyPosition>) =
+        positions.iter().partition(|p| p.x + p.w / 2.0 < mid);
+    let max_left_x = left.iter().map(|p| p.x + p.w).fold(f32::MIN, f32::max);
+    let min_right_x = right.iter().map(|p| p.x).fold(f32::MAX, f32::min);
+    let gap = min_right_x - max_left_x;
+    (!left.is_empty() && !right.is_empty() && gap >= SPLIT_MIN_GAP).then(|| max_left_x + gap / 2.0)
+}
+
+/// Longest source path shown in the info row before it is truncated
+const SOURCE_PATH_MAX_CHARS: usize = 40;
+
+/// Keep the last `max_chars` characters of `path`, replacing the dropped prefix with "…"
+fn truncate_path_left(path: &str, max_chars: usize) -> String {
+    let len = path.chars().count();
+    if len <= max_chars {
+        return path.to_string();
+    }
+    let tail: String = path
+        .chars()
+        .skip(len - max_chars.saturating_sub(1))
+        .collect();
+    format!("…{}", tail)
+}
+
+/// Source path as shown in the info row: home directory as `~`, then truncated from the left
+fn source_path_display(path: &str) -> String {
+    let path = tilde_home(path, dirs::home_dir().as_deref());
+    truncate_path_left(&path, SOURCE_PATH_MAX_CHARS)
+}
+
+/// `path` with a leading `home` written as `~`; only whole path components match, so
+/// `/home/alice` is left alone for the home `/home/al`
+fn tilde_home(path: &str, home: Option<&std::path::Path>) -> String {
+    match home.and_then(|h| std::path::Path::new(path).strip_prefix(h).ok()) {
+        Some(rest) if !rest.as_os_str().is_empty() => {
+            format!("~{}{}", std::path::MAIN_SEPARATOR, rest.display())
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// File a loaded keymap is saved from: `keymap.c` for a keymap folder, otherwise the path
+fn keymap_file_for(path: &str) -> String {
+    let path = std::path::Path::new(path);
+    if path.is_dir() {
+        path.join("keymap.c").to_string_lossy().to_string()
+    } else {
+        path.to_string_lossy().to_string()
+    }
+}
+
+/// Parse a keymap folder (keymap.c with its config.h, keymap.h and rules.mk)
+fn parse_keymap_folder(dir: &str) -> Result<KeymapConfig, String> {
+    match KeymapConfig::load_from_dir(dir) {
+        Ok(config) => {
+            println!("✅ Successfully loaded keymap folder: {}", dir);
+            Ok(config)
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to load keymap folder: {:#}", e);
+            Err(format!("Failed to load keymap folder: {:#}", e))
+        }
+    }
+}
+
+/// Whether `source` should be fetched with [`KeyboardViewerApp::load_from_url`]
+fn is_url(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://") || source.starts_with("file://")
+}
+
+/// GitHub API URL listing the files of a Gist page URL such as
+/// `https://gist.github.com/user/<id>`
+#[cfg(feature = "http_fetch")]
+fn gist_api_url(url: &str) -> Option<String> {
+    let path = url
+        .strip_prefix("https://gist.github.com/")
+        .or_else(|| url.strip_prefix("http://gist.github.com/"))?;
+    let id = path.trim_end_matches('/').rsplit('/').next()?;
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some(format!("https://api.github.com/gists/{}", id))
+}
+
+/// Name and raw URL of the first keymap file (.json, .c or .h) in a Gist API response
+#[cfg(feature = "http_fetch")]
+fn gist_raw_file(api_response: &str) -> Option<(String, String)> {
+    let gist: serde_json::Value = serde_json::from_str(api_response).ok()?;
+    gist.get("files")?.as_object()?.values().find_map(|file| {
+        let name = file.get("filename")?.as_str()?;
+        let raw_url = file.get("raw_url")?.as_str()?;
+        [".json", ".c", ".h"]
+            .iter()
+            .any(|ext| name.ends_with(ext))
+            .then(|| (name.to_string(), raw_url.to_string()))
+    })
+}
+
+#[cfg(feature = "http_fetch")]
+fn fetch_text(url: &str) -> anyhow::Result<String> {
+    use anyhow::Context as _;
+    ureq::get(url)
+        .set(
+            "User-Agent",
+            concat!("qmk_viewer/", env!("CARGO_PKG_VERSION")),
+        )
+        .call()
+        .with_context(|| format!("failed to fetch {}", url))?
+        .into_string()
+        .with_context(|| format!("failed to read response from {}", url))
+}
+
+/// Fetch and parse a keymap URL
+fn parse_keymap_url(url: &str) -> Result<KeymapConfig, String> {
+    match KeyboardViewerApp::load_from_url(url) {
+        Ok(config) => {
+            println!("✅ Successfully loaded keymap from: {}", url);
+            Ok(config)
+        }
+        Err(e) => {
+            eprintln!("❌ Failed to load keymap URL: {:#}", e);
+            Err(format!("Failed to load keymap URL: {:#}", e))
+        }
+    }
+}
+
+/// Read and parse a keymap file, picking the parser from the file extension
+fn parse_keymap_file(path: &str) -> Result<KeymapConfig, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        eprintln!("❌ Failed to read file '{}': {}", path, e);
+        format!("Failed to read file '{}': {}", path, e)
+    })?;
+
+    if path.ends_with(".json") {
+        // Try to parse as JSON
+        match serde_json::from_str::<KeymapConfig>(&content) {
+            Ok(mut config) => {
+                println!("✅ Successfully loaded JSON keymap from: {}", path);
+                if let Some(dir) = std::path::Path::new(path).parent() {
+                    config.merge_custom_keycodes_file(&dir.join(CUSTOM_KEYCODES_FILE));
+                }
+                Ok(config)
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to parse JSON keymap: {}", e);
+                Err(format!("Failed to parse JSON keymap: {}", e))
+            }
+        }
+    } else if path.ends_with(".c") || path.ends_with(".h") {
+        // Try to parse as C keymap (sibling config.h and keymap.c are merged in)
+        match KeymapConfig::load_from_path(path) {
+            Ok(config) => {
+                println!("✅ Successfully loaded C keymap from: {}", path);
+                Ok(config)
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to parse C keymap: {}", e);
+                Err(format!("Failed to parse C keymap: {}", e))
+            }
+        }
+    } else {
+        eprintln!("❌ Unsupported file type. Please use .json, .c, or .h files.");
+        Err("Unsupported file type. Please use .json, .c, or .h files.".to_string())
+    }
+}
+
+/// Files a QMK keyboard folder describes its layouts in
+const INFO_JSON_FILES: [&str; 2] = ["info.json", "keyboard.json"];
+
+/// Key positions for `config` from the QMK `info.json` of the keyboard the keymap at `path`
+/// belongs to, `None` when there is none or none of its layouts has the keymap's keys
+///
+/// Keymaps live in `keyboards/<kb>/keymaps/<name>/`, so the folders up to the keyboard's
+/// are searched. The layout named by the keymap is tried first.
+fn info_json_positions(path: &str, config: &KeymapConfig) -> Option<Vec<KeyPosition>> {
+    let json = std::path::Path::new(path)
+        .ancestors()
+        .take(4)
+        .flat_map(|dir| INFO_JSON_FILES.map(|name| dir.join(name)))
+        .find_map(|file| std::fs::read_to_string(file).ok())?;
+    let names = KeyboardLayout::info_json_layout_names(&json).ok()?;
+    config.layout.iter().chain(&names).find_map(|name| {
+        KeyboardLayout::from_info_json(&json, name, config.layers.clone())
+            .ok()?
+            .physical_positions
+    })
+}
+
+/// Like [`parse_keymap_file`], but a `.json` file that isn't a keymap may be a KLE layout
+fn parse_keymap_or_kle_file(path: &str) -> Result<Loaded, String> {
+    let keymap_err = match parse_keymap_file(path) {
+        Ok(config) => {
+            return Ok(match info_json_positions(path, &config) {
+                Some(positions) => Loaded::KeymapWithPositions(config, positions),
+                None => Loaded::Keymap(config),
+            })
+        }
+        Err(e) if path.ends_with(".json") => e,
+        Err(e) => return Err(e),
+    };
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    match KeyboardLayout::from_kle_json(&content) {
+        Ok(layout) => {
+            println!("✅ Loaded KLE layout from: {}", path);
+            Ok(Loaded::KleLayout(layout))
+        }
+        // The file is more likely a broken keymap than a broken KLE layout
+        Err(_) => Err(keymap_err),
+    }
+}
+
+/// Probe which of the [`PROBED_GLYPHS`] the installed font stack can render
+///
+/// Fonts are only available once the context has run a frame.
+pub fn probe_glyph_support(ctx: &Context) -> HashSet<char> {
+    let font_id = egui::FontId::proportional(14.0);
+    ctx.fonts(|fonts| {
+        PROBED_GLYPHS
+            .iter()
+            .map(|(glyph, _)| *glyph)
+            .filter(|glyph| fonts.has_glyph(&font_id, *glyph))
+            .collect()
+    })
+}
+
+/// Rolling words-per-minute computed from characters typed into the text area
+///
+/// This is independent from any WPM reported by the firmware.
+#[derive(Debug, Default)]
+pub struct WpmHistory {
+    samples: VecDeque<(Instant, usize)>, // (timestamp, cumulative char count)
+    sparkline: VecDeque<f32>,
+    last_sparkline_push: Option<Instant>,
+}
+
+impl WpmHistory {
+    const WINDOW: Duration = Duration::from_secs(10);
+    const IDLE_RESET: Duration = Duration::from_secs(5);
+    const SPARKLINE_LEN: usize = 30;
+
+    /// Record the cumulative character count of the text area at `now`
+    pub fn record(&mut self, now: Instant, char_count: usize) {
+        if let Some(&(last_t, last_count)) = self.samples.back() {
+            if last_count == char_count {
+                return;
+            }
+            if now.duration_since(last_t) >= Self::IDLE_RESET {
+                self.samples.clear();
+            }
+        }
+        self.samples.push_back((now, char_count));
+        while let Some(&(t, _)) = self.samples.front() {
+            if now.duration_since(t) > Self::WINDOW {
+                self.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Rolling WPM over the last 10 seconds: `(chars / 5) / (window_secs / 60)`
+    pub fn wpm(&self, now: Instant) -> f32 {
+        let (Some(&(first_t, first_count)), Some(&(last_t, last_count))) =
+            (self.samples.front(), self.samples.back())
+        else {
+            return 0.0;
+        };
+        if now.duration_since(last_t) >= Self::IDLE_RESET {
+            return 0.0;
+        }
+        let window_secs = now
+            .duration_since(first_t)
+            .min(Self::WINDOW)
+            .as_secs_f32()
+            .max(1.0);
+        let chars_in_window = last_count.saturating_sub(first_count) as f32;
+        (chars_in_window / 5.0) / (window_secs / 60.0)
+    }
+
+    /// Push the current WPM into the sparkline, at most once per second
+    pub fn tick(&mut self, now: Instant) {
+        let due = self
+            .last_sparkline_push
+            .map(|t| now.duration_since(t) >= Duration::from_secs(1))
+            .unwrap_or(true);
+        if due {
+            self.sparkline.push_back(self.wpm(now));
+            while self.sparkline.len() > Self::SPARKLINE_LEN {
+                self.sparkline.pop_front();
+            }
+            self.last_sparkline_push = Some(now);
+        }
+    }
+
+    pub fn sparkline(&self) -> impl Iterator<Item = f32> + '_ {
+        self.sparkline.iter().copied()
+    }
+}
+
+/// Merge single-layer keymap files into one keymap with one layer per file, in order
+fn merge_layer_files(paths: &[&str]) -> Result<KeymapConfig, String> {
+    let mut merged = KeymapConfig {
+        keyboard: String::new(),
+        keymap: "layers".to_string(),
+        layers: Vec::new(),
+        layout: None,
+        layer_names: None,
+        encoder_map: Vec::new(),
+        custom_keycodes: CustomKeycodeTable::default(),
+    };
+    let mut names = Vec::new();
+    for (idx, path) in paths.iter().enumerate() {
+        let config = parse_keymap_file(path)?;
+        let file = std::path::Path::new(path);
+        let file_name = file
+            .file_name()
+            .map_or_else(|| path.to_string(), |f| f.to_string_lossy().to_string());
+        let [layer] = <[Vec<String>; 1]>::try_from(config.layers).map_err(|layers| {
+            format!(
+                "{} has {} layers, expected exactly 1",
+                file_name,
+                layers.len()
+            )
+        })?;
+        if let Some(first) = merged.layers.first() {
+            if first.len() != layer.len() {
+                return Err(format!(
+                    "{} has {} keys, expected {}",
+                    file_name,
+                    layer.len(),
+                    first.len()
+                ));
+            }
+        }
+        let name = config
+            .layer_names
+            .and_then(|n| n.into_iter().next())
+            .or_else(|| file.file_stem().map(|s| s.to_string_lossy().to_string()))
+            .unwrap_or_else(|| format!("Layer {}", idx));
+        names.push(name);
+        merged.layers.push(layer);
+        merged.encoder_map.extend(
+            config
+                .encoder_map
+                .into_iter()
+                .map(|a| EncoderAction { layer: idx, ..a }),
+        );
+        if merged.keyboard.is_empty() {
+            merged.keyboard = config.keyboard;
+        }
+    }
+    merged.layer_names = Some(names);
+    Ok(merged)
+}
+
+/// Result of a background load
+#[derive(Debug)]
+enum Loaded {
+    Keymap(KeymapConfig),
+    /// A keymap with the key positions of its keyboard's `info.json`
+    KeymapWithPositions(KeymapConfig, Vec<KeyPosition>),
+    /// Key shapes without keycodes, from [`KeyboardLayout::from_kle_json`]
+    KleLayout(KeyboardLayout),
+    /// Several dropped files, parsed for the confirmation window
+    LayerFiles(LayerFilesPreview),
+}
+
+/// Several files dropped at once, shown for confirmation before they are loaded as layers
+#[derive(Debug, Clone)]
+struct LayerFilesPreview {
+    paths: Vec<String>,
+    /// The files merged into one keymap, or why they can't be
+    config: Result<KeymapConfig, String>,
+}
+
+impl LayerFilesPreview {
+    /// Parses every file, so this runs on the loader thread
+    fn new(paths: Vec<String>, max_files: usize) -> Self {
+        let config = if paths.len() > max_files {
+            Err(format!(
+                "{} files dropped, at most {} layers can be loaded",
+                paths.len(),
+                max_files
+            ))
+        } else {
+            let refs: Vec<&str> = paths.iter().map(String::as_str).collect();
+            merge_layer_files(&refs)
+        };
+        Self { paths, config }
+    }
+
+    /// Layer name detected for each file
+    fn layer_names(&self) -> Option<&[String]> {
+        self.config.as_ref().ok()?.layer_names.as_deref()
+    }
+}
+
+/// Most layers a QMK keymap can have
+const MAX_LAYERS: usize = 32;
+
+/// Change requested from the layers panel, applied once the panel is drawn
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LayerAction {
+    Select(usize),
+    Rename(usize, String),
+    MoveUp(usize),
+    MoveDown(usize),
+    Duplicate(usize),
+    Remove(usize),
+    Add,
+    Save,
+    TogglePin(usize),
+}
+
+/// Popup editing the raw token of one key in key edit mode
+#[derive(Debug, Clone)]
+struct KeyEditor {
+    layer: usize,
+    row: usize,
+    col: usize,
+    token: String,
+    /// Screen position of the popup, just under the clicked key
+    pos: egui::Pos2,
+    focus_requested: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyEventType {
+    Press,
+    Release,
+}
+
+/// A key going down or up, as shown in the key log
+#[derive(Debug, Clone)]
+struct KeyEvent {
+    timestamp: Instant,
+    key_idx: usize,
+    /// Raw token of the key on the layer active when the event happened
+    raw_label: String,
+    event_type: KeyEventType,
+}
+
+/// Two layers shown side by side in place of the keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LayerCompare {
+    left: usize,
+    right: usize,
+    /// Hide keys that are the same on both layers
+    diff_only: bool,
+}
+
+/// How a key differs between the two compared layers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyDiff {
+    /// Same raw token on both layers (or transparent on both)
+    Same,
+    /// Different tokens, both assigned
+    Changed,
+    /// Assigned on one layer, transparent on the other
+    Transparent,
+}
+
+impl KeyDiff {
+    fn color(self) -> Color32 {
+        match self {
+            KeyDiff::Same => Palette::GREEN,
+            KeyDiff::Changed => Palette::YELLOW,
+            KeyDiff::Transparent => Palette::RED,
+        }
+    }
+}
+
+/// A file dragged over the window from the OS, before it is dropped
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum DropHover {
+    Supported(String),
+    Unsupported(String),
+}
+
+impl DropHover {
+    /// Classify the first hovered file by extension (.json, .c and .h can be loaded)
+    fn from_hovered(files: &[egui::HoveredFile]) -> Option<Self> {
+        let file = files.first()?;
+        let name = file
+            .path
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let ext = file
+            .path
+            .as_ref()
+            .and_then(|p| p.extension())
+            .and_then(|e| e.to_str());
+        match ext {
+            Some("json" | "c" | "h") => Some(Self::Supported(name)),
+            // Without a path (e.g. on the web) only the MIME type is known, so let it through
+            None if file.path.is_none() => Some(Self::Supported(name)),
+            _ => Some(Self::Unsupported(name)),
+        }
+    }
+}
+
+/// How long a toast stays on screen
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+/// How long the detected-keyboard banner stays up
+const LAYOUT_BANNER_DURATION: Duration = Duration::from_secs(8);
+
+/// Banner naming the keyboard a loaded `keymap.c` was matched to, with other layouts to try
+#[derive(Debug, Clone)]
+struct LayoutBanner {
+    detected: &'static KnownKeyboard,
+    key_count: usize,
+    shown_at: Instant,
+}
+
+impl LayoutBanner {
+    fn message(&self) -> String {
+        format!(
+            "This looks like a {} layout ({}×{}, {} keys). Using {} physical positions.",
+            self.detected.display_name,
+            self.detected.rows,
+            self.detected.cols,
+            self.key_count,
+            self.detected.display_name
+        )
+    }
+
+    /// Other known keyboards with a position for every key
+    fn alternatives(&self) -> Vec<&'static KnownKeyboard> {
+        KNOWN_KEYBOARDS
+            .iter()
+            .filter(|kb| kb.name != self.detected.name && kb.key_count >= self.key_count)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    Error,
+    Warning,
+    Success,
+}
+
+/// Short-lived notification shown in the bottom-right corner
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub message: String,
+    pub kind: ToastKind,
+    pub expires_at: Instant,
+}
+
 pub struct KeyboardViewerApp {
     state: KeyboardState,
     rx: Receiver<Report>,
+    /// Connection changes from the reader thread, see [`Self::set_connection_receiver`]
+    connection_rx: Option<Receiver<ConnectionStatus>>,
+    connection_status: ConnectionStatus,
     show_debug: bool,
+    show_settings: bool,
+    show_layers_panel: bool,
     show_legend: bool,
     show_textarea: bool,
-    pressed_started: HashMap<usize, Instant>,
+    show_log: bool,
+    /// Recent key presses and releases, newest last, at most [`KEY_LOG_LIMIT`]
+    key_log: VecDeque<KeyEvent>,
+    /// Fullscreen view: keys are scaled to fill the window and the side panels are hidden
+    fullscreen: bool,
+    /// Layers compared side by side, `None` when the keyboard is shown
+    compare: Option<LayerCompare>,
+    /// Key backgrounds show how often each key was pressed
+    show_heatmap: bool,
+    /// The heatmap only counts presses made on the displayed layer
+    heatmap_per_layer: bool,
     text_input: String,
+    wpm_history: WpmHistory,
     keyboard_loaded: bool,
+    focused_key: Option<(usize, usize, usize)>, // (layer, row, col) highlighted from the debug panel
+    /// Scroll the focused key into view on the next paint
+    scroll_to_focus: bool,
+    search_open: bool,
+    search_query: String,
+    /// Set when the search field should grab keyboard focus
+    search_focus_requested: bool,
+    /// Index of the match focused by the last Enter
+    search_cursor: usize,
+    /// Keys of the active layer matching the search; `None` when no search is active
+    search_matches: Option<Vec<(usize, usize)>>,
+    /// Clicking a key opens the token editor instead of toggling it
+    edit_keys: bool,
+    key_editor: Option<KeyEditor>,
+    /// Original raw token of each key edited this session, by (layer, key index)
+    manual_overrides: HashMap<(usize, usize), String>,
+    /// Key edits as (layer, row, col, previous raw token), newest last, at most [`UNDO_LIMIT`]
+    undo_stack: VecDeque<(usize, usize, usize, String)>,
+    /// Undone key edits as (layer, row, col, token to restore), newest last
+    redo_stack: Vec<(usize, usize, usize, String)>,
+    pending_load: Option<(String, Receiver<Result<Loaded, String>>)>,
+    load_error: Option<String>,
+    toasts: VecDeque<Toast>,
+    /// Reference point for the drop zone hover animation
+    started_at: Instant,
+    /// Files dropped together, waiting for the user to load them as layers
+    layer_files_preview: Option<LayerFilesPreview>,
+    /// URL typed into the "Load URL…" window, `None` while the window is closed
+    url_input: Option<String>,
+    /// Saved keymap profiles shown in the top bar
+    profiles: Vec<KeymapProfile>,
+    /// Name typed into the "Save as profile…" window, `None` while the window is closed
+    profile_name_input: Option<String>,
+    layout_banner: Option<LayoutBanner>,
+    /// Rows and columns typed into the custom grid window, `None` while it is closed
+    custom_grid_input: Option<(usize, usize)>,
+    /// Key positions of the last loaded KLE layout, used for keymaps with as many keys
+    kle_positions: Option<Vec<KeyPosition>>,
+    /// Rotary encoder bindings of the loaded keymap
+    encoder_map: Vec<EncoderAction>,
+    /// Keymap currently shown, kept so a single reloaded layer can be merged into it
+    keymap_config: Option<KeymapConfig>,
+    /// Keyboard name and source path of the loaded keymap
+    keyboard_name: Option<String>,
+    source_path: Option<String>,
+    /// Watches the file of the loaded keymap to reload it when it changes
+    file_watcher: Option<FileWatcher>,
+    config: AppConfig,
     #[cfg(not(any(feature = "rawhid", feature = "qmk_console")))]
     manual_pressed: std::collections::HashSet<usize>,
+    #[cfg(feature = "ipc")]
+    ipc_rx: Option<Receiver<crate::ipc::IpcCommand>>,
+    #[cfg(feature = "via")]
+    via_keymap_rx: Option<Receiver<KeymapConfig>>,
 }
 
 impl KeyboardViewerApp {
@@ -38,6 +820,20 @@ impl KeyboardViewerApp {
         state: KeyboardState,
         rx: Receiver<Report>,
     ) -> Self {
+        let mut app = Self::with_context(&cc.egui_ctx, state, rx);
+        app.config = load_app_config().unwrap_or_default();
+        let locale = app
+            .config
+            .keyboard_locale
+            .as_deref()
+            .and_then(KeyboardLocale::from_name);
+        app.state.set_keyboard_locale(locale);
+        app.refresh_profiles();
+        app
+    }
+
+    /// Build the app for an existing egui context (also used for headless tests)
+    pub fn with_context(ctx: &Context, state: KeyboardState, rx: Receiver<Report>) -> Self {
         // Try to install symbol-capable fonts so glyphs render (macOS paths first)
         let mut fonts = egui::FontDefinitions::default();
         // Known macOS fonts with symbols/emoji
@@ -66,103 +862,2286 @@ impl KeyboardViewerApp {
                     .insert(0, key);
             }
         }
-        cc.egui_ctx.set_fonts(fonts);
+        ctx.set_fonts(fonts);
 
         Self {
             state,
             rx,
+            connection_rx: None,
+            connection_status: ConnectionStatus::Disconnected,
             show_debug: false,
+            show_settings: false,
+            show_layers_panel: false,
             show_legend: false,
             show_textarea: false,
-            pressed_started: HashMap::new(),
+            show_log: false,
+            key_log: VecDeque::new(),
+            fullscreen: false,
+            compare: None,
+            show_heatmap: false,
+            heatmap_per_layer: false,
             text_input: String::new(),
+            wpm_history: WpmHistory::default(),
             keyboard_loaded: true, // Will be set correctly in main.rs
+            focused_key: None,
+            scroll_to_focus: false,
+            search_open: false,
+            search_query: String::new(),
+            search_focus_requested: false,
+            search_cursor: 0,
+            search_matches: None,
+            edit_keys: false,
+            key_editor: None,
+            manual_overrides: HashMap::new(),
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            pending_load: None,
+            load_error: None,
+            toasts: VecDeque::new(),
+            layer_files_preview: None,
+            url_input: None,
+            profiles: Vec::new(),
+            profile_name_input: None,
+            layout_banner: None,
+            custom_grid_input: None,
+            kle_positions: None,
+            encoder_map: Vec::new(),
+            keymap_config: None,
+            started_at: Instant::now(),
+            keyboard_name: None,
+            source_path: None,
+            file_watcher: None,
+            config: AppConfig::default(),
             #[cfg(not(any(feature = "rawhid", feature = "qmk_console")))]
             manual_pressed: std::collections::HashSet::new(),
+            #[cfg(feature = "ipc")]
+            ipc_rx: None,
+            #[cfg(feature = "via")]
+            via_keymap_rx: None,
+        }
+    }
+
+    /// Start loading a keymap file (or URL) on a background thread
+    fn load_keymap_from_path(&mut self, path: &str) {
+        if is_url(path) {
+            self.start_load_with(path.to_string(), parse_keymap_url);
+        } else {
+            self.start_load(path.to_string(), parse_keymap_or_kle_file);
+        }
+    }
+
+    /// Download a keymap; `file://` URLs are read from disk and GitHub Gist pages are
+    /// resolved to their first keymap file
+    ///
+    /// Blocks until the download finishes, so call it from a background thread.
+    pub fn load_from_url(url: &str) -> anyhow::Result<KeymapConfig> {
+        if let Some(path) = url.strip_prefix("file://") {
+            return KeymapConfig::load_from_path(path);
+        }
+        #[cfg(feature = "http_fetch")]
+        {
+            let (file_name, raw_url) = match gist_api_url(url) {
+                Some(api_url) => gist_raw_file(&fetch_text(&api_url)?)
+                    .ok_or_else(|| anyhow::anyhow!("no .json, .c or .h file in gist {}", url))?,
+                None => {
+                    let file_name = url.split(['?', '#']).next().unwrap_or(url).to_string();
+                    (file_name, url.to_string())
+                }
+            };
+            KeymapConfig::parse_source(&file_name, &fetch_text(&raw_url)?)
+        }
+        #[cfg(not(feature = "http_fetch"))]
+        anyhow::bail!("loading {} needs a build with the http_fetch feature", url)
+    }
+
+    /// Load each file as one layer of a single keymap; returns `false` (and shows why) if
+    /// a file can't be parsed, has more than one layer or a different key count
+    pub fn load_keymaps_as_layers(&mut self, paths: &[&str]) -> bool {
+        self.apply_layer_files(paths, merge_layer_files(paths))
+    }
+
+    /// Show the keymap merged from `paths` by [`merge_layer_files`]
+    fn apply_layer_files<S: AsRef<str>>(
+        &mut self,
+        paths: &[S],
+        merged: Result<KeymapConfig, String>,
+    ) -> bool {
+        match merged {
+            Ok(config) => {
+                // The info row links to the folder holding the layer files
+                let source = paths
+                    .first()
+                    .and_then(|p| std::path::Path::new(p.as_ref()).parent())
+                    .map(|dir| dir.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let layer_count = config.layers.len();
+                self.apply_loaded_config(config, source);
+                self.push_toast(
+                    format!("Loaded {} layers from {} files", layer_count, paths.len()),
+                    ToastKind::Success,
+                );
+                true
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to load layers: {}", e);
+                self.push_toast(e, ToastKind::Error);
+                false
+            }
+        }
+    }
+
+    /// Replace the shown keymap with `config`, keeping view settings such as the locale
+    fn apply_loaded_config(&mut self, config: KeymapConfig, source_path: String) {
+        let watched = std::path::PathBuf::from(keymap_file_for(&source_path));
+        if self
+            .file_watcher
+            .as_ref()
+            .is_some_and(|w| w.path() != watched)
+        {
+            self.file_watcher = None;
+        }
+        self.layout_banner = None;
+        self.set_keymap_config(config);
+        self.source_path = Some(source_path);
+        self.keyboard_loaded = true;
+    }
+
+    /// Show the key shapes of a KLE layout with empty keys; keymaps loaded next with the
+    /// same number of keys are drawn at its positions
+    ///
+    /// The layout is not saved for the next start, which only restores keymaps.
+    fn apply_kle_layout(&mut self, layout: KeyboardLayout, source_path: String) {
+        let key_count = layout.physical_positions.as_ref().map_or(0, Vec::len);
+        self.kle_positions = layout.physical_positions.clone();
+        self.layout_banner = None;
+        self.replace_layout(layout);
+        self.keymap_config = None;
+        self.keyboard_name = None;
+        self.encoder_map.clear();
+        self.clear_key_edits();
+        self.source_path = Some(source_path);
+        self.keyboard_loaded = true;
+        if !self.config.show_physical_layout {
+            self.set_show_physical_layout(true);
+        }
+        self.push_toast(
+            format!(
+                "Loaded KLE layout with {} keys; load a keymap to fill them in",
+                key_count
+            ),
+            ToastKind::Success,
+        );
+    }
+
+    /// Show `config` without touching the source path or loaded flag
+    pub fn set_keymap_config(&mut self, config: KeymapConfig) {
+        self.replace_layout(config.to_keyboard_layout());
+        let key_count = config.layers.iter().map(Vec::len).max().unwrap_or(0);
+        if let Some(positions) = self
+            .kle_positions
+            .as_ref()
+            .filter(|positions| positions.len() == key_count)
+        {
+            self.state.keyboard.physical_positions = Some(positions.clone());
+        }
+        self.state.custom_table = config.custom_keycodes.clone();
+        let warnings = self.state.keyboard.validate_consistency();
+        for warning in &warnings {
+            eprintln!("⚠️ Inconsistent keymap: {}", warning);
+        }
+        if let Some(first) = warnings.first() {
+            let more = match warnings.len() {
+                1 => String::new(),
+                n => format!(" (+{} more)", n - 1),
+            };
+            self.push_toast(
+                format!("Inconsistent keymap: {}{}", first, more),
+                ToastKind::Warning,
+            );
+        }
+        self.keyboard_name = Some(config.keyboard.clone());
+        self.encoder_map = config.encoder_map.clone();
+        self.keymap_config = Some(config);
+        self.clear_key_edits();
+    }
+
+    /// Match the loaded keymap to a known keyboard by key count, show it with that
+    /// keyboard's physical positions and offer the other layouts in a banner
+    fn detect_keyboard_layout(&mut self) {
+        // Positions from an imported KLE layout fit better than a guess by key count
+        if self.state.keyboard.physical_positions.is_some() {
+            return;
+        }
+        let key_count = self
+            .keymap_config
+            .as_ref()
+            .and_then(|c| c.layers.iter().map(Vec::len).max())
+            .unwrap_or(0);
+        // Keys past the end of the known positions would not be drawn
+        let Some(detected) = detect_by_key_count(key_count).filter(|kb| kb.key_count >= key_count)
+        else {
+            return;
+        };
+        self.relayout_keymap(
+            detected.rows,
+            detected.cols,
+            detected.layout().physical_positions,
+        );
+        self.layout_banner = Some(LayoutBanner {
+            detected,
+            key_count,
+            shown_at: Instant::now(),
+        });
+    }
+
+    /// Lay the loaded keymap out again on another grid, keeping its key assignments
+    fn relayout_keymap(&mut self, rows: usize, cols: usize, positions: Option<Vec<KeyPosition>>) {
+        let Some(config) = &self.keymap_config else {
+            return;
+        };
+        let mut layout = KeyboardLayout::from_layout_data_with_dims(
+            config.layers.clone(),
+            config.layer_names.clone(),
+            rows,
+            cols,
+        );
+        layout.physical_positions = positions;
+        let custom_table = config.custom_keycodes.clone();
+        self.replace_layout(layout);
+        self.state.custom_table = custom_table;
+        self.clear_key_edits();
+    }
+
+    /// Switch to a layout picked from the banner
+    fn choose_known_keyboard(&mut self, kb: &KnownKeyboard) {
+        self.relayout_keymap(kb.rows, kb.cols, kb.layout().physical_positions);
+        self.set_show_physical_layout(true);
+        self.layout_banner = None;
+        self.push_toast(
+            format!("Using {} physical positions", kb.display_name),
+            ToastKind::Success,
+        );
+    }
+
+    /// In merge mode, fold a single-layer keymap into the shown one; returns the merged
+    /// layer index, or `None` when `config` should replace the keymap instead
+    fn merge_single_layer(&mut self, config: &KeymapConfig) -> Option<usize> {
+        if !self.config.merge_mode || !self.keyboard_loaded || config.layers.len() != 1 {
+            return None;
+        }
+        let mut merged = self.keymap_config.clone()?;
+        // Layers are matched by name, falling back to the layer being viewed
+        let name = config.layer_names.as_ref().and_then(|n| n.first());
+        let layer_idx = name
+            .and_then(|name| {
+                self.state
+                    .keyboard
+                    .layer_names
+                    .iter()
+                    .position(|n| n.eq_ignore_ascii_case(name))
+            })
+            .unwrap_or(self.state.active_layer as usize);
+        merged.merge_layer(layer_idx, config.layers[0].clone());
+        self.set_keymap_config(merged);
+        self.state.set_layer(layer_idx as u8);
+        Some(layer_idx)
+    }
+
+    /// Save the keymap with a merged layer as the copy restored on the next start
+    fn save_merged_keymap(&mut self, source: &str) {
+        let Some(config) = &self.keymap_config else {
+            return;
+        };
+        let saved = serde_json::to_string_pretty(config)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| save_keymap_json(&json, Some(source)));
+        match saved {
+            Ok(_) => self.refresh_profiles(),
+            Err(e) => {
+                eprintln!("⚠️ Failed to save merged keymap: {}", e);
+                self.push_toast(
+                    format!("Failed to save merged keymap: {}", e),
+                    ToastKind::Warning,
+                );
+            }
+        }
+    }
+
+    fn start_load_with<F>(&mut self, path: String, loader: F)
+    where
+        F: FnOnce(&str) -> Result<KeymapConfig, String> + Send + 'static,
+    {
+        self.start_load(path, move |path| loader(path).map(Loaded::Keymap));
+    }
+
+    fn start_load<F>(&mut self, path: String, loader: F)
+    where
+        F: FnOnce(&str) -> Result<Loaded, String> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        let thread_path = path.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(loader(&thread_path));
+        });
+        self.load_error = None;
+        self.pending_load = Some((path, rx));
+    }
+
+    fn push_toast(&mut self, message: String, kind: ToastKind) {
+        self.toasts.push_back(Toast {
+            message,
+            kind,
+            expires_at: Instant::now() + TOAST_DURATION,
+        });
+    }
+
+    /// Drop toasts that have expired at `now`
+    fn prune_toasts(&mut self, now: Instant) {
+        self.toasts.retain(|toast| toast.expires_at > now);
+    }
+
+    fn is_loading(&self) -> bool {
+        self.pending_load.is_some()
+    }
+
+    /// Apply the result of a background load once it arrives
+    fn poll_pending_load(&mut self) {
+        let Some((path, rx)) = &self.pending_load else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                Err("keymap loader stopped unexpectedly".to_string())
+            }
+        };
+        let path = path.clone();
+        self.pending_load = None;
+        let (config, positions) = match result {
+            Ok(Loaded::KleLayout(layout)) => {
+                self.apply_kle_layout(layout, path.clone());
+                self.watch_keymap_file(&path);
+                return;
+            }
+            Ok(Loaded::LayerFiles(preview)) => {
+                self.layer_files_preview = Some(preview);
+                return;
+            }
+            Ok(Loaded::Keymap(config)) => (config, None),
+            Ok(Loaded::KeymapWithPositions(config, positions)) => (config, Some(positions)),
+            Err(e) => {
+                self.push_toast(e.clone(), ToastKind::Error);
+                self.load_error = Some(e);
+                return;
+            }
+        };
+        if let Some(layer_idx) = self.merge_single_layer(&config) {
+            // Edits of the layer file are merged again like this one
+            self.source_path = Some(path.clone());
+            self.watch_keymap_file(&path);
+            self.save_merged_keymap(&path);
+            let name = self.state.keyboard.layer_names.get(layer_idx).cloned();
+            self.push_toast(
+                format!(
+                    "Merged layer {}",
+                    name.unwrap_or_else(|| layer_idx.to_string())
+                ),
+                ToastKind::Success,
+            );
+            return;
+        }
+        // Update the keyboard state with new layout
+        self.apply_loaded_config(config, path.clone());
+        if let Some(positions) = positions {
+            // The keyboard's own positions fit better than a guess by key count
+            self.state.keyboard.physical_positions = Some(positions);
+        } else if !path.ends_with(".json") {
+            self.detect_keyboard_layout();
+        }
+        if is_url(&path) {
+            self.push_toast(format!("Loaded: {}", path), ToastKind::Success);
+            return;
+        }
+        self.watch_keymap_file(&path);
+        // Save the keymap file
+        let save_path = keymap_file_for(&path);
+        if let Err(e) = save_keymap_file(&save_path) {
+            eprintln!("⚠️ Failed to save keymap file: {}", e);
+            self.push_toast(
+                format!("Failed to save keymap file: {}", e),
+                ToastKind::Warning,
+            );
+        }
+        self.refresh_profiles();
+        let file_name = std::path::Path::new(&save_path)
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or(save_path);
+        self.push_toast(format!("Loaded: {}", file_name), ToastKind::Success);
+    }
+
+    fn unload_keyboard(&mut self) {
+        if let Err(e) = clear_saved_keymap() {
+            eprintln!("⚠️ Failed to clear saved keymap: {}", e);
+            self.push_toast(
+                format!("Failed to clear saved keymap: {}", e),
+                ToastKind::Warning,
+            );
+        }
+        self.refresh_profiles();
+        self.keyboard_loaded = false;
+        self.keyboard_name = None;
+        self.source_path = None;
+        self.file_watcher = None;
+        self.encoder_map.clear();
+        self.keymap_config = None;
+        self.layout_banner = None;
+        self.kle_positions = None;
+        self.compare = None;
+        self.clear_key_edits();
+        // Reset to default Planck layout
+        self.replace_layout(crate::keyboards::planck::PlanckLayout::planck_default());
+    }
+
+    /// Show another layout, keeping view settings such as the locale and the hold timing
+    /// of keys that are still down
+    fn replace_layout(&mut self, layout: crate::keyboard::KeyboardLayout) {
+        let locale = self.state.keyboard_locale;
+        let pressed_started = std::mem::take(&mut self.state.pressed_started);
+        let mt_hold_threshold = self.state.mt_hold_threshold;
+        let press_counts = std::mem::take(&mut self.state.press_counts);
+        let layer_press_counts = std::mem::take(&mut self.state.layer_press_counts);
+        self.state = KeyboardState::new(layout);
+        self.state.set_keyboard_locale(locale);
+        self.state.pressed_started = pressed_started;
+        self.state.mt_hold_threshold = mt_hold_threshold;
+        self.state.press_counts = press_counts;
+        self.state.layer_press_counts = layer_press_counts;
+    }
+
+    pub fn set_keyboard_loaded(&mut self, loaded: bool) {
+        self.keyboard_loaded = loaded;
+    }
+
+    /// Record which keyboard and file the current keymap came from
+    pub fn set_source(&mut self, keyboard_name: String, source_path: String) {
+        self.keyboard_name = Some(keyboard_name);
+        self.source_path = Some(source_path);
+    }
+
+    /// Reload the keymap when the file at `source`, or the `keymap.c` of a keymap folder,
+    /// changes; URLs are not watched
+    pub fn watch_keymap_file(&mut self, source: &str) {
+        if is_url(source) {
+            self.file_watcher = None;
+            return;
+        }
+        let file = std::path::PathBuf::from(keymap_file_for(source));
+        if self.file_watcher.as_ref().is_some_and(|w| w.path() == file) {
+            return;
+        }
+        self.file_watcher = Some(FileWatcher::spawn(file));
+    }
+
+    /// Reload the keymap from its source once the watched file has changed
+    fn poll_file_watcher(&mut self) {
+        // A change seen during another load stays queued until that load is done
+        if self.is_loading() {
+            return;
+        }
+        let changed = self.file_watcher.as_ref().is_some_and(FileWatcher::changed);
+        if !changed {
+            return;
+        }
+        let Some(source) = self.source_path.clone() else {
+            return;
+        };
+        if std::path::Path::new(&source).is_dir() {
+            self.start_load_with(source, parse_keymap_folder);
+        } else {
+            self.load_keymap_from_path(&source);
+        }
+    }
+
+    pub fn set_connection_receiver(&mut self, rx: Receiver<ConnectionStatus>) {
+        self.connection_rx = Some(rx);
+    }
+
+    /// Keep the latest status sent by the reader thread
+    fn poll_connection_status(&mut self) {
+        if let Some(rx) = &self.connection_rx {
+            if let Some(status) = rx.try_iter().last() {
+                self.connection_status = status;
+            }
+        }
+    }
+
+    /// Bottom bar with the keyboard connection state
+    fn draw_status_bar(&self, ctx: &Context) {
+        let (color, text) = match &self.connection_status {
+            ConnectionStatus::Disconnected => (
+                Palette::TEXT.gamma_multiply(0.7),
+                "Disconnected".to_string(),
+            ),
+            ConnectionStatus::Scanning => (Palette::YELLOW, "Scanning for keyboard…".to_string()),
+            ConnectionStatus::Connecting { device_name } => {
+                (Palette::YELLOW, format!("Connecting to {}…", device_name))
+            }
+            ConnectionStatus::Connected {
+                device_name,
+                vid,
+                pid,
+            } => (
+                Palette::GREEN,
+                format!("Connected: {} ({:04X}:{:04X})", device_name, vid, pid),
+            ),
+            ConnectionStatus::Error(e) => (Palette::RED, format!("Connection error: {}", e)),
+        };
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.add_space(10.0);
+                ui.label(RichText::new("●").color(color));
+                ui.label(RichText::new(text).small().color(color));
+            });
+        });
+    }
+
+    /// Show the layer named `name` (case-insensitive); returns `false` if no layer matches
+    pub fn set_active_layer_by_name(&mut self, name: &str) -> bool {
+        let Some(idx) = self
+            .state
+            .keyboard
+            .layer_names
+            .iter()
+            .position(|n| n.eq_ignore_ascii_case(name.trim()))
+        else {
+            return false;
+        };
+        self.state.set_layer(idx as u8);
+        true
+    }
+
+    /// Receive commands from the IPC listener (see [`crate::ipc::spawn_listener`])
+    #[cfg(feature = "ipc")]
+    pub fn set_ipc_receiver(&mut self, rx: Receiver<crate::ipc::IpcCommand>) {
+        self.ipc_rx = Some(rx);
+    }
+
+    #[cfg(feature = "ipc")]
+    fn poll_ipc(&mut self) {
+        let Some(rx) = &self.ipc_rx else {
+            return;
+        };
+        let commands: Vec<_> = rx.try_iter().collect();
+        for cmd in commands {
+            match cmd {
+                crate::ipc::IpcCommand::SetLayer { layer } => {
+                    if !self.set_active_layer_by_name(&layer) {
+                        eprintln!("⚠️ IPC: unknown layer {:?}", layer);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Receive keymaps read from the keyboard over VIA
+    #[cfg(feature = "via")]
+    pub fn set_via_keymap_receiver(&mut self, rx: Receiver<KeymapConfig>) {
+        self.via_keymap_rx = Some(rx);
+    }
+
+    /// Show the latest keymap downloaded over VIA
+    #[cfg(feature = "via")]
+    fn poll_via_keymap(&mut self) {
+        let Some(config) = self
+            .via_keymap_rx
+            .as_ref()
+            .and_then(|rx| rx.try_iter().last())
+        else {
+            return;
+        };
+        self.apply_loaded_config(config, "VIA".to_string());
+        self.detect_keyboard_layout();
+        self.push_toast(
+            "Loaded the keymap from the keyboard".to_string(),
+            ToastKind::Success,
+        );
+    }
+
+    /// Whether keys are drawn at their physical positions instead of the uniform grid
+    fn use_physical_layout(&self) -> bool {
+        self.config.show_physical_layout && self.state.keyboard.physical_positions.is_some()
+    }
+
+    /// Apply `update` to the in-memory config and persist the same change
+    fn update_config(&mut self, update: impl Fn(&mut AppConfig)) {
+        update(&mut self.config);
+        // Re-read the stored config so other persisted fields are kept as they are on disk
+        let mut stored = load_app_config().unwrap_or_default();
+        update(&mut stored);
+        if let Err(e) = save_app_config(&stored) {
+            eprintln!("⚠️ Failed to save config: {}", e);
+            self.push_toast(format!("Failed to save config: {}", e), ToastKind::Warning);
+        }
+    }
+
+    fn set_show_physical_layout(&mut self, show: bool) {
+        self.update_config(|c| c.show_physical_layout = show);
+    }
+
+    fn toggle_pinned_layer(&mut self, layer: u8) {
+        let pin = !self.config.pinned_layers.contains(&layer);
+        self.update_config(|c| {
+            c.pinned_layers.retain(|&l| l != layer);
+            if pin {
+                c.pinned_layers.push(layer);
+            }
+        });
+    }
+
+    fn set_show_split(&mut self, show: bool) {
+        self.update_config(|c| c.show_split = show);
+    }
+
+    fn set_show_effective_keys(&mut self, show: bool) {
+        self.update_config(|c| c.show_effective_keys = show);
+    }
+
+    fn set_keyboard_locale(&mut self, locale: Option<KeyboardLocale>) {
+        self.state.set_keyboard_locale(locale);
+        let name = locale.map(|l| l.name().to_string());
+        self.update_config(|c| c.keyboard_locale = name.clone());
+    }
+
+    fn set_merge_mode(&mut self, merge: bool) {
+        self.update_config(|c| c.merge_mode = merge);
+    }
+
+    /// Confirmation window listing dropped layer files and the layer name found in each
+    fn draw_layer_files_preview(&mut self, ctx: &Context) {
+        let Some(preview) = &self.layer_files_preview else {
+            return;
+        };
+        let mut load = false;
+        let mut cancel = false;
+        egui::Window::new("Load layers from files")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                egui::Grid::new("layer_files")
+                    .num_columns(3)
+                    .show(ui, |ui| {
+                        for (idx, path) in preview.paths.iter().enumerate() {
+                            let file_name = std::path::Path::new(path)
+                                .file_name()
+                                .map_or_else(|| path.clone(), |f| f.to_string_lossy().to_string());
+                            ui.label(format!("Layer {}", idx));
+                            ui.monospace(file_name);
+                            if let Some(name) = preview.layer_names().and_then(|n| n.get(idx)) {
+                                ui.label(RichText::new(name).strong());
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if let Err(e) = &preview.config {
+                    ui.add_space(5.0);
+                    ui.colored_label(Palette::PEACH, e);
+                }
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    load = ui
+                        .add_enabled(preview.config.is_ok(), egui::Button::new("Load"))
+                        .clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if load {
+            // The files were parsed for the preview, so they're not read again
+            if let Some(preview) = self.layer_files_preview.take() {
+                self.apply_layer_files(&preview.paths, preview.config);
+            }
+        }
+        if cancel {
+            self.layer_files_preview = None;
+        }
+    }
+
+    fn refresh_profiles(&mut self) {
+        match list_profiles() {
+            Ok(profiles) => self.profiles = profiles,
+            Err(e) => eprintln!("⚠️ Failed to list profiles: {}", e),
+        }
+    }
+
+    /// Load the stored copy of profile `name`
+    fn open_profile(&mut self, name: &str) {
+        match load_profile(name) {
+            Ok(path) => self.load_keymap_from_path(&path),
+            Err(e) => self.push_toast(format!("Failed to load profile: {}", e), ToastKind::Error),
+        }
+        self.refresh_profiles();
+    }
+
+    fn remove_profile(&mut self, name: &str) {
+        match delete_profile(name) {
+            Ok(()) => self.push_toast(format!("Deleted profile {}", name), ToastKind::Success),
+            Err(e) => self.push_toast(
+                format!("Failed to delete profile: {}", e),
+                ToastKind::Warning,
+            ),
+        }
+        self.refresh_profiles();
+    }
+
+    /// Local file the shown keymap can be saved from as a profile
+    fn profile_source(&self) -> Option<String> {
+        self.source_path
+            .as_deref()
+            .filter(|path| !is_url(path))
+            .map(keymap_file_for)
+    }
+
+    /// Window asking for the name to save the shown keymap under
+    fn draw_profile_dialog(&mut self, ctx: &Context) {
+        let Some(name) = &mut self.profile_name_input else {
+            return;
+        };
+        let mut save = false;
+        let mut cancel = false;
+        egui::Window::new("Save as profile")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                let resp = ui.add(
+                    egui::TextEdit::singleline(name)
+                        .hint_text("Profile name")
+                        .desired_width(240.0),
+                );
+                let valid = !name.trim().is_empty();
+                let submitted = resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if self.profiles.iter().any(|p| p.name == name.trim()) {
+                    ui.colored_label(Palette::PEACH, "Replaces the saved profile of this name");
+                }
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    save = ui.add_enabled(valid, egui::Button::new("Save")).clicked()
+                        || (submitted && valid);
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if save {
+            let name = name.trim().to_string();
+            if let Some(source) = self.profile_source() {
+                match save_profile(&name, &source) {
+                    Ok(_) => self.push_toast(format!("Saved profile {}", name), ToastKind::Success),
+                    Err(e) => {
+                        eprintln!("⚠️ Failed to save profile: {}", e);
+                        self.push_toast(
+                            format!("Failed to save profile: {}", e),
+                            ToastKind::Warning,
+                        );
+                    }
+                }
+                self.refresh_profiles();
+            }
+        }
+        if save || cancel {
+            self.profile_name_input = None;
+        }
+    }
+
+    /// Window asking for a keymap URL to fetch
+    fn draw_url_dialog(&mut self, ctx: &Context) {
+        let Some(url) = &mut self.url_input else {
+            return;
+        };
+        let mut fetch = false;
+        let mut cancel = false;
+        egui::Window::new("Load keymap from URL")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                let resp = ui.add(
+                    egui::TextEdit::singleline(url)
+                        .hint_text("https://gist.github.com/…")
+                        .desired_width(360.0),
+                );
+                let valid = is_url(url.trim());
+                let submitted = resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    fetch = ui.add_enabled(valid, egui::Button::new("Fetch")).clicked()
+                        || (submitted && valid);
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if fetch {
+            let url = url.trim().to_string();
+            self.load_keymap_from_path(&url);
+        }
+        if fetch || cancel {
+            self.url_input = None;
+        }
+    }
+
+    /// Thin row under the top bar naming the detected keyboard, hidden after
+    /// [`LAYOUT_BANNER_DURATION`]
+    fn draw_layout_banner(&mut self, ctx: &Context) {
+        let Some(banner) = &self.layout_banner else {
+            return;
+        };
+        if banner.shown_at.elapsed() >= LAYOUT_BANNER_DURATION {
+            self.layout_banner = None;
+            return;
+        }
+        let mut chosen = None;
+        let mut custom = false;
+        let mut dismiss = false;
+        egui::TopBottomPanel::top("layout_banner")
+            .exact_height(26.0)
+            .show(ctx, |ui| {
+                ui.horizontal_centered(|ui| {
+                    ui.add_space(10.0);
+                    ui.label(RichText::new(banner.message()).small().color(Palette::BLUE));
+                    ui.label(RichText::new("Change:").small());
+                    for kb in banner.alternatives() {
+                        if ui.small_button(kb.display_name).clicked() {
+                            chosen = Some(kb);
+                        }
+                    }
+                    custom = ui.small_button("Custom…").clicked();
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.add_space(10.0);
+                        dismiss = ui.small_button("✖").on_hover_text("Dismiss").clicked();
+                    });
+                });
+            });
+        if let Some(kb) = chosen {
+            self.choose_known_keyboard(kb);
+        }
+        if custom {
+            self.custom_grid_input = Some((self.state.keyboard.rows, self.state.keyboard.cols));
+        }
+        if dismiss || custom {
+            self.layout_banner = None;
+        }
+    }
+
+    /// Window asking for the rows and columns of a plain grid to show the keymap on
+    fn draw_custom_grid_dialog(&mut self, ctx: &Context) {
+        let Some((rows, cols)) = &mut self.custom_grid_input else {
+            return;
+        };
+        let mut apply = false;
+        let mut cancel = false;
+        egui::Window::new("Custom grid")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(rows).range(1..=16).suffix(" rows"));
+                    ui.label("×");
+                    ui.add(egui::DragValue::new(cols).range(1..=32).suffix(" cols"));
+                });
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    apply = ui.button("Apply").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if apply {
+            let (rows, cols) = (*rows, *cols);
+            self.relayout_keymap(rows, cols, None);
+        }
+        if apply || cancel {
+            self.custom_grid_input = None;
+        }
+    }
+
+    /// Secondary row under the top bar with the loaded keyboard and its source file
+    fn draw_info_row(&mut self, ctx: &Context) {
+        let color = Palette::TEXT.gamma_multiply(0.7);
+        let mut open_failed = None;
+        egui::TopBottomPanel::top("info").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.add_space(10.0);
+                if let Some(name) = &self.keyboard_name {
+                    ui.label(
+                        RichText::new(format!("keyboard: {}", name))
+                            .small()
+                            .color(color),
+                    );
+                }
+                if let Some(path) = &self.source_path {
+                    ui.separator();
+                    let source = ui
+                        .add(
+                            egui::Label::new(
+                                RichText::new(format!("source: {}", source_path_display(path)))
+                                    .small()
+                                    .color(color),
+                            )
+                            .sense(Sense::click()),
+                        )
+                        .on_hover_text(path.as_str())
+                        .on_hover_cursor(egui::CursorIcon::PointingHand);
+                    if source.clicked() {
+                        if let Err(e) = open::that(path) {
+                            open_failed = Some(format!("Failed to open {}: {}", path, e));
+                        }
+                    }
+                }
+            });
+        });
+        if let Some(message) = open_failed {
+            eprintln!("❌ {}", message);
+            self.push_toast(message, ToastKind::Error);
+        }
+    }
+
+    /// Load keymap text pasted into the drop zone, whatever its format
+    fn load_from_clipboard(&mut self, text: &str) {
+        match detect_format_and_parse(text) {
+            Ok((format, config)) => {
+                println!("✅ Loaded {} from clipboard", format.name());
+                self.apply_loaded_config(config, "clipboard".to_string());
+                self.push_toast(
+                    format!("Loaded {} from clipboard", format.name()),
+                    ToastKind::Success,
+                );
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to load keymap from clipboard: {:#}", e);
+                self.push_toast(
+                    format!("Failed to load keymap from clipboard: {:#}", e),
+                    ToastKind::Error,
+                );
+            }
+        }
+    }
+
+    /// One-page HTML report with every layer of the loaded keymap
+    pub fn export_report(&self) -> String {
+        export_report(
+            &self.state,
+            &Theme::default(),
+            self.keyboard_name.as_deref().unwrap_or("keyboard"),
+            self.source_path.as_deref(),
+        )
+    }
+
+    fn open_file_dialog(&mut self) {
+        // Use rfd to open file dialog synchronously
+        if let Some(file) = rfd::FileDialog::new()
+            .add_filter("Keymap files", &["json", "c", "h"])
+            .add_filter("JSON files", &["json"])
+            .add_filter("C files", &["c", "h"])
+            .set_title("Select keymap file")
+            .pick_file()
+        {
+            if let Some(path_str) = file.to_str() {
+                self.load_keymap_from_path(path_str);
+            }
+        }
+    }
+
+    fn open_folder_dialog(&mut self) {
+        if let Some(dir) = rfd::FileDialog::new()
+            .set_title("Select keymap folder")
+            .pick_folder()
+        {
+            if let Some(dir_str) = dir.to_str() {
+                self.start_load_with(dir_str.to_string(), parse_keymap_folder);
+            }
+        }
+    }
+
+    #[cfg(not(any(feature = "rawhid", feature = "qmk_console")))]
+    fn toggle_manual_press(&mut self, r: usize, c: usize) {
+        if let Some(idx) = self.state.index_for(r, c) {
+            if self.manual_pressed.contains(&idx) {
+                self.manual_pressed.remove(&idx);
+            } else {
+                self.manual_pressed.insert(idx);
+            }
+        }
+    }
+
+    fn open_key_editor(&mut self, layer: usize, row: usize, col: usize, pos: egui::Pos2) {
+        let token = self
+            .state
+            .raw_legend_at(layer, row, col)
+            .unwrap_or_default()
+            .to_string();
+        self.key_editor = Some(KeyEditor {
+            layer,
+            row,
+            col,
+            token,
+            pos,
+            focus_requested: false,
+        });
+    }
+
+    /// Log the keys pressed and released since `previous` was the pressed state
+    fn record_key_events(&mut self, previous: crate::hid::PressedBits, now: Instant) {
+        let current = self.state.pressed_bits;
+        let layer = self.state.active_layer as usize;
+        let cols = self.state.keyboard.cols.max(1);
+        for (bits, event_type) in [
+            (current & !previous, KeyEventType::Press),
+            (previous & !current, KeyEventType::Release),
+        ] {
+            for key_idx in bits.iter() {
+                let raw_label = self
+                    .state
+                    .raw_legend_at(layer, key_idx / cols, key_idx % cols)
+                    .unwrap_or_default()
+                    .to_string();
+                if self.key_log.len() == KEY_LOG_LIMIT {
+                    self.key_log.pop_front();
+                }
+                self.key_log.push_back(KeyEvent {
+                    timestamp: now,
+                    key_idx,
+                    raw_label,
+                    event_type,
+                });
+            }
+        }
+    }
+
+    /// Side panel listing the key log, newest first
+    fn draw_key_log(&mut self, ctx: &Context) {
+        egui::SidePanel::left("key_log")
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    ui.heading("Key Log");
+                    if ui.button("Clear").clicked() {
+                        self.key_log.clear();
+                    }
+                });
+                ui.add_space(5.0);
+                let now = Instant::now();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for event in self.key_log.iter().rev() {
+                        let (arrow, color) = match event.event_type {
+                            KeyEventType::Press => ("↓", Palette::GREEN),
+                            KeyEventType::Release => ("↑", Palette::TEXT),
+                        };
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(arrow).color(color).strong());
+                            ui.monospace(&event.raw_label)
+                                .on_hover_text(format!("Key #{}", event.key_idx));
+                            ui.label(translate_token(&event.raw_label));
+                            ui.label(
+                                RichText::new(format_ago(
+                                    now.saturating_duration_since(event.timestamp),
+                                ))
+                                .small()
+                                .weak(),
+                            );
+                        });
+                    }
+                });
+            });
+    }
+
+    /// Show `token` on a key for this session, remembering the key's original token
+    fn override_key(&mut self, layer: usize, row: usize, col: usize, token: &str) {
+        let Some(previous) = self.set_key_token(layer, row, col, token) else {
+            return;
+        };
+        if previous == token {
+            return;
+        }
+        if self.undo_stack.len() == UNDO_LIMIT {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back((layer, row, col, previous));
+        self.redo_stack.clear();
+    }
+
+    /// Set a key's raw token and keep `manual_overrides` in step; returns the previous token
+    fn set_key_token(
+        &mut self,
+        layer: usize,
+        row: usize,
+        col: usize,
+        token: &str,
+    ) -> Option<String> {
+        let idx = self.state.index_for(row, col)?;
+        let previous = self.state.set_raw_token(layer, row, col, token)?;
+        let original = self
+            .manual_overrides
+            .entry((layer, idx))
+            .or_insert(previous.clone());
+        if original == token {
+            self.manual_overrides.remove(&(layer, idx));
+        }
+        Some(previous)
+    }
+
+    /// Revert the most recent key edit
+    fn undo(&mut self) {
+        if let Some((layer, row, col, previous)) = self.undo_stack.pop_back() {
+            if let Some(current) = self.set_key_token(layer, row, col, &previous) {
+                self.redo_stack.push((layer, row, col, current));
+            }
+        }
+    }
+
+    /// Apply the most recently undone key edit again
+    fn redo(&mut self) {
+        if let Some((layer, row, col, token)) = self.redo_stack.pop() {
+            if let Some(previous) = self.set_key_token(layer, row, col, &token) {
+                self.undo_stack.push_back((layer, row, col, previous));
+            }
+        }
+    }
+
+    /// Forget the key editor, overrides and undo history, e.g. when another keymap is shown
+    fn clear_key_edits(&mut self) {
+        self.key_editor = None;
+        self.manual_overrides.clear();
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// Put every overridden key back to its original token
+    fn reset_overrides(&mut self) {
+        let cols = self.state.keyboard.cols;
+        for ((layer, idx), original) in std::mem::take(&mut self.manual_overrides) {
+            self.state
+                .set_raw_token(layer, idx / cols, idx % cols, &original);
+        }
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// Token editor popup opened by clicking a key in edit mode
+    fn draw_key_editor(&mut self, ctx: &Context) {
+        let Some(editor) = &mut self.key_editor else {
+            return;
+        };
+        let mut confirm = false;
+        let mut cancel = false;
+        egui::Area::new(egui::Id::new("key_editor"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(editor.pos)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    let resp = ui.add(
+                        egui::TextEdit::singleline(&mut editor.token)
+                            .font(egui::TextStyle::Monospace)
+                            .desired_width(180.0),
+                    );
+                    if !editor.focus_requested {
+                        resp.request_focus();
+                        editor.focus_requested = true;
+                    }
+                    let (enter, escape) = ui.input(|i| {
+                        (
+                            i.key_pressed(egui::Key::Enter),
+                            i.key_pressed(egui::Key::Escape),
+                        )
+                    });
+                    ui.horizontal(|ui| {
+                        confirm = ui.button("OK").clicked() || (resp.lost_focus() && enter);
+                        cancel = ui.button("Cancel").clicked() || escape;
+                    });
+                });
+            });
+        if confirm {
+            let KeyEditor {
+                layer,
+                row,
+                col,
+                token,
+                ..
+            } = editor.clone();
+            self.override_key(layer, row, col, token.trim());
+        }
+        if confirm || cancel {
+            self.key_editor = None;
+        }
+    }
+
+    /// Paint one key (background, borders and labels) into `rect`
+    fn paint_key(
+        &self,
+        painter: &egui::Painter,
+        rect: egui::Rect,
+        layer_idx: usize,
+        r: usize,
+        c: usize,
+        font_id: &egui::FontId,
+    ) {
+        self.paint_key_face(painter, rect, layer_idx, r, c, font_id);
+        for &pinned in &self.config.pinned_layers {
+            let pinned = pinned as usize;
+            if pinned != layer_idx && pinned < self.state.keyboard.legends.len() {
+                self.paint_pinned_key(painter, rect, (layer_idx, pinned), r, c, font_id);
+            }
+        }
+    }
+
+    /// Overlay of a pinned layer's key: a faint fill, a dashed border and its label in the
+    /// corner, striped when the active layer also defines the key
+    fn paint_pinned_key(
+        &self,
+        painter: &egui::Painter,
+        rect: egui::Rect,
+        (layer_idx, pinned): (usize, usize),
+        r: usize,
+        c: usize,
+        font_id: &egui::FontId,
+    ) {
+        if self.state.is_transparent_key(pinned, r, c) {
+            return;
+        }
+        let inner = rect.shrink(3.0);
+        let radius = self.border_radius();
+        painter.rect_filled(inner, radius, Palette::YELLOW.gamma_multiply(0.3));
+        if !self.state.is_transparent_key(layer_idx, r, c) {
+            // Both layers define this key: hatch it so the conflict stands out
+            let stripes = painter.with_clip_rect(inner);
+            let stroke = egui::Stroke::new(1.0, Palette::YELLOW.gamma_multiply(0.4));
+            let step = 8.0;
+            let mut x = inner.left() - inner.height();
+            while x < inner.right() {
+                stripes.line_segment(
+                    [
+                        egui::pos2(x, inner.bottom()),
+                        egui::pos2(x + inner.height(), inner.top()),
+                    ],
+                    stroke,
+                );
+                x += step;
+            }
+        }
+        let corners = [
+            inner.left_top(),
+            inner.right_top(),
+            inner.right_bottom(),
+            inner.left_bottom(),
+            inner.left_top(),
+        ];
+        painter.extend(egui::Shape::dashed_line(
+            &corners,
+            egui::Stroke::new(1.0, Palette::YELLOW),
+            4.0,
+            3.0,
+        ));
+        let label = self.state.legend_at(pinned, r, c).unwrap_or_default();
+        painter.text(
+            inner.left_bottom() + Vec2::new(3.0, -2.0),
+            egui::Align2::LEFT_BOTTOM,
+            label,
+            egui::FontId::proportional(font_id.size * 0.6),
+            Palette::YELLOW,
+        );
+    }
+
+    fn paint_key_face(
+        &self,
+        painter: &egui::Painter,
+        rect: egui::Rect,
+        layer_idx: usize,
+        r: usize,
+        c: usize,
+        font_id: &egui::FontId,
+    ) {
+        // While searching, keys that do not match fade out
+        let mut painter = painter.clone();
+        let matched = match &self.search_matches {
+            Some(matches) => {
+                let hit = matches.contains(&(r, c));
+                if !hit {
+                    painter.multiply_opacity(0.4);
+                }
+                hit
+            }
+            None => false,
+        };
+        let painter = &painter;
+        let pressed = self.state.is_pressed(r, c);
+        let is_trns = self.state.is_transparent_key(layer_idx, r, c);
+        let is_fn = self.state.is_function_key(layer_idx, r, c);
+        let bg = if self.show_heatmap {
+            let layer = self.heatmap_per_layer.then_some(layer_idx);
+            let count = self
+                .state
+                .index_for(r, c)
+                .map_or(0, |idx| self.state.press_count(idx, layer));
+            heatmap_color(count, self.state.max_press_count(layer))
+        } else if is_trns {
+            Color32::from_rgba_unmultiplied(0, 0, 0, 0)
+        } else if pressed {
+            // Pressed color: start green; held MT keys switch to the MT border color
+            if self.state.is_mt_held(layer_idx, r, c, Instant::now()) {
+                Palette::PEACH
+            } else {
+                Palette::GREEN
+            }
+        } else {
+            Palette::OVERLAY
+        };
+        let radius = self.border_radius();
+        painter.rect_filled(rect.shrink(3.0), radius, bg);
+        if matched || self.focused_key == Some((layer_idx, r, c)) {
+            painter.rect_stroke(
+                rect.shrink(1.0),
+                6.0,
+                egui::Stroke::new(2.5, Palette::GREEN),
+            );
+        }
+        let overridden = self
+            .state
+            .index_for(r, c)
+            .is_some_and(|idx| self.manual_overrides.contains_key(&(layer_idx, idx)));
+        if overridden {
+            painter.text(
+                rect.right_top() + Vec2::new(-7.0, 5.0),
+                egui::Align2::RIGHT_TOP,
+                "✏",
+                egui::FontId::proportional(font_id.size * 0.6),
+                Palette::YELLOW,
+            );
+        }
+
+        // Colored border by function type (Catppuccin Mocha)
+        if is_fn {
+            let mt = self.state.is_mt_key(layer_idx, r, c);
+            let lt = self.state.is_lt_key(layer_idx, r, c);
+            let osl = self.state.is_osl_key(layer_idx, r, c);
+            // TO/DF change the default layer behavior, so they share the OSL color
+            let layer_switch =
+                self.state.is_to_key(layer_idx, r, c) || self.state.is_df_key(layer_idx, r, c);
+            let color = if mt {
+                Palette::PEACH
+            } else if lt {
+                Palette::BLUE
+            } else if osl || layer_switch {
+                Palette::YELLOW
+            } else {
+                Palette::TEXT
+            };
+            painter.rect_stroke(rect.shrink(2.5), radius, egui::Stroke { width: 1.2, color });
+            // Second line color will match this border color
+            let (main, sub) = self.state.display_parts(layer_idx, r, c);
+            // For MT: once held, swap main/sub display
+            let held_swap = self.state.is_mt_held(layer_idx, r, c, Instant::now());
+            let (big, small) = if held_swap { (sub, main) } else { (main, sub) };
+            if !big.is_empty() {
+                painter.text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    big,
+                    font_id.clone(),
+                    Color32::WHITE,
+                );
+            }
+            if !small.is_empty() {
+                let sub_pos = egui::pos2(rect.center().x, rect.center().y + 10.0);
+                painter.text(
+                    sub_pos,
+                    egui::Align2::CENTER_TOP,
+                    small,
+                    egui::FontId {
+                        size: font_id.size * 0.7,
+                        family: font_id.family.clone(),
+                    },
+                    color,
+                );
+            }
+            return; // already drew labels above
+        }
+
+        // Transparent keys: the key they inherit from a lower layer, smaller and muted so it
+        // doesn't read as assigned
+        if is_trns && self.config.show_effective_keys {
+            let below = layer_idx.checked_sub(1);
+            if let Some(layer) = below.and_then(|l| self.state.effective_layer_from(l, r, c)) {
+                let (main, _) = self.state.display_parts(layer, r, c);
+                painter.text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    main,
+                    egui::FontId {
+                        size: font_id.size * 0.8,
+                        family: font_id.family.clone(),
+                    },
+                    Palette::TEXT.linear_multiply(0.4),
+                );
+            }
+            return;
+        }
+
+        // Draw main and sub labels (for MT/LT, etc.)
+        let (main, sub) = self.state.display_parts(layer_idx, r, c);
+        if !main.is_empty() {
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                main,
+                font_id.clone(),
+                Color32::WHITE,
+            );
+        }
+        if !sub.is_empty() {
+            let sub_pos = egui::pos2(rect.center().x, rect.center().y + 10.0);
+            painter.text(
+                sub_pos,
+                egui::Align2::CENTER_TOP,
+                sub,
+                egui::FontId {
+                    size: font_id.size * 0.7,
+                    family: font_id.family.clone(),
+                },
+                Palette::TEXT,
+            );
+        }
+    }
+
+    fn export_report_dialog(&mut self) {
+        if let Some(file) = rfd::FileDialog::new()
+            .add_filter("HTML files", &["html"])
+            .set_file_name("keymap_report.html")
+            .set_title("Export keymap report")
+            .save_file()
+        {
+            match std::fs::write(&file, self.export_report()) {
+                Ok(()) => println!("✅ Exported keymap report to: {}", file.display()),
+                Err(e) => {
+                    eprintln!("❌ Failed to export keymap report: {}", e);
+                    self.push_toast(
+                        format!("Failed to export keymap report: {}", e),
+                        ToastKind::Error,
+                    );
+                }
+            }
+        }
+    }
+
+    fn export_svg_dialog(&mut self) {
+        let layer = self.state.active_layer as usize;
+        if let Some(file) = rfd::FileDialog::new()
+            .add_filter("SVG files", &["svg"])
+            .set_file_name(format!("keymap_layer_{}.svg", layer))
+            .set_title("Export layer as SVG")
+            .save_file()
+        {
+            match export_svg(&self.state, layer, &file.to_string_lossy()) {
+                Ok(()) => println!("✅ Exported SVG layer to: {}", file.display()),
+                Err(e) => {
+                    eprintln!("❌ Failed to export SVG layer: {}", e);
+                    self.push_toast(
+                        format!("Failed to export SVG layer: {}", e),
+                        ToastKind::Error,
+                    );
+                }
+            }
+        }
+    }
+
+    fn export_png_dialog(&mut self) {
+        let layer = self.state.active_layer as usize;
+        if let Some(file) = rfd::FileDialog::new()
+            .add_filter("PNG images", &["png"])
+            .set_file_name(format!("keymap_layer_{}.png", layer))
+            .set_title("Export layer as PNG")
+            .save_file()
+        {
+            let result = export_png(&self.state, layer, self.zoom(), self.show_legend)
+                .and_then(|bytes| Ok(std::fs::write(&file, bytes)?));
+            match result {
+                Ok(()) => println!("✅ Exported PNG layer to: {}", file.display()),
+                Err(e) => {
+                    eprintln!("❌ Failed to export PNG layer: {}", e);
+                    self.push_toast(
+                        format!("Failed to export PNG layer: {}", e),
+                        ToastKind::Error,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Open the compare view on the active layer and the one below it, or close it
+    fn toggle_compare(&mut self) {
+        self.compare = match self.compare {
+            Some(_) => None,
+            None => {
+                let active = self.state.active_layer as usize;
+                let other = if active == 0 { 1 } else { active - 1 };
+                let last = self.state.keyboard.legends.len().saturating_sub(1);
+                Some(LayerCompare {
+                    left: active.min(last),
+                    right: other.min(last),
+                    diff_only: false,
+                })
+            }
+        };
+    }
+
+    /// Compare a key of two layers by raw token: aliases of one keycode translate to the
+    /// same label but are still reported as a change
+    fn key_diff(&self, left: usize, right: usize, r: usize, c: usize) -> KeyDiff {
+        let token = |layer| self.state.raw_legend_at(layer, r, c).map(str::trim);
+        let left_trns = self.state.is_transparent_key(left, r, c);
+        let right_trns = self.state.is_transparent_key(right, r, c);
+        if token(left) == token(right) || (left_trns && right_trns) {
+            KeyDiff::Same
+        } else if left_trns || right_trns {
+            KeyDiff::Transparent
+        } else {
+            KeyDiff::Changed
+        }
+    }
+
+    /// Layer selectors and the two boards of the compare view
+    fn draw_compare_view(&mut self, ui: &mut egui::Ui, mut compare: LayerCompare) {
+        let names = self.state.keyboard.layer_names.clone();
+        let name = |layer: usize| {
+            names
+                .get(layer)
+                .cloned()
+                .unwrap_or_else(|| format!("Layer {}", layer))
+        };
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            for (salt, layer) in [
+                ("compare_left", &mut compare.left),
+                ("compare_right", &mut compare.right),
+            ] {
+                egui::ComboBox::from_id_salt(salt)
+                    .selected_text(name(*layer))
+                    .show_ui(ui, |ui| {
+                        for idx in 0..self.state.keyboard.legends.len() {
+                            ui.selectable_value(layer, idx, name(idx));
+                        }
+                    });
+            }
+            ui.checkbox(&mut compare.diff_only, "Diff only")
+                .on_hover_text("Hide keys that are the same on both layers");
+            ui.separator();
+            for diff in [KeyDiff::Same, KeyDiff::Changed, KeyDiff::Transparent] {
+                let label = match diff {
+                    KeyDiff::Same => "identical",
+                    KeyDiff::Changed => "changed",
+                    KeyDiff::Transparent => "transparent on one side",
+                };
+                ui.label(RichText::new(format!("■ {}", label)).color(diff.color()));
+            }
+        });
+        self.compare = Some(compare);
+
+        ui.add_space(20.0);
+        egui::ScrollArea::horizontal().show(ui, |ui| {
+            ui.horizontal_top(|ui| {
+                for (layer, other) in [(compare.left, compare.right), (compare.right, compare.left)]
+                {
+                    ui.vertical(|ui| {
+                        ui.label(RichText::new(name(layer)).strong());
+                        ui.add_space(5.0);
+                        self.draw_compare_board(ui, (layer, other), compare.diff_only);
+                    });
+                    ui.add_space(40.0);
+                }
+            });
+        });
+    }
+
+    /// One board of the compare view: keys of `layer` colored by how they differ from
+    /// `other`; pressed keys keep an outline so live presses stay visible
+    fn draw_compare_board(
+        &self,
+        ui: &mut egui::Ui,
+        (layer, other): (usize, usize),
+        diff_only: bool,
+    ) {
+        let (rows, cols) = (self.state.keyboard.rows, self.state.keyboard.cols);
+        let spacing = self.key_spacing();
+        let key_size = self.key_size();
+        let unit = key_size.x + spacing;
+        let mut font_id = ui.style().text_styles[&egui::TextStyle::Body].clone();
+        font_id.size *= self.config.label_scale * self.zoom();
+
+        let units: Vec<(usize, egui::Rect)> = match self
+            .state
+            .keyboard
+            .physical_positions
+            .as_ref()
+            .filter(|_| self.use_physical_layout())
+        {
+            Some(positions) => positions
+                .iter()
+                .map(KeyPosition::placed)
+                .enumerate()
+                .take(rows * cols)
+                .map(|(idx, p)| {
+                    let min = egui::pos2(p.x * unit, p.y * unit);
+                    (
+                        idx,
+                        egui::Rect::from_min_size(min, Vec2::new(p.w, p.h) * unit),
+                    )
+                })
+                .collect(),
+            None => (0..rows * cols)
+                .filter_map(|idx| {
+                    let (r, c) = (idx / cols, idx % cols);
+                    let (grid_col, right_half) = self.state.keyboard.grid_column(r, c)?;
+                    let gap = if right_half {
+                        self.grid_split_gap()
+                    } else {
+                        0.0
+                    };
+                    let min = egui::pos2(grid_col as f32 * unit + gap, r as f32 * unit);
+                    Some((idx, egui::Rect::from_min_size(min, Vec2::splat(unit))))
+                })
+                .collect(),
+        };
+        let board = units
+            .iter()
+            .fold(Vec2::ZERO, |acc, (_, rect)| acc.max(rect.max.to_vec2()));
+        let (board_rect, _) = ui.allocate_exact_size(board, Sense::hover());
+        let painter = ui.painter();
+        let radius = self.border_radius();
+        for (idx, rect) in units {
+            let (r, c) = (idx / cols, idx % cols);
+            let rect = rect
+                .translate(board_rect.min.to_vec2())
+                .shrink(spacing / 2.0);
+            let diff = self.key_diff(layer, other, r, c);
+            if diff_only && diff == KeyDiff::Same {
+                continue;
+            }
+            let color = diff.color();
+            painter.rect_filled(rect.shrink(3.0), radius, color.gamma_multiply(0.25));
+            painter.rect_stroke(rect.shrink(2.5), radius, egui::Stroke::new(1.2, color));
+            if self.state.is_pressed(r, c) {
+                painter.rect_stroke(
+                    rect.shrink(1.0),
+                    radius,
+                    egui::Stroke::new(2.5, Color32::WHITE),
+                );
+            }
+            let (main, sub) = self.state.display_parts(layer, r, c);
+            if !main.is_empty() {
+                painter.text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    main,
+                    font_id.clone(),
+                    Color32::WHITE,
+                );
+            }
+            if !sub.is_empty() {
+                painter.text(
+                    egui::pos2(rect.center().x, rect.center().y + 10.0),
+                    egui::Align2::CENTER_TOP,
+                    sub,
+                    egui::FontId {
+                        size: font_id.size * 0.7,
+                        family: font_id.family.clone(),
+                    },
+                    Palette::TEXT,
+                );
+            }
+        }
+    }
+
+    /// Search field above the keyboard; Enter focuses the next match and Escape clears it
+    fn draw_search_bar(&mut self, ui: &mut egui::Ui, layer_idx: usize) {
+        ui.horizontal(|ui| {
+            ui.label("🔍");
+            let resp = ui.add(
+                egui::TextEdit::singleline(&mut self.search_query)
+                    .hint_text("Search keys")
+                    .desired_width(240.0),
+            );
+            if std::mem::take(&mut self.search_focus_requested) {
+                resp.request_focus();
+            }
+            if resp.changed() {
+                self.search_cursor = 0;
+            }
+            let matches = self.state.find_key_by_label(&self.search_query, layer_idx);
+            let (enter, escape) = ui.input(|i| {
+                (
+                    i.key_pressed(egui::Key::Enter),
+                    i.key_pressed(egui::Key::Escape),
+                )
+            });
+            if escape {
+                self.search_query.clear();
+                self.search_open = false;
+                self.search_matches = None;
+                return;
+            }
+            if enter && resp.lost_focus() {
+                if !matches.is_empty() {
+                    let (row, col) = matches[self.search_cursor % matches.len()];
+                    self.focused_key = Some((layer_idx, row, col));
+                    self.scroll_to_focus = true;
+                    self.search_cursor += 1;
+                }
+                resp.request_focus();
+            }
+            if self.search_query.trim().is_empty() {
+                self.search_matches = None;
+            } else {
+                let mut label = match matches.len() {
+                    1 => "1 match".to_string(),
+                    n => format!("{} matches", n),
+                };
+                let elsewhere = self
+                    .state
+                    .find_key_by_label_all_layers(&self.search_query)
+                    .len()
+                    - matches.len();
+                if elsewhere > 0 {
+                    label.push_str(&format!(" ({} on other layers)", elsewhere));
+                }
+                ui.label(RichText::new(label).color(Palette::TEXT.gamma_multiply(0.7)));
+                self.search_matches = Some(matches);
+            }
+        });
+    }
+
+    /// One widget per encoder bound on `layer_idx`: the counter-clockwise keycode on the
+    /// left of the knob, the clockwise one on the right
+    fn draw_encoders(&self, ui: &mut egui::Ui, layer_idx: usize) {
+        let mut actions: Vec<&EncoderAction> = self
+            .encoder_map
+            .iter()
+            .filter(|a| a.layer == layer_idx)
+            .collect();
+        if actions.is_empty() {
+            return;
+        }
+        actions.sort_by_key(|a| a.encoder);
+        let size = Vec2::new(220.0, 56.0);
+        let font_id = ui.style().text_styles[&egui::TextStyle::Body].clone();
+        let label = |tok: &str| match translate_token(tok) {
+            t if t.is_empty() => "▼".to_string(),
+            t => t,
+        };
+        ui.horizontal(|ui| {
+            let total = size.x * actions.len() as f32;
+            ui.add_space(((ui.available_width() - total) / 2.0).max(0.0));
+            for action in actions {
+                let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+                let painter = ui.painter();
+                let center = rect.center();
+                let radius = 20.0;
+                painter.circle_filled(center, radius, Palette::OVERLAY);
+                painter.circle_stroke(center, radius, egui::Stroke::new(1.5, Palette::BLUE));
+                painter.text(
+                    center,
+                    egui::Align2::CENTER_CENTER,
+                    format!("E{}", action.encoder),
+                    font_id.clone(),
+                    Palette::TEXT,
+                );
+                painter.text(
+                    center - Vec2::new(radius + 6.0, 0.0),
+                    egui::Align2::RIGHT_CENTER,
+                    format!("{} ↺", label(&action.counter_clockwise)),
+                    font_id.clone(),
+                    Color32::WHITE,
+                );
+                painter.text(
+                    center + Vec2::new(radius + 6.0, 0.0),
+                    egui::Align2::LEFT_CENTER,
+                    format!("↻ {}", label(&action.clockwise)),
+                    font_id.clone(),
+                    Color32::WHITE,
+                );
+            }
+        });
+    }
+
+    /// Hover tooltip with the key's raw token and, for transparent keys, the layers it
+    /// falls through
+    fn key_tooltip(&self, ui: &mut egui::Ui, r: usize, c: usize) {
+        let chain = self.state.transparent_chain(r, c);
+        if chain.len() <= 1 {
+            ui.monospace(chain.first().map_or("", |(_, raw)| raw.as_str()));
+            return;
+        }
+        for (layer, raw) in &chain {
+            let name = self
+                .state
+                .keyboard
+                .layer_names
+                .get(*layer)
+                .map_or_else(|| layer.to_string(), |n| n.clone());
+            let token = if matches!(raw.as_str(), "KC_TRNS" | "KC_TRANSPARENT" | "_______") {
+                "▼ transparent"
+            } else {
+                raw.as_str()
+            };
+            ui.horizontal(|ui| {
+                ui.label(RichText::new(name).strong());
+                ui.monospace(token);
+            });
+        }
+    }
+
+    /// Scroll to `rect` if it holds the key focused by the search
+    fn scroll_to_focused(
+        &mut self,
+        ui: &egui::Ui,
+        rect: egui::Rect,
+        layer_idx: usize,
+        r: usize,
+        c: usize,
+    ) {
+        if self.scroll_to_focus && self.focused_key == Some((layer_idx, r, c)) {
+            ui.scroll_to_rect(rect, None);
+            self.scroll_to_focus = false;
+        }
+    }
+
+    /// Layer list with rename, reorder, duplicate and delete controls
+    fn draw_layers_panel(&mut self, ctx: &Context) {
+        let mut actions = Vec::new();
+        let layout = &self.state.keyboard;
+        let count = layout.raw_legends.len();
+        egui::SidePanel::left("layers")
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.add_space(10.0);
+                ui.heading("Layers");
+                ui.label(format!("{} / {} layers", count, MAX_LAYERS));
+                ui.add_space(5.0);
+                for idx in 0..count {
+                    ui.horizontal(|ui| {
+                        let active = self.state.active_layer as usize == idx;
+                        if ui.selectable_label(active, idx.to_string()).clicked() {
+                            actions.push(LayerAction::Select(idx));
+                        }
+                        let mut name = layout.layer_names.get(idx).cloned().unwrap_or_default();
+                        if ui
+                            .add(egui::TextEdit::singleline(&mut name).desired_width(100.0))
+                            .changed()
+                        {
+                            actions.push(LayerAction::Rename(idx, name));
+                        }
+                        if ui.add_enabled(idx > 0, egui::Button::new("⏶")).clicked() {
+                            actions.push(LayerAction::MoveUp(idx));
+                        }
+                        if ui
+                            .add_enabled(idx + 1 < count, egui::Button::new("⏷"))
+                            .clicked()
+                        {
+                            actions.push(LayerAction::MoveDown(idx));
+                        }
+                        if ui
+                            .add_enabled(count < MAX_LAYERS, egui::Button::new("⧉"))
+                            .on_hover_text("Duplicate")
+                            .clicked()
+                        {
+                            actions.push(LayerAction::Duplicate(idx));
+                        }
+                        if ui
+                            .add_enabled(count > 1, egui::Button::new("🗑"))
+                            .on_hover_text("Delete")
+                            .clicked()
+                        {
+                            actions.push(LayerAction::Remove(idx));
+                        }
+                        let pinned = self.config.pinned_layers.contains(&(idx as u8));
+                        if ui
+                            .add(egui::Button::new("📌").selected(pinned))
+                            .on_hover_text("Pin Layer: show it on top of the active layer")
+                            .clicked()
+                        {
+                            actions.push(LayerAction::TogglePin(idx));
+                        }
+                    });
+                }
+                ui.add_space(5.0);
+                if ui
+                    .add_enabled(count < MAX_LAYERS, egui::Button::new("Add Layer"))
+                    .clicked()
+                {
+                    actions.push(LayerAction::Add);
+                }
+                ui.add_space(10.0);
+                if ui
+                    .add_enabled(self.keyboard_loaded, egui::Button::new("Save Changes"))
+                    .clicked()
+                {
+                    actions.push(LayerAction::Save);
+                }
+            });
+        for action in actions {
+            self.apply_layer_action(action);
+        }
+    }
+
+    fn apply_layer_action(&mut self, action: LayerAction) {
+        let active = self.state.active_layer as usize;
+        if !matches!(
+            action,
+            LayerAction::Select(_)
+                | LayerAction::Rename(..)
+                | LayerAction::Save
+                | LayerAction::TogglePin(_)
+        ) {
+            // Overrides are tracked by layer index, so structural edits keep them as part of
+            // the keymap instead of leaving indexes that may no longer match
+            self.clear_key_edits();
+        }
+        let layout = &mut self.state.keyboard;
+        let count = layout.raw_legends.len();
+        let result = match action {
+            LayerAction::Select(idx) => {
+                self.state.set_layer(idx as u8);
+                Ok(())
+            }
+            LayerAction::Rename(idx, name) => {
+                self.rename_layer(idx, name);
+                Ok(())
+            }
+            LayerAction::MoveUp(idx) | LayerAction::MoveDown(idx) => {
+                let other = if matches!(action, LayerAction::MoveUp(_)) {
+                    idx.wrapping_sub(1)
+                } else {
+                    idx + 1
+                };
+                let result = layout.swap_layers(idx, other);
+                // The viewed layer moves with its data
+                if result.is_ok() && active == idx {
+                    self.state.set_layer(other as u8);
+                } else if result.is_ok() && active == other {
+                    self.state.set_layer(idx as u8);
+                }
+                if result.is_ok() {
+                    self.remap_layer_refs(|l| {
+                        Some(match l {
+                            l if l == idx => other,
+                            l if l == other => idx,
+                            l => l,
+                        })
+                    });
+                }
+                result
+            }
+            LayerAction::Duplicate(idx) if count < MAX_LAYERS => {
+                let name = layout
+                    .layer_names
+                    .get(idx)
+                    .map_or_else(|| format!("Layer {}", count), |n| format!("{} copy", n));
+                layout.add_layer(name);
+                layout.clone_layer(idx, count);
+                Ok(())
+            }
+            LayerAction::Add if count < MAX_LAYERS => {
+                layout.add_layer(format!("Layer {}", count));
+                Ok(())
+            }
+            LayerAction::Remove(idx) => {
+                let result = layout.remove_layer(idx);
+                if result.is_ok() && active >= idx && active > 0 {
+                    self.state.set_layer(active as u8 - 1);
+                }
+                if result.is_ok() {
+                    self.remap_layer_refs(|l| match l.cmp(&idx) {
+                        std::cmp::Ordering::Less => Some(l),
+                        std::cmp::Ordering::Equal => None,
+                        std::cmp::Ordering::Greater => Some(l - 1),
+                    });
+                }
+                result
+            }
+            LayerAction::Save => {
+                self.save_layer_changes();
+                Ok(())
+            }
+            LayerAction::TogglePin(idx) => {
+                self.toggle_pinned_layer(idx as u8);
+                Ok(())
+            }
+            LayerAction::Duplicate(_) | LayerAction::Add => Err(anyhow::anyhow!(
+                "a keymap has at most {} layers",
+                MAX_LAYERS
+            )),
+        };
+        if let Err(e) = result {
+            self.push_toast(e.to_string(), ToastKind::Warning);
+        }
+    }
+
+    /// Follow a change of layer order in the encoder bindings and pinned layers, which
+    /// refer to layers by index; references to a layer mapped to `None` are dropped
+    fn remap_layer_refs(&mut self, map: impl Fn(usize) -> Option<usize>) {
+        self.encoder_map
+            .retain_mut(|action| match map(action.layer) {
+                Some(layer) => {
+                    action.layer = layer;
+                    true
+                }
+                None => false,
+            });
+        let pinned: Vec<u8> = self
+            .config
+            .pinned_layers
+            .iter()
+            .filter_map(|&l| map(l as usize))
+            .map(|l| l as u8)
+            .collect();
+        if pinned != self.config.pinned_layers {
+            self.update_config(|c| c.pinned_layers = pinned.clone());
+        }
+    }
+
+    fn rename_layer(&mut self, idx: usize, name: String) {
+        let names = &mut self.state.keyboard.layer_names;
+        while names.len() <= idx {
+            names.push(format!("Layer {}", names.len()));
+        }
+        names[idx] = name;
+    }
+
+    /// Current layers (with their names and any edits) as a keymap config
+    fn edited_keymap_config(&self) -> KeymapConfig {
+        let base = self.keymap_config.as_ref();
+        KeymapConfig {
+            keyboard: self
+                .keyboard_name
+                .clone()
+                .unwrap_or_else(|| "keyboard".to_string()),
+            keymap: base.map_or_else(|| "default".to_string(), |c| c.keymap.clone()),
+            layers: self.state.keyboard.raw_legends.clone(),
+            layout: base.and_then(|c| c.layout.clone()),
+            layer_names: Some(self.state.keyboard.layer_names.clone()),
+            encoder_map: self.encoder_map.clone(),
+            custom_keycodes: base.map(|c| c.custom_keycodes.clone()).unwrap_or_default(),
+        }
+    }
+
+    /// Write the edited keymap over the saved keymap copy restored on the next start
+    fn save_layer_changes(&mut self) {
+        let config = self.edited_keymap_config();
+        // The edits are still those of the keymap loaded from the source path
+        let saved = serde_json::to_string_pretty(&config)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| save_keymap_json(&json, self.source_path.as_deref()));
+        match saved {
+            Ok(path) => {
+                println!("✅ Saved keymap changes to: {}", path);
+                self.keymap_config = Some(config);
+                self.push_toast("Saved changes".to_string(), ToastKind::Success);
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to save keymap changes: {}", e);
+                self.push_toast(
+                    format!("Failed to save keymap changes: {}", e),
+                    ToastKind::Error,
+                );
+            }
+        }
+    }
+
+    /// Scale of the keyboard view; fullscreen fits the board to the window instead
+    fn zoom(&self) -> f32 {
+        if self.fullscreen {
+            1.0
+        } else {
+            self.config.zoom.clamp(MIN_ZOOM, MAX_ZOOM)
+        }
+    }
+
+    /// Size of one 1U key in the keyboard view
+    fn key_size(&self) -> Vec2 {
+        Vec2::splat(self.config.key_size_px * self.zoom())
+    }
+
+    fn key_spacing(&self) -> f32 {
+        self.config.key_spacing_px * self.zoom()
+    }
+
+    fn border_radius(&self) -> f32 {
+        self.config.border_radius_px * self.zoom()
+    }
+
+    /// Extra horizontal space between the halves of a split keyboard in the grid view
+    fn grid_split_gap(&self) -> f32 {
+        match &self.state.keyboard.split {
+            Some(split) if !self.use_physical_layout() => split.gap_px * self.zoom(),
+            _ => 0.0,
         }
     }
 
-    fn load_keymap_from_path(&mut self, path: &str) -> bool {
-        match std::fs::read_to_string(path) {
-            Ok(content) => {
-                // Determine file type by extension
-                let is_json = path.ends_with(".json");
-                let is_c = path.ends_with(".c") || path.ends_with(".h");
+    /// Compact zoom slider for the top bar, saved when a drag ends
+    fn draw_zoom_slider(&mut self, ui: &mut egui::Ui) {
+        let resp = ui
+            .add(
+                egui::Slider::new(&mut self.config.zoom, MIN_ZOOM..=MAX_ZOOM)
+                    .step_by(ZOOM_STEP as f64)
+                    .custom_formatter(|v, _| format!("{:.0}%", v * 100.0))
+                    .custom_parser(|s| {
+                        let percent: f64 = s.trim().trim_end_matches('%').trim().parse().ok()?;
+                        Some(percent / 100.0)
+                    })
+                    .text("Zoom"),
+            )
+            .on_hover_text("Scale the keyboard view");
+        if resp.drag_stopped() || (resp.changed() && !resp.dragged()) {
+            let zoom = self.config.zoom;
+            self.update_config(|c| c.zoom = zoom);
+        }
+    }
 
-                let result = if is_json {
-                    // Try to parse as JSON
-                    match serde_json::from_str::<KeymapConfig>(&content) {
-                        Ok(config) => {
-                            // Update the keyboard state with new layout
-                            self.state = KeyboardState::new(config.to_keyboard_layout());
-                            println!("✅ Successfully loaded JSON keymap from: {}", path);
-                            true
-                        }
-                        Err(e) => {
-                            eprintln!("❌ Failed to parse JSON keymap: {}", e);
-                            false
-                        }
-                    }
-                } else if is_c {
-                    // Try to parse as C keymap
-                    match crate::keymap_c::parse_keymap_c(&content) {
-                        Ok(config) => {
-                            // Update the keyboard state with new layout
-                            self.state = KeyboardState::new(config.to_keyboard_layout());
-                            println!("✅ Successfully loaded C keymap from: {}", path);
-                            true
-                        }
-                        Err(e) => {
-                            eprintln!("❌ Failed to parse C keymap: {}", e);
-                            false
-                        }
-                    }
-                } else {
-                    eprintln!("❌ Unsupported file type. Please use .json, .c, or .h files.");
-                    false
-                };
+    /// Size of the keyboard in key units: the physical layout bounds or the grid dimensions
+    fn board_units(&self) -> Vec2 {
+        match &self.state.keyboard.physical_positions {
+            Some(positions) if self.use_physical_layout() => positions
+                .iter()
+                .map(KeyPosition::placed)
+                .fold(Vec2::ZERO, |acc, p| {
+                    acc.max(Vec2::new(p.x + p.w, p.y + p.h))
+                }),
+            _ => Vec2::new(
+                self.state.keyboard.cols as f32,
+                self.state.keyboard.rows as f32,
+            ),
+        }
+    }
 
-                if result {
-                    // Save the keymap file
-                    if let Err(e) = save_keymap_file(path) {
-                        eprintln!("⚠️ Failed to save keymap file: {}", e);
-                    }
-                    self.keyboard_loaded = true;
+    /// Key geometry sliders; the view follows them live and they are saved when a drag ends
+    fn draw_settings_panel(&mut self, ctx: &Context) {
+        let mut commit = false;
+        egui::SidePanel::left("settings")
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.add_space(10.0);
+                ui.heading("Settings");
+                ui.add_space(5.0);
+                let config = &mut self.config;
+                for (label, value, range, suffix) in [
+                    ("Key Size", &mut config.key_size_px, 32.0..=80.0, " px"),
+                    ("Spacing", &mut config.key_spacing_px, 2.0..=16.0, " px"),
+                    (
+                        "Border Radius",
+                        &mut config.border_radius_px,
+                        0.0..=12.0,
+                        " px",
+                    ),
+                    ("Label Scale", &mut config.label_scale, 0.8..=1.5, "×"),
+                ] {
+                    let resp = ui.add(egui::Slider::new(value, range).text(label).suffix(suffix));
+                    commit |= resp.drag_stopped() || (resp.changed() && !resp.dragged());
                 }
+            });
+        if commit {
+            self.save_key_geometry();
+        }
+    }
 
-                result
+    /// Store the slider values of the settings panel
+    fn save_key_geometry(&mut self) {
+        let (size, spacing, radius, scale) = (
+            self.config.key_size_px,
+            self.config.key_spacing_px,
+            self.config.border_radius_px,
+            self.config.label_scale,
+        );
+        self.update_config(|c| {
+            c.key_size_px = size;
+            c.key_spacing_px = spacing;
+            c.border_radius_px = radius;
+            c.label_scale = scale;
+        });
+    }
+
+    fn run_shortcut(&mut self, shortcut: Shortcut) {
+        // Counted as usize: stepping in u8 overflows past layer 255
+        let layer_count = self.state.keyboard.layer_names.len().max(1);
+        match shortcut {
+            Shortcut::OpenFile if !self.is_loading() => self.open_file_dialog(),
+            Shortcut::Unload if self.keyboard_loaded => self.unload_keyboard(),
+            Shortcut::ExportSvg if self.keyboard_loaded => self.export_svg_dialog(),
+            Shortcut::ToggleDebug => self.show_debug = !self.show_debug,
+            Shortcut::ToggleLegend => self.show_legend = !self.show_legend,
+            Shortcut::ToggleTextarea => self.show_textarea = !self.show_textarea,
+            Shortcut::ToggleFullscreen => self.fullscreen = !self.fullscreen,
+            Shortcut::Undo if self.keyboard_loaded => self.undo(),
+            Shortcut::Redo if self.keyboard_loaded => self.redo(),
+            Shortcut::ToggleSearch if self.keyboard_loaded => {
+                self.search_open = !self.search_open;
+                self.search_focus_requested = self.search_open;
+                if !self.search_open {
+                    self.search_matches = None;
+                }
             }
-            Err(e) => {
-                eprintln!("❌ Failed to read file '{}': {}", path, e);
-                false
+            Shortcut::JumpToLayer(layer) if (layer as usize) < layer_count => {
+                self.state.set_layer(layer)
+            }
+            // Layer stepping is for mock mode; real input sources report the layer themselves
+            #[cfg(not(any(feature = "rawhid", feature = "qmk_console")))]
+            Shortcut::NextLayer => {
+                self.state
+                    .set_layer(((self.state.active_layer as usize + 1) % layer_count) as u8);
+            }
+            #[cfg(not(any(feature = "rawhid", feature = "qmk_console")))]
+            Shortcut::PreviousLayer => {
+                self.state.set_layer(
+                    ((self.state.active_layer as usize + layer_count - 1) % layer_count) as u8,
+                );
             }
+            _ => {}
         }
     }
 
-    fn unload_keyboard(&mut self) {
-        if let Err(e) = clear_saved_keymap() {
-            eprintln!("⚠️ Failed to clear saved keymap: {}", e);
+    fn export_csv_dialog(&mut self) {
+        if let Some(file) = rfd::FileDialog::new()
+            .add_filter("CSV files", &["csv"])
+            .set_file_name("keymap.csv")
+            .set_title("Export keymap as CSV")
+            .save_file()
+        {
+            match std::fs::write(&file, export_csv(&self.state)) {
+                Ok(()) => println!("✅ Exported CSV keymap to: {}", file.display()),
+                Err(e) => {
+                    eprintln!("❌ Failed to export CSV keymap: {}", e);
+                    self.push_toast(
+                        format!("Failed to export CSV keymap: {}", e),
+                        ToastKind::Error,
+                    );
+                }
+            }
         }
-        self.keyboard_loaded = false;
-        // Reset to default Planck layout
-        self.state = KeyboardState::new(crate::keyboards::planck::PlanckLayout::planck_default());
-    }
-
-    pub fn set_keyboard_loaded(&mut self, loaded: bool) {
-        self.keyboard_loaded = loaded;
     }
 
-    fn open_file_dialog(&mut self) {
-        // Use rfd to open file dialog synchronously
+    fn export_html_dialog(&mut self) {
         if let Some(file) = rfd::FileDialog::new()
-            .add_filter("Keymap files", &["json", "c", "h"])
-            .add_filter("JSON files", &["json"])
-            .add_filter("C files", &["c", "h"])
-            .set_title("Select keymap file")
-            .pick_file()
+            .add_filter("HTML files", &["html"])
+            .set_file_name("keymap.html")
+            .set_title("Export keymap as HTML")
+            .save_file()
         {
-            if let Some(path_str) = file.to_str() {
-                self.load_keymap_from_path(path_str);
+            let html = export_html(&self.state, &Theme::default());
+            match std::fs::write(&file, html) {
+                Ok(()) => println!("✅ Exported HTML keymap to: {}", file.display()),
+                Err(e) => {
+                    eprintln!("❌ Failed to export HTML keymap: {}", e);
+                    self.push_toast(
+                        format!("Failed to export HTML keymap: {}", e),
+                        ToastKind::Error,
+                    );
+                }
             }
         }
     }
@@ -170,20 +3149,47 @@ impl KeyboardViewerApp {
 
 impl eframe::App for KeyboardViewerApp {
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
-        // Drain any pending reports
+        self.draw(ctx);
+    }
+}
+
+impl KeyboardViewerApp {
+    /// Draw one frame of the UI
+    fn draw(&mut self, ctx: &Context) {
+        self.poll_file_watcher();
+        self.poll_pending_load();
+
+        // Probe glyph support once fonts are loaded (and again after a new keymap is loaded)
+        if self.state.supported_glyphs.is_none() {
+            self.state.supported_glyphs = Some(probe_glyph_support(ctx));
+        }
+
+        // Drain any pending reports, logging every press and release in between
         while let Ok(rep) = self.rx.try_recv() {
-            self.state.set_layer(rep.active_layer);
-            self.state.set_pressed_bits(rep.pressed_bits);
+            let previous = self.state.pressed_bits;
+            self.state.apply_report(&rep);
+            self.record_key_events(previous, Instant::now());
+        }
+        self.poll_connection_status();
+        #[cfg(feature = "via")]
+        self.poll_via_keymap();
+        // Scripted layer changes win over the layer of the last report
+        #[cfg(feature = "ipc")]
+        self.poll_ipc();
+
+        let was_fullscreen = self.fullscreen;
+        let text_focused = ctx.wants_keyboard_input();
+        for shortcut in ctx.input_mut(|i| Shortcut::from_input(i, text_focused)) {
+            self.run_shortcut(shortcut);
         }
 
         #[cfg(not(any(feature = "rawhid", feature = "qmk_console")))]
         {
             // In mock mode, use manual pressed keys
-            let mut bits = 0u64;
-            for &idx in &self.manual_pressed {
-                bits |= 1u64 << idx;
-            }
+            let bits: crate::hid::PressedBits = self.manual_pressed.iter().copied().collect();
+            let previous = self.state.pressed_bits;
             self.state.set_pressed_bits(bits);
+            self.record_key_events(previous, Instant::now());
         }
 
         let layer_idx = self.state.active_layer as usize;
@@ -206,11 +3212,34 @@ impl eframe::App for KeyboardViewerApp {
                     ui.label(
                         RichText::new(format!("{} (#{})", layer_name.clone(), layer_idx)).strong(),
                     );
+                    if let Some(watcher) = &self.file_watcher {
+                        ui.add_space(10.0);
+                        ui.label(RichText::new("auto-reload: ON").small().color(Palette::GREEN))
+                            .on_hover_text(format!(
+                                "Reloading when {} changes",
+                                watcher.path().display()
+                            ));
+                    }
 
                     // Right side: Buttons
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                         ui.add_space(10.0); // Right padding
 
+                        if ui
+                            .add(
+                                egui::Button::new("⛶")
+                                    .selected(self.fullscreen)
+                                    .fill(Palette::OVERLAY)
+                                    .stroke(egui::Stroke::new(1.0, Palette::TEXT))
+                                    .rounding(egui::Rounding::same(6.0))
+                                    .min_size(egui::Vec2::new(30.0, 30.0)),
+                            )
+                            .on_hover_text("Toggle fullscreen (F11)")
+                            .clicked()
+                        {
+                            self.fullscreen = !self.fullscreen;
+                        }
+
                         #[cfg(not(any(feature = "rawhid", feature = "qmk_console")))]
                         {
                             if ui
@@ -276,22 +3305,345 @@ impl eframe::App for KeyboardViewerApp {
                             self.show_legend = !self.show_legend;
                         }
 
-                        let debug_btn = "Debug";
-                        if ui
-                            .add(
-                                egui::Button::new(debug_btn)
-                                    .fill(Palette::OVERLAY)
-                                    .stroke(egui::Stroke::new(1.0, Palette::TEXT))
-                                    .rounding(egui::Rounding::same(6.0))
-                                    .min_size(egui::Vec2::new(60.0, 30.0)),
-                            )
-                            .clicked()
-                        {
-                            self.show_debug = !self.show_debug;
-                        }
+                        let mut show_physical = self.config.show_physical_layout;
+                        let toggle = ui.checkbox(&mut show_physical, "Physical Layout");
+                        let toggle = if show_physical
+                            && self.state.keyboard.physical_positions.is_none()
+                        {
+                            toggle.on_hover_text(
+                                "No physical layout data is loaded for this keyboard; showing the grid",
+                            )
+                        } else {
+                            toggle
+                        };
+                        if toggle.changed() {
+                            self.set_show_physical_layout(show_physical);
+                        }
+
+                        let mut show_split = self.config.show_split;
+                        if ui
+                            .add_enabled(
+                                self.use_physical_layout(),
+                                egui::Checkbox::new(&mut show_split, "Show Split"),
+                            )
+                            .on_hover_text("Mark the left and right halves of a split keyboard")
+                            .changed()
+                        {
+                            self.set_show_split(show_split);
+                        }
+
+                        let mut show_effective = self.config.show_effective_keys;
+                        if ui
+                            .checkbox(&mut show_effective, "Show inherited keys")
+                            .on_hover_text("Label transparent keys with the key from a lower layer")
+                            .changed()
+                        {
+                            self.set_show_effective_keys(show_effective);
+                        }
+
+                        ui.toggle_value(&mut self.show_heatmap, "Heatmap")
+                            .on_hover_text("Color keys by how often they were pressed");
+                        if self.show_heatmap {
+                            ui.checkbox(&mut self.heatmap_per_layer, "Per Layer")
+                                .on_hover_text("Only count presses made on the displayed layer");
+                            if ui
+                                .button("Reset")
+                                .on_hover_text("Forget all counted key presses")
+                                .clicked()
+                            {
+                                self.state.reset_heatmap();
+                            }
+                        }
+
+                        let mut merge_mode = self.config.merge_mode;
+                        if ui
+                            .checkbox(&mut merge_mode, "Merge Layers")
+                            .on_hover_text(
+                                "Reloading a single-layer keymap replaces only that layer",
+                            )
+                            .changed()
+                        {
+                            self.set_merge_mode(merge_mode);
+                        }
+
+                        let current = self.state.keyboard_locale;
+                        let mut selected = current;
+                        egui::ComboBox::from_id_salt("keyboard_locale")
+                            .selected_text(selected.map_or("US", KeyboardLocale::name))
+                            .show_ui(ui, |ui| {
+                                for locale in KeyboardLocale::ALL {
+                                    ui.selectable_value(&mut selected, Some(locale), locale.name());
+                                }
+                            })
+                            .response
+                            .on_hover_text("OS keyboard layout used to label letter keys");
+                        if selected != current {
+                            self.set_keyboard_locale(selected);
+                        }
+
+                        self.draw_zoom_slider(ui);
+
+                        let debug_btn = "Debug";
+                        if ui
+                            .add(
+                                egui::Button::new(debug_btn)
+                                    .fill(Palette::OVERLAY)
+                                    .stroke(egui::Stroke::new(1.0, Palette::TEXT))
+                                    .rounding(egui::Rounding::same(6.0))
+                                    .min_size(egui::Vec2::new(60.0, 30.0)),
+                            )
+                            .clicked()
+                        {
+                            self.show_debug = !self.show_debug;
+                        }
+
+                        if ui
+                            .add(
+                                egui::Button::new("Log")
+                                    .selected(self.show_log)
+                                    .fill(Palette::OVERLAY)
+                                    .stroke(egui::Stroke::new(1.0, Palette::TEXT))
+                                    .rounding(egui::Rounding::same(6.0))
+                                    .min_size(egui::Vec2::new(50.0, 30.0)),
+                            )
+                            .on_hover_text("Recent key presses and releases")
+                            .clicked()
+                        {
+                            self.show_log = !self.show_log;
+                        }
+
+                        if ui
+                            .add(
+                                egui::Button::new("Settings")
+                                    .selected(self.show_settings)
+                                    .fill(Palette::OVERLAY)
+                                    .stroke(egui::Stroke::new(1.0, Palette::TEXT))
+                                    .rounding(egui::Rounding::same(6.0))
+                                    .min_size(egui::Vec2::new(70.0, 30.0)),
+                            )
+                            .clicked()
+                        {
+                            self.show_settings = !self.show_settings;
+                        }
+
+                        if !self.keyboard_loaded
+                            && ui
+                                .add(
+                                    egui::Button::new("Load Folder…")
+                                        .fill(Palette::OVERLAY)
+                                        .stroke(egui::Stroke::new(1.0, Palette::TEXT))
+                                        .rounding(egui::Rounding::same(6.0))
+                                        .min_size(egui::Vec2::new(90.0, 30.0)),
+                                )
+                                .clicked()
+                            && !self.is_loading()
+                        {
+                            self.open_folder_dialog();
+                        }
+
+                        if !self.keyboard_loaded
+                            && ui
+                                .add(
+                                    egui::Button::new("Load URL…")
+                                        .fill(Palette::OVERLAY)
+                                        .stroke(egui::Stroke::new(1.0, Palette::TEXT))
+                                        .rounding(egui::Rounding::same(6.0))
+                                        .min_size(egui::Vec2::new(90.0, 30.0)),
+                                )
+                                .clicked()
+                            && !self.is_loading()
+                        {
+                            self.url_input = Some(String::new());
+                        }
+
+                        if self.keyboard_loaded
+                            && ui
+                                .add_enabled(
+                                    self.profile_source().is_some(),
+                                    egui::Button::new("Save as profile…")
+                                        .fill(Palette::OVERLAY)
+                                        .stroke(egui::Stroke::new(1.0, Palette::TEXT))
+                                        .rounding(egui::Rounding::same(6.0))
+                                        .min_size(egui::Vec2::new(110.0, 30.0)),
+                                )
+                                .on_disabled_hover_text("Only keymaps loaded from a file can be saved")
+                                .clicked()
+                        {
+                            self.profile_name_input = Some(String::new());
+                        }
+
+                        if !self.profiles.is_empty() {
+                            let current = self
+                                .profiles
+                                .iter()
+                                .find(|p| self.source_path.as_deref() == Some(p.path.as_str()))
+                                .map_or("Profiles", |p| p.name.as_str());
+                            let mut open = None;
+                            let mut delete = None;
+                            egui::ComboBox::from_id_salt("profiles")
+                                .selected_text(current)
+                                .show_ui(ui, |ui| {
+                                    for profile in &self.profiles {
+                                        ui.horizontal(|ui| {
+                                            if ui
+                                                .small_button("🗑")
+                                                .on_hover_text("Delete this profile")
+                                                .clicked()
+                                            {
+                                                delete = Some(profile.name.clone());
+                                            }
+                                            if ui
+                                                .selectable_label(
+                                                    profile.name == current,
+                                                    &profile.name,
+                                                )
+                                                .clicked()
+                                            {
+                                                open = Some(profile.name.clone());
+                                            }
+                                        });
+                                    }
+                                })
+                                .response
+                                .on_hover_text("Load a saved keymap profile");
+                            if let Some(name) = open {
+                                if !self.is_loading() {
+                                    self.open_profile(&name);
+                                }
+                            }
+                            if let Some(name) = delete {
+                                self.remove_profile(&name);
+                            }
+                        }
+
+                        // Unload button (only show when keyboard is loaded)
+                        if self.keyboard_loaded {
+                            if ui
+                                .add(
+                                    egui::Button::new("Export HTML")
+                                        .fill(Palette::OVERLAY)
+                                        .stroke(egui::Stroke::new(1.0, Palette::TEXT))
+                                        .rounding(egui::Rounding::same(6.0))
+                                        .min_size(egui::Vec2::new(90.0, 30.0)),
+                                )
+                                .clicked()
+                            {
+                                self.export_html_dialog();
+                            }
+
+                            if ui
+                                .add(
+                                    egui::Button::new("Save as SVG…")
+                                        .fill(Palette::OVERLAY)
+                                        .stroke(egui::Stroke::new(1.0, Palette::TEXT))
+                                        .rounding(egui::Rounding::same(6.0))
+                                        .min_size(egui::Vec2::new(100.0, 30.0)),
+                                )
+                                .on_hover_text("Export the current layer (Ctrl+S)")
+                                .clicked()
+                            {
+                                self.export_svg_dialog();
+                            }
+
+                            if ui
+                                .add(
+                                    egui::Button::new("Save as PNG…")
+                                        .fill(Palette::OVERLAY)
+                                        .stroke(egui::Stroke::new(1.0, Palette::TEXT))
+                                        .rounding(egui::Rounding::same(6.0))
+                                        .min_size(egui::Vec2::new(100.0, 30.0)),
+                                )
+                                .on_hover_text("Export the current layer as an image")
+                                .clicked()
+                            {
+                                self.export_png_dialog();
+                            }
+
+                            if ui
+                                .add(
+                                    egui::Button::new("Export CSV")
+                                        .fill(Palette::OVERLAY)
+                                        .stroke(egui::Stroke::new(1.0, Palette::TEXT))
+                                        .rounding(egui::Rounding::same(6.0))
+                                        .min_size(egui::Vec2::new(90.0, 30.0)),
+                                )
+                                .clicked()
+                            {
+                                self.export_csv_dialog();
+                            }
+
+                            if ui
+                                .add(
+                                    egui::Button::new("Export Report…")
+                                        .fill(Palette::OVERLAY)
+                                        .stroke(egui::Stroke::new(1.0, Palette::TEXT))
+                                        .rounding(egui::Rounding::same(6.0))
+                                        .min_size(egui::Vec2::new(110.0, 30.0)),
+                                )
+                                .clicked()
+                            {
+                                self.export_report_dialog();
+                            }
+
+                            if ui
+                                .add(
+                                    egui::Button::new("Layers")
+                                        .selected(self.show_layers_panel)
+                                        .fill(Palette::OVERLAY)
+                                        .stroke(egui::Stroke::new(1.0, Palette::TEXT))
+                                        .rounding(egui::Rounding::same(6.0))
+                                        .min_size(egui::Vec2::new(60.0, 30.0)),
+                                )
+                                .clicked()
+                            {
+                                self.show_layers_panel = !self.show_layers_panel;
+                            }
+
+                            if ui
+                                .add(
+                                    egui::Button::new("Compare")
+                                        .selected(self.compare.is_some())
+                                        .fill(Palette::OVERLAY)
+                                        .stroke(egui::Stroke::new(1.0, Palette::TEXT))
+                                        .rounding(egui::Rounding::same(6.0))
+                                        .min_size(egui::Vec2::new(70.0, 30.0)),
+                                )
+                                .on_hover_text("Show two layers side by side")
+                                .clicked()
+                            {
+                                self.toggle_compare();
+                            }
+
+                            if ui
+                                .add(
+                                    egui::Button::new("Edit Keys")
+                                        .selected(self.edit_keys)
+                                        .fill(Palette::OVERLAY)
+                                        .stroke(egui::Stroke::new(1.0, Palette::TEXT))
+                                        .rounding(egui::Rounding::same(6.0))
+                                        .min_size(egui::Vec2::new(80.0, 30.0)),
+                                )
+                                .on_hover_text("Click a key to try another keycode on it")
+                                .clicked()
+                            {
+                                self.edit_keys = !self.edit_keys;
+                                self.key_editor = None;
+                            }
+
+                            if !self.manual_overrides.is_empty()
+                                && ui
+                                    .add(
+                                        egui::Button::new("Reset Overrides")
+                                            .fill(Palette::OVERLAY)
+                                            .stroke(egui::Stroke::new(1.0, Palette::TEXT))
+                                            .rounding(egui::Rounding::same(6.0))
+                                            .min_size(egui::Vec2::new(110.0, 30.0)),
+                                    )
+                                    .clicked()
+                            {
+                                self.reset_overrides();
+                            }
 
-                        // Unload button (only show when keyboard is loaded)
-                        if self.keyboard_loaded {
                             ui.separator();
                             if ui
                                 .add(
@@ -310,7 +3662,35 @@ impl eframe::App for KeyboardViewerApp {
                 });
             });
 
-        if self.show_debug {
+        if self.keyboard_loaded && (self.keyboard_name.is_some() || self.source_path.is_some()) {
+            self.draw_info_row(ctx);
+        }
+        self.draw_layout_banner(ctx);
+        self.draw_layer_files_preview(ctx);
+        self.draw_url_dialog(ctx);
+        self.draw_profile_dialog(ctx);
+        self.draw_custom_grid_dialog(ctx);
+        self.draw_key_editor(ctx);
+        if self.connection_rx.is_some() {
+            self.draw_status_bar(ctx);
+        }
+
+        if self.show_settings {
+            self.draw_settings_panel(ctx);
+        }
+        if self.show_layers_panel && self.keyboard_loaded {
+            self.draw_layers_panel(ctx);
+        }
+
+        if self.fullscreen != was_fullscreen {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(self.fullscreen));
+        }
+
+        if self.show_log && self.keyboard_loaded && !self.fullscreen {
+            self.draw_key_log(ctx);
+        }
+
+        if self.show_debug && !self.fullscreen {
             egui::SidePanel::right("debug")
                 .resizable(true)
                 .show(ctx, |ui| {
@@ -319,12 +3699,32 @@ impl eframe::App for KeyboardViewerApp {
                     ui.add_space(5.0);
                     ui.label(format!("Active layer index: {}", layer_idx));
                     ui.monospace(format!("Pressed bits: 0x{:012X}", self.state.pressed_bits));
-                    let mut pressed_indices: Vec<usize> =
-                        (0..(self.state.keyboard.rows * self.state.keyboard.cols).min(64))
-                            .filter(|i| ((self.state.pressed_bits >> i) & 1) == 1)
-                            .collect();
-                    pressed_indices.sort_unstable();
+                    let pressed_indices: Vec<usize> = self.state.iter_pressed_indices().collect();
                     ui.monospace(format!("Pressed indices: {:?}", pressed_indices));
+                    let total_presses: u64 = self.state.press_counts.iter().sum();
+                    ui.label(format!("Presses this session: {}", total_presses));
+                    if let Some(split) = &mut self.state.keyboard.split {
+                        ui.add(
+                            egui::Slider::new(&mut split.gap_px, 0.0..=200.0)
+                                .text("Split Gap")
+                                .suffix(" px"),
+                        );
+                    }
+                    ui.add_space(10.0);
+
+                    // Unused (transparent) keys per layer; click to jump to the first one
+                    ui.label(RichText::new("Unused keys").strong());
+                    for (layer, name) in self.state.keyboard.layer_names.clone().iter().enumerate()
+                    {
+                        let unused = self.state.keyboard.unused_keys_in_layer(layer);
+                        let label = format!("{}: {}", name, unused.len());
+                        if ui.link(label).clicked() {
+                            if let Some(&(row, col)) = unused.first() {
+                                self.state.set_layer(layer as u8);
+                                self.focused_key = Some((layer, row, col));
+                            }
+                        }
+                    }
                     ui.add_space(10.0);
                 });
         }
@@ -348,19 +3748,64 @@ impl eframe::App for KeyboardViewerApp {
                         Palette::_SURFACE
                     };
 
+                    let drop_hover = ctx.input(|i| DropHover::from_hovered(&i.raw.hovered_files));
+
                     ui.painter().rect_filled(rect, 10.0, bg_color);
-                    ui.painter().rect_stroke(rect, 10.0, egui::Stroke::new(2.0, Palette::TEXT));
+                    match &drop_hover {
+                        Some(hover) => {
+                            // Pulse the border between 2 and 4 px while a file is dragged over the window
+                            let t = self.started_at.elapsed().as_secs_f32();
+                            let width = 3.0 + (t * 6.0).sin();
+                            let color = match hover {
+                                DropHover::Supported(_) => Palette::GREEN,
+                                DropHover::Unsupported(_) => Palette::PEACH,
+                            };
+                            ui.painter().rect_stroke(rect, 10.0, egui::Stroke::new(width, color));
+                            ctx.request_repaint();
+                        }
+                        None => {
+                            ui.painter().rect_stroke(rect, 10.0, egui::Stroke::new(2.0, Palette::TEXT));
+                        }
+                    }
 
+                    if let (Some(hover), false) = (&drop_hover, self.is_loading()) {
+                        let (text, color) = match hover {
+                            DropHover::Supported(name) => (format!("Release to load\n{}", name), Palette::GREEN),
+                            DropHover::Unsupported(name) => (format!("Unsupported file type\n{}", name), Palette::PEACH),
+                        };
+                        ui.painter().text(
+                            rect.center(),
+                            egui::Align2::CENTER_CENTER,
+                            text,
+                            egui::FontId::proportional(24.0),
+                            color,
+                        );
+                    } else if self.is_loading() {
+                        // Indeterminate progress while the keymap is parsed in the background
+                        let t = ui.input(|i| i.time);
+                        let progress = (t % 1.0) as f32;
+                        let bar_rect = egui::Rect::from_center_size(
+                            rect.center(),
+                            egui::vec2(rect.width() * 0.5, 24.0),
+                        );
+                        ui.put(bar_rect, egui::ProgressBar::new(progress).animate(true).text("Loading keymap..."));
+                        ctx.request_repaint_after(Duration::from_millis(50));
+                    } else {
                     // Draw text
-                    let text = "Drop your keymap file here\nor click to browse\n(.json, keymap.c, keymap.h)";
-                    let text_color = if response.hovered() {
+                    let text = match &self.load_error {
+                        Some(err) => format!("{}\n\nDrop your keymap file here\nor click to browse\n(.json, keymap.c, keymap.h)", err),
+                        None => "Drop your keymap file here\nor click to browse\n(.json, keymap.c, keymap.h)".to_string(),
+                    };
+                    let text_color = if self.load_error.is_some() {
+                        Palette::PEACH
+                    } else if response.hovered() {
                         Palette::GREEN
                     } else {
                         Palette::TEXT
                     };
 
                     let text_galley = ui.painter().layout(
-                        text.to_string(),
+                        text,
                         egui::FontId::proportional(24.0),
                         text_color,
                         rect.width() - 40.0
@@ -372,23 +3817,52 @@ impl eframe::App for KeyboardViewerApp {
                     );
 
                     ui.painter().galley(text_pos, text_galley, text_color);
+                    }
 
-                    // Handle dropped files
-                    if let Some(dropped_files) = ui.input(|i| i.raw.dropped_files.first().cloned()) {
-                        if let Some(path) = &dropped_files.path {
-                            if let Some(path_str) = path.to_str() {
-                                self.load_keymap_from_path(path_str);
-                            }
+                    // Handle dropped files; several files at once become one layer each
+                    let dropped: Vec<String> = ui.input(|i| {
+                        i.raw
+                            .dropped_files
+                            .iter()
+                            .filter_map(|f| f.path.as_ref()?.to_str().map(str::to_string))
+                            .collect()
+                    });
+                    match dropped.as_slice() {
+                        [] => {}
+                        [path] => self.load_keymap_from_path(path),
+                        _ => {
+                            let max_files = self.state.keyboard.layer_names.len().max(1);
+                            let path = dropped[0].clone();
+                            self.start_load(path, move |_| {
+                                Ok(Loaded::LayerFiles(LayerFilesPreview::new(dropped, max_files)))
+                            });
                         }
                     }
 
+                    // Keymap text pasted from the clipboard
+                    let pasted = ui.input(|i| {
+                        i.events.iter().find_map(|e| match e {
+                            egui::Event::Paste(text) => Some(text.clone()),
+                            _ => None,
+                        })
+                    });
+                    if let (Some(text), false) = (pasted, self.is_loading()) {
+                        self.load_from_clipboard(&text);
+                    }
+
                     // Handle click to browse
-                    if response.clicked() {
+                    if response.clicked() && !self.is_loading() {
                         self.open_file_dialog();
                     }
                 });
+            } else if let Some(compare) = self.compare {
+                self.draw_compare_view(ui, compare);
             } else {
                 // Show keyboard
+                if self.search_open {
+                    ui.add_space(10.0);
+                    self.draw_search_bar(ui, layer_idx);
+                }
             // Ajouter plus d'espace autour du clavier
             ui.add_space(20.0);
 
@@ -398,139 +3872,132 @@ impl eframe::App for KeyboardViewerApp {
 
                 // Conteneur avec padding pour le clavier
                 egui::Frame::none()
-                    .inner_margin(egui::Margin::same(30.0))
+                    .inner_margin(egui::Margin::same(30.0 * self.zoom()))
                     .show(ui, |ui| {
                         let rows = self.state.keyboard.rows;
                         let cols = self.state.keyboard.cols;
-                        let key_size = Vec2::new(56.0, 56.0);
-                        let spacing_y = 8.0;
+                        let spacing_y = self.key_spacing();
+                        let key_size = if self.fullscreen {
+                            // Leave room for the frame margin and padding below the board
+                            let available = ui.available_size()
+                                - Vec2::new(FULLSCREEN_MARGIN + self.grid_split_gap(), FULLSCREEN_MARGIN);
+                            Vec2::splat(fit_key_size(available, self.board_units(), spacing_y))
+                        } else {
+                            self.key_size()
+                        };
                         let mut font_id = ui.style().text_styles[&egui::TextStyle::Body].clone();
-                        font_id.size *= 1.4;
+                        font_id.size *= self.config.label_scale * self.zoom();
 
-            // Track press start times for color transition (MT keys after 2s)
             let total_keys = rows * cols;
-            for i in 0..total_keys.min(64) { // Limit to 64 bits to avoid overflow
-                let pressed = ((self.state.pressed_bits >> i) & 1) == 1;
-                if pressed {
-                    self.pressed_started.entry(i).or_insert_with(Instant::now);
-                } else {
-                    self.pressed_started.remove(&i);
-                }
-            }
-
-            for r in 0..rows {
-				ui.horizontal(|ui| {
-					for c in 0..cols {
-						let pressed = self.state.is_pressed(r, c);
-						let is_trns = self.state.is_transparent_key(layer_idx, r, c);
-						let is_fn = self.state.is_function_key(layer_idx, r, c);
-						let resp = ui.add_sized(key_size, egui::Label::new(" ").sense(Sense::click()));
-						let rect = resp.rect;
-
-						#[cfg(not(any(feature = "rawhid", feature = "qmk_console")))]
-						{
-							if resp.clicked() {
-								if let Some(idx) = self.state.index_for(r, c) {
-									if self.manual_pressed.contains(&idx) {
-										self.manual_pressed.remove(&idx);
-									} else {
-										self.manual_pressed.insert(idx);
-									}
-								}
-							}
-						}
-                        let bg = if is_trns {
-                            Color32::from_rgba_unmultiplied(0, 0, 0, 0)
-                        } else if pressed {
-                            // Pressed color: start green; for MT keys after 2s switch to border color
-                            let idx = self.state.index_for(r, c).unwrap_or(usize::MAX);
-                            let mt = self.state.is_mt_key(layer_idx, r, c);
-                            if mt {
-                                if let Some(t0) = self.pressed_started.get(&idx) {
-                                    if t0.elapsed() >= Duration::from_millis(500) {
-                                        Palette::PEACH
-                                    } else {
-                                        Palette::GREEN
-                                    }
-                                } else {
-                                    Palette::GREEN
-                                }
+            let physical = if self.use_physical_layout() {
+                self.state.keyboard.physical_positions.as_ref().map(|positions| {
+                    positions.iter().map(KeyPosition::placed).collect::<Vec<_>>()
+                })
+            } else {
+                None
+            };
+            if let Some(positions) = physical {
+                // Physical layout: paint every key at its position and hit test the pointer
+                let unit = key_size.x + spacing_y;
+                let board = positions.iter().fold(Vec2::ZERO, |acc, p| {
+                    acc.max(Vec2::new((p.x + p.w) * unit, (p.y + p.h) * unit))
+                });
+                let (board_rect, resp) = ui.allocate_exact_size(board, Sense::click());
+                if let Some(split) = split_line_x(&positions).filter(|_| self.config.show_split) {
+                    // Halves on slightly different backgrounds with a dashed line between them
+                    let split_x = board_rect.min.x + split * unit;
+                    let (left, right) = (
+                        egui::Rect::from_x_y_ranges(board_rect.min.x..=split_x, board_rect.y_range()),
+                        egui::Rect::from_x_y_ranges(split_x..=board_rect.max.x, board_rect.y_range()),
+                    );
+                    ui.painter().rect_filled(left, self.border_radius(), Palette::OVERLAY);
+                    ui.painter().rect_filled(right, self.border_radius(), Palette::MANTLE);
+                    ui.painter().extend(egui::Shape::dashed_line(
+                        &[egui::pos2(split_x, board_rect.min.y), egui::pos2(split_x, board_rect.max.y)],
+                        egui::Stroke::new(1.0, Palette::TEXT.gamma_multiply(0.5)),
+                        6.0,
+                        4.0,
+                    ));
+                }
+                let hit = |pos: egui::Pos2| {
+                    let local = pos - board_rect.min;
+                    self.state.keyboard.key_at_position(local.x, local.y, unit)
+                };
+                let hovered = resp.hover_pos().and_then(hit);
+                if resp.clicked() {
+                    if let Some(pos) = resp.interact_pointer_pos() {
+                        if let Some((r, c)) = hit(pos) {
+                            if self.edit_keys {
+                                self.open_key_editor(layer_idx, r, c, pos);
                             } else {
-                                Palette::GREEN
-                            }
-                        } else {
-                            Palette::OVERLAY
-                        };
-						ui.painter().rect_filled(rect.shrink(3.0), 6.0, bg);
-
-                        // Colored border by function type (Catppuccin Mocha)
-                        if is_fn {
-                            let mt = self.state.is_mt_key(layer_idx, r, c);
-                            let lt = self.state.is_lt_key(layer_idx, r, c);
-                            let osl = self.state.is_osl_key(layer_idx, r, c);
-                            let color = if mt { Palette::PEACH } else if lt { Palette::BLUE } else if osl { Palette::YELLOW } else { Palette::TEXT };
-                            ui.painter().rect_stroke(rect.shrink(2.5), 6.0, egui::Stroke { width: 1.2, color });
-                            // Second line color will match this border color
-                            let (main, sub) = self.state.display_parts(layer_idx, r, c);
-                            // For MT: after 1s hold, swap main/sub display
-                            let idx = self.state.index_for(r, c).unwrap_or(usize::MAX);
-                            let held_swap = mt && self.pressed_started.get(&idx).map(|t0| t0.elapsed() >= Duration::from_millis(500)).unwrap_or(false);
-                            let (big, small) = if held_swap { (sub.clone(), main.clone()) } else { (main.clone(), sub.clone()) };
-                            if !big.is_empty() {
-                                ui.painter().text(
-                                    rect.center(),
-                                    egui::Align2::CENTER_CENTER,
-                                    big,
-                                    font_id.clone(),
-                                    Color32::WHITE,
-                                );
-                            }
-                            if !small.is_empty() && !osl {
-                                let sub_pos = egui::pos2(rect.center().x, rect.center().y + 10.0);
-                                ui.painter().text(
-                                    sub_pos,
-                                    egui::Align2::CENTER_TOP,
-                                    small,
-                                    egui::FontId { size: font_id.size * 0.7, family: font_id.family.clone() },
-                                    color,
-                                );
+                                #[cfg(not(any(feature = "rawhid", feature = "qmk_console")))]
+                                self.toggle_manual_press(r, c);
                             }
-                            continue; // already drew labels above
-                        }
-
-                        // Draw main and sub labels (for MT/LT, etc.)
-                        let (main, sub) = self.state.display_parts(layer_idx, r, c);
-                        if !main.is_empty() {
-                            ui.painter().text(
-                                rect.center(),
-                                egui::Align2::CENTER_CENTER,
-                                main,
-                                font_id.clone(),
-                                Color32::WHITE,
-                            );
-                        }
-                        if !sub.is_empty() {
-                            let sub_pos = egui::pos2(rect.center().x, rect.center().y + 10.0);
-                            ui.painter().text(
-                                sub_pos,
-                                egui::Align2::CENTER_TOP,
-                                sub,
-                                egui::FontId { size: font_id.size * 0.7, family: font_id.family.clone() },
-                                Palette::TEXT,
-                            );
                         }
                     }
-                        });
-                        ui.add_space(spacing_y);
+                }
+                for (idx, p) in positions.iter().enumerate().take(total_keys) {
+                    let (r, c) = (idx / cols, idx % cols);
+                    let rect = egui::Rect::from_min_size(
+                        board_rect.min + Vec2::new(p.x * unit, p.y * unit),
+                        Vec2::new(p.w * unit, p.h * unit),
+                    )
+                    .shrink(spacing_y / 2.0);
+                    self.paint_key(ui.painter(), rect, layer_idx, r, c, &font_id);
+                    self.scroll_to_focused(ui, rect, layer_idx, r, c);
+                    if hovered == Some((r, c)) {
+                        resp.clone().on_hover_ui_at_pointer(|ui| self.key_tooltip(ui, r, c));
+                        ui.painter().rect_stroke(rect.shrink(1.0), self.border_radius(), egui::Stroke::new(1.0, Palette::TEXT));
                     }
+                }
+            } else {
+                for r in 0..rows {
+                    ui.horizontal(|ui| {
+                        // Keys in a row are as far apart as the rows
+                        ui.spacing_mut().item_spacing.x = spacing_y;
+                        let mut past_gap = false;
+                        for c in 0..cols {
+                            let Some((grid_col, right_half)) = self.state.keyboard.grid_column(r, c)
+                            else {
+                                continue;
+                            };
+                            if c == 0 && grid_col > 0 {
+                                // Thumb keys start under the inner columns
+                                ui.add_space(grid_col as f32 * (key_size.x + spacing_y));
+                            }
+                            if right_half && !past_gap {
+                                ui.add_space(self.grid_split_gap());
+                                past_gap = true;
+                            }
+                            let resp = ui.add_sized(key_size, egui::Label::new(" ").sense(Sense::click()));
+
+                            if resp.clicked() {
+                                if self.edit_keys {
+                                    self.open_key_editor(layer_idx, r, c, resp.rect.left_bottom());
+                                } else {
+                                    #[cfg(not(any(feature = "rawhid", feature = "qmk_console")))]
+                                    self.toggle_manual_press(r, c);
+                                }
+                            }
+                            self.paint_key(ui.painter(), resp.rect, layer_idx, r, c, &font_id);
+                            self.scroll_to_focused(ui, resp.rect, layer_idx, r, c);
+                            resp.on_hover_ui(|ui| self.key_tooltip(ui, r, c));
+                        }
+                    });
+                    ui.add_space(spacing_y);
+                }
+            }
                 });
 
+                if !self.encoder_map.is_empty() {
+                    self.draw_encoders(ui, layer_idx);
+                }
                 ui.add_space(20.0);
             });
             }
 
             // Legend and text input under the keyboard (outside the centered container)
-            if self.keyboard_loaded && (self.show_legend || self.show_textarea) {
+            if self.keyboard_loaded && !self.fullscreen && (self.show_legend || self.show_textarea) {
                 ui.add_space(20.0);
                 ui.horizontal(|ui| {
                     // Legend on the left (if enabled)
@@ -552,7 +4019,17 @@ impl eframe::App for KeyboardViewerApp {
                                 };
                                 row(ui, Palette::PEACH, "MT(mod, key)", "");
                                 row(ui, Palette::BLUE, "LT(layer, key)", "");
-                                row(ui, Palette::YELLOW, "OSL ★", "");
+                                row(ui, Palette::YELLOW, "OSL ★ (★★ armed)", "");
+                                row(ui, Palette::YELLOW, "TO / DF(layer)", "");
+                                ui.add_space(10.0);
+                                ui.label(RichText::new("Shortcuts").strong());
+                                egui::Grid::new("shortcuts").num_columns(2).show(ui, |ui| {
+                                    for (keys, action) in SHORTCUT_HELP {
+                                        ui.monospace(*keys);
+                                        ui.label(*action);
+                                        ui.end_row();
+                                    }
+                                });
                                 ui.add_space(10.0);
                             });
                         });
@@ -573,6 +4050,25 @@ impl eframe::App for KeyboardViewerApp {
                                     .desired_width(ui.available_width())
                                     .desired_rows(8)
                                     .hint_text("Type here to test your keyboard layout..."));
+                                ui.add_space(5.0);
+
+                                // Typing speed measured from the text area itself
+                                let now = Instant::now();
+                                self.wpm_history.record(now, self.text_input.chars().count());
+                                self.wpm_history.tick(now);
+                                ui.label(RichText::new(format!("WPM: {:.0}", self.wpm_history.wpm(now))).strong());
+                                let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width().min(240.0), 30.0), egui::Sense::hover());
+                                let values: Vec<f32> = self.wpm_history.sparkline().collect();
+                                let max = values.iter().copied().fold(1.0f32, f32::max);
+                                let bar_w = rect.width() / WpmHistory::SPARKLINE_LEN as f32;
+                                for (i, v) in values.iter().enumerate() {
+                                    let h = (v / max) * rect.height();
+                                    let bar = egui::Rect::from_min_max(
+                                        egui::pos2(rect.left() + i as f32 * bar_w, rect.bottom() - h),
+                                        egui::pos2(rect.left() + (i as f32 + 1.0) * bar_w - 1.0, rect.bottom()),
+                                    );
+                                    ui.painter().rect_filled(bar, 1.0, Palette::GREEN);
+                                }
                                 ui.add_space(10.0);
                             });
                         });
@@ -581,6 +4077,1003 @@ impl eframe::App for KeyboardViewerApp {
             }
         });
 
+        // Toast notifications, newest at the bottom
+        self.prune_toasts(Instant::now());
+        if !self.toasts.is_empty() {
+            egui::Area::new(egui::Id::new("toasts"))
+                .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-20.0, -20.0))
+                .show(ctx, |ui| {
+                    for toast in &self.toasts {
+                        let color = match toast.kind {
+                            ToastKind::Error => Palette::RED,
+                            ToastKind::Warning => Palette::YELLOW,
+                            ToastKind::Success => Palette::GREEN,
+                        };
+                        egui::Frame::popup(ui.style())
+                            .stroke(egui::Stroke::new(1.0, color))
+                            .show(ui, |ui| {
+                                ui.label(RichText::new(&toast.message).color(color).strong());
+                            });
+                        ui.add_space(6.0);
+                    }
+                });
+        }
+
         ctx.request_repaint_after(std::time::Duration::from_millis(16));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glyph_probe_is_consistent() {
+        let ctx = Context::default();
+        let _ = ctx.run(egui::RawInput::default(), |_| {});
+        let first = probe_glyph_support(&ctx);
+        let second = probe_glyph_support(&ctx);
+        assert_eq!(first, second);
+    }
+
+    /// Point the config, data and cache dirs at a temporary folder, so tests that save
+    /// settings or keymaps leave the user's own files alone
+    fn use_temp_app_dirs() {
+        static INIT: std::sync::Once = std::sync::Once::new();
+        INIT.call_once(|| {
+            let root = std::env::temp_dir().join(format!("qmk_viewer_ui_{}", std::process::id()));
+            std::env::set_var("XDG_CONFIG_HOME", root.join("config"));
+            std::env::set_var("XDG_DATA_HOME", root.join("data"));
+            std::env::set_var("XDG_CACHE_HOME", root.join("cache"));
+        });
+    }
+
+    fn headless_app() -> (Context, KeyboardViewerApp) {
+        use_temp_app_dirs();
+        let ctx = Context::default();
+        let (_tx, rx) = mpsc::channel();
+        let state = KeyboardState::new(crate::keyboards::planck::PlanckLayout::planck_default());
+        let mut app = KeyboardViewerApp::with_context(&ctx, state, rx);
+        app.set_keyboard_loaded(false);
+        (ctx, app)
+    }
+
+    #[test]
+    fn test_slow_load_shows_progress_then_keyboard() {
+        let (ctx, mut app) = headless_app();
+        app.start_load_with("slow_load_test.json".to_string(), |_| {
+            std::thread::sleep(Duration::from_millis(100));
+            Ok(KeymapConfig {
+                keyboard: "test".to_string(),
+                keymap: "test".to_string(),
+                layers: vec![vec!["KC_Q".to_string(); 12]],
+                layout: None,
+                layer_names: Some(vec!["Only".to_string()]),
+                encoder_map: Vec::new(),
+                custom_keycodes: CustomKeycodeTable::default(),
+            })
+        });
+
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+        assert!(app.is_loading());
+        assert!(!app.keyboard_loaded);
+
+        std::thread::sleep(Duration::from_millis(200));
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+        assert!(!app.is_loading());
+        assert!(app.keyboard_loaded);
+        assert_eq!(app.state.keyboard.layer_names, vec!["Only"]);
+        // The fake path cannot be copied to the config dir, so a warning may come first
+        let success = app.toasts.iter().find(|t| t.kind == ToastKind::Success);
+        assert_eq!(
+            success.map(|t| t.message.as_str()),
+            Some("Loaded: slow_load_test.json")
+        );
+    }
+
+    #[test]
+    fn test_export_report_sections_and_title() {
+        let (_ctx, mut app) = headless_app();
+        app.set_source("thooams".to_string(), "/tmp/thooams.json".to_string());
+        let html = app.export_report();
+        let layers = app.state.keyboard.legends.len();
+        assert_eq!(html.matches("<section").count(), layers);
+        let title_re = regex::Regex::new(r"<title>([^<]*)</title>").unwrap();
+        let title = &title_re.captures(&html).unwrap()[1];
+        assert!(title.contains("thooams"));
+    }
+
+    #[test]
+    fn test_physical_layout_branch() {
+        let (_ctx, mut app) = headless_app();
+        // Without physical data the toggle alone keeps the grid
+        let positions = app.state.keyboard.physical_positions.take();
+        app.config.show_physical_layout = true;
+        assert!(!app.use_physical_layout());
+
+        // The default Planck layout comes with a uniform grid of positions
+        assert!(positions.is_some());
+        app.state.keyboard.physical_positions = positions;
+        assert!(app.use_physical_layout());
+
+        app.config.show_physical_layout = false;
+        assert!(!app.use_physical_layout());
+    }
+
+    #[test]
+    fn test_set_active_layer_by_name() {
+        let (_ctx, mut app) = headless_app();
+        // Planck defaults: Base, Lower, Raise, Adjust
+        assert!(app.set_active_layer_by_name("raise"));
+        assert_eq!(app.state.active_layer, 2);
+        assert!(app.set_active_layer_by_name("ADJUST"));
+        assert_eq!(app.state.active_layer, 3);
+
+        assert!(!app.set_active_layer_by_name("NAV"));
+        assert_eq!(app.state.active_layer, 3);
+    }
+
+    #[test]
+    fn test_truncate_path_left() {
+        let short = "/home/me/keymap.c";
+        assert_eq!(truncate_path_left(short, 40), short);
+
+        let long = "/home/me/qmk_firmware/keyboards/planck/keymaps/thooams/keymap.c";
+        let truncated = truncate_path_left(long, 40);
+        assert_eq!(truncated.chars().count(), 40);
+        assert!(truncated.starts_with('…'));
+        assert!(truncated.ends_with("keymaps/thooams/keymap.c"));
+        assert!(long.ends_with(truncated.trim_start_matches('…')));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tilde_home_matches_whole_components() {
+        let home = Some(std::path::Path::new("/home/al"));
+        assert_eq!(tilde_home("/home/al/x/keymap.c", home), "~/x/keymap.c");
+        assert_eq!(tilde_home("/home/alice/x", home), "/home/alice/x");
+        assert_eq!(tilde_home("/home/al", home), "/home/al");
+        assert_eq!(tilde_home("/home/al/x", None), "/home/al/x");
+    }
+
+    fn shortcuts_for(
+        key: egui::Key,
+        modifiers: egui::Modifiers,
+        text_focused: bool,
+    ) -> Vec<Shortcut> {
+        let ctx = Context::default();
+        let raw = egui::RawInput {
+            modifiers,
+            events: vec![egui::Event::Key {
+                key,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers,
+            }],
+            ..Default::default()
+        };
+        let mut shortcuts = Vec::new();
+        let _ = ctx.run(raw, |ctx| {
+            shortcuts = ctx.input_mut(|i| Shortcut::from_input(i, text_focused));
+        });
+        shortcuts
+    }
+
+    #[test]
+    fn test_shortcuts() {
+        let ctrl = egui::Modifiers {
+            ctrl: true,
+            command: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            shortcuts_for(egui::Key::O, ctrl, false),
+            vec![Shortcut::OpenFile]
+        );
+        assert_eq!(
+            shortcuts_for(egui::Key::F, ctrl, false),
+            vec![Shortcut::ToggleSearch]
+        );
+        assert_eq!(
+            shortcuts_for(egui::Key::Num3, ctrl, false),
+            vec![Shortcut::JumpToLayer(2)]
+        );
+        assert_eq!(
+            shortcuts_for(egui::Key::Tab, egui::Modifiers::SHIFT, false),
+            vec![Shortcut::PreviousLayer]
+        );
+        // Plain letters and anything typed into the text area are not shortcuts
+        assert!(shortcuts_for(egui::Key::O, egui::Modifiers::NONE, false).is_empty());
+        assert!(shortcuts_for(egui::Key::O, ctrl, true).is_empty());
+
+        let (_ctx, mut app) = headless_app();
+        app.run_shortcut(Shortcut::JumpToLayer(3));
+        assert_eq!(app.state.active_layer, 3);
+        app.run_shortcut(Shortcut::JumpToLayer(8));
+        assert_eq!(app.state.active_layer, 3);
+        app.run_shortcut(Shortcut::ToggleDebug);
+        assert!(app.show_debug);
+    }
+
+    #[cfg(not(any(feature = "rawhid", feature = "qmk_console")))]
+    #[test]
+    fn test_layer_stepping_wraps_with_256_layers() {
+        let (_ctx, mut app) = headless_app();
+        app.state.keyboard.layer_names = (0..256).map(|i| i.to_string()).collect();
+        app.run_shortcut(Shortcut::PreviousLayer);
+        assert_eq!(app.state.active_layer, 255);
+        app.run_shortcut(Shortcut::NextLayer);
+        assert_eq!(app.state.active_layer, 0);
+    }
+
+    #[test]
+    fn test_reports_only_count_changes() {
+        let (_ctx, mut app) = headless_app();
+        app.state.apply_report(&Report::new_for_test(0, &[0]));
+        let started = app.state.pressed_started[&0];
+        app.state.apply_report(&Report::new_for_test(0, &[0]));
+        app.state.apply_report(&Report::new_for_test(0, &[0, 1]));
+        assert_eq!(app.state.pressed_started[&0], started);
+        assert_eq!(app.state.press_count(0, None), 1);
+        assert_eq!(app.state.press_count(1, None), 1);
+        app.state.apply_report(&Report::new_for_test(0, &[1]));
+        assert!(!app.state.pressed_started.contains_key(&0));
+        assert!(app.state.pressed_started.contains_key(&1));
+    }
+
+    #[test]
+    fn test_load_keymaps_as_layers() {
+        let (_ctx, mut app) = headless_app();
+        let paths = [
+            "tests/files/layers/base.json",
+            "tests/files/layers/lower.json",
+            "tests/files/layers/raise.json",
+        ];
+        assert!(app.load_keymaps_as_layers(&paths));
+        assert!(app.keyboard_loaded);
+        assert_eq!(
+            app.state.keyboard.layer_names,
+            vec!["Base", "Lower", "Raise"]
+        );
+        assert_eq!(app.state.keyboard.raw_legends.len(), 3);
+        assert_eq!(app.state.keyboard.raw_legends[2][0], "KC_F1");
+
+        // A file with several layers can't be one layer of the merged keymap
+        let (_ctx, mut app) = headless_app();
+        assert!(!app.load_keymaps_as_layers(&[paths[0], "tests/files/thooams.json"]));
+        assert!(!app.keyboard_loaded);
+
+        let preview = LayerFilesPreview::new(paths.map(String::from).to_vec(), 2);
+        assert!(preview.config.is_err());
+    }
+
+    #[test]
+    fn test_dropped_layer_files_are_parsed_for_the_preview() {
+        let (ctx, mut app) = headless_app();
+        // Up to one file per layer of the shown keyboard is accepted
+        app.load_keymap_from_path("tests/files/configurator_planck_rev6.json");
+        while app.is_loading() {
+            std::thread::sleep(Duration::from_millis(5));
+            let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+        }
+        let names = ["base", "lower", "raise"];
+        let dropped_files = names
+            .iter()
+            .map(|name| egui::DroppedFile {
+                path: Some(format!("tests/files/layers/{}.json", name).into()),
+                ..Default::default()
+            })
+            .collect();
+        let input = egui::RawInput {
+            dropped_files,
+            ..Default::default()
+        };
+        let _ = ctx.run(input, |ctx| app.draw(ctx));
+        while app.is_loading() {
+            std::thread::sleep(Duration::from_millis(5));
+            let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+        }
+
+        let preview = app.layer_files_preview.take().unwrap();
+        assert_eq!(
+            preview.layer_names().unwrap(),
+            ["Base", "Lower", "Raise"].map(String::from)
+        );
+        assert!(app.apply_layer_files(&preview.paths, preview.config));
+        assert_eq!(app.state.keyboard.raw_legends.len(), 3);
+    }
+
+    #[test]
+    fn test_connection_status_updates() {
+        let (ctx, mut app) = headless_app();
+        let (tx, rx) = mpsc::channel();
+        app.set_connection_receiver(rx);
+        assert_eq!(app.connection_status, ConnectionStatus::Disconnected);
+
+        tx.send(ConnectionStatus::Scanning).unwrap();
+        let connected = ConnectionStatus::Connected {
+            device_name: "Planck".to_string(),
+            vid: 0x03A8,
+            pid: 0xA4F9,
+        };
+        tx.send(connected.clone()).unwrap();
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+        assert_eq!(app.connection_status, connected);
+    }
+
+    #[test]
+    fn test_merge_mode_replaces_one_layer() {
+        let (ctx, mut app) = headless_app();
+        let keymap = KeymapConfig::load_from_path("tests/files/thooams.json").unwrap();
+        let layer_count = keymap.layers.len();
+        app.apply_loaded_config(keymap.clone(), "tests/files/thooams.json".to_string());
+        app.config.merge_mode = true;
+        app.state.set_layer(1);
+
+        app.start_load_with("layer.json".to_string(), |_| {
+            Ok(KeymapConfig {
+                keyboard: "planck".to_string(),
+                keymap: "layer".to_string(),
+                layers: vec![vec!["KC_F1".to_string(); 48]],
+                layout: None,
+                layer_names: None,
+                encoder_map: Vec::new(),
+                custom_keycodes: CustomKeycodeTable::default(),
+            })
+        });
+        while app.is_loading() {
+            std::thread::sleep(Duration::from_millis(5));
+            let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+        }
+
+        let raw = &app.state.keyboard.raw_legends;
+        assert_eq!(raw.len(), layer_count);
+        assert!(raw[1].iter().all(|t| t == "KC_F1"));
+        assert_eq!(raw[0], keymap.layers[0]);
+        // The merged keymap is saved with the layer file as its source
+        assert_eq!(app.source_path.as_deref(), Some("layer.json"));
+        let merged = app.keymap_config.as_ref().unwrap();
+        assert!(merged.layers[1].iter().all(|t| t == "KC_F1"));
+    }
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url("https://gist.github.com/user/abc123"));
+        assert!(is_url("http://example.com/keymap.c"));
+        assert!(is_url("file:///tmp/keymap.json"));
+        assert!(!is_url("tests/files/thooams.json"));
+        assert!(!is_url("/home/user/http/keymap.c"));
+    }
+
+    #[test]
+    fn test_load_from_file_url() {
+        let path = std::env::current_dir()
+            .unwrap()
+            .join("tests/files/thooams.json");
+        let url = format!("file://{}", path.display());
+        let from_url = KeyboardViewerApp::load_from_url(&url).unwrap();
+        let from_path = KeymapConfig::load_from_path(path.to_str().unwrap()).unwrap();
+        assert_eq!(from_url.layers, from_path.layers);
+        assert_eq!(from_url.keyboard, from_path.keyboard);
+    }
+
+    #[cfg(feature = "http_fetch")]
+    #[test]
+    fn test_gist_urls() {
+        assert_eq!(
+            gist_api_url("https://gist.github.com/someone/0123abcd/").as_deref(),
+            Some("https://api.github.com/gists/0123abcd")
+        );
+        assert!(gist_api_url("https://raw.githubusercontent.com/a/b/keymap.c").is_none());
+        let response = r#"{"files": {
+            "README.md": {"filename": "README.md", "raw_url": "https://x/readme"},
+            "keymap.c": {"filename": "keymap.c", "raw_url": "https://x/keymap"}
+        }}"#;
+        assert_eq!(
+            gist_raw_file(response),
+            Some(("keymap.c".to_string(), "https://x/keymap".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_override_and_reset_keys() {
+        let (_ctx, mut app) = headless_app();
+        app.state.keyboard.raw_legends[0][0] = "KC_A".to_string();
+        app.override_key(0, 0, 0, "KC_ESC");
+        assert_eq!(app.state.display_parts(0, 0, 0).0, "Esc");
+        app.override_key(0, 0, 0, "KC_TAB");
+        assert_eq!(app.manual_overrides.get(&(0, 0)), Some(&"KC_A".to_string()));
+
+        // Typing the original token back clears the override
+        app.override_key(0, 0, 0, "KC_A");
+        assert!(app.manual_overrides.is_empty());
+
+        app.override_key(0, 0, 1, "KC_B");
+        app.reset_overrides();
+        assert!(app.manual_overrides.is_empty());
+        assert_eq!(app.state.raw_legend_at(0, 0, 1), Some(""));
+    }
+
+    #[test]
+    fn test_key_size_follows_config() {
+        let (ctx, mut app) = headless_app();
+        app.config = AppConfig::default();
+        assert_eq!(app.key_size(), Vec2::splat(56.0));
+        app.config.key_size_px = 40.0;
+        app.show_settings = true;
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+        assert_eq!(app.key_size(), Vec2::new(40.0, 40.0));
+    }
+
+    #[test]
+    fn test_key_spacing_is_saved() {
+        let (ctx, mut app) = headless_app();
+        app.set_keyboard_loaded(true);
+        app.config.key_spacing_px = 12.0;
+        app.save_key_geometry();
+        assert_eq!(load_app_config().unwrap().key_spacing_px, 12.0);
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+        assert_eq!(app.key_spacing(), 12.0);
+    }
+
+    #[test]
+    fn test_zoom_scales_key_geometry() {
+        let (ctx, mut app) = headless_app();
+        app.config = AppConfig::default();
+        app.config.zoom = 1.5;
+        assert_eq!(app.key_size(), Vec2::splat(56.0 * 1.5));
+        assert_eq!(app.key_spacing(), 8.0 * 1.5);
+        assert_eq!(app.border_radius(), 6.0 * 1.5);
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+
+        // Out of range values from a hand-edited config are clamped
+        app.config.zoom = 5.0;
+        assert_eq!(app.zoom(), 2.0);
+        // Fullscreen sizes keys to the window
+        app.fullscreen = true;
+        assert_eq!(app.zoom(), 1.0);
+    }
+
+    #[test]
+    fn test_paste_keymap_into_drop_zone() {
+        let (ctx, mut app) = headless_app();
+        let paste = |text: &str| egui::RawInput {
+            events: vec![egui::Event::Paste(text.to_string())],
+            ..Default::default()
+        };
+        let _ = ctx.run(paste("not a keymap"), |ctx| app.draw(ctx));
+        assert!(!app.keyboard_loaded);
+        assert_eq!(app.toasts.back().map(|t| t.kind), Some(ToastKind::Error));
+
+        let via = r#"{"name":"Pasted","layers":[["KC_A","KC_B"]]}"#;
+        let _ = ctx.run(paste(via), |ctx| app.draw(ctx));
+        assert!(app.keyboard_loaded);
+        assert_eq!(app.keyboard_name.as_deref(), Some("Pasted"));
+        let toast = app.toasts.back().unwrap();
+        assert_eq!(toast.message, "Loaded VIA keymap from clipboard");
+    }
+
+    #[test]
+    fn test_undo_redo_key_edits() {
+        let (_ctx, mut app) = headless_app();
+        let config = KeymapConfig::load_from_path("tests/files/thooams.json").unwrap();
+        let original = config.to_keyboard_layout();
+        app.apply_loaded_config(config, "tests/files/thooams.json".to_string());
+
+        app.override_key(0, 0, 0, "KC_F1");
+        app.override_key(0, 0, 0, "KC_F2");
+        app.override_key(1, 2, 3, "KC_F3");
+        assert_eq!(app.undo_stack.len(), 3);
+        app.undo();
+        app.undo();
+        assert_eq!(app.state.raw_legend_at(0, 0, 0), Some("KC_F1"));
+        app.redo();
+        assert_eq!(app.state.raw_legend_at(0, 0, 0), Some("KC_F2"));
+        for _ in 0..3 {
+            app.undo();
+        }
+        assert_eq!(app.state.keyboard.raw_legends, original.raw_legends);
+        assert_eq!(app.state.keyboard.legends, original.legends);
+        assert!(app.manual_overrides.is_empty());
+        assert_eq!(app.redo_stack.len(), 3);
+
+        // A new edit drops the redo history; the undo history is bounded
+        for i in 0..UNDO_LIMIT + 5 {
+            app.override_key(0, 0, 1, &format!("KC_F{}", i % 2 + 1));
+        }
+        assert!(app.redo_stack.is_empty());
+        assert_eq!(app.undo_stack.len(), UNDO_LIMIT);
+
+        // Loading a keymap clears the history
+        let config = KeymapConfig::load_from_path("tests/files/thooams.json").unwrap();
+        app.apply_loaded_config(config, "tests/files/thooams.json".to_string());
+        assert!(app.undo_stack.is_empty());
+    }
+
+    #[test]
+    fn test_pin_layer_toggle() {
+        let (ctx, mut app) = headless_app();
+        app.set_keyboard_loaded(true);
+        app.config = AppConfig::default();
+        app.apply_layer_action(LayerAction::TogglePin(1));
+        assert_eq!(app.config.pinned_layers, vec![1]);
+        // Pinned overlays are painted on top of the active layer
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+        app.apply_layer_action(LayerAction::TogglePin(1));
+        assert!(app.config.pinned_layers.is_empty());
+    }
+
+    #[test]
+    fn test_compare_layers_by_raw_token() {
+        let (ctx, mut app) = headless_app();
+        app.set_keyboard_loaded(true);
+        let mut layout = KeyboardLayout::new(1, 4, vec!["Base".to_string(), "Nav".to_string()]);
+        layout.raw_legends[0] = ["KC_A", "KC_ENT", "KC_B", "KC_TRNS"]
+            .map(String::from)
+            .to_vec();
+        layout.raw_legends[1] = ["KC_A", "KC_ENTER", "KC_TRNS", "_______"]
+            .map(String::from)
+            .to_vec();
+        layout.legends = layout
+            .raw_legends
+            .iter()
+            .map(|layer| layer.iter().map(|t| translate_token(t)).collect())
+            .collect();
+        app.replace_layout(layout);
+
+        assert_eq!(app.key_diff(0, 1, 0, 0), KeyDiff::Same);
+        // Aliases are compared as written, not by their translated label
+        assert_eq!(app.key_diff(0, 1, 0, 1), KeyDiff::Changed);
+        assert_eq!(app.key_diff(0, 1, 0, 2), KeyDiff::Transparent);
+        assert_eq!(app.key_diff(1, 0, 0, 2), KeyDiff::Transparent);
+        assert_eq!(app.key_diff(0, 1, 0, 3), KeyDiff::Same);
+
+        app.toggle_compare();
+        assert_eq!(
+            app.compare,
+            Some(LayerCompare {
+                left: 0,
+                right: 1,
+                diff_only: false
+            })
+        );
+        // Comparing never switches the active layer
+        app.compare = Some(LayerCompare {
+            left: 1,
+            right: 0,
+            diff_only: true,
+        });
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+        assert_eq!(app.state.active_layer, 0);
+        app.toggle_compare();
+        assert!(app.compare.is_none());
+    }
+
+    #[test]
+    fn test_key_log_records_presses_and_releases() {
+        let (ctx, mut app) = headless_app();
+        app.set_keyboard_loaded(true);
+        let now = Instant::now();
+        let previous = app.state.pressed_bits;
+        app.state
+            .set_pressed_bits(crate::hid::PressedBits::from_iter([0, 13]));
+        app.record_key_events(previous, now);
+        let previous = app.state.pressed_bits;
+        app.state
+            .set_pressed_bits(crate::hid::PressedBits::from_iter([13]));
+        app.record_key_events(previous, now);
+
+        let events: Vec<_> = app
+            .key_log
+            .iter()
+            .map(|e| (e.key_idx, e.event_type))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (0, KeyEventType::Press),
+                (13, KeyEventType::Press),
+                (0, KeyEventType::Release),
+            ]
+        );
+        assert_eq!(
+            app.key_log[0].raw_label,
+            app.state.raw_legend_at(0, 0, 0).unwrap()
+        );
+
+        app.show_log = true;
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+
+        for _ in 0..KEY_LOG_LIMIT {
+            let previous = app.state.pressed_bits;
+            app.state
+                .set_pressed_bits(crate::hid::PressedBits::default());
+            app.record_key_events(previous, now);
+            let previous = app.state.pressed_bits;
+            app.state
+                .set_pressed_bits(crate::hid::PressedBits::from_iter([13]));
+            app.record_key_events(previous, now);
+        }
+        assert_eq!(app.key_log.len(), KEY_LOG_LIMIT);
+    }
+
+    #[test]
+    fn test_format_ago() {
+        assert_eq!(format_ago(Duration::from_millis(120)), "120ms ago");
+        assert_eq!(format_ago(Duration::from_millis(4500)), "4.5s ago");
+        assert_eq!(format_ago(Duration::from_secs(190)), "3m ago");
+    }
+
+    #[test]
+    fn test_heatmap_color() {
+        assert_eq!(heatmap_color(0, 10), Palette::OVERLAY);
+        assert_eq!(heatmap_color(0, 0), Palette::OVERLAY);
+        assert_eq!(heatmap_color(10, 10), Palette::HEAT);
+        let half = heatmap_color(5, 10);
+        assert!(half.r() > Palette::OVERLAY.r() && half.r() < Palette::HEAT.r());
+    }
+
+    #[test]
+    fn test_split_line_corne() {
+        // Corne: 3x6 columns and 3 thumb keys per half, halves 2U apart at the thumbs
+        let mut positions = Vec::new();
+        for row in 0..3 {
+            for col in 0..6 {
+                positions.push(KeyPosition::new(col as f32, row as f32, 1.0, 1.0));
+                positions.push(KeyPosition::new(9.0 + col as f32, row as f32, 1.0, 1.0));
+            }
+        }
+        for i in 0..3 {
+            positions.push(KeyPosition::new(3.5 + i as f32, 3.25, 1.0, 1.0));
+            positions.push(KeyPosition::new(8.5 + i as f32, 3.25, 1.0, 1.0));
+        }
+        assert_eq!(split_line_x(&positions), Some(7.5));
+
+        // A unibody board has no split
+        let planck: Vec<KeyPosition> = (0..48)
+            .map(|i| KeyPosition::new((i % 12) as f32, (i / 12) as f32, 1.0, 1.0))
+            .collect();
+        assert_eq!(split_line_x(&planck), None);
+
+        let ferris = crate::keyboards::ferris::FerrisLayout::ferris_36();
+        assert!(split_line_x(&ferris.physical_positions.unwrap()).is_some());
+    }
+
+    #[test]
+    fn test_layout_banner_for_detected_keyboard() {
+        let (ctx, mut app) = headless_app();
+        app.config = AppConfig::default();
+        app.start_load_with("keymap.c".to_string(), |_| {
+            Ok(KeymapConfig {
+                keyboard: "crkbd".to_string(),
+                keymap: "default".to_string(),
+                layers: vec![vec!["KC_A".to_string(); 42]],
+                layout: None,
+                layer_names: None,
+                encoder_map: Vec::new(),
+                custom_keycodes: CustomKeycodeTable::default(),
+            })
+        });
+        while app.is_loading() {
+            std::thread::sleep(Duration::from_millis(5));
+            let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+        }
+
+        let banner = app.layout_banner.clone().expect("banner shown");
+        assert_eq!(banner.detected.name, "corne");
+        assert!(banner
+            .message()
+            .starts_with("This looks like a Corne layout (4×12, 42 keys)"));
+        let positions = app.state.keyboard.physical_positions.as_ref();
+        assert_eq!(positions.map(Vec::len), Some(42));
+
+        // Alternatives must place every key; picking one keeps the key assignments
+        let alternatives = banner.alternatives();
+        assert!(alternatives.iter().all(|kb| kb.key_count >= 42));
+        let planck = alternatives.iter().find(|kb| kb.name == "planck").unwrap();
+        app.choose_known_keyboard(planck);
+        assert!(app.layout_banner.is_none());
+        assert_eq!(
+            app.state.keyboard.physical_positions.as_ref().map(Vec::len),
+            Some(48)
+        );
+        assert_eq!(app.state.keyboard.raw_legends[0][41], "KC_A");
+
+        // The banner goes away on its own
+        app.layout_banner = Some(LayoutBanner {
+            shown_at: Instant::now() - LAYOUT_BANNER_DURATION,
+            ..banner
+        });
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+        assert!(app.layout_banner.is_none());
+    }
+
+    #[test]
+    fn test_fullscreen_key_size() {
+        let (ctx, mut app) = headless_app();
+        app.config = AppConfig::default();
+        // A maximized 1080p window minus the top bar is taller than the default window
+        let available = Vec2::new(1920.0, 1080.0 - 50.0) - Vec2::splat(FULLSCREEN_MARGIN);
+        let size = fit_key_size(available, app.board_units(), app.config.key_spacing_px);
+        assert!(size > 56.0, "fullscreen key size {}", size);
+        // Aspect ratio is kept: the board never overflows the available area
+        let unit = size + app.config.key_spacing_px;
+        assert!(app.board_units().x * unit <= available.x + 0.01);
+        assert!(app.board_units().y * unit <= available.y + 0.01);
+        assert_eq!(
+            fit_key_size(Vec2::new(10.0, 10.0), Vec2::new(10.0, 4.0), 8.0),
+            16.0
+        );
+
+        let f11 = egui::RawInput {
+            events: vec![egui::Event::Key {
+                key: egui::Key::F11,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers: egui::Modifiers::NONE,
+            }],
+            ..Default::default()
+        };
+        app.show_debug = true;
+        let _ = ctx.run(f11, |ctx| app.draw(ctx));
+        assert!(app.fullscreen);
+        // Panels are hidden, not switched off, so they come back after fullscreen
+        assert!(app.show_debug);
+    }
+
+    #[test]
+    fn test_layers_panel_actions() {
+        let (ctx, mut app) = headless_app();
+        app.set_keyboard_loaded(true);
+        app.show_layers_panel = true;
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+        let count = app.state.keyboard.raw_legends.len();
+
+        app.apply_layer_action(LayerAction::Rename(1, "Symbols".to_string()));
+        assert_eq!(app.state.keyboard.layer_names[1], "Symbols");
+
+        app.state.set_layer(1);
+        app.apply_layer_action(LayerAction::MoveUp(1));
+        assert_eq!(app.state.keyboard.layer_names[0], "Symbols");
+        assert_eq!(app.state.active_layer, 0);
+
+        app.apply_layer_action(LayerAction::Duplicate(0));
+        assert_eq!(app.state.keyboard.raw_legends.len(), count + 1);
+        assert_eq!(app.state.keyboard.layer_names[count], "Symbols copy");
+
+        // Edits survive a rename; encoder bindings and pins follow their layer
+        app.manual_overrides.insert((0, 0), "KC_A".to_string());
+        app.apply_layer_action(LayerAction::Rename(0, "Sym".to_string()));
+        assert_eq!(app.manual_overrides.len(), 1);
+        app.encoder_map = vec![
+            EncoderAction {
+                layer: 0,
+                encoder: 0,
+                clockwise: "KC_VOLU".to_string(),
+                counter_clockwise: "KC_VOLD".to_string(),
+            },
+            EncoderAction {
+                layer: 1,
+                encoder: 0,
+                clockwise: "KC_PGDN".to_string(),
+                counter_clockwise: "KC_PGUP".to_string(),
+            },
+        ];
+        app.config.pinned_layers = vec![1];
+        app.apply_layer_action(LayerAction::MoveDown(0));
+        let layers = |app: &KeyboardViewerApp| -> Vec<usize> {
+            app.encoder_map.iter().map(|a| a.layer).collect()
+        };
+        assert_eq!(layers(&app), [1, 0]);
+        assert_eq!(app.config.pinned_layers, [0]);
+        app.apply_layer_action(LayerAction::Remove(0));
+        assert_eq!(layers(&app), [0]);
+        assert!(app.config.pinned_layers.is_empty());
+
+        // Removing stops at one layer
+        let count = app.state.keyboard.raw_legends.len();
+        for _ in 0..count + 3 {
+            app.apply_layer_action(LayerAction::Remove(0));
+        }
+        assert_eq!(app.state.keyboard.raw_legends.len(), 1);
+        assert_eq!(app.state.active_layer, 0);
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+    }
+
+    #[test]
+    fn test_toasts_expire() {
+        let (ctx, mut app) = headless_app();
+        app.push_toast("Something went wrong".to_string(), ToastKind::Error);
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+        assert_eq!(app.toasts.len(), 1);
+
+        // Four seconds later the 3 second toast is gone
+        app.prune_toasts(Instant::now() + Duration::from_secs(4));
+        assert!(app.toasts.is_empty());
+    }
+
+    #[test]
+    fn test_drop_hover_unsupported_extension() {
+        let hovered = |path: &str| egui::HoveredFile {
+            path: Some(std::path::PathBuf::from(path)),
+            ..Default::default()
+        };
+        assert_eq!(
+            DropHover::from_hovered(&[hovered("/tmp/notes.txt")]),
+            Some(DropHover::Unsupported("notes.txt".to_string()))
+        );
+        assert_eq!(
+            DropHover::from_hovered(&[hovered("/tmp/keymap.c")]),
+            Some(DropHover::Supported("keymap.c".to_string()))
+        );
+        assert_eq!(DropHover::from_hovered(&[]), None);
+
+        // A frame with a hovered .txt file renders the drop zone without loading anything
+        let (ctx, mut app) = headless_app();
+        let input = egui::RawInput {
+            hovered_files: vec![hovered("/tmp/notes.txt")],
+            ..Default::default()
+        };
+        let _ = ctx.run(input, |ctx| app.draw(ctx));
+        assert!(!app.is_loading());
+        assert!(!app.keyboard_loaded);
+    }
+
+    #[test]
+    fn test_failed_load_reports_error() {
+        let (ctx, mut app) = headless_app();
+        app.start_load_with("broken.json".to_string(), |_| Err("boom".to_string()));
+        std::thread::sleep(Duration::from_millis(50));
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+        assert!(!app.keyboard_loaded);
+        assert_eq!(app.load_error.as_deref(), Some("boom"));
+        assert_eq!(app.toasts.len(), 1);
+        assert_eq!(app.toasts[0].kind, ToastKind::Error);
+    }
+
+    #[test]
+    fn test_kle_layout_positions_apply_to_matching_keymap() {
+        let dir = std::env::temp_dir().join(format!("qmk_viewer_kle_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("layout.json");
+        std::fs::write(&path, r#"[["Q", {"w": 2}, "W"], [{"x": 0.5}, "A"]]"#).unwrap();
+
+        let (ctx, mut app) = headless_app();
+        app.config.show_physical_layout = true;
+        app.load_keymap_from_path(path.to_str().unwrap());
+        std::thread::sleep(Duration::from_millis(100));
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+        assert!(app.keyboard_loaded);
+        let positions = app.state.keyboard.physical_positions.clone().unwrap();
+        assert_eq!(positions.len(), 3);
+        assert_eq!((positions[2].x, positions[2].y), (0.5, 1.0));
+
+        let keymap = |keys: usize| KeymapConfig {
+            keyboard: "test".to_string(),
+            keymap: "test".to_string(),
+            layers: vec![vec!["KC_A".to_string(); keys]],
+            layout: None,
+            layer_names: None,
+            encoder_map: Vec::new(),
+            custom_keycodes: CustomKeycodeTable::default(),
+        };
+        app.set_keymap_config(keymap(3));
+        assert_eq!(app.state.keyboard.physical_positions, Some(positions));
+        app.set_keymap_config(keymap(4));
+        assert!(app.state.keyboard.physical_positions.is_none());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_keymap_uses_positions_from_keyboard_info_json() {
+        let dir = std::env::temp_dir().join(format!("qmk_viewer_info_{}", std::process::id()));
+        let keymap_dir = dir.join("keymaps").join("default");
+        std::fs::create_dir_all(&keymap_dir).unwrap();
+        std::fs::write(
+            dir.join("info.json"),
+            r#"{"layouts": {"LAYOUT_2x1": {"layout": [{"x": 0, "y": 0}]},
+                "LAYOUT": {"layout": [{"x": 0, "y": 0, "w": 2}, {"x": 2.5, "y": 0.25}]}}}"#,
+        )
+        .unwrap();
+        let path = keymap_dir.join("keymap.c");
+        std::fs::write(
+            &path,
+            "const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {\n\
+             [0] = LAYOUT(KC_A, KC_B)\n};\n",
+        )
+        .unwrap();
+
+        // The layout with as many keys as the keymap is picked
+        let path = path.to_str().unwrap();
+        let Ok(Loaded::KeymapWithPositions(config, positions)) = parse_keymap_or_kle_file(path)
+        else {
+            panic!("info.json not picked up");
+        };
+        assert_eq!(positions[1], KeyPosition::new(2.5, 0.25, 1.0, 1.0));
+
+        let (ctx, mut app) = headless_app();
+        app.load_keymap_from_path(path);
+        for _ in 0..40 {
+            let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+            if app.keyboard_loaded {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        assert_eq!(
+            app.keymap_config.as_ref().map(|c| &c.layers),
+            Some(&config.layers)
+        );
+        assert_eq!(app.state.keyboard.physical_positions, Some(positions));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_watched_keymap_reloads_on_change() {
+        let dir = std::env::temp_dir().join(format!("qmk_viewer_reload_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("keymap.json");
+        let keymap = |name: &str| {
+            format!(
+                r#"{{"keyboard":"test","keymap":"test","layout":null,"layers":[["KC_A"]],"layer_names":["{}"]}}"#,
+                name
+            )
+        };
+        std::fs::write(&path, keymap("Before")).unwrap();
+
+        let (ctx, mut app) = headless_app();
+        app.load_keymap_from_path(path.to_str().unwrap());
+        // The debounce is tested in the watcher; this only needs the reload to happen at all
+        let run_until = |app: &mut KeyboardViewerApp, name: &str| {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while Instant::now() < deadline {
+                let _ = ctx.run(egui::RawInput::default(), |ctx| app.draw(ctx));
+                if app.state.keyboard.layer_names == [name] {
+                    return true;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            false
+        };
+        assert!(run_until(&mut app, "Before"));
+        assert!(app.file_watcher.is_some());
+
+        std::fs::write(&path, keymap("After, a longer name")).unwrap();
+        assert!(run_until(&mut app, "After, a longer name"));
+
+        // Keymaps from elsewhere stop the watcher
+        app.apply_loaded_config(app.keymap_config.clone().unwrap(), "clipboard".to_string());
+        assert!(app.file_watcher.is_none());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_wpm_formula() {
+        let t0 = Instant::now();
+        let mut wpm = WpmHistory::default();
+        wpm.record(t0, 0);
+        // 50 characters over 10 seconds => (50 / 5) / (10 / 60) = 60 WPM
+        for i in 1..=50u64 {
+            wpm.record(t0 + Duration::from_millis(i * 200), i as usize);
+        }
+        let now = t0 + Duration::from_secs(10);
+        assert!((wpm.wpm(now) - 60.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_wpm_idle_reset() {
+        let t0 = Instant::now();
+        let mut wpm = WpmHistory::default();
+        wpm.record(t0, 0);
+        wpm.record(t0 + Duration::from_secs(1), 10);
+        assert_eq!(wpm.wpm(t0 + Duration::from_secs(7)), 0.0);
+
+        // Typing again after the idle gap starts a fresh window
+        wpm.record(t0 + Duration::from_secs(8), 15);
+        wpm.record(t0 + Duration::from_secs(9), 20);
+        let now = t0 + Duration::from_secs(9);
+        assert!((wpm.wpm(now) - 60.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,179 @@
+//! Watch the loaded keymap file so the viewer can reload it after an edit or a build
+//!
+//! Changes come from the platform's file-watching API through `notify`. The folder holding
+//! the file is watched rather than the file itself, so editors that save by replacing the
+//! file are still followed.
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often the watcher thread checks whether it should stop or report a settled change
+const TICK: Duration = Duration::from_millis(50);
+/// Editors often save in several writes; wait until the file stays put this long
+pub const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Sent by a [`FileWatcher`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatcherEvent {
+    FileChanged(PathBuf),
+}
+
+/// Background thread reporting changes of one file; stopped and joined when dropped
+pub struct FileWatcher {
+    path: PathBuf,
+    rx: Receiver<WatcherEvent>,
+    /// Set up before the thread starts so no change made right after [`FileWatcher::spawn`]
+    /// is missed; `None` when the platform refused to watch the file
+    _watcher: Option<RecommendedWatcher>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// Debounce of the file's change events, driven by the time each one arrived
+#[derive(Debug, Clone, Copy, Default)]
+struct Settle {
+    changed_at: Option<Instant>,
+}
+
+impl Settle {
+    fn touch(&mut self, now: Instant) {
+        self.changed_at = Some(now);
+    }
+
+    /// Whether a change is to be reported at `now`: the file changed, has been left alone
+    /// for `debounce` since and exists (a file replaced by the editor may be missing for a
+    /// moment). A reported change is only reported once.
+    fn settled(&mut self, now: Instant, debounce: Duration, exists: bool) -> bool {
+        let settled = exists
+            && self
+                .changed_at
+                .is_some_and(|at| now.saturating_duration_since(at) >= debounce);
+        if settled {
+            self.changed_at = None;
+        }
+        settled
+    }
+}
+
+/// Whether `event` is about the file at `path`, ignoring reads of it
+fn touches(event: &Event, path: &Path) -> bool {
+    !event.kind.is_access()
+        && event
+            .paths
+            .iter()
+            .any(|p| p.file_name() == path.file_name())
+}
+
+/// Watch the folder holding `path` for events sent on `events`
+fn watch_folder(
+    path: &Path,
+    events: Sender<notify::Result<Event>>,
+) -> notify::Result<RecommendedWatcher> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut watcher = notify::recommended_watcher(events)?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    Ok(watcher)
+}
+
+fn watch(
+    path: PathBuf,
+    debounce: Duration,
+    event_rx: Receiver<notify::Result<Event>>,
+    tx: Sender<WatcherEvent>,
+    stop: Arc<AtomicBool>,
+) {
+    let mut settle = Settle::default();
+    while !stop.load(Ordering::Relaxed) {
+        match event_rx.recv_timeout(TICK) {
+            Ok(Ok(event)) if touches(&event, &path) => settle.touch(Instant::now()),
+            Ok(Err(e)) => eprintln!("⚠️ Error watching {}: {}", path.display(), e),
+            Ok(Ok(_)) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        if settle.settled(Instant::now(), debounce, path.exists())
+            && tx.send(WatcherEvent::FileChanged(path.clone())).is_err()
+        {
+            return;
+        }
+    }
+}
+
+impl FileWatcher {
+    pub fn spawn(path: impl Into<PathBuf>) -> Self {
+        Self::spawn_with_debounce(path, DEBOUNCE)
+    }
+
+    pub fn spawn_with_debounce(path: impl Into<PathBuf>, debounce: Duration) -> Self {
+        let path = path.into();
+        let (tx, rx) = mpsc::channel();
+        let (event_tx, event_rx) = mpsc::channel();
+        let watcher = watch_folder(&path, event_tx)
+            .map_err(|e| eprintln!("⚠️ Cannot watch {} for changes: {}", path.display(), e))
+            .ok();
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let (path, stop) = (path.clone(), Arc::clone(&stop));
+            std::thread::spawn(move || watch(path, debounce, event_rx, tx, stop))
+        };
+        Self {
+            path,
+            rx,
+            _watcher: watcher,
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file changed since the last call; several changes count once
+    pub fn changed(&self) -> bool {
+        self.rx.try_iter().count() > 0
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_are_debounced() {
+        let debounce = Duration::from_millis(150);
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+        let mut settle = Settle::default();
+        assert!(!settle.settled(at(1000), debounce, true));
+
+        // Two quick writes are one change, reported once the file settles
+        settle.touch(at(0));
+        settle.touch(at(50));
+        assert!(!settle.settled(at(100), debounce, true));
+        assert!(!settle.settled(at(199), debounce, true));
+        assert!(settle.settled(at(200), debounce, true));
+        assert!(!settle.settled(at(400), debounce, true));
+
+        // A file being replaced is reported once it is back
+        settle.touch(at(500));
+        assert!(!settle.settled(at(700), debounce, false));
+        assert!(settle.settled(at(710), debounce, true));
+    }
+}
@@ -0,0 +1,45 @@
+use std::process::Command;
+
+#[test]
+fn test_version_flag() {
+    let output = Command::new(env!("CARGO_BIN_EXE_qmk_viewer"))
+        .arg("--version")
+        .output()
+        .expect("Failed to run qmk_viewer");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let semver = regex::Regex::new(r"\d+\.\d+\.\d+").unwrap();
+    assert!(semver.is_match(&stdout), "unexpected output: {}", stdout);
+    assert!(stdout.contains(env!("CARGO_PKG_VERSION")));
+}
+
+#[test]
+fn test_help_flag() {
+    let output = Command::new(env!("CARGO_BIN_EXE_qmk_viewer"))
+        .arg("--help")
+        .output()
+        .expect("Failed to run qmk_viewer");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("--version"));
+}
+
+#[test]
+fn test_validate_flag() {
+    let output = Command::new(env!("CARGO_BIN_EXE_qmk_viewer"))
+        .args(["--validate", "tests/files/thooams.json", "--json"])
+        .output()
+        .expect("Failed to run qmk_viewer");
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["ok"], true);
+    assert_eq!(report["summary"]["layer_count"], 2);
+    assert_eq!(report["summary"]["keys_per_layer"][0], 48);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_qmk_viewer"))
+        .args(["--validate", "tests/files/missing.c"])
+        .output()
+        .expect("Failed to run qmk_viewer");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("missing.c"));
+}
@@ -0,0 +1,174 @@
+//! Helpers shared by the compatibility test suites
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub struct CompatibilityStats {
+    pub total_keyboards: usize,
+    pub successful_parsing: usize,
+    pub failed_parsing: usize,
+    pub successful_ui_rendering: usize,
+    pub failed_ui_rendering: usize,
+    pub total_parse_time_ms: u64,
+    pub total_render_time_ms: u64,
+    pub error_breakdown: HashMap<String, usize>,
+}
+
+impl CompatibilityStats {
+    pub fn new() -> Self {
+        Self {
+            total_keyboards: 0,
+            successful_parsing: 0,
+            failed_parsing: 0,
+            successful_ui_rendering: 0,
+            failed_ui_rendering: 0,
+            total_parse_time_ms: 0,
+            total_render_time_ms: 0,
+            error_breakdown: HashMap::new(),
+        }
+    }
+}
+
+impl CompatibilityStats {
+    pub fn parsing_success_rate(&self) -> f64 {
+        if self.total_keyboards == 0 {
+            0.0
+        } else {
+            (self.successful_parsing as f64 / self.total_keyboards as f64) * 100.0
+        }
+    }
+
+    pub fn ui_rendering_success_rate(&self) -> f64 {
+        if self.total_keyboards == 0 {
+            0.0
+        } else {
+            (self.successful_ui_rendering as f64 / self.total_keyboards as f64) * 100.0
+        }
+    }
+
+    pub fn avg_parse_time_ms(&self) -> f64 {
+        if self.total_keyboards == 0 {
+            0.0
+        } else {
+            self.total_parse_time_ms as f64 / self.total_keyboards as f64
+        }
+    }
+
+    pub fn avg_render_time_ms(&self) -> f64 {
+        if self.total_keyboards == 0 {
+            0.0
+        } else {
+            self.total_render_time_ms as f64 / self.total_keyboards as f64
+        }
+    }
+}
+
+impl CompatibilityStats {
+    /// Fold another set of stats into this one (used to combine per-thread results)
+    pub fn merge(&mut self, other: &CompatibilityStats) {
+        self.total_keyboards += other.total_keyboards;
+        self.successful_parsing += other.successful_parsing;
+        self.failed_parsing += other.failed_parsing;
+        self.successful_ui_rendering += other.successful_ui_rendering;
+        self.failed_ui_rendering += other.failed_ui_rendering;
+        self.total_parse_time_ms += other.total_parse_time_ms;
+        self.total_render_time_ms += other.total_render_time_ms;
+        for (error_type, count) in &other.error_breakdown {
+            *self.error_breakdown.entry(error_type.clone()).or_insert(0) += count;
+        }
+    }
+
+    /// Panic when the parsing success rate (in percent) is below `threshold`
+    pub fn assert_minimum_success_rate(&self, threshold: f64) {
+        let rate = self.parsing_success_rate();
+        assert!(
+            rate >= threshold,
+            "Parsing success rate {:.1}% is below the required {:.1}% ({} of {} keyboards)",
+            rate,
+            threshold,
+            self.successful_parsing,
+            self.total_keyboards
+        );
+    }
+}
+
+impl Default for CompatibilityStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn categorize_error(error: &str) -> String {
+    if error.contains("Parse error") {
+        "Parse Error".to_string()
+    } else if error.contains("File read error") {
+        "File Read Error".to_string()
+    } else if error.contains("No layers found") {
+        "No Layers Found".to_string()
+    } else if error.contains("Too many keys") {
+        "Too Many Keys".to_string()
+    } else if error.contains("Could not determine") {
+        "Dimension Detection Error".to_string()
+    } else {
+        "Other Error".to_string()
+    }
+}
+
+/// Why a keymap failed to parse, derived from the error message
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ParseErrorKind {
+    NoLayoutFound,
+    EmptyLayer,
+    CommentStripperPanic,
+    UnclosedParen,
+    EncodingError,
+    UnknownMacro,
+    /// Anything else, with the full message
+    Other(String),
+}
+
+impl ParseErrorKind {
+    /// Variant name, used to group failures in reports
+    pub fn name(&self) -> &'static str {
+        match self {
+            ParseErrorKind::NoLayoutFound => "NoLayoutFound",
+            ParseErrorKind::EmptyLayer => "EmptyLayer",
+            ParseErrorKind::CommentStripperPanic => "CommentStripperPanic",
+            ParseErrorKind::UnclosedParen => "UnclosedParen",
+            ParseErrorKind::EncodingError => "EncodingError",
+            ParseErrorKind::UnknownMacro => "UnknownMacro",
+            ParseErrorKind::Other(_) => "Other",
+        }
+    }
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::Other(message) => f.write_str(message),
+            kind => f.write_str(kind.name()),
+        }
+    }
+}
+
+pub fn classify_parse_error(error: &str) -> ParseErrorKind {
+    let lower = error.to_lowercase();
+    if lower.contains("no layout") {
+        ParseErrorKind::NoLayoutFound
+    } else if lower.contains("empty layer") {
+        ParseErrorKind::EmptyLayer
+    } else if lower.contains("panicked") && lower.contains("comment") {
+        ParseErrorKind::CommentStripperPanic
+    } else if lower.contains("unclosed") || lower.contains("unbalanced") {
+        ParseErrorKind::UnclosedParen
+    } else if lower.contains("utf-8") || lower.contains("encoding") {
+        ParseErrorKind::EncodingError
+    } else if lower.contains("unknown macro") {
+        ParseErrorKind::UnknownMacro
+    } else {
+        ParseErrorKind::Other(error.to_string())
+    }
+}
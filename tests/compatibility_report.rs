@@ -1,75 +1,11 @@
+mod common;
+
 use chrono::Utc;
+use common::{categorize_error, CompatibilityStats};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
-#[derive(Debug, Clone)]
-pub struct CompatibilityStats {
-    pub total_keyboards: usize,
-    pub successful_parsing: usize,
-    pub failed_parsing: usize,
-    pub successful_ui_rendering: usize,
-    pub failed_ui_rendering: usize,
-    pub total_parse_time_ms: u64,
-    pub total_render_time_ms: u64,
-    pub error_breakdown: HashMap<String, usize>,
-}
-
-impl CompatibilityStats {
-    pub fn new() -> Self {
-        Self {
-            total_keyboards: 0,
-            successful_parsing: 0,
-            failed_parsing: 0,
-            successful_ui_rendering: 0,
-            failed_ui_rendering: 0,
-            total_parse_time_ms: 0,
-            total_render_time_ms: 0,
-            error_breakdown: HashMap::new(),
-        }
-    }
-}
-
-impl CompatibilityStats {
-    pub fn parsing_success_rate(&self) -> f64 {
-        if self.total_keyboards == 0 {
-            0.0
-        } else {
-            (self.successful_parsing as f64 / self.total_keyboards as f64) * 100.0
-        }
-    }
-
-    pub fn ui_rendering_success_rate(&self) -> f64 {
-        if self.total_keyboards == 0 {
-            0.0
-        } else {
-            (self.successful_ui_rendering as f64 / self.total_keyboards as f64) * 100.0
-        }
-    }
-
-    pub fn avg_parse_time_ms(&self) -> f64 {
-        if self.total_keyboards == 0 {
-            0.0
-        } else {
-            self.total_parse_time_ms as f64 / self.total_keyboards as f64
-        }
-    }
-
-    pub fn avg_render_time_ms(&self) -> f64 {
-        if self.total_keyboards == 0 {
-            0.0
-        } else {
-            self.total_render_time_ms as f64 / self.total_keyboards as f64
-        }
-    }
-}
-
-impl Default for CompatibilityStats {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 pub fn generate_comprehensive_report() -> Result<String, Box<dyn std::error::Error>> {
     let mut stats = CompatibilityStats::new();
     let mut successful_keyboards = Vec::new();
@@ -214,7 +150,7 @@ pub fn generate_comprehensive_report() -> Result<String, Box<dyn std::error::Err
 
         // Sort by parse time for better analysis
         let mut sorted_successful = successful_keyboards.clone();
-        sorted_successful.sort_by(|a, b| a.parse_time_ms.cmp(&b.parse_time_ms));
+        sorted_successful.sort_by_key(|a| a.parse_time_ms);
 
         for result in sorted_successful.iter().take(50) {
             // Show top 50
@@ -342,22 +278,6 @@ pub fn generate_comprehensive_report() -> Result<String, Box<dyn std::error::Err
     Ok(report)
 }
 
-fn categorize_error(error: &str) -> String {
-    if error.contains("Parse error") {
-        "Parse Error".to_string()
-    } else if error.contains("File read error") {
-        "File Read Error".to_string()
-    } else if error.contains("No layers found") {
-        "No Layers Found".to_string()
-    } else if error.contains("Insufficient keys") {
-        "Insufficient Keys".to_string()
-    } else if error.contains("Could not determine") {
-        "Dimension Detection Error".to_string()
-    } else {
-        "Other Error".to_string()
-    }
-}
-
 #[derive(Debug, Clone)]
 struct ParsingResult {
     keyboard_name: String,
@@ -541,3 +461,60 @@ fn test_generate_comprehensive_report() {
         println!("No individual reports found. Run compatibility tests first.");
     }
 }
+
+#[test]
+fn test_stats_merge_sums_fields_and_errors() {
+    let mut a = CompatibilityStats {
+        total_keyboards: 10,
+        successful_parsing: 8,
+        failed_parsing: 2,
+        successful_ui_rendering: 7,
+        failed_ui_rendering: 3,
+        total_parse_time_ms: 100,
+        total_render_time_ms: 50,
+        error_breakdown: HashMap::from([
+            ("Parse Error".to_string(), 1),
+            ("No Layers Found".to_string(), 1),
+        ]),
+    };
+    let b = CompatibilityStats {
+        total_keyboards: 5,
+        successful_parsing: 2,
+        failed_parsing: 3,
+        successful_ui_rendering: 4,
+        failed_ui_rendering: 1,
+        total_parse_time_ms: 40,
+        total_render_time_ms: 10,
+        error_breakdown: HashMap::from([
+            ("Parse Error".to_string(), 2),
+            ("File Read Error".to_string(), 1),
+        ]),
+    };
+
+    a.merge(&b);
+    assert_eq!(a.total_keyboards, 15);
+    assert_eq!(a.successful_parsing, 10);
+    assert_eq!(a.failed_parsing, 5);
+    assert_eq!(a.successful_ui_rendering, 11);
+    assert_eq!(a.failed_ui_rendering, 4);
+    assert_eq!(a.total_parse_time_ms, 140);
+    assert_eq!(a.total_render_time_ms, 60);
+    assert_eq!(a.error_breakdown.len(), 3);
+    assert_eq!(a.error_breakdown["Parse Error"], 3);
+    assert_eq!(a.error_breakdown["No Layers Found"], 1);
+    assert_eq!(a.error_breakdown["File Read Error"], 1);
+
+    a.assert_minimum_success_rate(60.0);
+}
+
+#[test]
+#[should_panic(expected = "below the required")]
+fn test_assert_minimum_success_rate_fails_below_threshold() {
+    let stats = CompatibilityStats {
+        total_keyboards: 4,
+        successful_parsing: 1,
+        failed_parsing: 3,
+        ..CompatibilityStats::new()
+    };
+    stats.assert_minimum_success_rate(50.0);
+}
@@ -0,0 +1,72 @@
+use qmk_viewer::config_persistence::{
+    get_cache_dir, get_config_dir, get_data_dir, get_saved_keymap_path, load_app_config,
+    save_keymap_file, save_keymap_json, SavedKeymapInfo,
+};
+use std::path::PathBuf;
+
+fn temp_root(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("qmk_viewer_xdg_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Environment variables are process-wide, so every override lives in this single test
+#[test]
+fn xdg_variables_override_app_dirs() {
+    let config = temp_root("config");
+    let data = temp_root("data");
+    let cache = temp_root("cache");
+    std::env::set_var("XDG_CONFIG_HOME", &config);
+    std::env::set_var("XDG_DATA_HOME", &data);
+    std::env::set_var("XDG_CACHE_HOME", &cache);
+
+    assert_eq!(get_config_dir().unwrap(), config.join("qmk_viewer"));
+    assert_eq!(get_data_dir().unwrap(), data.join("qmk_viewer"));
+    assert_eq!(get_cache_dir().unwrap(), cache.join("qmk_viewer"));
+    assert!(cache.join("qmk_viewer").is_dir());
+
+    // Saved keymaps go to the data dir
+    let saved = save_keymap_file("tests/files/thooams.json").unwrap();
+    assert_eq!(
+        PathBuf::from(&saved),
+        data.join("qmk_viewer/last_keymap.json")
+    );
+    // The copy remembers the file it was made from, as an absolute path
+    let source = std::fs::canonicalize("tests/files/thooams.json")
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
+    assert_eq!(
+        get_saved_keymap_path().unwrap(),
+        Some(SavedKeymapInfo {
+            cached_path: saved,
+            source_path: Some(source.clone()),
+        })
+    );
+    assert_eq!(
+        load_app_config().unwrap().last_keymap_source_path,
+        Some(source)
+    );
+    // An edited copy names its source too, or none at all
+    save_keymap_json("{}", Some("https://example.com/keymap.json")).unwrap();
+    assert_eq!(
+        load_app_config()
+            .unwrap()
+            .last_keymap_source_path
+            .as_deref(),
+        Some("https://example.com/keymap.json")
+    );
+    save_keymap_json("{}", None).unwrap();
+    assert_eq!(load_app_config().unwrap().last_keymap_source_path, None);
+
+    // Relative values are not valid XDG paths and fall back to the platform default
+    std::env::set_var("XDG_CACHE_HOME", "relative/cache");
+    assert_ne!(
+        get_cache_dir().unwrap(),
+        PathBuf::from("relative/cache/qmk_viewer")
+    );
+
+    for dir in [config, data, cache] {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -23,8 +23,10 @@ fn test_config_persistence() {
 
     // Test getting saved keymap path
     let retrieved_path = get_saved_keymap_path().expect("Failed to get saved keymap path");
-    assert!(retrieved_path.is_some());
-    assert_eq!(retrieved_path.unwrap(), saved_path);
+    let retrieved = retrieved_path.expect("saved keymap");
+    assert_eq!(retrieved.cached_path, saved_path);
+    let source = fs::canonicalize(test_file).unwrap();
+    assert_eq!(retrieved.source_path.as_deref(), source.to_str());
 
     // Test clearing saved keymap
     clear_saved_keymap().expect("Failed to clear saved keymap");
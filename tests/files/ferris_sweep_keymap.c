@@ -0,0 +1,28 @@
+#include QMK_KEYBOARD_H
+
+const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+  [0] = LAYOUT_split_3x5_2(
+    KC_Q,         KC_W,         KC_F,         KC_P,         KC_B,            KC_J,    KC_L,         KC_U,         KC_Y,         KC_QUOT,
+    LGUI_T(KC_A), LALT_T(KC_R), LCTL_T(KC_S), LSFT_T(KC_T), KC_G,            KC_M,    RSFT_T(KC_N), RCTL_T(KC_E), LALT_T(KC_I), RGUI_T(KC_O),
+    KC_Z,         KC_X,         KC_C,         KC_D,         KC_V,            KC_K,    KC_H,         KC_COMM,      KC_DOT,       KC_SLSH,
+                                              LT(1, KC_TAB), KC_SPC,         KC_ENT,  LT(2, KC_BSPC)
+  ),
+  [1] = LAYOUT_split_3x5_2(
+    KC_ESC,  XXXXXXX, XXXXXXX, XXXXXXX, XXXXXXX,         KC_PGUP, KC_HOME, KC_UP,   KC_END,  KC_DEL,
+    KC_LGUI, KC_LALT, KC_LCTL, KC_LSFT, XXXXXXX,         KC_PGDN, KC_LEFT, KC_DOWN, KC_RGHT, KC_BSPC,
+    XXXXXXX, XXXXXXX, XXXXXXX, XXXXXXX, XXXXXXX,         XXXXXXX, XXXXXXX, XXXXXXX, XXXXXXX, XXXXXXX,
+                               _______, _______,         _______, MO(3)
+  ),
+  [2] = LAYOUT_split_3x5_2(
+    KC_1,    KC_2,    KC_3,    KC_4,    KC_5,            KC_6,    KC_7,    KC_8,    KC_9,    KC_0,
+    KC_EXLM, KC_AT,   KC_HASH, KC_DLR,  KC_PERC,         KC_CIRC, KC_AMPR, KC_ASTR, KC_LPRN, KC_RPRN,
+    KC_GRV,  KC_MINS, KC_EQL,  KC_LBRC, KC_RBRC,         KC_BSLS, KC_SCLN, KC_COMM, KC_DOT,  KC_SLSH,
+                               MO(3),   _______,         _______, _______
+  ),
+  [3] = LAYOUT_split_3x5_2(
+    KC_F1,   KC_F2,   KC_F3,   KC_F4,   KC_F5,           KC_F6,   KC_F7,   KC_F8,   KC_F9,   KC_F10,
+    KC_F11,  KC_F12,  XXXXXXX, XXXXXXX, XXXXXXX,         KC_MPRV, KC_VOLD, KC_MUTE, KC_VOLU, KC_MNXT,
+    QK_BOOT, XXXXXXX, XXXXXXX, XXXXXXX, XXXXXXX,         XXXXXXX, XXXXXXX, XXXXXXX, XXXXXXX, QK_BOOT,
+                               _______, _______,         _______, _______
+  )
+};
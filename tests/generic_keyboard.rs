@@ -1,5 +1,7 @@
 use qmk_viewer::config::KeymapConfig;
+use qmk_viewer::hid::Report;
 use qmk_viewer::keyboard::{KeyboardLayout, KeyboardState};
+use qmk_viewer::keyboards::registry::detect_by_key_count;
 
 #[test]
 fn test_different_keyboard_sizes() {
@@ -21,7 +23,7 @@ fn test_different_keyboard_sizes() {
 #[test]
 fn test_keyboard_state_with_different_sizes() {
     // Test with 3x10 keyboard
-    let layout = KeyboardLayout::new(3, 10, vec!["Base".to_string()]);
+    let layout = KeyboardLayout::with_uniform_grid(3, 10, 1);
     let mut state = KeyboardState::new(layout);
 
     // Test index calculation
@@ -32,14 +34,14 @@ fn test_keyboard_state_with_different_sizes() {
     assert_eq!(state.index_for(0, 10), None); // Out of bounds
 
     // Test key press detection
-    state.set_pressed_bits(1 << 15); // Press key at row 1, col 5
+    state.apply_report(&Report::new_for_test(0, &[15])); // Press key at row 1, col 5
     assert!(state.is_pressed(1, 5));
     assert!(!state.is_pressed(0, 0));
 }
 
 #[test]
 fn test_keycode_mapping_comprehensive() {
-    let layout = KeyboardLayout::new(4, 12, vec!["Base".to_string()]);
+    let layout = KeyboardLayout::with_uniform_grid(4, 12, 1);
     let mut state = KeyboardState::new(layout);
 
     // Set up some test keycodes
@@ -81,14 +83,15 @@ fn test_keycode_mapping_comprehensive() {
     assert_eq!(main, "1");
     assert_eq!(sub, "MO");
 
+    // OSL(3) names its layer; with a single layer loaded that is just the number
     let (main, sub) = state.display_parts(0, 0, 7);
     assert_eq!(main, "★");
-    assert_eq!(sub, "");
+    assert_eq!(sub, "3");
 }
 
 #[test]
 fn test_keyboard_function_detection() {
-    let layout = KeyboardLayout::new(4, 12, vec!["Base".to_string()]);
+    let layout = KeyboardLayout::with_uniform_grid(4, 12, 1);
     let mut state = KeyboardState::new(layout);
 
     // Set up test keycodes
@@ -143,6 +146,8 @@ fn test_config_to_keyboard_layout() {
         ],
         layout: None,
         layer_names: Some(vec!["QWERTY".to_string(), "NUMBERS".to_string()]),
+        encoder_map: Vec::new(),
+        custom_keycodes: Default::default(),
     };
 
     let layout = config.to_keyboard_layout();
@@ -153,3 +158,147 @@ fn test_config_to_keyboard_layout() {
     assert!(layout.rows > 0);
     assert!(layout.cols > 0);
 }
+
+#[test]
+fn test_detect_known_keyboard_by_key_count() {
+    let name = |n| detect_by_key_count(n).map(|kb| kb.name);
+    assert_eq!(name(48), Some("planck"));
+    assert_eq!(name(47), Some("planck"));
+    assert_eq!(name(42), Some("corne"));
+    assert_eq!(name(34), Some("ferris"));
+    assert_eq!(name(20), None);
+    assert_eq!(
+        detect_by_key_count(42)
+            .unwrap()
+            .layout()
+            .physical_positions
+            .map(|p| p.len()),
+        Some(42)
+    );
+}
+
+#[test]
+fn test_dimension_estimation_for_common_counts() {
+    for (key_count, expected) in [
+        (34, (4, 10)), // Ferris / Sweep
+        (36, (4, 10)),
+        (40, (4, 10)),
+        (42, (4, 12)), // Corne
+        (44, (4, 11)),
+        (47, (4, 12)), // Planck MIT: 2U spacebar fills two cells
+        (48, (4, 12)),
+        (60, (5, 12)),
+    ] {
+        let layout =
+            KeyboardLayout::from_layout_data(vec![vec!["KC_A".to_string(); key_count]], None);
+        assert_eq!((layout.rows, layout.cols), expected, "{} keys", key_count);
+        assert!(layout.rows * layout.cols >= key_count);
+        assert!(layout.rows <= layout.cols);
+    }
+}
+
+#[test]
+fn test_split_boards_get_a_gap() {
+    let layout = |key_count| {
+        KeyboardLayout::from_layout_data(vec![vec!["KC_A".to_string(); key_count]], None)
+    };
+    // Corne halves of 6 columns
+    assert_eq!(layout(42).split.map(|s| s.split_col), Some(6));
+    // Planck is one piece, unknown counts are left alone
+    assert_eq!(layout(48).split, None);
+    assert_eq!(layout(60).split, None);
+
+    let mut corne = layout(42);
+    corne.resize(4, 10);
+    assert_eq!(corne.split, None);
+}
+
+#[test]
+fn test_corne_keymap_c_keeps_its_split_gap() {
+    let cfg = KeymapConfig::load_from_path("tests/files/cornia_keymap.c").expect("load corne");
+    // Loaded as is, then laid out again on the detected board like the UI does
+    let layouts = [
+        cfg.to_keyboard_layout(),
+        KeyboardLayout::from_layout_data_with_dims(
+            cfg.layers.clone(),
+            cfg.layer_names.clone(),
+            4,
+            12,
+        ),
+    ];
+    for layout in layouts {
+        let split = layout.split.expect("corne is split");
+        assert_eq!((split.split_col, split.thumb_keys), (6, Some(6)));
+        assert_eq!(layout.grid_column(0, 5), Some((5, false)));
+        assert_eq!(layout.grid_column(0, 6), Some((6, true)));
+        // Three thumb keys on each side of the gap, under the inner columns
+        assert_eq!(layout.grid_column(3, 0), Some((3, false)));
+        assert_eq!(layout.grid_column(3, 2), Some((5, false)));
+        assert_eq!(layout.grid_column(3, 3), Some((6, true)));
+        assert_eq!(layout.grid_column(3, 5), Some((8, true)));
+        assert_eq!(layout.grid_column(3, 6), None);
+    }
+}
+
+#[test]
+fn test_sofle_keymap_fits_matrix() {
+    use qmk_viewer::keyboards::sofle::SofleLayout;
+
+    let cfg = KeymapConfig::load_from_path("tests/files/sofle_keymap.c").expect("load sofle");
+    assert_eq!(cfg.layers.len(), 4);
+    for layer in &cfg.layers {
+        assert_eq!(layer.len(), SofleLayout::ROWS * SofleLayout::COLS);
+    }
+
+    let layout = cfg.to_keyboard_layout();
+    assert_eq!(
+        (layout.rows, layout.cols),
+        (SofleLayout::ROWS, SofleLayout::COLS)
+    );
+    // The 58 keys plus both encoder clicks fill the 5x12 matrix exactly
+    assert!(SofleLayout::KEY_COUNT <= layout.rows * layout.cols);
+    assert_eq!(
+        layout.raw_legends[0][SofleLayout::ENCODER_INDICES[0]],
+        "KC_MUTE"
+    );
+    assert_eq!(layout.raw_legends[0][59], "KC_RGUI");
+}
+
+#[test]
+fn test_ferris_sweep_keymap() {
+    let cfg =
+        KeymapConfig::load_from_path("tests/files/ferris_sweep_keymap.c").expect("load sweep");
+    assert_eq!(cfg.layers.len(), 4);
+    assert!(cfg.layers.iter().all(|layer| layer.len() == 34));
+
+    let layout = cfg.to_keyboard_layout();
+    assert_eq!((layout.rows, layout.cols), (4, 10));
+    assert_eq!(layout.raw_legends[0][30], "LT(1, KC_TAB)");
+}
+
+#[test]
+fn test_find_key_by_label_finds_shift() {
+    let cfg =
+        KeymapConfig::load_from_path("tests/files/ferris_sweep_keymap.c").expect("load sweep");
+    let state = KeyboardState::new(cfg.to_keyboard_layout());
+    // LSFT_T(KC_T) and RSFT_T(KC_N) on the home row; labels read "Shift"
+    let shift = state.find_key_by_label("shift", 0);
+    assert_eq!(shift, vec![(1, 3), (1, 6)]);
+    assert_eq!(state.find_key_by_label("SHIFT", 0), shift);
+    let (rows, cols) = (state.keyboard.rows, state.keyboard.cols);
+    for (row, col) in (0..rows).flat_map(|r| (0..cols).map(move |c| (r, c))) {
+        let raw = state.raw_legend_at(0, row, col).unwrap_or_default();
+        if raw.contains("SFT") || raw.to_lowercase().contains("shift") {
+            assert!(shift.contains(&(row, col)), "{} not found", raw);
+        }
+    }
+    // A raw token substring matches too, and an empty query matches nothing
+    assert_eq!(state.find_key_by_label("lt(1", 0), vec![(3, 0)]);
+    assert!(state.find_key_by_label("  ", 0).is_empty());
+
+    let everywhere = state.find_key_by_label_all_layers("shift");
+    assert!(everywhere.starts_with(&[(0, 1, 3), (0, 1, 6)]));
+    assert!(everywhere.iter().all(|&(layer, row, col)| state
+        .find_key_by_label("shift", layer)
+        .contains(&(row, col))));
+}
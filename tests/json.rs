@@ -1,4 +1,4 @@
-use qmk_viewer::config::KeymapConfig;
+use qmk_viewer::config::{detect_and_parse, detect_format_and_parse, KeymapConfig, KeymapFormat};
 
 #[test]
 fn parse_thooams_ok() {
@@ -8,3 +8,132 @@ fn parse_thooams_ok() {
         assert_eq!(layer.len(), 48, "each layer must have 48 entries");
     }
 }
+
+#[test]
+fn merge_layer_changes_only_that_layer() {
+    let original = KeymapConfig::load_from_path("tests/files/thooams.json").expect("load json");
+    let mut cfg = original.clone();
+    let new_keys = vec!["KC_F1".to_string(); 48];
+    cfg.merge_layer(1, new_keys.clone());
+    assert_eq!(cfg.layers.len(), original.layers.len());
+    assert_eq!(cfg.layers[1], new_keys);
+    for (i, layer) in cfg.layers.iter().enumerate().filter(|(i, _)| *i != 1) {
+        assert_eq!(layer, &original.layers[i]);
+    }
+
+    // Merging past the last layer adds empty layers in between
+    let past_end = original.layers.len() + 1;
+    cfg.merge_layer(past_end, new_keys.clone());
+    assert_eq!(cfg.layers.len(), past_end + 1);
+    assert!(cfg.layers[past_end - 1].is_empty());
+    assert_eq!(cfg.layers[past_end], new_keys);
+}
+
+const VIA_JSON: &str = r#"{
+  "name": "Planck",
+  "vendorProductId": 65261,
+  "macros": [],
+  "layers": [["KC_ESC", "KC_Q", "KC_W"], ["KC_TRNS", "KC_1", 4]]
+}"#;
+
+const KEYMAP_C: &str = r#"
+const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+  [0] = LAYOUT(KC_Q, KC_W, KC_E, KC_R)
+};
+"#;
+
+#[test]
+fn detect_and_parse_each_format() {
+    let qmk = std::fs::read_to_string("tests/files/thooams.json").expect("read json");
+    let cfg = detect_and_parse(&qmk).expect("QMK JSON");
+    assert!(!cfg.layers.is_empty());
+    assert_eq!(
+        detect_format_and_parse(&qmk).unwrap().0,
+        KeymapFormat::QmkJson
+    );
+
+    let (format, cfg) = detect_format_and_parse(VIA_JSON).expect("VIA JSON");
+    assert_eq!(format, KeymapFormat::Via);
+    assert_eq!(cfg.keyboard, "Planck");
+    assert_eq!(cfg.layers[1], ["KC_TRNS", "KC_1", "0x0004"]);
+
+    let (format, cfg) = detect_format_and_parse(KEYMAP_C).expect("keymap.c");
+    assert_eq!(format, KeymapFormat::KeymapC);
+    assert_eq!(cfg.layers[0], ["KC_Q", "KC_W", "KC_E", "KC_R"]);
+
+    let err = detect_and_parse("hello").unwrap_err();
+    assert!(format!("{:#}", err).contains("QMK JSON"), "{:#}", err);
+}
+
+#[test]
+fn load_reads_custom_keycodes_sidecar() {
+    let dir = std::env::temp_dir().join(format!("qmk_viewer_custom_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let keymap = dir.join("keymap.json");
+    std::fs::write(
+        &keymap,
+        r#"{"keyboard":"planck","keymap":"custom","layout":null,"layer_names":null,
+            "layers":[["SNIP","U_LAYER_1","KC_A"]],"custom_keycodes":{"SNIP":"Snip"}}"#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("custom_keycodes.json"),
+        r#"{"SNIP":"Ignored","U_LAYER_1":"L1"}"#,
+    )
+    .unwrap();
+
+    let cfg = KeymapConfig::load_from_path(keymap.to_str().unwrap()).expect("load json");
+    // The keymap's own labels win over the sidecar
+    assert_eq!(cfg.custom_keycodes.translate("SNIP"), Some("Snip"));
+    assert_eq!(cfg.custom_keycodes.translate("U_LAYER_1"), Some("L1"));
+    assert_eq!(cfg.custom_keycodes.translate("KC_A"), None);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn unknown_tokens_checks_keycode_arguments() {
+    let mut cfg = KeymapConfig::load_from_path("tests/files/thooams.json").expect("load json");
+    assert!(cfg.unknown_tokens().is_empty());
+
+    cfg.layers[0][0] = "MY_MACRO".to_string();
+    cfg.layers[0][1] = "LT(_NAV, KC_SPCE)".to_string();
+    cfg.layers[0][2] = "MT(MOD_LSFT, KC_A)".to_string();
+    cfg.layers[1][0] = "MY_MACRO".to_string();
+    cfg.layers[1][1] = "CUSTOM".to_string();
+    cfg.custom_keycodes
+        .0
+        .insert("CUSTOM".to_string(), "Hi".to_string());
+    assert_eq!(cfg.unknown_tokens(), ["KC_SPCE", "MY_MACRO"]);
+
+    let summary = cfg.validation_summary();
+    assert_eq!(summary.layer_count, cfg.layers.len());
+    assert!(summary.keys_per_layer.iter().all(|&keys| keys == 48));
+}
+
+#[test]
+fn parse_qmk_configurator_export() {
+    let cfg = KeymapConfig::load_from_path("tests/files/configurator_planck_rev6.json")
+        .expect("load configurator export");
+    assert_eq!(cfg.keyboard, "planck/rev6");
+    assert_eq!(cfg.layout.as_deref(), Some("LAYOUT_ortho_4x12"));
+    assert_eq!(cfg.layers.len(), 3);
+    assert!(cfg.layers.iter().all(|layer| layer.len() == 48));
+    assert_eq!(cfg.layers[0][40], "MO(1)");
+    assert!(cfg.layer_names.is_none());
+
+    // Older exports store keycodes as numbers
+    let text = r#"{
+        "version": 1,
+        "notes": "",
+        "keyboard": "planck/rev6",
+        "layout": "LAYOUT_ortho_4x12",
+        "layers": [[4, 41, 1, 0, 21025, "KC_SPC"]]
+    }"#;
+    let (format, cfg) = detect_format_and_parse(text).unwrap();
+    assert_eq!(format, KeymapFormat::QmkJson);
+    assert_eq!(
+        cfg.layers[0],
+        ["KC_A", "KC_ESC", "KC_TRNS", "KC_NO", "MO(1)", "KC_SPC"]
+    );
+    assert_eq!(cfg.keymap, "");
+}
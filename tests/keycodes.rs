@@ -0,0 +1,229 @@
+use qmk_viewer::keycodes::{sanitize_glyphs, translate_token};
+use std::collections::HashSet;
+
+#[test]
+fn rgb_keycodes_have_short_labels() {
+    let cases = [
+        ("RGB_TOG", "RGB"),
+        ("RGB_MOD", "RGB>"),
+        ("RGB_RMOD", "RGB<"),
+        ("RGB_HUI", "Hue+"),
+        ("RGB_HUD", "Hue-"),
+        ("RGB_SAI", "Sat+"),
+        ("RGB_SAD", "Sat-"),
+        ("RGB_VAI", "Val+"),
+        ("RGB_VAD", "Val-"),
+        ("RGB_SPI", "Spd+"),
+        ("RGB_SPD", "Spd-"),
+        ("RGB_MODE_FORWARD", "RGB>"),
+        ("RGB_MODE_REVERSE", "RGB<"),
+        ("RGB_MODE_PLAIN", "Plain"),
+        ("RGB_MODE_BREATHE", "Breath"),
+        ("RGB_MODE_RAINBOW", "Rainbw"),
+        ("RGB_MODE_SWIRL", "Swirl"),
+        ("RGB_MODE_SNAKE", "Snake"),
+        ("RGB_MODE_KNIGHT", "Knight"),
+        ("RGB_MODE_XMAS", "Xmas"),
+        ("RGB_MODE_GRADIENT", "Grad"),
+        ("RGB_MODE_RGBTEST", "Test"),
+        ("RGB_MODE_TWINKLE", "Twinkl"),
+    ];
+    for (token, label) in cases {
+        assert_eq!(translate_token(token), label, "{}", token);
+    }
+}
+
+#[test]
+fn rgb_mode_short_names_match_long_names() {
+    let pairs = [
+        ("RGB_M_P", "RGB_MODE_PLAIN"),
+        ("RGB_M_B", "RGB_MODE_BREATHE"),
+        ("RGB_M_R", "RGB_MODE_RAINBOW"),
+        ("RGB_M_SW", "RGB_MODE_SWIRL"),
+        ("RGB_M_SN", "RGB_MODE_SNAKE"),
+        ("RGB_M_K", "RGB_MODE_KNIGHT"),
+        ("RGB_M_X", "RGB_MODE_XMAS"),
+        ("RGB_M_G", "RGB_MODE_GRADIENT"),
+        ("RGB_M_T", "RGB_MODE_RGBTEST"),
+        ("RGB_M_TW", "RGB_MODE_TWINKLE"),
+    ];
+    for (short, long) in pairs {
+        assert_eq!(translate_token(short), translate_token(long), "{}", short);
+    }
+}
+
+#[test]
+fn backlight_keycodes_have_short_labels() {
+    let cases = [
+        ("BL_TOGG", "BL"),
+        ("BL_UP", "BL+"),
+        ("BL_DOWN", "BL-"),
+        ("BL_INC", "BL+"),
+        ("BL_DEC", "BL-"),
+        ("BL_ON", "BLOn"),
+        ("BL_OFF", "BLOff"),
+        ("BL_STEP", "BL>"),
+        ("BL_BRTG", "BLBrth"),
+    ];
+    for (token, label) in cases {
+        assert_eq!(translate_token(token), label, "{}", token);
+    }
+}
+
+#[test]
+fn mouse_keycodes_have_compact_labels() {
+    // (legacy long name, legacy short alias, current alias, QK_ name, label)
+    let cases = [
+        ("KC_MS_UP", "KC_MS_U", "MS_UP", "QK_MOUSE_CURSOR_UP", "🖱↑"),
+        (
+            "KC_MS_DOWN",
+            "KC_MS_D",
+            "MS_DOWN",
+            "QK_MOUSE_CURSOR_DOWN",
+            "🖱↓",
+        ),
+        (
+            "KC_MS_LEFT",
+            "KC_MS_L",
+            "MS_LEFT",
+            "QK_MOUSE_CURSOR_LEFT",
+            "🖱←",
+        ),
+        (
+            "KC_MS_RIGHT",
+            "KC_MS_R",
+            "MS_RGHT",
+            "QK_MOUSE_CURSOR_RIGHT",
+            "🖱→",
+        ),
+        (
+            "KC_MS_BTN1",
+            "KC_BTN1",
+            "MS_BTN1",
+            "QK_MOUSE_BUTTON_1",
+            "LMB",
+        ),
+        (
+            "KC_MS_BTN2",
+            "KC_BTN2",
+            "MS_BTN2",
+            "QK_MOUSE_BUTTON_2",
+            "RMB",
+        ),
+        (
+            "KC_MS_BTN3",
+            "KC_BTN3",
+            "MS_BTN3",
+            "QK_MOUSE_BUTTON_3",
+            "MMB",
+        ),
+        (
+            "KC_MS_BTN4",
+            "KC_BTN4",
+            "MS_BTN4",
+            "QK_MOUSE_BUTTON_4",
+            "MB4",
+        ),
+        (
+            "KC_MS_BTN5",
+            "KC_BTN5",
+            "MS_BTN5",
+            "QK_MOUSE_BUTTON_5",
+            "MB5",
+        ),
+        (
+            "KC_MS_BTN6",
+            "KC_BTN6",
+            "MS_BTN6",
+            "QK_MOUSE_BUTTON_6",
+            "MB6",
+        ),
+        (
+            "KC_MS_BTN7",
+            "KC_BTN7",
+            "MS_BTN7",
+            "QK_MOUSE_BUTTON_7",
+            "MB7",
+        ),
+        (
+            "KC_MS_BTN8",
+            "KC_BTN8",
+            "MS_BTN8",
+            "QK_MOUSE_BUTTON_8",
+            "MB8",
+        ),
+        (
+            "KC_MS_WH_UP",
+            "KC_WH_U",
+            "MS_WHLU",
+            "QK_MOUSE_WHEEL_UP",
+            "WH↑",
+        ),
+        (
+            "KC_MS_WH_DOWN",
+            "KC_WH_D",
+            "MS_WHLD",
+            "QK_MOUSE_WHEEL_DOWN",
+            "WH↓",
+        ),
+        (
+            "KC_MS_WH_LEFT",
+            "KC_WH_L",
+            "MS_WHLL",
+            "QK_MOUSE_WHEEL_LEFT",
+            "WH←",
+        ),
+        (
+            "KC_MS_WH_RIGHT",
+            "KC_WH_R",
+            "MS_WHLR",
+            "QK_MOUSE_WHEEL_RIGHT",
+            "WH→",
+        ),
+        (
+            "KC_MS_ACCEL0",
+            "KC_ACL0",
+            "MS_ACL0",
+            "QK_MOUSE_ACCELERATION_0",
+            "Acc0",
+        ),
+        (
+            "KC_MS_ACCEL1",
+            "KC_ACL1",
+            "MS_ACL1",
+            "QK_MOUSE_ACCELERATION_1",
+            "Acc1",
+        ),
+        (
+            "KC_MS_ACCEL2",
+            "KC_ACL2",
+            "MS_ACL2",
+            "QK_MOUSE_ACCELERATION_2",
+            "Acc2",
+        ),
+    ];
+    for (long, short, current, qk, label) in cases {
+        for token in [long, short, current, qk] {
+            assert_eq!(translate_token(token), label, "{}", token);
+        }
+    }
+}
+
+#[test]
+fn mouse_labels_fall_back_to_ascii() {
+    let arrows: HashSet<char> = ['↑', '↓', '←', '→'].into_iter().collect();
+    assert_eq!(sanitize_glyphs(&translate_token("KC_MS_UP"), &arrows), "M↑");
+    assert_eq!(sanitize_glyphs(&translate_token("MS_LEFT"), &arrows), "M←");
+    assert_eq!(
+        sanitize_glyphs(&translate_token("KC_MS_DOWN"), &HashSet::new()),
+        "MDown"
+    );
+    assert_eq!(
+        sanitize_glyphs(&translate_token("KC_WH_U"), &HashSet::new()),
+        "WHUp"
+    );
+    assert_eq!(
+        sanitize_glyphs(&translate_token("KC_BTN1"), &HashSet::new()),
+        "LMB"
+    );
+}
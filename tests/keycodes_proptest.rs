@@ -0,0 +1,50 @@
+use proptest::prelude::*;
+use qmk_viewer::keycodes::{translate_token, ALL_QMK_TOKENS};
+use std::collections::HashSet;
+
+const CASES: u32 = 10_000;
+
+fn known_outputs() -> HashSet<String> {
+    ALL_QMK_TOKENS.iter().map(|t| translate_token(t)).collect()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+    #[test]
+    fn translate_never_panics(input in any::<String>()) {
+        let _ = translate_token(&input);
+    }
+
+    #[test]
+    fn known_tokens_translate_to_non_empty(idx in 0..ALL_QMK_TOKENS.len()) {
+        let token = ALL_QMK_TOKENS[idx];
+        prop_assert!(!translate_token(token).is_empty(), "{} translated to an empty label", token);
+    }
+
+    #[test]
+    fn translation_is_idempotent(idx in 0..ALL_QMK_TOKENS.len()) {
+        let once = translate_token(ALL_QMK_TOKENS[idx]);
+        let twice = translate_token(&once);
+        prop_assert_eq!(&twice, &once, "token {}", ALL_QMK_TOKENS[idx]);
+    }
+
+    #[test]
+    fn short_kc_letter_tokens(token in proptest::string::string_regex("KC_[A-Z]{1,10}").unwrap()) {
+        let label = translate_token(&token);
+        match token.len() {
+            // KC_A .. KC_Z
+            4 => {
+                prop_assert_eq!(label.chars().count(), 1);
+                prop_assert!(label.chars().all(|c| c.is_ascii_lowercase()));
+            }
+            // Two-letter keycodes (KC_UP, KC_AT) map to a known label, KC_NO blanks the key
+            // and anything else passes through untouched
+            5 => prop_assert!(
+                label.is_empty() || known_outputs().contains(&label) || label == token,
+                "{} -> {}", token, label
+            ),
+            _ => {}
+        }
+    }
+}
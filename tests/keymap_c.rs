@@ -1,4 +1,7 @@
-use qmk_viewer::keymap_c::parse_keymap_c;
+use qmk_viewer::config::KeymapConfig;
+use qmk_viewer::keymap_c::{
+    extract_encoder_map, merge_header, parse_keymap_c, parse_keymap_c_multi, EncoderAction,
+};
 
 const SAMPLE: &str = r#"
 #include QMK_KEYBOARD_H
@@ -28,3 +31,382 @@ fn parse_two_layers() {
     // The raw layer should contain the full MT wrapper, not the inner keycode
     assert!(cfg.layers[1].iter().any(|s| s == "MT(MOD_LALT, KC_TAB)"));
 }
+
+#[test]
+fn parse_keeps_layer_switch_wrappers() {
+    let src = SAMPLE
+        .replace("MO(NAV)", "TO(2)")
+        .replace("KC_MINS", "DF(_BASE)");
+    let cfg = parse_keymap_c(&src).expect("parse ok");
+    assert!(cfg.layers[1].iter().any(|s| s == "TO(2)"));
+    assert!(cfg.layers[1].iter().any(|s| s == "DF(_BASE)"));
+}
+
+const HEADER: &str = r#"
+#pragma once
+
+enum planck_layers {
+  _QWERTY = 0,
+  _LOWER,
+  _RAISE,
+  _ADJUST
+};
+
+#define LOWER MO(_LOWER)
+#define RAISE MO(_RAISE)
+#define LAYOUT_wrapper(...) LAYOUT(__VA_ARGS__)
+"#;
+
+#[test]
+fn parse_header_layer_names() {
+    let cfg = parse_keymap_c(HEADER).expect("parse header");
+    assert!(cfg.layers.is_empty());
+    assert_eq!(
+        cfg.layer_names,
+        Some(vec![
+            "_QWERTY".to_string(),
+            "_LOWER".to_string(),
+            "_RAISE".to_string(),
+            "_ADJUST".to_string()
+        ])
+    );
+}
+
+#[test]
+fn merge_header_expands_aliases() {
+    let src = SAMPLE.replace("MO(NAV)", "LOWER");
+    let mut cfg = parse_keymap_c(&src).expect("parse ok");
+    merge_header(&mut cfg, HEADER);
+    assert!(cfg.layers[1].iter().any(|s| s == "MO(_LOWER)"));
+    let names = cfg.layer_names.unwrap();
+    assert_eq!(names[0], "_QWERTY");
+    assert_eq!(names[1], "_LOWER");
+}
+
+#[test]
+fn load_header_merges_sibling_c() {
+    let dir = std::env::temp_dir().join("qmk_viewer_header_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("keymap.h"), HEADER).unwrap();
+    std::fs::write(dir.join("keymap.c"), SAMPLE.replace("MO(NAV)", "RAISE")).unwrap();
+
+    let header_path = dir.join("keymap.h");
+    let cfg = KeymapConfig::load_from_path(header_path.to_str().unwrap()).expect("load .h");
+    assert_eq!(cfg.layers.len(), 2);
+    assert!(cfg.layers[1].iter().any(|s| s == "MO(_RAISE)"));
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+const SPLIT_CONFIG_H: &str = r#"
+#pragma once
+#define MATRIX_ROWS 4
+#define MATRIX_COLS 12
+#define HOME_A LGUI_T(KC_A)
+#define NUM_ROW KC_1, KC_2, KC_3, KC_4, KC_5, KC_6, KC_7, KC_8, KC_9, KC_0, KC_MINS, KC_EQL
+"#;
+
+const SPLIT_KEYMAP_C: &str = r#"
+#include QMK_KEYBOARD_H
+
+const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+  [0] = LAYOUT(
+    NUM_ROW,
+    HOME_A, KC_S, KC_D, KC_F, KC_G, KC_H, KC_J, KC_K, KC_L, KC_SCLN
+  )
+};
+"#;
+
+#[test]
+fn parse_multi_uses_config_h_defines() {
+    let cfg = parse_keymap_c_multi([
+        ("keymap.c".to_string(), SPLIT_KEYMAP_C.to_string()),
+        (
+            "rules.mk".to_string(),
+            "MOUSEKEY_ENABLE = yes\n".to_string(),
+        ),
+        ("config.h".to_string(), SPLIT_CONFIG_H.to_string()),
+    ])
+    .expect("parse multi");
+    assert_eq!(cfg.layers.len(), 1);
+    // NUM_ROW expands to 12 keys and the matrix defines pad the layer out to 4x12
+    assert_eq!(cfg.layers[0].len(), 48);
+    assert_eq!(cfg.layers[0][0], "KC_1");
+    assert_eq!(cfg.layers[0][12], "LGUI_T(KC_A)");
+    assert_eq!(cfg.layers[0][47], "KC_NO");
+
+    // Without config.h the aliases stay unresolved and nothing is padded
+    let alone = parse_keymap_c(SPLIT_KEYMAP_C).expect("parse keymap.c alone");
+    assert_eq!(alone.layers[0].len(), 11);
+}
+
+#[test]
+fn parse_multi_skips_conditional_config_h_defines() {
+    let config_h = r#"
+#ifndef CONFIG_H
+#define CONFIG_H
+#define HOME_A LGUI_T(KC_A)
+#ifdef RGB_MATRIX_ENABLE
+#define EXTRA RGB_TOG
+#else
+#define EXTRA KC_NO
+#endif
+#endif
+"#;
+    let keymap_c = r#"
+const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+  [0] = LAYOUT(HOME_A, EXTRA, KC_B)
+};
+"#;
+    let cfg = parse_keymap_c_multi([
+        ("config.h".to_string(), config_h.to_string()),
+        ("keymap.c".to_string(), keymap_c.to_string()),
+    ])
+    .expect("parse multi");
+    // The include guard doesn't hide config.h; the #ifdef branches are both skipped
+    assert_eq!(cfg.layers[0], ["LGUI_T(KC_A)", "EXTRA", "KC_B"]);
+}
+
+#[test]
+fn load_c_discovers_sibling_config_h() {
+    let dir = std::env::temp_dir().join("qmk_viewer_config_h_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.h"), SPLIT_CONFIG_H).unwrap();
+    std::fs::write(dir.join("keymap.c"), SPLIT_KEYMAP_C).unwrap();
+
+    let keymap_path = dir.join("keymap.c");
+    let cfg = KeymapConfig::load_from_path(keymap_path.to_str().unwrap()).expect("load .c");
+    assert_eq!(cfg.layers[0].len(), 48);
+
+    let from_dir = KeymapConfig::load_from_dir(dir.to_str().unwrap()).expect("load folder");
+    assert_eq!(from_dir.layers, cfg.layers);
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+const PROGMEM_MULTILINE: &str = r#"
+#include QMK_KEYBOARD_H
+
+const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+    [0] = LAYOUT_ortho_4x12(
+        KC_TAB,  KC_Q,    KC_W,    KC_E,    KC_R,    KC_T,
+                 KC_Y,    KC_U,    KC_I,    KC_O,    KC_P,    KC_BSPC,
+        KC_ESC,  KC_A,    KC_S,    KC_D,    KC_F,    KC_G,
+                 KC_H,    KC_J,    KC_K,    KC_L,    KC_SCLN, KC_QUOT,
+        KC_LSFT, KC_Z,    KC_X,    KC_C,    KC_V,    KC_B,
+                 KC_N,    KC_M,    KC_COMM, KC_DOT,  KC_SLSH, KC_ENT,
+        KC_LCTL, KC_LGUI, KC_LALT, MO(1),   KC_SPC,  KC_SPC,
+                 KC_SPC,  KC_SPC,  MO(2),   KC_LEFT, KC_DOWN, KC_RGHT
+    ),
+    [1] = LAYOUT_ortho_4x12(
+        KC_GRV,  KC_1,    KC_2,    KC_3,    KC_4,    KC_5,
+                 KC_6,    KC_7,    KC_8,    KC_9,    KC_0,    KC_DEL,
+        _______, KC_F1,   KC_F2,   KC_F3,   KC_F4,   KC_F5,
+                 KC_F6,   KC_MINS, KC_EQL,  KC_LBRC, KC_RBRC, KC_BSLS,
+        _______, KC_F7,   KC_F8,   KC_F9,   KC_F10,  KC_F11,
+                 KC_F12,  _______, _______, _______, _______, _______,
+        _______, _______, _______, _______, _______, _______,
+                 _______, _______, _______, KC_HOME, KC_PGDN, KC_END
+    )
+};
+"#;
+
+#[test]
+fn parse_progmem_multiline_layouts() {
+    let cfg = parse_keymap_c(PROGMEM_MULTILINE).expect("parse multi-line PROGMEM keymaps");
+    assert_eq!(cfg.layers.len(), 2);
+    for layer in &cfg.layers {
+        assert_eq!(layer.len(), 48);
+    }
+    assert_eq!(cfg.layers[0][0], "KC_TAB");
+    assert_eq!(cfg.layers[0][11], "KC_BSPC");
+    assert_eq!(cfg.layers[0][47], "KC_RGHT");
+    assert_eq!(cfg.layers[1][42], "_______");
+    assert_eq!(cfg.layers[1][47], "KC_END");
+    assert_eq!(cfg.layer_names.as_deref().map(|n| n.len()), Some(2));
+}
+
+#[test]
+fn parse_keeps_short_layers_as_written() {
+    let src = "const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {\n  [0] = LAYOUT(KC_A, KC_B, KC_C, KC_D),\n  [1] = LAYOUT(KC_1, KC_2)\n};\n";
+    let cfg = parse_keymap_c(src).expect("parse ok");
+    assert_eq!(cfg.layers.len(), 2);
+    // The layout pads short layers when it places the keys
+    assert_eq!(cfg.layers[1], vec!["KC_1", "KC_2"]);
+}
+
+#[test]
+fn parse_encoder_map() {
+    let cfg = KeymapConfig::load_from_path("tests/files/sofle_keymap.c").expect("load sofle");
+    assert_eq!(cfg.encoder_map.len(), 8);
+    assert_eq!(
+        cfg.encoder_map[0],
+        EncoderAction {
+            layer: 0,
+            encoder: 0,
+            clockwise: "KC_VOLU".to_string(),
+            counter_clockwise: "KC_VOLD".to_string(),
+        }
+    );
+    // Wrapped keycodes keep their parentheses
+    let redo = &cfg.encoder_map[5];
+    assert_eq!((redo.layer, redo.encoder), (2, 1));
+    assert_eq!(redo.clockwise, "LCTL(KC_Y)");
+    assert_eq!(redo.counter_clockwise, "LCTL(KC_Z)");
+
+    assert!(extract_encoder_map(SAMPLE).is_empty());
+    assert!(parse_keymap_c(SAMPLE).unwrap().encoder_map.is_empty());
+}
+
+const BRACE_TOKEN_KEYMAP: &str = r#"
+const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+  [0] = LAYOUT(
+    KC_A, CUSTOM_KEY({1, 2}, KC_B), KC_C, KC_D
+  )
+};
+"#;
+
+#[test]
+fn parse_keeps_brace_initializer_in_one_token() {
+    let cfg = parse_keymap_c(BRACE_TOKEN_KEYMAP).expect("parse ok");
+    assert_eq!(
+        cfg.layers[0][..4],
+        ["KC_A", "CUSTOM_KEY({1, 2}, KC_B)", "KC_C", "KC_D"]
+    );
+}
+
+const COMMENT_NAMED_LAYERS: &str = r#"
+const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {
+  [0] /* QWERTY */ = LAYOUT(
+    KC_Q, KC_W, KC_E, KC_R
+  ),
+  [1] // LOWER
+    = LAYOUT(
+    KC_1, KC_2, KC_3, KC_4
+  ),
+  [2] = LAYOUT(
+    KC_F1, KC_F2, KC_F3, KC_F4
+  ),
+  [_ADJUST] /* Adjust */ = LAYOUT(
+    QK_BOOT, KC_NO, KC_NO, KC_NO
+  )
+};
+"#;
+
+#[test]
+fn parse_layer_names_from_bracket_comments() {
+    let cfg = parse_keymap_c(COMMENT_NAMED_LAYERS).expect("parse ok");
+    // Bracket names win over comments; bare indexes without a comment stay as they are
+    assert_eq!(
+        cfg.layer_names.unwrap(),
+        ["QWERTY", "LOWER", "2", "_ADJUST"]
+    );
+}
+
+// Edge cases --------------------------------------------------------------------------------
+
+const KEYMAPS_OPEN: &str = "const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {\n";
+
+fn keymap(body: &str) -> String {
+    format!("{}{}\n}};\n", KEYMAPS_OPEN, body)
+}
+
+#[test]
+fn parse_windows_line_endings() {
+    let source = keymap("  [0] = LAYOUT(\n    KC_A, KC_B,\n    KC_C, KC_D\n  ),\n  [1] = LAYOUT(KC_1, KC_2, KC_3, KC_4)")
+        .replace('\n', "\r\n");
+    let cfg = parse_keymap_c(&source).expect("parse ok");
+    assert_eq!(cfg.layers[0], ["KC_A", "KC_B", "KC_C", "KC_D"]);
+    assert_eq!(cfg.layers[1], ["KC_1", "KC_2", "KC_3", "KC_4"]);
+    assert_eq!(cfg.layer_names.unwrap(), ["0", "1"]);
+}
+
+#[test]
+fn parse_ignores_comment_markers_in_string_literals() {
+    let source = format!(
+        "// Disposition française — réglée ✓\nconst char *banner = \"fin */ début /* ✓\";\n{}",
+        keymap("  [0] = LAYOUT(KC_A, /* é, ✓ */ KC_B)")
+    );
+    let cfg = parse_keymap_c(&source).expect("parse ok");
+    assert_eq!(cfg.layers, [["KC_A", "KC_B"]]);
+}
+
+#[test]
+fn parse_layout_macro_with_version_suffix() {
+    let source = keymap("  [0] = LAYOUT_planck_1_0(KC_A, KC_B, KC_C)");
+    let cfg = parse_keymap_c(&source).expect("parse ok");
+    assert_eq!(cfg.layers, [["KC_A", "KC_B", "KC_C"]]);
+}
+
+#[test]
+fn parse_keymap_without_layers_fails() {
+    let err = parse_keymap_c(&keymap("")).unwrap_err();
+    assert!(err.to_string().contains("no LAYOUT"), "{}", err);
+}
+
+#[test]
+fn parse_all_transparent_layer() {
+    let cfg = parse_keymap_c(&keymap(
+        "  [0] = LAYOUT(_______, _______, _______, _______)",
+    ))
+    .expect("parse ok");
+    assert_eq!(cfg.layers[0], ["_______"; 4]);
+    let layout = cfg.to_keyboard_layout();
+    assert!(layout.legends[0].iter().all(String::is_empty));
+}
+
+#[test]
+fn parse_trailing_comma_adds_no_key() {
+    let cfg = parse_keymap_c(&keymap(
+        "  [0] = LAYOUT(KC_A, KC_B, KC_C,),\n  [1] = LAYOUT(KC_1, KC_2, KC_3)",
+    ))
+    .expect("parse ok");
+    assert_eq!(cfg.layers[0], ["KC_A", "KC_B", "KC_C"]);
+    assert_eq!(cfg.layers[0].len(), cfg.layers[1].len());
+}
+
+#[test]
+fn parse_empty_layout_is_an_empty_layer() {
+    let cfg = parse_keymap_c(&keymap(
+        "  [0] = LAYOUT(KC_A, KC_B),\n  [1] = LAYOUT(  \n  ),\n  [2] = LAYOUT(KC_1, KC_2)",
+    ))
+    .expect("parse ok");
+    assert_eq!(cfg.layers.len(), 3);
+    assert!(cfg.layers[1].is_empty());
+    assert_eq!(cfg.layers[2], ["KC_1", "KC_2"]);
+    assert_eq!(cfg.layer_names.unwrap(), ["0", "1", "2"]);
+}
+
+#[test]
+fn parse_skips_layout_in_block_comment() {
+    let cfg = parse_keymap_c(&keymap(
+        "  [0] = LAYOUT(KC_A, KC_B),\n/*\n  [1] = LAYOUT(KC_A)\n*/",
+    ))
+    .expect("parse ok");
+    assert_eq!(cfg.layers, [["KC_A", "KC_B"]]);
+    assert_eq!(cfg.layer_names.unwrap(), ["0"]);
+}
+
+const LAYOUT_DEFINES: &str = r#"
+#define _NAV 1
+#define LOWER MO(_NAV)
+#define MY_KEY MT(MOD_LCTL, KC_SPC)
+#ifdef RGB_MATRIX_ENABLE
+#define EXTRA RGB_TOG
+#else
+#define EXTRA KC_NO
+#endif
+"#;
+
+#[test]
+fn parse_expands_defines_in_layouts() {
+    let layers =
+        "  [_BASE] = LAYOUT(LOWER, MY_KEY, EXTRA),\n  [_NAV] = LAYOUT(TG(_NAV), KC_B, KC_C)";
+    let source = format!("{}{}", LAYOUT_DEFINES, keymap(layers));
+    let cfg = parse_keymap_c(&source).expect("parse ok");
+    // One define referencing another is followed; multi-token values stay one key
+    assert_eq!(cfg.layers[0], ["MO(1)", "MT(MOD_LCTL, KC_SPC)", "EXTRA"]);
+    assert_eq!(cfg.layers[1], ["TG(1)", "KC_B", "KC_C"]);
+    // Bracket names are read before expansion
+    assert_eq!(cfg.layer_names.unwrap(), ["_BASE", "_NAV"]);
+}
@@ -0,0 +1,77 @@
+use qmk_viewer::hid::{HidSource, MockConfig, MockHidSource, MockScenario, Report};
+
+#[test]
+fn mt_hold_release_crosses_threshold() {
+    let key_idx = 5;
+    let mut src = MockHidSource::new_with_scenario(MockScenario::MtHoldRelease {
+        key_idx,
+        hold_ms: 550,
+    });
+
+    // Run through 600ms of simulated polling
+    let polls = 600 / MockHidSource::POLL_INTERVAL_MS;
+    let reports: Vec<Report> = (0..polls).filter_map(|_| src.poll()).collect();
+    assert_eq!(reports.len() as u64, polls);
+
+    let held_polls = reports
+        .iter()
+        .take_while(|r| r.pressed_bits.is_set(key_idx))
+        .count() as u64;
+    let held_ms = held_polls * MockHidSource::POLL_INTERVAL_MS;
+    assert!(held_ms >= 500, "held for only {}ms", held_ms);
+
+    // Key is released once the hold duration is over
+    let last = reports.last().unwrap();
+    assert_eq!(last.pressed_bits, 0);
+}
+
+#[test]
+fn all_keys_pressed_then_released() {
+    let mut src = MockHidSource::new_with_scenario(MockScenario::AllKeysPressed);
+    let first = src.poll().unwrap();
+    assert_eq!(
+        first.pressed_bits.count_ones() as usize,
+        Report::PLANCK_NUM_KEYS
+    );
+    let later: Vec<Report> = (0..200).filter_map(|_| src.poll()).collect();
+    assert!(later.iter().any(|r| r.pressed_bits == 0));
+}
+
+#[test]
+fn idle_never_reports() {
+    let mut src = MockHidSource::new_with_scenario(MockScenario::Idle);
+    assert!((0..100).all(|_| src.poll().is_none()));
+}
+
+#[test]
+fn layer_cycle_presses_nothing() {
+    let mut src = MockHidSource::new_with_scenario(MockScenario::LayerCycle);
+    let reports: Vec<Report> = (0..500).filter_map(|_| src.poll()).collect();
+    assert!(reports.iter().all(|r| r.pressed_bits == 0));
+    assert!(reports.iter().any(|r| r.active_layer > 0));
+}
+
+#[test]
+fn simultaneous_keys_press_adjacent_keys() {
+    let mut src = MockHidSource::new_with_config(MockConfig {
+        simultaneous_keys: 3,
+        ..MockConfig::default()
+    });
+    for _ in 0..100 {
+        let report = src.poll().unwrap();
+        assert_eq!(report.pressed_bits.count_ones(), 3);
+    }
+}
+
+#[test]
+fn mock_config_limits_layers() {
+    let mut src = MockHidSource::new_with_config(MockConfig {
+        layer_cycle_ms: MockHidSource::POLL_INTERVAL_MS,
+        min_layer: 2,
+        max_layer: 3,
+        ..MockConfig::default()
+    });
+    let layers: Vec<u8> = (0..10).map(|_| src.poll().unwrap().active_layer).collect();
+    assert!(layers.iter().all(|l| (2..=3).contains(l)));
+    assert!(layers.contains(&2) && layers.contains(&3));
+}
@@ -1,6 +1,11 @@
-use qmk_viewer::hid::parse_rawhid_packet;
-use qmk_viewer::keyboard::KeyboardState;
+use qmk_viewer::hid::{
+    download_via_keymap, parse_console_line, parse_rawhid_packet, parse_rawhid_packet_v2,
+    via_exchange, ConnectionStatus, ConsoleFormat, ParseError, PressedBits, RawHidFormat, Report,
+    StatusReporter, ViaCommand, ViaError, ViaTransport, VIA_PACKET_LEN,
+};
+use qmk_viewer::keyboard::{KeyboardLayout, KeyboardState};
 use qmk_viewer::keyboards::planck::PlanckLayout;
+use std::collections::VecDeque;
 
 #[test]
 fn parse_packet_ok() {
@@ -9,9 +14,132 @@ fn parse_packet_ok() {
     let mut pkt = vec![layer];
     let le = bits.to_le_bytes();
     pkt.extend_from_slice(&le[..6]);
-    let rep = parse_rawhid_packet(&pkt).expect("parsed");
+    let rep = parse_rawhid_packet(&pkt, &RawHidFormat::default()).expect("parsed");
     assert_eq!(rep.active_layer, layer);
-    assert_eq!(rep.pressed_bits & 0xFFFF_FFFF_FFFF, bits & 0xFFFF_FFFF_FFFF);
+    assert_eq!(rep.pressed_bits, bits & 0xFFFF_FFFF_FFFF);
+}
+
+#[test]
+fn parse_packet_too_short() {
+    assert_eq!(
+        parse_rawhid_packet(&[1, 2, 3], &RawHidFormat::default()),
+        Err(ParseError::TooShort { got: 3, need: 7 })
+    );
+    assert_eq!(
+        parse_rawhid_packet_v2(&[0; 7], &RawHidFormat::default()),
+        Err(ParseError::TooShort { got: 7, need: 17 })
+    );
+    // The checksum byte is part of the packet once it is turned on
+    assert_eq!(
+        parse_rawhid_packet(
+            &[0; 7],
+            &RawHidFormat {
+                checksum: true,
+                ..Default::default()
+            }
+        ),
+        Err(ParseError::TooShort { got: 7, need: 8 })
+    );
+}
+
+#[test]
+fn parse_packet_checksum() {
+    let mut pkt = vec![1u8, 0x0F, 0, 0, 0, 0, 0x80];
+    let xor = pkt.iter().fold(0u8, |acc, b| acc ^ b);
+    pkt.push(xor);
+    let format = RawHidFormat {
+        checksum: true,
+        ..Default::default()
+    };
+    let rep = parse_rawhid_packet(&pkt, &format).expect("valid checksum");
+    assert_eq!(rep.active_layer, 1);
+    assert_eq!(rep.pressed_bits, 0x8000_0000_000F);
+
+    pkt[7] ^= 0xFF;
+    assert_eq!(
+        parse_rawhid_packet(&pkt, &format),
+        Err(ParseError::InvalidChecksum)
+    );
+    // Without the setting the trailing byte is padding, whatever its value
+    let rep = parse_rawhid_packet(&pkt, &RawHidFormat::default()).expect("no checksum");
+    assert_eq!(rep.pressed_bits, 0x8000_0000_000F);
+}
+
+#[test]
+fn parse_legacy_packet_zero_padded_to_32_bytes() {
+    // Layer 1, keys 0 and 47 down; QMK fills the rest of the report with zeros
+    let mut pkt = [0u8; 32];
+    pkt[..7].copy_from_slice(&[1, 0x01, 0, 0, 0, 0, 0x80]);
+    let rep = parse_rawhid_packet(&pkt, &RawHidFormat::default()).expect("parsed");
+    assert_eq!(rep.active_layer, 1);
+    assert_eq!(rep.pressed_bits, PressedBits::from_iter([0, 47]));
+}
+
+#[test]
+fn parse_legacy_packet_with_checksum_zero_padded_to_32_bytes() {
+    let mut pkt = [0u8; 32];
+    pkt[..7].copy_from_slice(&[1, 0x01, 0, 0, 0, 0, 0x80]);
+    pkt[7] = pkt[..7].iter().fold(0u8, |acc, b| acc ^ b);
+    let format = RawHidFormat {
+        checksum: true,
+        ..Default::default()
+    };
+    // The checksum byte is not read as pressed bits 48-55
+    let rep = parse_rawhid_packet(&pkt, &format).expect("valid checksum");
+    assert_eq!(rep.pressed_bits, PressedBits::from_iter([0, 47]));
+
+    pkt[7] ^= 0xFF;
+    assert_eq!(
+        parse_rawhid_packet(&pkt, &format),
+        Err(ParseError::InvalidChecksum)
+    );
+}
+
+#[test]
+fn parse_wide_packet_zero_padded_report() {
+    // QMK sends 32-byte reports: the payload is followed by zeros, not by the end of data
+    let mut pkt = [0u8; 32];
+    pkt[0] = 2;
+    pkt[1] = 0x01;
+    pkt[16] = 0x80;
+    let rep = parse_rawhid_packet_v2(&pkt, &RawHidFormat::default()).expect("padding ignored");
+    assert_eq!(rep.active_layer, 2);
+    assert_eq!(rep.pressed_bits, PressedBits::from(1u128 << 127 | 1));
+
+    let format = RawHidFormat {
+        checksum: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        parse_rawhid_packet_v2(&pkt, &format),
+        Err(ParseError::InvalidChecksum)
+    );
+    pkt[17] = pkt[..17].iter().fold(0u8, |acc, b| acc ^ b);
+    let rep = parse_rawhid_packet_v2(&pkt, &format).expect("valid checksum");
+    assert_eq!(rep.pressed_bits, PressedBits::from(1u128 << 127 | 1));
+}
+
+#[test]
+fn parse_packet_v2_wide_bits() {
+    let bits: u128 = 1 << 63 | 1;
+    let mut pkt = vec![3u8];
+    pkt.extend_from_slice(&bits.to_le_bytes());
+    let rep = parse_rawhid_packet_v2(&pkt, &RawHidFormat::default()).expect("parsed");
+    assert_eq!(rep.active_layer, 3);
+    assert_eq!(rep.pressed_bits, 1 << 63 | 1);
+
+    let xor = pkt.iter().fold(0u8, |acc, b| acc ^ b);
+    pkt.push(xor ^ 1);
+    assert_eq!(
+        parse_rawhid_packet_v2(
+            &pkt,
+            &RawHidFormat {
+                checksum: true,
+                ..Default::default()
+            }
+        ),
+        Err(ParseError::InvalidChecksum)
+    );
 }
 
 #[test]
@@ -20,12 +148,25 @@ fn mapping_and_pressed() {
     let mut st = KeyboardState::new(kb);
     // Press row 1, col 3
     let idx = st.index_for(1, 3).unwrap();
-    let bits = 1u64 << idx;
-    st.set_pressed_bits(bits);
+    st.apply_report(&Report::new_for_test(0, &[idx]));
     assert!(st.is_pressed(1, 3));
     assert!(!st.is_pressed(0, 0));
 }
 
+#[test]
+fn report_new_for_test_sets_only_given_keys() {
+    let rep = Report::new_for_test(2, &[0, 5, 47]);
+    assert_eq!(rep.active_layer, 2);
+    assert_eq!(rep.pressed_bits, 1 | 1 << 5 | 1 << 47);
+    assert_eq!(rep.pressed_bits.count_ones(), 3);
+
+    let mut st = KeyboardState::new(PlanckLayout::planck_default());
+    st.apply_report(&rep);
+    assert_eq!(st.active_layer, 2);
+    assert!(st.is_pressed(0, 0) && st.is_pressed(0, 5) && st.is_pressed(3, 11));
+    assert!(!st.is_pressed(0, 1));
+}
+
 #[test]
 fn layer_set() {
     let kb = PlanckLayout::planck_default();
@@ -33,3 +174,213 @@ fn layer_set() {
     st.set_layer(3);
     assert_eq!(st.active_layer, 3);
 }
+
+#[test]
+fn report_diff_bitmasks() {
+    let prev = Report::now(0, 0b01u64);
+    let next = Report::now(0, 0b10u64);
+    let diff = next.diff(&prev);
+    assert!(!diff.layer_changed);
+    assert_eq!(diff.newly_pressed, 0b10);
+    assert_eq!(diff.newly_released, 0b01);
+
+    let diff = Report::now(2, 0b10u64).diff(&next);
+    assert!(diff.layer_changed);
+    assert_eq!(diff.newly_pressed, 0);
+    assert_eq!(diff.newly_released, 0);
+}
+
+#[test]
+fn status_reporter_skips_repeats() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut reporter = StatusReporter::new(tx);
+    reporter.report(ConnectionStatus::Scanning);
+    reporter.report(ConnectionStatus::Scanning);
+    reporter.report(ConnectionStatus::Disconnected);
+    reporter.report(ConnectionStatus::Scanning);
+    let sent: Vec<_> = rx.try_iter().collect();
+    assert_eq!(
+        sent,
+        vec![
+            ConnectionStatus::Scanning,
+            ConnectionStatus::Disconnected,
+            ConnectionStatus::Scanning
+        ]
+    );
+}
+
+#[test]
+fn console_prefix_format() {
+    let report = parse_console_line("L:2 B:00000000A55A", &ConsoleFormat::Auto).unwrap();
+    assert_eq!(
+        (report.active_layer, report.pressed_bits),
+        (2, PressedBits::from(0xA55Au64))
+    );
+    assert!(parse_console_line("L:2 B:A55A", &ConsoleFormat::Json).is_none());
+}
+
+#[test]
+fn console_json_format() {
+    for line in [
+        r#"{"layer":2,"bits":"0x0000001234"}"#,
+        r#"{"layer":"2","bits":"4660"}"#,
+        r#"{"layer":2,"bits":4660}"#,
+    ] {
+        let report = parse_console_line(line, &ConsoleFormat::Auto).unwrap();
+        assert_eq!(
+            (report.active_layer, report.pressed_bits),
+            (2, PressedBits::from(0x1234u64)),
+            "{}",
+            line
+        );
+    }
+    assert!(parse_console_line(r#"{"layer":2,"bits":"0x1"}"#, &ConsoleFormat::Prefix).is_none());
+    assert!(parse_console_line(r#"{"layer":300,"bits":1}"#, &ConsoleFormat::Json).is_none());
+}
+
+#[test]
+fn console_invalid_json_falls_back_to_prefix() {
+    let report = parse_console_line("{oops L:1 B:FF", &ConsoleFormat::Auto).unwrap();
+    assert_eq!(
+        (report.active_layer, report.pressed_bits),
+        (1, PressedBits::from(0xFFu64))
+    );
+}
+
+#[test]
+fn console_garbled_line_is_ignored() {
+    for line in ["", "matrix scan 1234", "L:x B:zz", r#"{"layer":1}"#] {
+        assert!(
+            parse_console_line(line, &ConsoleFormat::Auto).is_none(),
+            "{}",
+            line
+        );
+    }
+}
+
+#[test]
+fn pressed_bits_past_64_keys() {
+    let mut bits = PressedBits::default();
+    bits.set(3);
+    bits.set(100);
+    bits.set(200); // past the capacity, ignored
+    assert!(bits.is_set(3) && bits.is_set(100));
+    assert!(!bits.is_set(64) && !bits.is_set(200));
+    assert_eq!(bits.iter().collect::<Vec<_>>(), [3, 100]);
+    bits.clear(100);
+    assert_eq!(bits, 1 << 3);
+
+    // Wide bits survive a JSON round trip; narrow ones still serialize as a plain number
+    let wide: PressedBits = [0, 127].into_iter().collect();
+    let json = serde_json::to_string(&wide).unwrap();
+    assert_eq!(serde_json::from_str::<PressedBits>(&json).unwrap(), wide);
+    assert_eq!(
+        serde_json::to_string(&PressedBits::from(161u64)).unwrap(),
+        "161"
+    );
+}
+
+#[test]
+fn parse_packet_with_16_pressed_bytes() {
+    let bits: u128 = 1 << 100 | 1 << 2;
+    let mut pkt = vec![1u8];
+    pkt.extend_from_slice(&bits.to_le_bytes());
+    let wide = RawHidFormat {
+        wide: true,
+        ..Default::default()
+    };
+    let rep = parse_rawhid_packet(&pkt, &wide).expect("parsed");
+    assert_eq!(rep.pressed_bits, PressedBits::from(bits));
+
+    // A full-size board: the key at index 100 shows as pressed
+    let layout = KeyboardLayout::from_layout_data(vec![vec!["KC_A".to_string(); 104]], None);
+    let mut st = KeyboardState::new(layout);
+    st.apply_report(&rep);
+    let (rows, cols) = (st.keyboard.rows, st.keyboard.cols);
+    assert!(rows * cols >= 104);
+    assert!(st.is_pressed(100 / cols, 100 % cols));
+    assert!(st.is_pressed(0, 2));
+    assert!(!st.is_pressed(0, 0));
+}
+
+/// Keyboard answering VIA requests from a 2x2 matrix, sending a report before every response
+struct FakeViaKeyboard {
+    protocol: u16,
+    keycodes: Vec<[[u16; 2]; 2]>,
+    pending: VecDeque<[u8; VIA_PACKET_LEN]>,
+}
+
+impl ViaTransport for FakeViaKeyboard {
+    fn send(&mut self, packet: &[u8; VIA_PACKET_LEN]) -> anyhow::Result<()> {
+        let mut response = *packet;
+        match packet[0] {
+            0x01 => response[1..3].copy_from_slice(&self.protocol.to_be_bytes()),
+            0x11 => response[1] = self.keycodes.len() as u8,
+            0x04 => match self.keycodes.get(packet[1] as usize) {
+                Some(layer) => {
+                    let code = layer[packet[2] as usize][packet[3] as usize];
+                    response[4..6].copy_from_slice(&code.to_be_bytes());
+                }
+                None => response[0] = ViaCommand::UNHANDLED,
+            },
+            _ => response[0] = ViaCommand::UNHANDLED,
+        }
+        let mut report = [0u8; VIA_PACKET_LEN];
+        report[0] = 0x03;
+        self.pending.push_back(report);
+        self.pending.push_back(response);
+        Ok(())
+    }
+
+    fn receive(&mut self) -> anyhow::Result<Option<[u8; VIA_PACKET_LEN]>> {
+        Ok(self.pending.pop_front())
+    }
+}
+
+#[test]
+fn download_keymap_over_via() {
+    let mut keyboard = FakeViaKeyboard {
+        protocol: 12,
+        keycodes: vec![
+            [[0x0004, 0x0005], [0x422C, 0x0001]],
+            [[0x5221, 0x0000], [0x0029, 0x2129]],
+        ],
+        pending: VecDeque::new(),
+    };
+    // LAYOUT order differs from the matrix order
+    let matrix = [(1, 0), (0, 0), (0, 1), (1, 1)];
+    let config = download_via_keymap(&mut keyboard, &matrix).expect("downloaded");
+    assert_eq!(
+        config.layers,
+        vec![
+            vec!["LT(2, KC_SPC)", "KC_A", "KC_B", "KC_TRNS"],
+            vec!["KC_ESC", "MO(1)", "KC_NO", "LCTL_T(KC_ESC)"],
+        ]
+    );
+
+    let err = via_exchange(
+        &mut keyboard,
+        ViaCommand::DynamicKeymapGetKeycode,
+        &[9, 0, 0],
+    )
+    .expect_err("no such layer");
+    assert_eq!(
+        err.downcast_ref::<ViaError>(),
+        Some(&ViaError::Unhandled(ViaCommand::DynamicKeymapGetKeycode))
+    );
+}
+
+#[test]
+fn download_keymap_refuses_old_via_protocols() {
+    // Protocol 11 numbers keycodes like QMK before 0.19
+    let mut keyboard = FakeViaKeyboard {
+        protocol: 11,
+        keycodes: vec![[[0x0004, 0x0005], [0x0006, 0x0007]]],
+        pending: VecDeque::new(),
+    };
+    let err = download_via_keymap(&mut keyboard, &[(0, 0)]).expect_err("protocol too old");
+    assert_eq!(
+        err.downcast_ref::<ViaError>(),
+        Some(&ViaError::UnsupportedProtocol(11))
+    );
+}
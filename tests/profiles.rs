@@ -0,0 +1,63 @@
+use qmk_viewer::config_persistence::{
+    delete_profile, get_saved_keymap_path, list_profiles, load_profile, save_keymap_file,
+    save_profile, DEFAULT_PROFILE,
+};
+use std::path::PathBuf;
+
+// Environment variables are process-wide, so the whole profile lifecycle is one test
+#[test]
+fn profiles_are_stored_by_name() {
+    let root = std::env::temp_dir().join(format!("qmk_viewer_profiles_{}", std::process::id()));
+    std::env::set_var("XDG_CONFIG_HOME", root.join("config"));
+    std::env::set_var("XDG_DATA_HOME", root.join("data"));
+    assert!(list_profiles().unwrap().is_empty());
+
+    let work = save_profile("work", "tests/files/thooams.json").unwrap();
+    assert_eq!(
+        PathBuf::from(&work),
+        root.join("config/qmk_viewer/profiles/work.json")
+    );
+    let gaming = save_profile("gaming", "tests/files/1k_keymap.c").unwrap();
+    assert!(gaming.ends_with("gaming.c"));
+    // Saving a new profile leaves the others alone
+    assert!(PathBuf::from(&work).exists());
+
+    // Loading a profile makes it the most recently used one
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    assert_eq!(load_profile("work").unwrap(), work);
+    let names: Vec<String> = list_profiles()
+        .unwrap()
+        .into_iter()
+        .map(|p| p.name)
+        .collect();
+    assert_eq!(names, ["work", "gaming"]);
+
+    // Re-saving under the same name with another extension replaces the old copy
+    let gaming_json = save_profile("gaming", "tests/files/thooams.json").unwrap();
+    assert!(!PathBuf::from(&gaming).exists());
+    assert!(PathBuf::from(&gaming_json).exists());
+    assert_eq!(list_profiles().unwrap().len(), 2);
+
+    // The saved keymap copy shows up as the default profile
+    let saved = save_keymap_file("tests/files/thooams.json").unwrap();
+    let profiles = list_profiles().unwrap();
+    assert_eq!(profiles[0].name, DEFAULT_PROFILE);
+    assert_eq!(load_profile(DEFAULT_PROFILE).unwrap(), saved);
+
+    delete_profile("gaming").unwrap();
+    assert!(!PathBuf::from(&gaming_json).exists());
+    assert!(load_profile("gaming").is_err());
+    assert!(delete_profile("gaming").is_err());
+    assert!(save_profile("../escape", "tests/files/thooams.json").is_err());
+
+    delete_profile(DEFAULT_PROFILE).unwrap();
+    assert!(get_saved_keymap_path().unwrap().is_none());
+    let names: Vec<String> = list_profiles()
+        .unwrap()
+        .into_iter()
+        .map(|p| p.name)
+        .collect();
+    assert_eq!(names, ["work"]);
+
+    let _ = std::fs::remove_dir_all(root);
+}
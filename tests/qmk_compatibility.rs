@@ -1,13 +1,17 @@
+mod common;
+
+use common::{classify_parse_error, CompatibilityStats, ParseErrorKind};
 use qmk_viewer::keymap_c;
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 
 #[derive(Debug)]
 struct CompatibilityResult {
     keyboard_name: String,
     success: bool,
-    error_message: Option<String>,
+    error: Option<ParseErrorKind>,
     layers_count: Option<usize>,
     keys_per_layer: Option<usize>,
     parse_time_ms: u64,
@@ -23,18 +27,18 @@ impl CompatibilityResult {
         Self {
             keyboard_name,
             success: true,
-            error_message: None,
+            error: None,
             layers_count: Some(layers_count),
             keys_per_layer: Some(keys_per_layer),
             parse_time_ms,
         }
     }
 
-    fn failure(keyboard_name: String, error_message: String, parse_time_ms: u64) -> Self {
+    fn failure(keyboard_name: String, error: ParseErrorKind, parse_time_ms: u64) -> Self {
         Self {
             keyboard_name,
             success: false,
-            error_message: Some(error_message),
+            error: Some(error),
             layers_count: None,
             keys_per_layer: None,
             parse_time_ms,
@@ -88,7 +92,7 @@ fn test_keymap_parsing(keyboard_name: &str) -> CompatibilityResult {
                     let clean_name = keyboard_name.replace("_keymap.c", "");
                     CompatibilityResult::failure(
                         clean_name,
-                        format!("Parse error: {}", e),
+                        classify_parse_error(&format!("{:#}", e)),
                         parse_time,
                     )
                 }
@@ -97,7 +101,11 @@ fn test_keymap_parsing(keyboard_name: &str) -> CompatibilityResult {
         Err(e) => {
             let parse_time = start_time.elapsed().as_millis() as u64;
             let clean_name = keyboard_name.replace("_keymap.c", "");
-            CompatibilityResult::failure(clean_name, format!("File read error: {}", e), parse_time)
+            CompatibilityResult::failure(
+                clean_name,
+                classify_parse_error(&format!("File read error: {}", e)),
+                parse_time,
+            )
         }
     }
 }
@@ -168,7 +176,10 @@ fn generate_compatibility_report(results: &[CompatibilityResult]) -> String {
         report.push_str("|----------|-------|-----------------|\n");
 
         for result in &failed {
-            let error = result.error_message.as_deref().unwrap_or("Unknown error");
+            let error = result
+                .error
+                .as_ref()
+                .map_or_else(|| "Unknown error".to_string(), ToString::to_string);
             report.push_str(&format!(
                 "| {} | {} | {} |\n",
                 result.keyboard_name, error, result.parse_time_ms
@@ -179,19 +190,14 @@ fn generate_compatibility_report(results: &[CompatibilityResult]) -> String {
 
     // Error analysis
     if !failed.is_empty() {
-        let mut error_counts: HashMap<String, usize> = HashMap::new();
+        let mut error_counts: HashMap<&str, usize> = HashMap::new();
         for result in &failed {
-            if let Some(error) = &result.error_message {
-                let error_type = if error.contains("Parse error") {
-                    "Parse error"
-                } else if error.contains("File read error") {
-                    "File read error"
-                } else {
-                    "Other error"
-                };
-                *error_counts.entry(error_type.to_string()).or_insert(0) += 1;
+            if let Some(error) = &result.error {
+                *error_counts.entry(error.name()).or_insert(0) += 1;
             }
         }
+        let mut error_counts: Vec<_> = error_counts.into_iter().collect();
+        error_counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
 
         report.push_str("## Error Analysis\n\n");
         for (error_type, count) in error_counts {
@@ -227,30 +233,57 @@ fn test_all_qmk_keyboards() {
         panic!("❌ No keyboards found in manifest. Please run: ./scripts/collect-qmk-keymaps.sh");
     }
 
-    // Test each keyboard
-    let mut results = Vec::new();
-    let mut successful_count = 0;
-    let mut failed_count = 0;
-
-    for (i, keyboard) in keyboards.iter().enumerate() {
-        print!("Testing {}/{}: {}... ", i + 1, keyboards.len(), keyboard);
-        std::io::Write::flush(&mut std::io::stdout()).unwrap();
-
-        let result = test_keymap_parsing(keyboard);
-
-        if result.success {
-            successful_count += 1;
-            println!("✅ ({}ms)", result.parse_time_ms);
-        } else {
-            failed_count += 1;
-            println!("❌ ({}ms)", result.parse_time_ms);
-            if let Some(error) = &result.error_message {
-                println!("   Error: {}", error);
-            }
-        }
+    // Test the keyboards in parallel, one chunk per thread
+    let started = std::time::Instant::now();
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4);
+    let chunk_size = keyboards.len().div_ceil(threads);
+    let results: Arc<Mutex<Vec<CompatibilityResult>>> = Arc::new(Mutex::new(Vec::new()));
+
+    let handles: Vec<_> = keyboards
+        .chunks(chunk_size)
+        .map(|chunk| {
+            let chunk = chunk.to_vec();
+            let results = Arc::clone(&results);
+            std::thread::spawn(move || {
+                let mut stats = CompatibilityStats::new();
+                for keyboard in &chunk {
+                    let result = test_keymap_parsing(keyboard);
+
+                    stats.total_keyboards += 1;
+                    stats.total_parse_time_ms += result.parse_time_ms;
+                    if result.success {
+                        stats.successful_parsing += 1;
+                        println!("✅ {} ({}ms)", keyboard, result.parse_time_ms);
+                    } else {
+                        stats.failed_parsing += 1;
+                        println!("❌ {} ({}ms)", keyboard, result.parse_time_ms);
+                        if let Some(error) = &result.error {
+                            println!("   Error: {}", error);
+                            *stats
+                                .error_breakdown
+                                .entry(error.name().to_string())
+                                .or_insert(0) += 1;
+                        }
+                    }
+
+                    results.lock().unwrap().push(result);
+                }
+                stats
+            })
+        })
+        .collect();
 
-        results.push(result);
+    let mut stats = CompatibilityStats::new();
+    for handle in handles {
+        stats.merge(&handle.join().expect("compatibility worker panicked"));
     }
+    let wall_time_ms = started.elapsed().as_millis();
+
+    // Keep the report stable regardless of thread scheduling
+    let mut results = std::mem::take(&mut *results.lock().unwrap());
+    results.sort_by(|a, b| a.keyboard_name.cmp(&b.keyboard_name));
 
     // Generate and save report
     let report = generate_compatibility_report(&results);
@@ -260,29 +293,25 @@ fn test_all_qmk_keyboards() {
     // Print summary
     println!("\n🎉 Compatibility testing completed!");
     println!("📊 Results:");
-    println!("   • Total: {}", keyboards.len());
-    println!("   • Successful: {}", successful_count);
-    println!("   • Failed: {}", failed_count);
+    println!("   • Total: {}", stats.total_keyboards);
+    println!("   • Successful: {}", stats.successful_parsing);
+    println!("   • Failed: {}", stats.failed_parsing);
+    println!("   • Success rate: {:.1}%", stats.parsing_success_rate());
     println!(
-        "   • Success rate: {:.1}%",
-        (successful_count as f64 / keyboards.len() as f64) * 100.0
+        "   • Wall time: {}ms on {} threads (summed parse time {}ms)",
+        wall_time_ms, threads, stats.total_parse_time_ms
     );
     println!("📄 Report saved to: tests/compatibility_report.md");
 
     // Assert that we have at least some successful tests
     assert!(
-        successful_count > 0,
+        stats.successful_parsing > 0,
         "No keyboards were successfully parsed!"
     );
+    assert_eq!(stats.total_keyboards, keyboards.len());
 
-    // Optional: Assert minimum success rate (adjust as needed)
-    let success_rate = (successful_count as f64 / keyboards.len() as f64) * 100.0;
-    if success_rate < 50.0 {
-        println!(
-            "⚠️  Warning: Success rate is below 50% ({:.1}%)",
-            success_rate
-        );
-    }
+    // Minimum success rate (adjust as needed)
+    stats.assert_minimum_success_rate(50.0);
 }
 
 #[test]
@@ -298,8 +327,27 @@ fn test_sample_keyboards() {
             assert!(
                 result.success,
                 "Sample keyboard {} failed: {:?}",
-                keyboard, result.error_message
+                keyboard, result.error
             );
         }
     }
 }
+
+#[test]
+fn test_classify_parse_error() {
+    assert_eq!(
+        classify_parse_error("no LAYOUT(...) blocks found"),
+        ParseErrorKind::NoLayoutFound
+    );
+    assert_eq!(
+        classify_parse_error("File read error: stream did not contain valid UTF-8"),
+        ParseErrorKind::EncodingError
+    );
+    let other = classify_parse_error("something unexpected");
+    assert_eq!(
+        other,
+        ParseErrorKind::Other("something unexpected".to_string())
+    );
+    assert_eq!(other.name(), "Other");
+    assert_eq!(other.to_string(), "something unexpected");
+}
@@ -0,0 +1,57 @@
+use qmk_viewer::config::KeymapConfig;
+use qmk_viewer::hid::Report;
+use qmk_viewer::keyboard::KeyboardState;
+use std::fs;
+
+const GOLDEN_PATH: &str = "tests/golden/thooams_state.json";
+
+fn thooams_state() -> KeyboardState {
+    let cfg = KeymapConfig::load_from_path("tests/files/thooams.json").expect("load json");
+    let mut state = KeyboardState::new(cfg.to_keyboard_layout());
+    state.apply_report(&Report::new_for_test(1, &[0, 5, 7]));
+    // Snapshots don't carry press times or counts
+    state.pressed_started.clear();
+    state.reset_heatmap();
+    state
+}
+
+/// Compare against the checked-in golden file; run with `UPDATE_GOLDEN=1` to regenerate it
+#[test]
+fn thooams_snapshot_matches_golden() {
+    let snapshot = thooams_state().to_snapshot_json() + "\n";
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(GOLDEN_PATH, &snapshot).expect("write golden file");
+        println!("📄 Golden file updated: {}", GOLDEN_PATH);
+        return;
+    }
+
+    let golden = fs::read_to_string(GOLDEN_PATH)
+        .expect("golden file missing, run with UPDATE_GOLDEN=1 to create it");
+    assert_eq!(
+        snapshot, golden,
+        "snapshot differs from {} (run with UPDATE_GOLDEN=1 to accept)",
+        GOLDEN_PATH
+    );
+}
+
+#[test]
+fn snapshot_round_trip() {
+    let state = thooams_state();
+    let json = state.to_snapshot_json();
+    let back = KeyboardState::from_snapshot_json(&json).expect("parse snapshot");
+    assert_eq!(back, state);
+
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    for key in [
+        "rows",
+        "cols",
+        "layer_names",
+        "raw_legends",
+        "legends",
+        "active_layer",
+        "pressed_bits",
+    ] {
+        assert!(value.get(key).is_some(), "missing {}", key);
+    }
+}
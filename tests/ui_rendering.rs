@@ -118,10 +118,10 @@ fn create_keyboard_layout(keymap: &KeymapConfig) -> Result<(usize, usize, usize)
     let (rows, cols) = detect_keyboard_dimensions(first_layer)?;
     let total_keys = rows * cols;
 
-    // Validate that we have enough keys
-    if first_layer.len() < total_keys {
+    // Validate that the grid holds every key (unused cells are padded)
+    if first_layer.len() > total_keys {
         return Err(format!(
-            "Insufficient keys: expected {}, found {}",
+            "Too many keys: grid holds {}, found {}",
             total_keys,
             first_layer.len()
         ));
@@ -133,67 +133,106 @@ fn create_keyboard_layout(keymap: &KeymapConfig) -> Result<(usize, usize, usize)
     Ok((rows, cols, total_keys))
 }
 
-fn detect_keyboard_dimensions(keys: &[String]) -> Result<(usize, usize), String> {
-    // Common keyboard layouts and their dimensions
-    let common_layouts = vec![
-        (1, 1),  // 1 key
-        (1, 2),  // 2 keys
-        (1, 3),  // 3 keys
-        (1, 4),  // 4 keys
-        (1, 5),  // 5 keys
-        (2, 2),  // 2x2
-        (2, 3),  // 2x3
-        (2, 4),  // 2x4
-        (2, 5),  // 2x5
-        (3, 3),  // 3x3
-        (3, 4),  // 3x4
-        (3, 5),  // 3x5
-        (4, 4),  // 4x4
-        (4, 5),  // 4x5
-        (4, 6),  // 4x6
-        (4, 8),  // 4x8
-        (4, 10), // 4x10
-        (4, 12), // 4x12
-        (4, 13), // 4x13
-        (4, 14), // 4x14
-        (4, 15), // 4x15
-        (5, 12), // 5x12
-        (5, 13), // 5x13
-        (5, 14), // 5x14
-        (5, 15), // 5x15
-        (6, 15), // 6x15
-        (6, 16), // 6x16
-        (6, 17), // 6x17
-        (6, 18), // 6x18
-        (6, 19), // 6x19
-        (6, 20), // 6x20
-    ];
+/// Common keyboard layouts and their dimensions
+const COMMON_LAYOUTS: &[(usize, usize)] = &[
+    (1, 1),  // 1 key
+    (1, 2),  // 2 keys
+    (1, 3),  // 3 keys
+    (1, 4),  // 4 keys
+    (1, 5),  // 5 keys
+    (2, 2),  // 2x2
+    (2, 3),  // 2x3
+    (2, 4),  // 2x4
+    (2, 5),  // 2x5
+    (3, 3),  // 3x3
+    (3, 4),  // 3x4
+    (3, 5),  // 3x5
+    (4, 4),  // 4x4
+    (4, 5),  // 4x5
+    (4, 6),  // 4x6
+    (4, 8),  // 4x8
+    (4, 10), // 4x10
+    (4, 12), // 4x12
+    (4, 13), // 4x13
+    (4, 14), // 4x14
+    (4, 15), // 4x15
+    (5, 12), // 5x12
+    (5, 13), // 5x13
+    (5, 14), // 5x14
+    (5, 15), // 5x15
+    (6, 15), // 6x15
+    (6, 16), // 6x16
+    (6, 17), // 6x17
+    (6, 18), // 6x18
+    (6, 19), // 6x19
+    (6, 20), // 6x20
+];
+
+/// Known split keyboard halves as (keys per half, rows, cols)
+const HALF_LAYOUTS: &[(usize, usize, usize)] = &[
+    (15, 3, 5), // 30-key 3x5
+    (17, 4, 5), // Ferris / Sweep (3x5 + 2 thumbs)
+    (18, 3, 6), // 3x6
+    (21, 4, 6), // Corne (3x6 + 3 thumbs)
+    (24, 4, 6), // 4x6
+    (25, 5, 6), // Kyria (3x6 + 7 thumbs)
+    (28, 5, 6), // Iris (4x6 + 4 thumbs)
+    (29, 5, 6), // Lily58 / Sofle (4x6 + 5 thumbs)
+];
+
+/// Target columns-per-row ratios (3:1 covers the 12:4 ortho grid)
+const ASPECT_RATIOS: &[f64] = &[3.0, 4.0];
 
+fn detect_keyboard_dimensions(keys: &[String]) -> Result<(usize, usize), String> {
     let key_count = keys.len();
 
-    // Find the best matching layout
-    for (rows, cols) in common_layouts {
-        if rows * cols == key_count {
-            return Ok((rows, cols));
+    // Exact match with a common layout
+    if let Some(&(rows, cols)) = COMMON_LAYOUTS
+        .iter()
+        .find(|(rows, cols)| rows * cols == key_count)
+    {
+        return Ok((rows, cols));
+    }
+
+    // Split keyboards: two identical halves side by side
+    if key_count.is_multiple_of(2) {
+        if let Some(&(_, rows, cols)) = HALF_LAYOUTS
+            .iter()
+            .find(|(half, _, _)| *half == key_count / 2)
+        {
+            return Ok((rows, cols * 2));
         }
     }
 
-    // If no exact match, try to find a reasonable approximation
-    // Look for factors of the key count
-    for rows in 1..=key_count {
-        if key_count.is_multiple_of(rows) {
-            let cols = key_count / rows;
-            if rows <= 10 && cols <= 25 {
-                // Reasonable keyboard dimensions
-                return Ok((rows, cols));
-            }
+    // Otherwise rank every grid that holds all keys by how close it is to a
+    // common aspect ratio, penalising unused cells
+    let mut best: Option<((usize, usize), f64)> = None;
+    for rows in 2..=key_count {
+        let cols = key_count.div_ceil(rows);
+        if rows > cols {
+            break;
+        }
+        if cols > 25 {
+            continue;
+        }
+        let ratio = cols as f64 / rows as f64;
+        let ratio_diff = ASPECT_RATIOS
+            .iter()
+            .map(|target| (ratio - target).abs())
+            .fold(f64::INFINITY, f64::min);
+        let waste = (rows * cols - key_count) as f64 / key_count as f64;
+        let score = ratio_diff + waste * 10.0;
+        if best.is_none_or(|(_, best_score)| score < best_score) {
+            best = Some(((rows, cols), score));
         }
     }
 
-    Err(format!(
-        "Could not determine keyboard dimensions for {} keys",
-        key_count
-    ))
+    best.map(|(dims, _)| dims).ok_or_else(|| {
+        format!(
+            "Could not determine keyboard dimensions for {} keys",
+            key_count
+        )
+    })
 }
 
 fn test_keycode_translation(keys: &[String]) -> Result<(), String> {
@@ -427,3 +466,36 @@ fn test_known_keyboards_ui() {
         }
     }
 }
+
+#[test]
+fn test_detect_dimensions_non_factor_counts() {
+    for key_count in [44, 46, 56, 65] {
+        let keys = vec!["KC_A".to_string(); key_count];
+        let (rows, cols) = detect_keyboard_dimensions(&keys)
+            .unwrap_or_else(|e| panic!("{} keys: {}", key_count, e));
+        assert!(
+            rows * cols >= key_count,
+            "{} keys -> {}x{}",
+            key_count,
+            rows,
+            cols
+        );
+        assert!(
+            (2..=6).contains(&rows),
+            "{} keys -> {}x{}",
+            key_count,
+            rows,
+            cols
+        );
+        assert!(cols <= 25);
+    }
+
+    let dims = |n: usize| detect_keyboard_dimensions(&vec!["KC_A".to_string(); n]).unwrap();
+    assert_eq!(dims(48), (4, 12));
+    assert_eq!(dims(44), (4, 11));
+    // Split boards are laid out as two halves side by side
+    assert_eq!(dims(42), (4, 12));
+    assert_eq!(dims(46), (4, 12));
+    assert_eq!(dims(58), (5, 12));
+    assert_eq!(dims(65), (5, 13));
+}